@@ -0,0 +1,18 @@
+use std::process::Command;
+
+/// Stamps the binary with the commit it is built from, for the image metadata.
+fn main() {
+    let commit = Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RAYTRACER_COMMIT={}", commit);
+    // Commits move HEAD or a branch; edits to the sources make the tree dirty
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=src");
+}
@@ -0,0 +1,422 @@
+use raytracing_rust::core::animation::AnimationFormat;
+use raytracing_rust::core::color_space::OutputColorSpace;
+use raytracing_rust::core::memory;
+use raytracing_rust::core::vec3::{Color, Point3};
+use raytracing_rust::geometry::accelerator::Accelerator;
+use raytracing_rust::geometry::bvh::BvhBuilder;
+use raytracing_rust::geometry::clip::{CapMaterial, ClipPlane};
+use raytracing_rust::geometry::material_override::OverrideRule;
+use raytracing_rust::integrators::path_tracer::{
+    DEFAULT_MAX_SPLITS, DEFAULT_RR_START_BOUNCE, LightSampling,
+};
+use raytracing_rust::scenes::city::LightSelection;
+use raytracing_rust::scenes::cornell_box::CornellContents;
+use raytracing_rust::scenes::showcase::MaterialFamily;
+
+/// Command-line options.
+/// Positional arguments are `<scene> [integrator]`, `inspect <scene> <i,j>` to
+/// re-trace a single pixel with full logging, `pick <scene> <i,j>` to report the
+/// object under a pixel, or `objects <scene>` to list its named objects; flags may
+/// appear anywhere.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub scene: String,
+    pub integrator: String,
+    /// Light sampling strategy of the path tracer (`--light-sampling mixture|mis`).
+    pub light_sampling: LightSampling,
+    /// Maximum occluder distance for the ambient occlusion AOV (`--ao <distance>`).
+    pub ao_distance: Option<f64>,
+    /// Also write the radiance split by number of bounces (`--bounce-aovs`).
+    pub bounce_aovs: bool,
+    /// Also write Cryptomatte object and material mattes (`--cryptomatte`).
+    pub cryptomatte: bool,
+    /// Bounces before Russian roulette kicks in (`--rr-start <n>`).
+    pub rr_start_bounce: u32,
+    /// Maximum branches at a specular split event, 1 disables splitting (`--max-splits <n>`).
+    pub max_splits: u32,
+    /// Cone half-angle in degrees for path regularization (`--regularize <angle>`).
+    pub regularization_angle: Option<f64>,
+    /// Material family swept by the showcase scene (`--family lambertian|metal|dielectric|layered`).
+    pub material_family: MaterialFamily,
+    /// Objects inside the Cornell box (`--contents boxes|sphere|mesh|cylinder`).
+    pub cornell_contents: CornellContents,
+    /// Turn the Cornell box's boxes into smoke (`--smoke`).
+    pub smoke: bool,
+    /// Scale and radiance of the Cornell box's light (`--cornell-light <scale>,<radiance>`).
+    pub cornell_light: Option<(f64, f64)>,
+    /// Colors of the Cornell box's left and right walls (`--walls r,g,b,r,g,b`).
+    pub cornell_walls: Option<(Color, Color)>,
+    /// Light selection weights of the city scene (`--light-selection uniform|area|power`).
+    pub light_selection: LightSelection,
+    /// Light probe positions for the probe integrator (`--probe x,y,z`, repeatable).
+    pub probes: Vec<Point3>,
+    /// Pixels whose paths are recorded instead of rendering (`--dump-path i,j`, repeatable).
+    pub dump_pixels: Vec<(u32, u32)>,
+    /// Paths recorded per dumped or inspected pixel (`--dump-count <n>`).
+    pub dump_count: u32,
+    /// Starburst glare around directly visible lights, adding this fraction of their
+    /// energy as streaks (`--glare <strength>`, `path` integrator).
+    pub glare: Option<f64>,
+    /// Exposure offsets in EV of extra copies of the image (`--bracket <ev,...>`,
+    /// `path` integrator).
+    pub bracket: Vec<f64>,
+    /// Lateral chromatic aberration, the red/blue magnification difference
+    /// (`--chromatic-aberration <amount>`, `path` integrator).
+    pub chromatic_aberration: f64,
+    /// Cosine-fourth vignetting strength in [0, 1] (`--vignetting <amount>`, `path` integrator).
+    pub vignetting: f64,
+    /// Primaries and transfer function of the written images
+    /// (`--color-space srgb|rec709|linear|acescg`).
+    pub color_space: Option<OutputColorSpace>,
+    /// Pure power transfer function overriding the color space's (`--gamma <gamma>`).
+    pub gamma: Option<f64>,
+    /// 3D LUT applied to rendered images after color space conversion (`--lut <file.cube>`).
+    pub lut: Option<String>,
+    /// Write the beauty image tile by tile into a tiled EXR (`--tiled-exr`, `path` integrator).
+    pub tiled_exr: bool,
+    /// Memory the scene may take in bytes, downscaling textures to fit and refusing
+    /// to render beyond it (`--max-memory <size>`, e.g. `512M` or `4G`).
+    pub max_memory: Option<u64>,
+    /// How BVHs are built (`--bvh-builder median|lbvh`).
+    pub bvh_builder: BvhBuilder,
+    /// Structure large meshes are put in (`--accelerator bvh|kdtree`).
+    pub accelerator: Accelerator,
+    /// Trace where non-finite samples originate and paint their pixels magenta (`--nan-check`).
+    pub nan_check: bool,
+    /// Save heatmaps of BVH node and primitive tests per sample (`--bvh-heatmap`).
+    pub bvh_heatmap: bool,
+    /// Render low-sample thumbnails of every scene into one image (`--contact-sheet`).
+    pub contact_sheet: bool,
+    /// Global seed for reproducible scene generation and path tracing (`--seed <n>`).
+    pub seed: Option<u64>,
+    /// Seed of random object layouts, to replay one recorded in an image (`--scatter-seed <n>`).
+    pub scatter_seed: Option<u64>,
+    /// Progressive pass to render, continuing the seeded sample streams of earlier
+    /// passes (`--pass <n>`).
+    pub pass: Option<u64>,
+    /// Render a sequence of frames through the shutter, resuming an interrupted
+    /// sequence from its checkpoints (`--frames <n>`).
+    pub frames: Option<u32>,
+    /// Assemble the frames into an animation (`--animation apng|gif|mp4`).
+    pub animation: Option<AnimationFormat>,
+    /// Frame rate of the animation (`--fps <n>`).
+    pub fps: u32,
+    /// Address to serve progress, previews and post settings of the render on
+    /// (`--http <address:port>`).
+    pub http: Option<String>,
+    /// Directory caching parsed models and built BVH layouts between runs
+    /// (`--bvh-cache <dir>`).
+    pub bvh_cache: Option<String>,
+    /// Write each image's metadata to a JSON file beside it (`--metadata-json`).
+    pub metadata_json: bool,
+    /// NanoVDB file rendered by the `volume` scene (`--volume <file.nvdb>`).
+    pub volume: Option<String>,
+    /// Extinction per world unit of a grid value of 1 in the `volume` scene
+    /// (`--volume-density <scale>`).
+    pub volume_density: f64,
+    /// Render the `volume` scene's grids with the cloud preset (`--cloud`).
+    pub cloud: bool,
+    /// Turn off the multiple scattering approximation of cloud media (`--single-scattering`).
+    pub single_scattering: bool,
+    /// Render the `volume` scene's density and temperature grids as fire (`--fire`).
+    pub fire: bool,
+    /// Kelvin per unit of the fire's temperature grid (`--fire-temperature <kelvin>`).
+    pub fire_temperature: f64,
+    /// Emitted radiance of fire at 1000 K (`--fire-intensity <scale>`).
+    pub fire_intensity: f64,
+    /// PLY model rendered by the `ply` scene (`--ply <file.ply>`).
+    pub ply: Option<String>,
+    /// Simplify the `ply` scene's model to at most this many faces at load (`--simplify <faces>`).
+    pub simplify: Option<usize>,
+    /// Largest angle in degrees between faces smoothed over when a model has no
+    /// normals of its own (`--crease-angle <degrees>`).
+    pub crease_angle: f64,
+    /// Height map displacing the ground of the `terrain` scene (`--height-map <image>`).
+    pub height_map: Option<String>,
+    /// Sun elevations in degrees the `terrain` scene's sky passes through over the
+    /// frames (`--sun-elevation <deg,deg,...>`).
+    pub sun_elevations: Option<Vec<f64>>,
+    /// Directories searched for textures and other assets (`--asset-dir <dir>`, repeatable).
+    pub asset_dirs: Vec<String>,
+    /// Material replacements (`--override-material <type>=<material>`, repeatable).
+    pub material_overrides: Vec<OverrideRule>,
+    /// Replace all surface materials with matte gray, keeping lights (`--clay`).
+    pub clay: bool,
+    /// Planes cutting away the scene on the side their normal points to
+    /// (`--clip x,y,z,nx,ny,nz`, repeatable).
+    pub clip_planes: Vec<ClipPlane>,
+    /// Close off cut glass with its own material or another (`--clip-cap self|<material>`).
+    pub clip_cap: Option<CapMaterial>,
+    /// Point the camera at the whole scene, keeping its viewing direction (`--auto-frame`).
+    pub auto_frame: bool,
+    /// Set the exposure from a quick preview's average luminance (`--auto-exposure`).
+    pub auto_exposure: bool,
+    /// Stop rendering after this many rays and save the partial image (`--max-rays <n>`).
+    pub max_rays: Option<u64>,
+    /// Stop rendering after this many seconds and save the partial image
+    /// (`--max-time <seconds>`).
+    pub max_time: Option<f64>,
+    /// Pixel re-traced by the `inspect` subcommand.
+    pub inspect: Option<(u32, u32)>,
+    /// Pixel queried by the `pick` subcommand.
+    pub pick: Option<(u32, u32)>,
+    /// List the named objects of the scene (`objects` subcommand).
+    pub list_objects: bool,
+    /// Reference and test images of the `compare` subcommand.
+    pub compare: Option<(String, String)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scene: "many_balls".to_string(),
+            integrator: "path".to_string(),
+            light_sampling: LightSampling::Mis,
+            ao_distance: None,
+            bounce_aovs: false,
+            cryptomatte: false,
+            rr_start_bounce: DEFAULT_RR_START_BOUNCE,
+            max_splits: DEFAULT_MAX_SPLITS,
+            regularization_angle: None,
+            material_family: MaterialFamily::Metal,
+            cornell_contents: CornellContents::Sphere,
+            smoke: false,
+            cornell_light: None,
+            cornell_walls: None,
+            light_selection: LightSelection::Area,
+            probes: Vec::new(),
+            dump_pixels: Vec::new(),
+            dump_count: 4,
+            glare: None,
+            bracket: Vec::new(),
+            chromatic_aberration: 0.0,
+            vignetting: 0.0,
+            nan_check: false,
+            bvh_heatmap: false,
+            contact_sheet: false,
+            seed: None,
+            scatter_seed: None,
+            pass: None,
+            frames: None,
+            animation: None,
+            fps: 24,
+            http: None,
+            bvh_cache: None,
+            metadata_json: false,
+            volume: None,
+            volume_density: 1.0,
+            cloud: false,
+            single_scattering: false,
+            fire: false,
+            fire_temperature: 1000.0,
+            fire_intensity: 1.0,
+            ply: None,
+            simplify: None,
+            crease_angle: 30.0,
+            height_map: None,
+            sun_elevations: None,
+            asset_dirs: Vec::new(),
+            material_overrides: Vec::new(),
+            clay: false,
+            clip_planes: Vec::new(),
+            clip_cap: None,
+            auto_frame: false,
+            auto_exposure: false,
+            max_rays: None,
+            max_time: None,
+            color_space: None,
+            gamma: None,
+            lut: None,
+            tiled_exr: false,
+            max_memory: None,
+            bvh_builder: BvhBuilder::Median,
+            accelerator: Accelerator::Bvh,
+            inspect: None,
+            pick: None,
+            list_objects: false,
+            compare: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut positional = Vec::new();
+        let mut iter = args.iter().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--light-sampling" => options.light_sampling = parse_value(arg, iter.next())?,
+                "--ao" => options.ao_distance = Some(parse_value(arg, iter.next())?),
+                "--bounce-aovs" => options.bounce_aovs = true,
+                "--cryptomatte" => options.cryptomatte = true,
+                "--rr-start" => options.rr_start_bounce = parse_value(arg, iter.next())?,
+                "--max-splits" => options.max_splits = parse_value(arg, iter.next())?,
+                "--regularize" => {
+                    options.regularization_angle = Some(parse_value(arg, iter.next())?)
+                }
+                "--family" => options.material_family = parse_value(arg, iter.next())?,
+                "--contents" => options.cornell_contents = parse_value(arg, iter.next())?,
+                "--smoke" => options.smoke = true,
+                "--cornell-light" => options.cornell_light = Some(parse_light(arg, iter.next())?),
+                "--walls" => options.cornell_walls = Some(parse_walls(arg, iter.next())?),
+                "--light-selection" => options.light_selection = parse_value(arg, iter.next())?,
+                "--probe" => options.probes.push(parse_point(arg, iter.next())?),
+                "--dump-path" => options.dump_pixels.push(parse_pixel(arg, iter.next())?),
+                "--bvh-builder" => options.bvh_builder = parse_value(arg, iter.next())?,
+                "--accelerator" => options.accelerator = parse_value(arg, iter.next())?,
+                "--bvh-cache" => options.bvh_cache = Some(parse_value(arg, iter.next())?),
+                "--metadata-json" => options.metadata_json = true,
+                "--volume" => options.volume = Some(parse_value(arg, iter.next())?),
+                "--volume-density" => options.volume_density = parse_value(arg, iter.next())?,
+                "--cloud" => options.cloud = true,
+                "--single-scattering" => options.single_scattering = true,
+                "--fire" => options.fire = true,
+                "--fire-temperature" => options.fire_temperature = parse_value(arg, iter.next())?,
+                "--fire-intensity" => options.fire_intensity = parse_value(arg, iter.next())?,
+                "--ply" => options.ply = Some(parse_value(arg, iter.next())?),
+                "--simplify" => options.simplify = Some(parse_value(arg, iter.next())?),
+                "--crease-angle" => options.crease_angle = parse_value(arg, iter.next())?,
+                "--height-map" => options.height_map = Some(parse_value(arg, iter.next())?),
+                "--sun-elevation" => {
+                    options.sun_elevations = Some(parse_components(arg, iter.next())?)
+                }
+                "--asset-dir" => options.asset_dirs.push(parse_value(arg, iter.next())?),
+                "--override-material" => options
+                    .material_overrides
+                    .push(parse_value(arg, iter.next())?),
+                "--clip" => options.clip_planes.push(parse_value(arg, iter.next())?),
+                "--clip-cap" => options.clip_cap = Some(parse_value(arg, iter.next())?),
+                "--auto-frame" => options.auto_frame = true,
+                "--auto-exposure" => options.auto_exposure = true,
+                "--clay" => options.clay = true,
+                "--seed" => options.seed = Some(parse_value(arg, iter.next())?),
+                "--scatter-seed" => options.scatter_seed = Some(parse_value(arg, iter.next())?),
+                "--pass" => options.pass = Some(parse_value(arg, iter.next())?),
+                "--frames" => options.frames = Some(parse_value(arg, iter.next())?),
+                "--animation" => options.animation = Some(parse_value(arg, iter.next())?),
+                "--fps" => options.fps = parse_value(arg, iter.next())?,
+                "--http" => options.http = Some(parse_value(arg, iter.next())?),
+                "--max-rays" => options.max_rays = Some(parse_value(arg, iter.next())?),
+                "--max-time" => {
+                    let seconds: f64 = parse_value(arg, iter.next())?;
+                    if !(seconds.is_finite() && seconds >= 0.0) {
+                        return Err(format!("Invalid value '{}' for option '{}'", seconds, arg));
+                    }
+                    options.max_time = Some(seconds);
+                }
+                "--color-space" => options.color_space = Some(parse_value(arg, iter.next())?),
+                "--gamma" => {
+                    let gamma: f64 = parse_value(arg, iter.next())?;
+                    if !(gamma.is_finite() && gamma > 0.0) {
+                        return Err(format!("Invalid value '{}' for option '{}'", gamma, arg));
+                    }
+                    options.gamma = Some(gamma);
+                }
+                "--lut" => options.lut = Some(parse_value(arg, iter.next())?),
+                "--tiled-exr" => options.tiled_exr = true,
+                "--max-memory" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("Option '{}' requires a value", arg))?;
+                    options.max_memory = Some(memory::parse_size(value).ok_or_else(|| {
+                        format!("Invalid value '{}' for option '{}'", value, arg)
+                    })?);
+                }
+                "--nan-check" => options.nan_check = true,
+                "--bvh-heatmap" => options.bvh_heatmap = true,
+                "--contact-sheet" => options.contact_sheet = true,
+                "--glare" => options.glare = Some(parse_value(arg, iter.next())?),
+                "--bracket" => options.bracket = parse_components(arg, iter.next())?,
+                "--chromatic-aberration" => {
+                    options.chromatic_aberration = parse_value(arg, iter.next())?
+                }
+                "--vignetting" => options.vignetting = parse_value(arg, iter.next())?,
+                "--dump-count" => options.dump_count = parse_value(arg, iter.next())?,
+                flag if flag.starts_with("--") => {
+                    return Err(format!("Unknown option '{}'", flag));
+                }
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        let mut positional = positional.into_iter().peekable();
+        if positional.next_if(|arg| arg == "inspect").is_some() {
+            options.scene = positional.next().ok_or("Usage: inspect <scene> <i,j>")?;
+            options.inspect = Some(parse_pixel("inspect", positional.next().as_ref())?);
+        } else if positional.next_if(|arg| arg == "pick").is_some() {
+            options.scene = positional.next().ok_or("Usage: pick <scene> <i,j>")?;
+            options.pick = Some(parse_pixel("pick", positional.next().as_ref())?);
+        } else if positional.next_if(|arg| arg == "objects").is_some() {
+            options.scene = positional.next().ok_or("Usage: objects <scene>")?;
+            options.list_objects = true;
+        } else if positional.next_if(|arg| arg == "compare").is_some() {
+            let usage = "Usage: compare <reference.png> <test.png>";
+            let reference = positional.next().ok_or(usage)?;
+            options.compare = Some((reference, positional.next().ok_or(usage)?));
+        } else if let Some(scene) = positional.next() {
+            options.scene = scene;
+        }
+        if let Some(integrator) = positional.next() {
+            options.integrator = integrator;
+        }
+        if let Some(extra) = positional.next() {
+            return Err(format!("Unexpected argument '{}'", extra));
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_point(flag: &str, value: Option<&String>) -> Result<Point3, String> {
+    match parse_components(flag, value)?[..] {
+        [x, y, z] => Ok(Point3::new(x, y, z)),
+        _ => Err(format!("Option '{}' expects x,y,z", flag)),
+    }
+}
+
+fn parse_pixel(flag: &str, value: Option<&String>) -> Result<(u32, u32), String> {
+    match parse_components(flag, value)?[..] {
+        [i, j] => Ok((i, j)),
+        _ => Err(format!("Option '{}' expects i,j", flag)),
+    }
+}
+
+fn parse_light(flag: &str, value: Option<&String>) -> Result<(f64, f64), String> {
+    match parse_components(flag, value)?[..] {
+        [scale, radiance] => Ok((scale, radiance)),
+        _ => Err(format!("Option '{}' expects scale,radiance", flag)),
+    }
+}
+
+fn parse_walls(flag: &str, value: Option<&String>) -> Result<(Color, Color), String> {
+    match parse_components(flag, value)?[..] {
+        [lr, lg, lb, rr, rg, rb] => Ok((Color::new(lr, lg, lb), Color::new(rr, rg, rb))),
+        _ => Err(format!("Option '{}' expects r,g,b,r,g,b", flag)),
+    }
+}
+
+/// Parses a comma-separated list such as `1,2,3`.
+fn parse_components<T: std::str::FromStr>(
+    flag: &str,
+    value: Option<&String>,
+) -> Result<Vec<T>, String> {
+    let value = value.ok_or_else(|| format!("Option '{}' requires a value", flag))?;
+    value
+        .split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid value '{}' for option '{}'", value, flag))
+}
+
+fn parse_value<T>(flag: &str, value: Option<&String>) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| format!("Option '{}' requires a value", flag))?;
+    value
+        .parse()
+        .map_err(|e| format!("Invalid value '{}' for option '{}': {}", value, flag, e))
+}
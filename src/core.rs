@@ -1,7 +1,25 @@
 pub mod aabb;
+pub mod animation;
+pub mod assets;
+pub mod blackbody;
 pub mod camera;
+pub mod color_space;
+pub mod cryptomatte;
+pub mod film;
+pub mod fnv;
+pub mod glare;
+pub mod heatmap;
+pub mod image_compare;
 pub mod interaction;
 pub mod interval;
+pub mod lens_effects;
+pub mod lut;
+pub mod memory;
+pub mod metadata;
+pub mod nanovdb;
 pub mod onb;
+pub mod ply;
 pub mod ray;
+pub mod tiled_exr;
+pub mod transform;
 pub mod vec3;
@@ -67,7 +67,12 @@ impl Aabb {
         }
     }
 
-    pub fn hit(&self, ray: &Ray, mut ray_t: Interval) -> bool {
+    pub fn hit(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.clip(ray, ray_t).is_some()
+    }
+
+    /// The part of `ray_t` during which the ray is inside the box, if any.
+    pub fn clip(&self, ray: &Ray, mut ray_t: Interval) -> Option<Interval> {
         for axis in 0..3 {
             let ax = self.axis_interval(axis);
             let ray_dir = match axis {
@@ -97,10 +102,10 @@ impl Aabb {
             }
 
             if ray_t.max <= ray_t.min {
-                return false;
+                return None;
             }
         }
-        true
+        Some(ray_t)
     }
 
     pub fn merge(&self, other: &Self) -> Self {
@@ -0,0 +1,183 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbImage};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// GIF quantization speed, from 1 (best palette, slowest) to 30.
+const GIF_SPEED: i32 = 10;
+
+/// Container that rendered frames are assembled into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// Animated PNG: lossless, true color, played by browsers.
+    Apng,
+    /// GIF, limited to 256 colors per frame but played everywhere.
+    Gif,
+    /// H.264 video encoded by an `ffmpeg` found on the path; needs the `ffmpeg`
+    /// feature.
+    Mp4,
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Apng => "png",
+            Self::Gif => "gif",
+            Self::Mp4 => "mp4",
+        }
+    }
+}
+
+impl std::str::FromStr for AnimationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "apng" => Ok(Self::Apng),
+            "gif" => Ok(Self::Gif),
+            "mp4" => Ok(Self::Mp4),
+            _ => Err(format!(
+                "Unknown animation format '{}'. Available: apng, gif, mp4",
+                s
+            )),
+        }
+    }
+}
+
+/// Assembles the images `frames`, in order and all of one size, into an animation
+/// at `fps` frames per second that loops forever. Frames are read one at a time,
+/// so long sequences need not fit in memory.
+pub fn assemble(
+    frames: &[String],
+    output: &str,
+    format: AnimationFormat,
+    fps: u32,
+) -> Result<(), String> {
+    if frames.is_empty() {
+        return Err("No frames to assemble".to_string());
+    }
+    let fps = fps.max(1);
+    let (width, height) = image::image_dimensions(&frames[0]).map_err(|e| e.to_string())?;
+    let load = |filename: &String| -> Result<RgbImage, String> {
+        let image = image::open(filename)
+            .map_err(|e| format!("{}: {}", filename, e))?
+            .to_rgb8();
+        if image.dimensions() != (width, height) {
+            return Err(format!(
+                "{} is {}x{}, unlike the first frame's {}x{}",
+                filename,
+                image.width(),
+                image.height(),
+                width,
+                height
+            ));
+        }
+        Ok(image)
+    };
+
+    match format {
+        AnimationFormat::Apng => {
+            let file = File::create(output).map_err(|e| e.to_string())?;
+            let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .set_animated(frames.len() as u32, 0)
+                .map_err(|e| e.to_string())?;
+            encoder
+                .set_frame_delay(1, fps.min(u16::MAX as u32) as u16)
+                .map_err(|e| e.to_string())?;
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            for filename in frames {
+                writer
+                    .write_image_data(load(filename)?.as_raw())
+                    .map_err(|e| e.to_string())?;
+            }
+            writer.finish().map_err(|e| e.to_string())
+        }
+        AnimationFormat::Gif => {
+            let file = File::create(output).map_err(|e| e.to_string())?;
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| e.to_string())?;
+            let delay = Delay::from_numer_denom_ms(1000, fps);
+            for filename in frames {
+                let rgba = image::DynamicImage::ImageRgb8(load(filename)?).to_rgba8();
+                encoder
+                    .encode_frame(Frame::from_parts(rgba, 0, 0, delay))
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        AnimationFormat::Mp4 => encode_video(frames, output, fps, (width, height), load),
+    }
+}
+
+/// Pipes the frames as raw RGB to `ffmpeg`, which encodes them as H.264 in the
+/// widely playable yuv420p layout.
+#[cfg(feature = "ffmpeg")]
+fn encode_video(
+    frames: &[String],
+    output: &str,
+    fps: u32,
+    (width, height): (u32, u32),
+    load: impl Fn(&String) -> Result<RgbImage, String>,
+) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+        ])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-r", &fps.to_string(), "-i", "-"])
+        // H.264 in yuv420p needs even dimensions
+        .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", output])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run ffmpeg: {}", e))?;
+    // Closing stdin ends the input; on an error ffmpeg is stopped instead, but
+    // reaped either way before returning
+    let written = match ffmpeg.stdin.take() {
+        Some(mut stdin) => frames.iter().try_for_each(|filename| {
+            stdin
+                .write_all(load(filename)?.as_raw())
+                .map_err(|e| format!("Cannot write to ffmpeg: {}", e))
+        }),
+        None => Err("Cannot write to ffmpeg".to_string()),
+    };
+    if written.is_err() {
+        let _ = ffmpeg.kill();
+    }
+    let status = ffmpeg.wait().map_err(|e| e.to_string());
+    written?;
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("ffmpeg failed with {}", status))
+    }
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+fn encode_video(
+    _frames: &[String],
+    _output: &str,
+    _fps: u32,
+    _size: (u32, u32),
+    _load: impl Fn(&String) -> Result<RgbImage, String>,
+) -> Result<(), String> {
+    Err(
+        "Video output needs a build with the ffmpeg feature: cargo build --features ffmpeg"
+            .to_string(),
+    )
+}
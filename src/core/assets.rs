@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Extra directories searched for assets, set once from the command line.
+static SEARCH_PATHS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Adds directories searched for assets (`--asset-dir`), in priority order.
+pub fn set_search_paths(dirs: Vec<PathBuf>) {
+    let _ = SEARCH_PATHS.set(dirs);
+}
+
+/// Candidate locations for an asset: the path itself (absolute, or relative to the
+/// working directory), then every search path, then the crate root that ships the
+/// bundled assets such as `earthmap.jpg`.
+fn candidates(name: &str) -> Vec<PathBuf> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut candidates = vec![path.to_path_buf()];
+    if let Some(dirs) = SEARCH_PATHS.get() {
+        candidates.extend(dirs.iter().map(|dir| dir.join(path)));
+    }
+    candidates.push(Path::new(env!("CARGO_MANIFEST_DIR")).join(path));
+    candidates
+}
+
+/// Finds an asset file, or reports every location that was tried.
+pub fn resolve(name: &str) -> Result<PathBuf, String> {
+    let candidates = candidates(name);
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(format!(
+            "asset '{}' not found (searched {})",
+            name,
+            candidates
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::core::vec3::Color;
+use std::sync::OnceLock;
+
+/// Temperatures covered by the color table, in Kelvin; colder emitters use the
+/// color of the first entry, hotter ones that of the last.
+const MIN_TEMPERATURE: f64 = 500.0;
+const MAX_TEMPERATURE: f64 = 20000.0;
+const TABLE_STEP: f64 = 50.0;
+
+/// Second radiation constant hc/k, in nm K.
+const C2: f64 = 1.4388e7;
+
+/// Linear sRGB color of a blackbody at `kelvin`, normalized to unit luminance:
+/// deep red below 1000 K through orange and white (about 6500 K) to blue.
+pub fn blackbody(kelvin: f64) -> Color {
+    static TABLE: OnceLock<Vec<Color>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let entries = ((MAX_TEMPERATURE - MIN_TEMPERATURE) / TABLE_STEP) as usize + 1;
+        (0..entries)
+            .map(|n| planck_color(MIN_TEMPERATURE + n as f64 * TABLE_STEP))
+            .collect()
+    });
+
+    let x = (kelvin.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE) - MIN_TEMPERATURE) / TABLE_STEP;
+    let n = (x as usize).min(table.len() - 2);
+    let f = x - n as f64;
+    (1.0 - f) * table[n] + f * table[n + 1]
+}
+
+/// Integrates Planck's law against the CIE 1931 color matching functions over the
+/// visible range, then converts to linear sRGB with out-of-gamut channels clipped.
+fn planck_color(kelvin: f64) -> Color {
+    let mut xyz = Color::zeros();
+    for lambda in (380..=780).step_by(5) {
+        let lambda = lambda as f64;
+        // Relative spectral radiance; constant factors cancel in the normalization
+        let radiance = 1.0 / (lambda.powi(5) * ((C2 / (lambda * kelvin)).exp() - 1.0));
+        xyz += radiance * cie_xyz(lambda);
+    }
+    let xyz = xyz / xyz.y;
+
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+    .map(|c| c.max(0.0))
+}
+
+/// Multi-lobe Gaussian fit of the CIE 1931 standard observer (Wyman et al. 2013).
+fn cie_xyz(lambda: f64) -> Color {
+    let lobe = |mu: f64, below: f64, above: f64| {
+        let sigma = if lambda < mu { below } else { above };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Color::new(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
@@ -1,7 +1,13 @@
-use crate::core::ray::Ray;
+use crate::core::aabb::Aabb;
+use crate::core::color_space::OutputColorSpace;
+use crate::core::interval::Interval;
+use crate::core::lut::Lut3d;
+use crate::core::ray::{Ray, RayDifferentials};
 use crate::core::vec3::Vec3Ext;
 use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::SHUTTER;
 use crate::sampling::random::{degrees_to_radians, random_double};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Camera {
@@ -20,6 +26,16 @@ pub struct Camera {
     pub defocus_angle: f64,
     pub focus_dist: f64,
 
+    /// Linear scale applied to radiance when the image is written.
+    pub exposure: f64,
+    /// Primaries and transfer function of the written image.
+    pub color_space: OutputColorSpace,
+    /// Look baked into rendered images, though not into ambient occlusion or bakes.
+    pub lut: Option<Arc<Lut3d>>,
+    /// Times camera rays are cast at, within `SHUTTER`; a slice of it for one frame
+    /// of a sequence.
+    pub shutter: Interval,
+
     // Internal computed values
     pub image_height: u32,
     pub center: Point3,
@@ -47,6 +63,10 @@ impl Camera {
             vup: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            exposure: 1.0,
+            color_space: OutputColorSpace::default(),
+            lut: None,
+            shutter: SHUTTER,
 
             // Dummy initialization, call initialize() before use
             image_height: 0,
@@ -99,6 +119,42 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
+    /// Aims at the center of `bbox` along the current viewing direction and moves back
+    /// until the box's bounding sphere fits the frame, whatever the scene's scale.
+    pub fn frame_bounds(&mut self, bbox: &Aabb) {
+        let center = Point3::new(
+            0.5 * (bbox.x.min + bbox.x.max),
+            0.5 * (bbox.y.min + bbox.y.max),
+            0.5 * (bbox.z.min + bbox.z.max),
+        );
+        let radius = 0.5 * Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).norm();
+        if !radius.is_finite() || radius <= 0.0 {
+            return;
+        }
+
+        // The narrower of the vertical and horizontal fields of view bounds the fit
+        let half_vertical = degrees_to_radians(self.vfov) / 2.0;
+        let half_horizontal = (half_vertical.tan() * self.aspect_ratio).atan();
+        let distance = radius / half_vertical.min(half_horizontal).sin();
+
+        let direction = (self.lookfrom - self.lookat).normalize();
+        self.lookat = center;
+        self.lookfrom = center + direction * distance;
+        self.focus_dist = distance;
+        self.initialize();
+    }
+
+    /// Copy of the camera exposing frame `frame` of `frames`, an equal slice of the
+    /// shutter, so that a sequence of them steps through the scene's motion.
+    pub fn frame(&self, frame: u32, frames: u32) -> Self {
+        let length = SHUTTER.size() / frames.max(1) as f64;
+        let start = SHUTTER.min + length * frame as f64;
+        Self {
+            shutter: Interval::new(start, start + length),
+            ..self.clone()
+        }
+    }
+
     pub fn get_ray(&self, i: u32, j: u32) -> Ray {
         let offset = self.sample_square();
         let pixel_sample = self.pixel00_loc
@@ -112,9 +168,67 @@ impl Camera {
         };
 
         let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random_double(); // Assume shutter open [0,1]
+        let ray_time = self.shutter.min + self.shutter.size() * random_double();
+
+        // Offset rays through the same spot of the next pixels, from the same point
+        // of the lens, narrowed to the spacing of the samples within a pixel
+        let mut ray = Ray::new(ray_origin, ray_direction, ray_time).with_differentials(Some(
+            RayDifferentials {
+                rx_origin: ray_origin,
+                rx_direction: ray_direction + self.pixel_delta_u,
+                ry_origin: ray_origin,
+                ry_direction: ray_direction + self.pixel_delta_v,
+            },
+        ));
+        ray.scale_differentials(1.0 / (self.samples_per_pixel.max(1) as f64).sqrt());
+        ray
+    }
+
+    /// Ray through the center of pixel (i, j) from the lens center, without jitter,
+    /// defocus or motion, for queries that need the same answer every time.
+    pub fn center_ray(&self, i: u32, j: u32) -> Ray {
+        let pixel_center =
+            self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
+        Ray::new(self.center, pixel_center - self.center, 0.0)
+    }
+
+    /// Projects a world-space point onto the image plane through the lens center.
+    /// Returns the pixel coordinates, or None if the point is behind the camera or off-screen.
+    pub fn project(&self, p: &Point3) -> Option<(u32, u32)> {
+        let d = p - self.center;
+        let depth = -d.dot(&self.w);
+        if depth <= 0.0 {
+            return None;
+        }
+
+        // Intersect with the focus plane and express relative to the viewport corner
+        let on_plane = self.center + d * (self.focus_dist / depth);
+        let corner = self.pixel00_loc - 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        let rel = on_plane - corner;
+
+        let x = rel.dot(&self.pixel_delta_u) / self.pixel_delta_u.norm_squared();
+        let y = rel.dot(&self.pixel_delta_v) / self.pixel_delta_v.norm_squared();
+
+        if x < 0.0 || y < 0.0 || x >= self.image_width as f64 || y >= self.image_height as f64 {
+            return None;
+        }
+
+        Some((x as u32, y as u32))
+    }
+
+    /// Importance of the pinhole camera for a direction making angle theta with the view axis.
+    /// Normalized so that it integrates to one over the whole image plane.
+    pub fn importance(&self, cos_theta: f64) -> f64 {
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        // Image plane area at unit distance from the lens
+        let film_width = self.pixel_delta_u.norm() * self.image_width as f64;
+        let film_height = self.pixel_delta_v.norm() * self.image_height as f64;
+        let area = film_width * film_height / (self.focus_dist * self.focus_dist);
 
-        Ray::new(ray_origin, ray_direction, ray_time)
+        1.0 / (area * cos_theta.powi(4))
     }
 
     fn sample_square(&self) -> Vec3 {
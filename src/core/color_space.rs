@@ -0,0 +1,103 @@
+use crate::core::vec3::Color;
+use nalgebra::Matrix3;
+use std::str::FromStr;
+
+/// Linear Rec.709 (the renderer's working space) to ACEScg, with Bradford
+/// adaptation from the D65 white point to ACES' D60.
+#[rustfmt::skip]
+const REC709_TO_ACESCG: Matrix3<f64> = Matrix3::new(
+    0.6130973, 0.3395229, 0.0473793,
+    0.0701942, 0.9163556, 0.0134526,
+    0.0206156, 0.1095698, 0.8698151,
+);
+
+/// RGB primaries of the written image. Scenes are rendered in linear Rec.709,
+/// which shares its primaries with sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primaries {
+    Rec709,
+    /// The AP1 primaries of ACEScg, for compositing in an ACES pipeline.
+    AcesCg,
+}
+
+/// Encoding of linear values into the written image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    /// Values written as they are.
+    Linear,
+    /// A pure power curve, raising values to `1 / gamma`.
+    Gamma(f64),
+    /// The piecewise sRGB curve (IEC 61966-2-1).
+    Srgb,
+    /// The Rec.709 camera curve (ITU-R BT.709).
+    Rec709,
+}
+
+impl TransferFunction {
+    pub fn encode(&self, linear: f64) -> f64 {
+        if linear <= 0.0 {
+            return 0.0;
+        }
+        match *self {
+            Self::Linear => linear,
+            Self::Gamma(gamma) => linear.powf(1.0 / gamma),
+            Self::Srgb if linear <= 0.0031308 => 12.92 * linear,
+            Self::Srgb => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+            Self::Rec709 if linear < 0.018 => 4.5 * linear,
+            Self::Rec709 => 1.099 * linear.powf(0.45) - 0.099,
+        }
+    }
+}
+
+/// Color space images are converted to when written: primaries, then transfer
+/// function. The default is the books' gamma 2 on Rec.709 primaries, close to
+/// sRGB but a little darker in the shadows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputColorSpace {
+    pub primaries: Primaries,
+    pub transfer: TransferFunction,
+}
+
+impl Default for OutputColorSpace {
+    fn default() -> Self {
+        Self {
+            primaries: Primaries::Rec709,
+            transfer: TransferFunction::Gamma(2.0),
+        }
+    }
+}
+
+impl OutputColorSpace {
+    /// Same primaries with a pure power curve of `gamma` instead.
+    pub fn with_gamma(mut self, gamma: f64) -> Self {
+        self.transfer = TransferFunction::Gamma(gamma);
+        self
+    }
+
+    /// Converts a linear Rec.709 color to this space, encoded but not clamped.
+    pub fn encode(&self, linear: Color) -> Color {
+        let converted = match self.primaries {
+            Primaries::Rec709 => linear,
+            Primaries::AcesCg => REC709_TO_ACESCG * linear,
+        };
+        converted.map(|c| self.transfer.encode(c))
+    }
+}
+
+impl FromStr for OutputColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (primaries, transfer) = match s {
+            "srgb" => (Primaries::Rec709, TransferFunction::Srgb),
+            "rec709" => (Primaries::Rec709, TransferFunction::Rec709),
+            "linear" => (Primaries::Rec709, TransferFunction::Linear),
+            "acescg" => (Primaries::AcesCg, TransferFunction::Linear),
+            _ => return Err(format!("Unknown color space '{}'", s)),
+        };
+        Ok(Self {
+            primaries,
+            transfer,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+use crate::core::metadata::{self, json_string};
+use exr::prelude::{
+    AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, Layer, LayerAttributes,
+    SmallVec, Text, WritableImage,
+};
+use std::collections::BTreeMap;
+
+/// ID/coverage pairs kept per pixel, two per RGBA channel group.
+pub const RANKS: usize = 6;
+
+/// One Cryptomatte layer, e.g. `CryptoObject`: for every pixel, the names covering
+/// it with the fraction of the pixel each covers, most coverage first. Names are
+/// stored as MurmurHash3 IDs, listed with their names in a manifest.
+#[derive(Debug, Clone)]
+pub struct Cryptomatte {
+    name: String,
+    width: u32,
+    height: u32,
+    ranks: Vec<Vec<(f32, f32)>>,
+    manifest: BTreeMap<String, f32>,
+}
+
+impl Cryptomatte {
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            ranks: vec![Vec::new(); (width * height) as usize],
+            manifest: BTreeMap::new(),
+        }
+    }
+
+    /// Records the `coverage` of pixel (i, j) by each name, keeping the `RANKS`
+    /// largest.
+    pub fn set(&mut self, i: u32, j: u32, coverage: &BTreeMap<String, f64>) {
+        let mut ranked: Vec<(f32, f32)> = coverage
+            .iter()
+            .map(|(name, &amount)| {
+                let id = *self
+                    .manifest
+                    .entry(name.clone())
+                    .or_insert_with(|| name_id(name));
+                (id, amount as f32)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(RANKS);
+        self.ranks[(j * self.width + i) as usize] = ranked;
+    }
+
+    pub fn name_count(&self) -> usize {
+        self.manifest.len()
+    }
+
+    /// The `<name>00` to `<name>02` channel groups: rank 2n in R (ID) and G
+    /// (coverage), rank 2n + 1 in B and A.
+    fn channels(&self) -> Vec<AnyChannel<FlatSamples>> {
+        let mut channels = Vec::new();
+        for group in 0..RANKS / 2 {
+            for (k, channel) in ["R", "G", "B", "A"].iter().enumerate() {
+                let (rank, coverage) = (2 * group + k / 2, k % 2 == 1);
+                let samples = self
+                    .ranks
+                    .iter()
+                    .map(|ranked| match ranked.get(rank) {
+                        Some(&(id, amount)) => {
+                            if coverage {
+                                amount
+                            } else {
+                                id
+                            }
+                        }
+                        None => 0.0,
+                    })
+                    .collect();
+                channels.push(AnyChannel::new(
+                    format!("{}{:02}.{}", self.name, group, channel).as_str(),
+                    FlatSamples::F32(samples),
+                ));
+            }
+        }
+        channels
+    }
+
+    /// Header attributes describing the layer, under a key derived from its name.
+    fn metadata(&self) -> Vec<(String, String)> {
+        let key = format!("{:08x}", murmur3_32(self.name.as_bytes()));
+        let prefix = format!("cryptomatte/{}", &key[..7]);
+        let entries: Vec<String> = self
+            .manifest
+            .iter()
+            .map(|(name, id)| format!("{}:\"{:08x}\"", json_string(name), id.to_bits()))
+            .collect();
+        vec![
+            (format!("{}/name", prefix), self.name.clone()),
+            (format!("{}/hash", prefix), "MurmurHash3_32".to_string()),
+            (
+                format!("{}/conversion", prefix),
+                "uint32_to_float32".to_string(),
+            ),
+            (
+                format!("{}/manifest", prefix),
+                format!("{{{}}}", entries.join(",")),
+            ),
+        ]
+    }
+}
+
+/// Writes Cryptomatte layers of the same size into one OpenEXR file, as read by
+/// the Cryptomatte plugins of Nuke, Fusion, Blender and others.
+pub fn save(layers: &[Cryptomatte], filename: &str) -> Result<(), String> {
+    let Some(first) = layers.first() else {
+        return Ok(());
+    };
+
+    let mut channels = Vec::new();
+    let mut attributes = LayerAttributes::default();
+    for layer in layers {
+        channels.extend(layer.channels());
+        for (key, value) in layer.metadata() {
+            attributes.other.insert(
+                Text::from(key.as_str()),
+                AttributeValue::Text(Text::from(value.as_str())),
+            );
+        }
+    }
+
+    metadata::add_exr_attributes(&mut attributes);
+    metadata::save_sidecar(filename);
+
+    let image = Image::from_layer(Layer::new(
+        (first.width as usize, first.height as usize),
+        attributes,
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    ));
+    image
+        .write()
+        .to_file(filename)
+        .map_err(|e| format!("{}: {}", filename, e))
+}
+
+/// Float ID of a name: its MurmurHash3 bits as a float, nudged away from the
+/// exponents of denormals, infinities and NaNs.
+pub fn name_id(name: &str) -> f32 {
+    let mut hash = murmur3_32(name.as_bytes());
+    let exponent = (hash >> 23) & 0xff;
+    if exponent == 0 || exponent == 0xff {
+        hash ^= 1 << 23;
+    }
+    f32::from_bits(hash)
+}
+
+/// 32-bit MurmurHash3 with seed 0, the hash Cryptomatte specifies.
+fn murmur3_32(key: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut hash: u32 = 0;
+    let mut chunks = key.chunks_exact(4);
+    for chunk in &mut chunks {
+        hash ^= mix(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .rev()
+            .fold(0u32, |k, &byte| (k << 8) | byte as u32);
+        hash ^= mix(k);
+    }
+
+    hash ^= key.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
@@ -0,0 +1,135 @@
+use crate::core::color_space::OutputColorSpace;
+use crate::core::lut::Lut3d;
+use crate::core::metadata;
+use crate::core::vec3::Color;
+use image::{ImageBuffer, Rgb, RgbImage};
+use std::sync::Arc;
+
+/// HDR accumulation buffer holding the summed samples of one image channel.
+#[derive(Debug, Clone)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Color>,
+    /// Linear scale applied when converting to an image.
+    exposure: f64,
+    /// Largest value of a channel, after exposure, when converting to an image.
+    clamp: Option<f64>,
+    /// Primaries and transfer function the image is written in.
+    color_space: OutputColorSpace,
+    /// Look applied after encoding to the color space.
+    lut: Option<Arc<Lut3d>>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::zeros(); (width * height) as usize],
+            exposure: 1.0,
+            clamp: None,
+            color_space: OutputColorSpace::default(),
+            lut: None,
+        }
+    }
+
+    pub fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Clamps every channel to `clamp` once exposed, taming fireflies in the written
+    /// image while the film keeps the true sums.
+    pub fn with_clamp(mut self, clamp: Option<f64>) -> Self {
+        self.clamp = clamp;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: OutputColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn with_lut(mut self, lut: Option<Arc<Lut3d>>) -> Self {
+        self.lut = lut;
+        self
+    }
+
+    #[inline]
+    pub fn index(&self, i: u32, j: u32) -> usize {
+        (j * self.width + i) as usize
+    }
+
+    #[inline]
+    pub fn get(&self, i: u32, j: u32) -> Color {
+        self.pixels[self.index(i, j)]
+    }
+
+    #[inline]
+    pub fn set(&mut self, i: u32, j: u32, color: Color) {
+        let index = self.index(i, j);
+        self.pixels[index] = color;
+    }
+
+    /// Adds a contribution to the pixel, dropping non-finite values.
+    #[inline]
+    pub fn splat(&mut self, index: usize, value: Color) {
+        if value.x.is_finite() && value.y.is_finite() && value.z.is_finite() {
+            self.pixels[index] += value;
+        }
+    }
+
+    /// Sums another film of the same size into this one.
+    pub fn merge(mut self, other: Film) -> Self {
+        for (a, b) in self.pixels.iter_mut().zip(other.pixels) {
+            *a += b;
+        }
+        self
+    }
+
+    /// Converts the accumulated samples to an 8-bit image in the film's color space,
+    /// through its LUT if it has one.
+    pub fn to_image(&self, samples_per_pixel: u32) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        let max = self
+            .clamp
+            .map_or(f64::INFINITY, |clamp| clamp * samples_per_pixel as f64);
+        for (i, j, pixel) in img.enumerate_pixels_mut() {
+            *pixel = color_to_rgb(
+                (self.get(i, j) * self.exposure).map(|c| c.min(max)),
+                samples_per_pixel,
+                &self.color_space,
+                self.lut.as_deref(),
+            );
+        }
+        img
+    }
+
+    /// Writes the film to disk with the scene metadata, reporting the outcome on the
+    /// console.
+    pub fn save(&self, filename: &str, samples_per_pixel: u32) {
+        match metadata::save_image(&self.to_image(samples_per_pixel), filename) {
+            Ok(_) => println!("Image saved to {}", filename),
+            Err(e) => eprintln!("Error saving image: {}", e),
+        }
+    }
+}
+
+pub fn color_to_rgb(
+    color: Color,
+    samples_per_pixel: u32,
+    color_space: &OutputColorSpace,
+    lut: Option<&Lut3d>,
+) -> Rgb<u8> {
+    let scale = 1.0 / samples_per_pixel as f64;
+    let mut encoded = color_space.encode(color * scale);
+    if let Some(lut) = lut {
+        encoded = lut.apply(encoded);
+    }
+    let r = encoded.x.clamp(0.0, 0.999);
+    let g = encoded.y.clamp(0.0, 0.999);
+    let b = encoded.z.clamp(0.0, 0.999);
+
+    Rgb([(r * 256.0) as u8, (g * 256.0) as u8, (b * 256.0) as u8])
+}
@@ -0,0 +1,82 @@
+use crate::core::film::Film;
+use crate::core::vec3::Color;
+use std::f64::consts::PI;
+
+/// Streaks per light unless set with `with_streaks`, as from a six-bladed aperture.
+const DEFAULT_STREAKS: u32 = 6;
+/// Streak length as a fraction of the larger image side.
+const STREAK_LENGTH: f64 = 0.15;
+/// Streak reach per channel relative to green: diffraction spreads longer wavelengths
+/// further, which gives the streaks their colored fringes.
+const WAVELENGTH_SCALE: [f64; 3] = [1.2, 1.0, 0.8];
+
+/// Starburst glare around directly visible lights, the diffraction pattern a camera
+/// aperture spreads bright sources into. Applied as a post effect from the light AOV,
+/// so only emitters streak, never bright reflections.
+#[derive(Debug, Clone, Copy)]
+pub struct Glare {
+    /// Fraction of each light's energy added back as streaks.
+    strength: f64,
+    streaks: u32,
+}
+
+impl Glare {
+    pub fn new(strength: f64) -> Self {
+        Self {
+            strength,
+            streaks: DEFAULT_STREAKS,
+        }
+    }
+
+    pub fn with_streaks(mut self, streaks: u32) -> Self {
+        self.streaks = streaks.max(1);
+        self
+    }
+
+    /// Adds the streaks of every pixel of `lights` to `film`. Both hold sums of samples.
+    pub fn apply(&self, film: &mut Film, lights: &Film) {
+        let (width, height) = (film.width, film.height);
+        let length = (STREAK_LENGTH * width.max(height) as f64).max(1.0) as usize;
+
+        // Falls off with distance; each streak carries an equal share of `strength`
+        let mut falloff: Vec<f64> = (1..=length).map(|r| (r as f64).powf(-1.5)).collect();
+        let total: f64 = falloff.iter().sum();
+        let share = self.strength / self.streaks as f64;
+        falloff.iter_mut().for_each(|w| *w *= share / total);
+
+        let directions: Vec<(f64, f64)> = (0..self.streaks)
+            .map(|k| {
+                let angle = 2.0 * PI * k as f64 / self.streaks as f64 + PI / 2.0;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+
+        let mut glare = vec![Color::zeros(); (width * height) as usize];
+        for j in 0..height {
+            for i in 0..width {
+                let light = lights.get(i, j);
+                if light == Color::zeros() {
+                    continue;
+                }
+
+                for &(dx, dy) in &directions {
+                    for (r, weight) in falloff.iter().enumerate() {
+                        for (c, scale) in WAVELENGTH_SCALE.iter().enumerate() {
+                            let distance = (r + 1) as f64 * scale;
+                            let x = (i as f64 + distance * dx).round();
+                            let y = (j as f64 + distance * dy).round();
+                            if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                                continue;
+                            }
+                            glare[(y as u32 * width + x as u32) as usize][c] += light[c] * weight;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (index, value) in glare.into_iter().enumerate() {
+            film.splat(index, value);
+        }
+    }
+}
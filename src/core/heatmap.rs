@@ -0,0 +1,95 @@
+use crate::core::metadata;
+use image::{ImageBuffer, Rgb, RgbImage};
+
+/// Ramp from no work (black) through blue, cyan, green and yellow to the most (red).
+const RAMP: [[f64; 3]; 6] = [
+    [0.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0],
+];
+
+/// Fraction of pixels below the value shown as full red; the hottest few saturate
+/// so that single outliers do not wash out the rest of the image.
+const SCALE_PERCENTILE: f64 = 0.99;
+
+/// Per-pixel scalar statistics, such as traversal costs, written as a false-color
+/// image.
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    pub width: u32,
+    pub height: u32,
+    values: Vec<f64>,
+    /// Value shown as full red, if fixed rather than taken from the values.
+    fixed_scale: Option<f64>,
+}
+
+impl Heatmap {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            values: vec![0.0; (width * height) as usize],
+            fixed_scale: None,
+        }
+    }
+
+    /// Shows `scale` as full red, for values with a meaningful range of their own
+    /// such as errors between 0 and 1.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.fixed_scale = Some(scale);
+        self
+    }
+
+    pub fn set(&mut self, i: u32, j: u32, value: f64) {
+        self.values[(j * self.width + i) as usize] = value;
+    }
+
+    /// Value shown as full red: the fixed scale if set, else the `SCALE_PERCENTILE`
+    /// percentile of all pixels.
+    pub fn scale(&self) -> f64 {
+        if let Some(scale) = self.fixed_scale {
+            return scale;
+        }
+        let mut sorted = self.values.clone();
+        sorted.sort_by(f64::total_cmp);
+        let index = ((sorted.len() as f64 * SCALE_PERCENTILE) as usize).min(sorted.len() - 1);
+        sorted[index]
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len().max(1) as f64
+    }
+
+    pub fn to_image(&self) -> RgbImage {
+        let scale = self.scale();
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        for (i, j, pixel) in img.enumerate_pixels_mut() {
+            let value = self.values[(j * self.width + i) as usize];
+            *pixel = ramp(if scale > 0.0 { value / scale } else { 0.0 });
+        }
+        img
+    }
+
+    /// Writes the heatmap to disk, reporting the outcome and its scale on the console.
+    pub fn save(&self, filename: &str) {
+        match metadata::save_image(&self.to_image(), filename) {
+            Ok(_) => println!("Heatmap saved to {} (red = {:.1})", filename, self.scale()),
+            Err(e) => eprintln!("Error saving image: {}", e),
+        }
+    }
+}
+
+/// Color of `t` in [0, 1], interpolated linearly between the ramp stops.
+fn ramp(t: f64) -> Rgb<u8> {
+    let x = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+    let k = (x as usize).min(RAMP.len() - 2);
+    let f = x - k as f64;
+    let channel = |c: usize| {
+        let value = (1.0 - f) * RAMP[k][c] + f * RAMP[k + 1][c];
+        (value.clamp(0.0, 0.999) * 256.0) as u8
+    };
+    Rgb([channel(0), channel(1), channel(2)])
+}
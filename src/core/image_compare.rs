@@ -0,0 +1,489 @@
+use crate::core::heatmap::Heatmap;
+use crate::core::vec3::Color;
+use image::RgbImage;
+use nalgebra::Matrix3;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Linear sRGB to CIE XYZ, D65 white.
+#[rustfmt::skip]
+const RGB_TO_XYZ: Matrix3<f64> = Matrix3::new(
+    0.4124564, 0.3575761, 0.1804375,
+    0.2126729, 0.7151522, 0.0721750,
+    0.0193339, 0.1191920, 0.9503041,
+);
+#[rustfmt::skip]
+const XYZ_TO_RGB: Matrix3<f64> = Matrix3::new(
+     3.2404542, -1.5371385, -0.4985314,
+    -0.9692660,  1.8760108,  0.0415560,
+     0.0556434, -0.2040259,  1.0572252,
+);
+
+/// Standard deviation of the Gaussian window SSIM averages over, in pixels.
+const SSIM_SIGMA: f64 = 1.5;
+/// SSIM's stabilizing constants for values in [0, 1].
+const SSIM_C1: f64 = 0.01 * 0.01;
+const SSIM_C2: f64 = 0.03 * 0.03;
+
+/// Pixels per degree of visual angle FLIP assumes: a 0.7 m wide 4K monitor seen
+/// from 0.7 m, the default of the paper.
+const PIXELS_PER_DEGREE: f64 = 67.0;
+/// Contrast sensitivity of the opponent channels Y, Cx and Cz, as the (a, b) of the
+/// Gaussians `a sqrt(pi / b) exp(-pi^2 x^2 / b)` summed for each, x in degrees.
+const CSF: [[(f64, f64); 2]; 3] = [
+    [(1.0, 0.0047), (0.0, 1e-5)],
+    [(1.0, 0.0053), (0.0, 1e-5)],
+    [(34.1, 0.04), (13.5, 0.025)],
+];
+/// Width in degrees of the edges and points the feature detectors respond to.
+const FEATURE_WIDTH: f64 = 0.082;
+/// Exponents compressing the color and feature differences, and the knee of the
+/// color difference's remapping to [0, 1].
+const COLOR_EXPONENT: f64 = 0.7;
+const FEATURE_EXPONENT: f64 = 0.5;
+const COLOR_KNEE: f64 = 0.4;
+const COLOR_KNEE_VALUE: f64 = 0.95;
+
+/// Differences between a render and a reference of the same size.
+#[derive(Debug, Clone)]
+pub struct ImageComparison {
+    /// Root mean square error of the 8-bit channel values, scaled to [0, 1].
+    pub rmse: f64,
+    /// Mean structural similarity of the luma, 1 for identical images.
+    pub ssim: f64,
+    /// Mean FLIP error, 0 for identical images up to 1.
+    pub flip: f64,
+    /// Per-pixel FLIP error, with full red at 1.
+    pub difference: Heatmap,
+}
+
+impl ImageComparison {
+    pub fn summary(&self) -> String {
+        format!(
+            "RMSE {:.5}, SSIM {:.5}, FLIP {:.5}",
+            self.rmse, self.ssim, self.flip
+        )
+    }
+}
+
+/// Loads two images and compares `test` against `reference`.
+pub fn compare_files(reference: &str, test: &str) -> Result<ImageComparison, String> {
+    let load = |filename: &str| {
+        image::open(filename)
+            .map(|image| image.to_rgb8())
+            .map_err(|e| format!("Cannot load {}: {}", filename, e))
+    };
+    compare(&load(reference)?, &load(test)?)
+}
+
+/// Compares `test` against `reference` by RMSE, SSIM and LDR FLIP, a perceptual
+/// error for images seen alternately (Andersson et al. 2020). Both are taken to
+/// be sRGB encoded.
+pub fn compare(reference: &RgbImage, test: &RgbImage) -> Result<ImageComparison, String> {
+    if reference.dimensions() != test.dimensions() {
+        return Err(format!(
+            "Images differ in size: {}x{} and {}x{}",
+            reference.width(),
+            reference.height(),
+            test.width(),
+            test.height()
+        ));
+    }
+    let (width, height) = reference.dimensions();
+    let encoded = |image: &RgbImage| -> Vec<Color> {
+        image
+            .pixels()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64) / 255.0)
+            .collect()
+    };
+    let reference = encoded(reference);
+    let test = encoded(test);
+
+    let squared_error: f64 = reference
+        .iter()
+        .zip(&test)
+        .map(|(a, b)| (a - b).norm_squared())
+        .sum();
+    let rmse = (squared_error / (3 * reference.len()).max(1) as f64).sqrt();
+
+    let ssim = ssim(
+        &Plane::from_colors(width, height, &reference, luma),
+        &Plane::from_colors(width, height, &test, luma),
+    );
+
+    let flip_map = flip(width, height, &reference, &test);
+    let mut difference = Heatmap::new(width, height).with_scale(1.0);
+    for (index, error) in flip_map.values.iter().enumerate() {
+        difference.set(index as u32 % width, index as u32 / width, *error);
+    }
+
+    Ok(ImageComparison {
+        rmse,
+        ssim,
+        flip: difference.mean(),
+        difference,
+    })
+}
+
+/// One channel of an image.
+#[derive(Debug, Clone)]
+struct Plane {
+    width: u32,
+    height: u32,
+    values: Vec<f64>,
+}
+
+impl Plane {
+    fn from_colors(width: u32, height: u32, colors: &[Color], f: impl Fn(&Color) -> f64) -> Self {
+        Self {
+            width,
+            height,
+            values: colors.iter().map(f).collect(),
+        }
+    }
+
+    fn zip(&self, other: &Plane, f: impl Fn(f64, f64) -> f64) -> Plane {
+        Plane {
+            values: self
+                .values
+                .iter()
+                .zip(&other.values)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+            ..*self
+        }
+    }
+
+    /// Convolution with the separable kernel `horizontal` x `vertical`, both of odd
+    /// length and centered, repeating the edge pixels outside the image.
+    fn convolve(&self, horizontal: &[f64], vertical: &[f64]) -> Plane {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let pass = |values: &[f64], kernel: &[f64], step: (i64, i64)| -> Vec<f64> {
+            let radius = (kernel.len() / 2) as i64;
+            (0..w * h)
+                .map(|index| {
+                    let (i, j) = (index % w, index / w);
+                    kernel
+                        .iter()
+                        .enumerate()
+                        .map(|(k, weight)| {
+                            let offset = k as i64 - radius;
+                            let x = (i + offset * step.0).clamp(0, w - 1);
+                            let y = (j + offset * step.1).clamp(0, h - 1);
+                            weight * values[(y * w + x) as usize]
+                        })
+                        .sum()
+                })
+                .collect()
+        };
+        let rows = pass(&self.values, horizontal, (1, 0));
+        Plane {
+            values: pass(&rows, vertical, (0, 1)),
+            ..*self
+        }
+    }
+}
+
+/// Mean SSIM over Gaussian windows (Wang et al. 2004).
+fn ssim(x: &Plane, y: &Plane) -> f64 {
+    let window = gaussian(SSIM_SIGMA, (3.0 * SSIM_SIGMA).ceil() as usize);
+    let blur = |plane: &Plane| plane.convolve(&window, &window);
+    let mean_x = blur(x);
+    let mean_y = blur(y);
+    let mean_xx = blur(&x.zip(x, |a, b| a * b));
+    let mean_yy = blur(&y.zip(y, |a, b| a * b));
+    let mean_xy = blur(&x.zip(y, |a, b| a * b));
+
+    let total: f64 = (0..x.values.len())
+        .map(|k| {
+            let (mx, my) = (mean_x.values[k], mean_y.values[k]);
+            let var_x = mean_xx.values[k] - mx * mx;
+            let var_y = mean_yy.values[k] - my * my;
+            let cov = mean_xy.values[k] - mx * my;
+            (2.0 * mx * my + SSIM_C1) * (2.0 * cov + SSIM_C2)
+                / ((mx * mx + my * my + SSIM_C1) * (var_x + var_y + SSIM_C2))
+        })
+        .sum();
+    total / x.values.len().max(1) as f64
+}
+
+/// Per-pixel LDR FLIP error: a color difference of the images filtered by the
+/// contrast sensitivity of the eye, amplified where edges and points differ.
+fn flip(width: u32, height: u32, reference: &[Color], test: &[Color]) -> Plane {
+    let white = RGB_TO_XYZ * Color::new(1.0, 1.0, 1.0);
+    let opponent = |colors: &[Color]| -> Vec<Color> {
+        colors
+            .iter()
+            .map(|c| xyz_to_ycxcz(&(RGB_TO_XYZ * c.map(srgb_to_linear)), &white))
+            .collect()
+    };
+    let reference = opponent(reference);
+    let test = opponent(test);
+
+    // Color: spatially filtered, then compared in Hunt-adjusted L*a*b*
+    let filtered = |colors: &[Color]| -> Vec<Color> {
+        let channels: Vec<Plane> = (0..3)
+            .map(|c| csf_filter(&Plane::from_colors(width, height, colors, |v| v[c]), c))
+            .collect();
+        (0..colors.len())
+            .map(|k| {
+                let ycxcz = Color::new(
+                    channels[0].values[k],
+                    channels[1].values[k],
+                    channels[2].values[k],
+                );
+                let rgb = (XYZ_TO_RGB * ycxcz_to_xyz(&ycxcz, &white)).map(|c| c.clamp(0.0, 1.0));
+                hunt_lab(&(RGB_TO_XYZ * rgb), &white)
+            })
+            .collect()
+    };
+    let reference_lab = filtered(&reference);
+    let test_lab = filtered(&test);
+    let max_difference = hyab(
+        &hunt_lab(&(RGB_TO_XYZ * Color::new(0.0, 1.0, 0.0)), &white),
+        &hunt_lab(&(RGB_TO_XYZ * Color::new(0.0, 0.0, 1.0)), &white),
+    )
+    .powf(COLOR_EXPONENT);
+    let knee = COLOR_KNEE * max_difference;
+
+    // Features: edges and points of the luminance
+    let sd = 0.5 * FEATURE_WIDTH * PIXELS_PER_DEGREE;
+    let radius = (3.0 * sd).ceil() as usize;
+    let blur = gaussian(sd, radius);
+    let edge = balanced(&weighted_gaussian(sd, radius, |x| -x));
+    let point = balanced(&weighted_gaussian(sd, radius, |x| x * x / (sd * sd) - 1.0));
+    let features = |colors: &[Color]| {
+        // Y of YCxCz is 116 Y / Yw - 16, mapped back to relative luminance
+        let luminance = Plane::from_colors(width, height, colors, |c| (c.x + 16.0) / 116.0);
+        let magnitude = |kernel: &[f64]| {
+            luminance
+                .convolve(kernel, &blur)
+                .zip(&luminance.convolve(&blur, kernel), f64::hypot)
+        };
+        (magnitude(&edge), magnitude(&point))
+    };
+    let (reference_edges, reference_points) = features(&reference);
+    let (test_edges, test_points) = features(&test);
+
+    let values = (0..reference.len())
+        .map(|k| {
+            let color = hyab(&reference_lab[k], &test_lab[k]).powf(COLOR_EXPONENT);
+            let color = if color < knee {
+                COLOR_KNEE_VALUE * color / knee
+            } else {
+                COLOR_KNEE_VALUE
+                    + (1.0 - COLOR_KNEE_VALUE) * (color - knee) / (max_difference - knee)
+            };
+            let feature = (reference_edges.values[k] - test_edges.values[k])
+                .abs()
+                .max((reference_points.values[k] - test_points.values[k]).abs());
+            let feature = (FRAC_1_SQRT_2 * feature).powf(FEATURE_EXPONENT);
+            color.powf(1.0 - feature)
+        })
+        .collect();
+    Plane {
+        width,
+        height,
+        values,
+    }
+}
+
+/// Filters opponent channel `channel` by its contrast sensitivity. Each Gaussian
+/// of the sum is applied on its own, weighted by its share of the kernel's mass.
+fn csf_filter(plane: &Plane, channel: usize) -> Plane {
+    let sigma = |b: f64| (b / (2.0 * PI * PI)).sqrt() * PIXELS_PER_DEGREE;
+    let radius = (3.0 * sigma(CSF[2][0].1)).ceil() as usize;
+    let terms: Vec<(f64, Plane)> = CSF[channel]
+        .iter()
+        .filter(|(a, _)| *a > 0.0)
+        .map(|&(a, b)| {
+            let kernel = gaussian(sigma(b), radius);
+            (a * b.sqrt(), plane.convolve(&kernel, &kernel))
+        })
+        .collect();
+    let total: f64 = terms.iter().map(|(weight, _)| weight).sum();
+    let mut values = vec![0.0; plane.values.len()];
+    for (weight, filtered) in &terms {
+        for (value, f) in values.iter_mut().zip(&filtered.values) {
+            *value += weight / total * f;
+        }
+    }
+    Plane { values, ..*plane }
+}
+
+/// Normalized Gaussian kernel of `2 radius + 1` taps.
+fn gaussian(sigma: f64, radius: usize) -> Vec<f64> {
+    let kernel = weighted_gaussian(sigma, radius, |_| 1.0);
+    let total: f64 = kernel.iter().sum();
+    kernel.iter().map(|w| w / total).collect()
+}
+
+/// Taps of a Gaussian of `sigma` multiplied by `f` of the offset, unnormalized.
+fn weighted_gaussian(sigma: f64, radius: usize, f: impl Fn(f64) -> f64) -> Vec<f64> {
+    let radius = radius as i64;
+    (-radius..=radius)
+        .map(|x| {
+            let x = x as f64;
+            f(x) * (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect()
+}
+
+/// Scales the positive taps to sum to 1 and the negative ones to -1, so the
+/// detector ignores flat regions whatever their level.
+fn balanced(kernel: &[f64]) -> Vec<f64> {
+    let positive: f64 = kernel.iter().filter(|w| **w > 0.0).sum();
+    let negative: f64 = -kernel.iter().filter(|w| **w < 0.0).sum::<f64>();
+    kernel
+        .iter()
+        .map(|&w| if w > 0.0 { w / positive } else { w / negative })
+        .collect()
+}
+
+fn luma(c: &Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The linearized L*a*b* of FLIP, in which the contrast sensitivity is applied.
+fn xyz_to_ycxcz(xyz: &Color, white: &Color) -> Color {
+    let n = xyz.component_div(white);
+    Color::new(116.0 * n.y - 16.0, 500.0 * (n.x - n.y), 200.0 * (n.y - n.z))
+}
+
+fn ycxcz_to_xyz(ycxcz: &Color, white: &Color) -> Color {
+    let y = (ycxcz.x + 16.0) / 116.0;
+    Color::new(y + ycxcz.y / 500.0, y, y - ycxcz.z / 200.0).component_mul(white)
+}
+
+/// CIE L*a*b* with a* and b* scaled by L* / 100, as colorfulness fades in the dark
+/// (the Hunt effect).
+fn hunt_lab(xyz: &Color, white: &Color) -> Color {
+    let f = |t: f64| {
+        const DELTA: f64 = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+    let n = xyz.component_div(white).map(f);
+    let l = 116.0 * n.y - 16.0;
+    Color::new(
+        l,
+        0.01 * l * 500.0 * (n.x - n.y),
+        0.01 * l * 200.0 * (n.y - n.z),
+    )
+}
+
+/// HyAB distance: lightness difference plus the Euclidean chroma difference.
+fn hyab(a: &Color, b: &Color) -> f64 {
+    (a.x - b.x).abs() + (a.y - b.y).hypot(a.z - b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// A smooth gradient with some structure for SSIM and FLIP to respond to.
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (64 + 4 * x) as u8,
+                (64 + 4 * y) as u8,
+                (96 + 2 * (x + y)) as u8,
+            ])
+        })
+    }
+
+    /// `image` with a fixed pseudo-random pattern of up to `amplitude` levels
+    /// added to every channel.
+    fn with_noise(image: &RgbImage, amplitude: f64) -> RgbImage {
+        let mut state: u32 = 12345;
+        let mut noise = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f64 / (1 << 24) as f64 * 2.0 - 1.0
+        };
+        let mut noisy = image.clone();
+        for pixel in noisy.pixels_mut() {
+            for channel in pixel.0.iter_mut() {
+                *channel = (*channel as f64 + amplitude * noise())
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+        }
+        noisy
+    }
+
+    #[test]
+    fn identical_images_match_exactly() {
+        let image = gradient(32, 32);
+        let comparison = compare(&image, &image).unwrap();
+        assert_eq!(comparison.rmse, 0.0);
+        assert!(
+            (comparison.ssim - 1.0).abs() < 1e-9,
+            "SSIM {}",
+            comparison.ssim
+        );
+        assert!(comparison.flip.abs() < 1e-9, "FLIP {}", comparison.flip);
+    }
+
+    #[test]
+    fn rmse_of_known_differences() {
+        let reference = RgbImage::from_pixel(8, 8, Rgb([100, 100, 100]));
+
+        // One pixel of 64 off by 51 levels in every channel: 0.2 / sqrt(64)
+        let mut one_pixel = reference.clone();
+        one_pixel.put_pixel(3, 5, Rgb([151, 151, 151]));
+        let comparison = compare(&reference, &one_pixel).unwrap();
+        assert!(
+            (comparison.rmse - 0.025).abs() < 1e-12,
+            "RMSE {}",
+            comparison.rmse
+        );
+
+        let offset = RgbImage::from_pixel(8, 8, Rgb([151, 151, 151]));
+        let comparison = compare(&reference, &offset).unwrap();
+        assert!(
+            (comparison.rmse - 0.2).abs() < 1e-12,
+            "RMSE {}",
+            comparison.rmse
+        );
+    }
+
+    #[test]
+    fn ssim_and_flip_worsen_with_noise() {
+        let reference = gradient(32, 32);
+        let scores: Vec<ImageComparison> = [0.0, 5.0, 15.0, 40.0]
+            .iter()
+            .map(|&amplitude| compare(&reference, &with_noise(&reference, amplitude)).unwrap())
+            .collect();
+        for pair in scores.windows(2) {
+            assert!(
+                pair[1].ssim < pair[0].ssim,
+                "SSIM {} then {}",
+                pair[0].ssim,
+                pair[1].ssim
+            );
+            assert!(
+                pair[1].flip > pair[0].flip,
+                "FLIP {} then {}",
+                pair[0].flip,
+                pair[1].flip
+            );
+            assert!(pair[1].rmse > pair[0].rmse);
+        }
+    }
+
+    #[test]
+    fn different_sizes_are_an_error() {
+        assert!(compare(&gradient(8, 8), &gradient(8, 9)).is_err());
+    }
+}
@@ -1,8 +1,15 @@
-use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
+use crate::core::onb::ONB;
+use crate::core::ray::{Ray, RayDifferentials};
+use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
 use crate::materials::material_trait::Material;
 use std::sync::Arc;
 
+/// Number of UV sets a surface can carry, the first being `Interaction::uv`.
+pub const UV_SETS: usize = 4;
+
+/// Texture coordinates of UV sets 1 and up, where the surface has them.
+pub type UvSets = [Option<(f64, f64)>; UV_SETS - 1];
+
 /// Interaction represents a point on a surface where a light ray interacts with geometry.
 /// It replaces the legacy 'HitRecord'.
 #[derive(Clone)]
@@ -10,38 +17,152 @@ pub struct Interaction {
     pub p: Point3,                           // Intersection point
     pub geometry_normal: Vec3,               // The true geometric normal
     pub shading_normal: Vec3,                // The interpolated/perturbed normal (for shading)
+    pub shading: ONB,                        // Frame around `shading_normal`, `u` along `dpdu`
     pub wo: Vec3,                            // Outgoing direction (usually -ray.direction)
     pub t: f64,                              // Ray parameter t
     pub uv: (f64, f64),                      // Texture coordinates
+    pub uv_sets: UvSets,                     // Further texture coordinates, see `uv_set`
+    pub dpdu: Vec3,                          // Partial derivative of the point by `uv.0`, if any
+    pub dpdv: Vec3,                          // Partial derivative of the point by `uv.1`, if any
+    pub dpdx: Vec3,                          // Offset to the next pixel's hit along x, if tracked
+    pub dpdy: Vec3,                          // Offset to the next pixel's hit along y, if tracked
+    pub duvdx: (f64, f64),                   // Change of `uv` to the next pixel along x
+    pub duvdy: (f64, f64),                   // Change of `uv` to the next pixel along y
     pub front_face: bool,                    // Is incident ray hitting the front face?
     pub material: Option<Arc<dyn Material>>, // The material at this point
+    pub max_specular_depth: Option<u32>,     // Per-object limit on specular bounces in a row
+    pub object: Option<ObjectTag>,           // Name and ID of the object that was hit
+    pub medium: bool,                        // Scattering event inside a participating medium
+    pub vertex_color: Option<Color>,         // Interpolated mesh vertex color, if any
+    pub material_params: MaterialParams,     // Per-instance adjustments to the material
+    pub outside_ior: f64,                    // IOR around a dielectric, 1 unless nested in another
 }
 
-impl Interaction {
-    pub fn new(p: Point3, t: f64, uv: (f64, f64), material: Option<Arc<dyn Material>>) -> Self {
+/// Identifies a named primitive or group, carried from the hit to the integrator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectTag {
+    pub id: u32,
+    pub name: Arc<str>,
+}
+
+/// Adjustments an instance makes to the materials it is hit on, so copies of one
+/// asset (a crowd, a forest) vary without materials of their own. The identity
+/// by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialParams {
+    /// Multiplies the reflectance of diffuse and metal surfaces.
+    pub tint: Color,
+    /// Multiplies the roughness of glossy surfaces, the fuzz of `Metal`.
+    pub roughness_scale: f64,
+    /// Multiplies the light emitted.
+    pub emission_scale: f64,
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
         Self {
-            p,
-            geometry_normal: Vec3::zeros(),
-            shading_normal: Vec3::zeros(),
-            wo: Vec3::zeros(),
-            t,
-            uv,
-            front_face: true,
-            material,
+            tint: Color::new(1.0, 1.0, 1.0),
+            roughness_scale: 1.0,
+            emission_scale: 1.0,
+        }
+    }
+}
+
+impl MaterialParams {
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_roughness_scale(mut self, scale: f64) -> Self {
+        self.roughness_scale = scale;
+        self
+    }
+
+    pub fn with_emission_scale(mut self, scale: f64) -> Self {
+        self.emission_scale = scale;
+        self
+    }
+
+    /// Both adjustments applied together, as for nested instances.
+    pub fn compose(&self, other: &MaterialParams) -> Self {
+        Self {
+            tint: self.tint.component_mul(&other.tint),
+            roughness_scale: self.roughness_scale * other.roughness_scale,
+            emission_scale: self.emission_scale * other.emission_scale,
         }
     }
+}
 
+impl Default for Interaction {
     /// Default initializer for empty/temp interactions
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self {
             p: Point3::origin(),
             geometry_normal: Vec3::zeros(),
             shading_normal: Vec3::zeros(),
+            shading: ONB::default(),
             wo: Vec3::zeros(),
             t: 0.0,
             uv: (0.0, 0.0),
+            uv_sets: [None; UV_SETS - 1],
+            dpdu: Vec3::zeros(),
+            dpdv: Vec3::zeros(),
+            dpdx: Vec3::zeros(),
+            dpdy: Vec3::zeros(),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
             front_face: true,
             material: None,
+            max_specular_depth: None,
+            object: None,
+            medium: false,
+            vertex_color: None,
+            material_params: MaterialParams::default(),
+            outside_ior: 1.0,
+        }
+    }
+}
+
+impl Interaction {
+    pub fn new(p: Point3, t: f64, uv: (f64, f64), material: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            p,
+            geometry_normal: Vec3::zeros(),
+            shading_normal: Vec3::zeros(),
+            shading: ONB::default(),
+            wo: Vec3::zeros(),
+            t,
+            uv,
+            uv_sets: [None; UV_SETS - 1],
+            dpdu: Vec3::zeros(),
+            dpdv: Vec3::zeros(),
+            dpdx: Vec3::zeros(),
+            dpdy: Vec3::zeros(),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
+            front_face: true,
+            material,
+            max_specular_depth: None,
+            object: None,
+            medium: false,
+            vertex_color: None,
+            material_params: MaterialParams::default(),
+            outside_ior: 1.0,
+        }
+    }
+
+    /// Texture coordinates of UV set `set`, falling back to the first set on
+    /// surfaces that do not have it.
+    pub fn uv_set(&self, set: usize) -> (f64, f64) {
+        match set {
+            0 => self.uv,
+            _ => self
+                .uv_sets
+                .get(set - 1)
+                .copied()
+                .flatten()
+                .unwrap_or(self.uv),
         }
     }
 
@@ -54,8 +175,127 @@ impl Interaction {
         } else {
             -outward_normal
         };
-        // For now, shading normal equals geometry normal
+        // Until the primitive sets one, the shading normal is the geometry normal
         self.shading_normal = self.geometry_normal;
+        self.update_shading_frame();
         self.wo = -ray.dir.normalize();
     }
+
+    /// Sets the shading normal, such as an interpolated vertex normal, and the
+    /// shading frame around it. `normal` is flipped to the side of the geometry
+    /// normal, so both face the incoming ray. Needs the geometry normal set.
+    pub fn set_shading_normal(&mut self, normal: Vec3) {
+        self.shading_normal = if normal.dot(&self.geometry_normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        self.update_shading_frame();
+    }
+
+    /// Sets the partial derivatives of the surface point by its `uv`, the tangents
+    /// the shading frame is aligned to, e.g. for normal maps and anisotropic
+    /// reflection. Zero for surfaces without a parameterization. Needs the normal set.
+    pub fn set_tangents(&mut self, dpdu: Vec3, dpdv: Vec3) {
+        self.dpdu = dpdu;
+        self.dpdv = dpdv;
+        self.update_shading_frame();
+    }
+
+    fn update_shading_frame(&mut self) {
+        self.shading = ONB::build_from_w_and_tangent(&self.shading_normal, &self.dpdu);
+    }
+
+    /// Sets the footprint of the ray's differentials, if it has them: where the
+    /// offset rays meet the tangent plane, relative to `p`, and from the partial
+    /// derivatives of the surface point by its `uv`, how much `uv` changes there.
+    /// Surfaces without a parameterization pass zero partials and get no `uv`
+    /// derivatives. The partials are kept as the tangents, see `set_tangents`.
+    /// Needs the normal set.
+    pub fn set_differentials(&mut self, r: &Ray, dpdu: &Vec3, dpdv: &Vec3) {
+        self.set_tangents(*dpdu, *dpdv);
+        self.dpdx = Vec3::zeros();
+        self.dpdy = Vec3::zeros();
+        self.duvdx = (0.0, 0.0);
+        self.duvdy = (0.0, 0.0);
+        let Some(d) = r.differentials else {
+            return;
+        };
+
+        let n = self.geometry_normal;
+        let plane = n.dot(&self.p.coords);
+        let offset = |origin: &Point3, direction: &Vec3| {
+            let t = (plane - n.dot(&origin.coords)) / n.dot(direction);
+            let offset = origin + t * direction - self.p;
+            offset.iter().all(|c| c.is_finite()).then_some(offset)
+        };
+        let (Some(dpdx), Some(dpdy)) = (
+            offset(&d.rx_origin, &d.rx_direction),
+            offset(&d.ry_origin, &d.ry_direction),
+        ) else {
+            return;
+        };
+        self.dpdx = dpdx;
+        self.dpdy = dpdy;
+
+        // Least-squares solution of dp = dpdu du + dpdv dv
+        let (a, b, c) = (dpdu.dot(dpdu), dpdu.dot(dpdv), dpdv.dot(dpdv));
+        let det = a * c - b * b;
+        if det.abs() < 1e-12 * (a * c).max(f64::MIN_POSITIVE) {
+            return;
+        }
+        let solve = |dp: &Vec3| {
+            let (pu, pv) = (dpdu.dot(dp), dpdv.dot(dp));
+            ((c * pu - b * pv) / det, (a * pv - b * pu) / det)
+        };
+        self.duvdx = solve(&dpdx);
+        self.duvdy = solve(&dpdy);
+    }
+
+    /// Width of the pixel footprint on the surface, or 0 where it is not tracked.
+    pub fn footprint(&self) -> f64 {
+        self.dpdx.norm().max(self.dpdy.norm())
+    }
+
+    /// Differentials of the ray `r_in` reflected into `wi`.
+    pub fn reflected_differentials(&self, r_in: &Ray, wi: &Vec3) -> Option<RayDifferentials> {
+        let n = self.geometry_normal;
+        self.specular_differentials(r_in, wi, |d| d.reflect(&n))
+    }
+
+    /// Differentials of the ray `r_in` refracted into `wi`, with the ratio of the
+    /// indices of refraction `eta` on the incident side over the far side.
+    pub fn refracted_differentials(
+        &self,
+        r_in: &Ray,
+        wi: &Vec3,
+        eta: f64,
+    ) -> Option<RayDifferentials> {
+        let n = self.geometry_normal;
+        self.specular_differentials(r_in, wi, |d| d.refract(&n, eta))
+    }
+
+    /// Offset rays leaving from the footprint, bent as the unit direction `bend`s,
+    /// the surface being taken as flat across it. The bend of each offset ray
+    /// relative to the main one is added to `wi`, so perturbations of the main
+    /// direction, such as by bumps or fuzz, carry over.
+    fn specular_differentials(
+        &self,
+        r_in: &Ray,
+        wi: &Vec3,
+        bend: impl Fn(&Vec3) -> Vec3,
+    ) -> Option<RayDifferentials> {
+        let d = r_in.differentials?;
+        if self.dpdx == Vec3::zeros() && self.dpdy == Vec3::zeros() {
+            return None;
+        }
+        let base = bend(&r_in.dir.normalize());
+        let offset = |direction: &Vec3| wi + (bend(&direction.normalize()) - base) * wi.norm();
+        Some(RayDifferentials {
+            rx_origin: self.p + self.dpdx,
+            rx_direction: offset(&d.rx_direction),
+            ry_origin: self.p + self.dpdy,
+            ry_direction: offset(&d.ry_direction),
+        })
+    }
 }
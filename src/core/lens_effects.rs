@@ -0,0 +1,76 @@
+use crate::core::camera::Camera;
+use crate::core::film::Film;
+use crate::core::vec3::Color;
+
+/// Imperfections of a real lens applied to the finished film, so renders composite
+/// over footage shot with one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LensEffects {
+    /// Relative magnification of red over green (and green over blue).
+    chromatic_aberration: f64,
+    /// Blend from no falloff (0) to full cosine-fourth falloff (1).
+    vignetting: f64,
+}
+
+impl LensEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lateral chromatic aberration: the red image is magnified by `1 + amount` and the
+    /// blue one by `1 - amount` about the image center, fringing edges towards the corners.
+    pub fn with_chromatic_aberration(mut self, amount: f64) -> Self {
+        self.chromatic_aberration = amount;
+        self
+    }
+
+    /// Natural vignetting: irradiance falls off with the fourth power of the cosine of
+    /// the angle off the optical axis, scaled by `amount` in [0, 1].
+    pub fn with_vignetting(mut self, amount: f64) -> Self {
+        self.vignetting = amount.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn apply(&self, film: &mut Film, camera: &Camera) {
+        let source = film.clone();
+        let (cx, cy) = (0.5 * film.width as f64, 0.5 * film.height as f64);
+        let magnification = [
+            1.0 + self.chromatic_aberration,
+            1.0,
+            1.0 - self.chromatic_aberration,
+        ];
+
+        for j in 0..film.height {
+            for i in 0..film.width {
+                let (x, y) = (i as f64 + 0.5, j as f64 + 0.5);
+                let mut color = Color::zeros();
+                for (c, m) in magnification.iter().enumerate() {
+                    color[c] = sample(&source, cx + (x - cx) / m, cy + (y - cy) / m)[c];
+                }
+
+                let to_pixel = camera.pixel00_loc
+                    + (i as f64) * camera.pixel_delta_u
+                    + (j as f64) * camera.pixel_delta_v
+                    - camera.center;
+                let cos_theta = -to_pixel.dot(&camera.w) / to_pixel.norm();
+                let falloff = 1.0 - self.vignetting * (1.0 - cos_theta.powi(4));
+
+                film.set(i, j, color * falloff);
+            }
+        }
+    }
+}
+
+/// Bilinear lookup at continuous pixel coordinates (pixel centers at +0.5), clamped
+/// to the edge.
+fn sample(film: &Film, x: f64, y: f64) -> Color {
+    let x = (x - 0.5).clamp(0.0, (film.width - 1) as f64);
+    let y = (y - 0.5).clamp(0.0, (film.height - 1) as f64);
+    let (i0, j0) = (x.floor() as u32, y.floor() as u32);
+    let (i1, j1) = ((i0 + 1).min(film.width - 1), (j0 + 1).min(film.height - 1));
+    let (tx, ty) = (x.fract(), y.fract());
+
+    let top = (1.0 - tx) * film.get(i0, j0) + tx * film.get(i1, j0);
+    let bottom = (1.0 - tx) * film.get(i0, j1) + tx * film.get(i1, j1);
+    (1.0 - ty) * top + ty * bottom
+}
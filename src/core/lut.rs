@@ -0,0 +1,137 @@
+use crate::core::assets;
+use crate::core::vec3::Color;
+
+/// A 3D color lookup table from a `.cube` file (the Resolve/Adobe format most
+/// grading tools export), for baking a studio look into renders. It maps colors
+/// after they are encoded to the output color space, so the space should be the
+/// one the LUT was authored for, usually `srgb` or `rec709`.
+#[derive(Debug, Clone)]
+pub struct Lut3d {
+    pub title: Option<String>,
+    size: usize,
+    domain_min: Color,
+    domain_max: Color,
+    /// Output colors with red varying fastest, then green, then blue.
+    table: Vec<Color>,
+}
+
+impl Lut3d {
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let path = assets::resolve(filename)?;
+        let text =
+            std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = Color::zeros();
+        let mut domain_max = Color::new(1.0, 1.0, 1.0);
+        let mut table = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some(keyword) = line.split_whitespace().next() else {
+                continue;
+            };
+            let rest = line[keyword.len()..].trim();
+
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_3D_SIZE" => {
+                    let n: usize = rest.parse().map_err(|_| error("invalid LUT_3D_SIZE"))?;
+                    if !(2..=256).contains(&n) {
+                        return Err(error("LUT_3D_SIZE must be between 2 and 256"));
+                    }
+                    size = Some(n);
+                }
+                "LUT_1D_SIZE" => return Err(error("1D LUTs are not supported")),
+                "DOMAIN_MIN" => {
+                    domain_min = parse_color(rest).ok_or_else(|| error("invalid DOMAIN_MIN"))?
+                }
+                "DOMAIN_MAX" => {
+                    domain_max = parse_color(rest).ok_or_else(|| error("invalid DOMAIN_MAX"))?
+                }
+                "LUT_3D_INPUT_RANGE" => {
+                    let range: Vec<f64> = rest
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| error("invalid LUT_3D_INPUT_RANGE"))?;
+                    let [min, max] = range[..] else {
+                        return Err(error("invalid LUT_3D_INPUT_RANGE"));
+                    };
+                    domain_min = Color::new(min, min, min);
+                    domain_max = Color::new(max, max, max);
+                }
+                // Other keywords are tool-specific metadata
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => table.push(parse_color(line).ok_or_else(|| error("invalid table entry"))?),
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if table.len() != size * size * size {
+            return Err(format!(
+                "expected {} table entries for LUT_3D_SIZE {}, found {}",
+                size * size * size,
+                size,
+                table.len()
+            ));
+        }
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            return Err("DOMAIN_MAX must exceed DOMAIN_MIN".to_string());
+        }
+        Ok(Self {
+            title,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// Looks `color` up with trilinear interpolation, clamping it to the domain.
+    pub fn apply(&self, color: Color) -> Color {
+        let last = (self.size - 1) as f64;
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for c in 0..3 {
+            let x = (color[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c]);
+            let x = if x.is_nan() {
+                0.0
+            } else {
+                x.clamp(0.0, 1.0) * last
+            };
+            base[c] = (x.floor() as usize).min(self.size - 2);
+            frac[c] = x - base[c] as f64;
+        }
+
+        let mut result = Color::zeros();
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut index = 0;
+            for c in (0..3).rev() {
+                let upper = (corner >> c) & 1;
+                weight *= if upper == 1 { frac[c] } else { 1.0 - frac[c] };
+                index = index * self.size + base[c] + upper;
+            }
+            result += self.table[index] * weight;
+        }
+        result
+    }
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let values: Vec<f64> = s
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match values[..] {
+        [r, g, b] => Some(Color::new(r, g, b)),
+        _ => None,
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Memory the scene may take, set once from the command line.
+static LIMIT: OnceLock<u64> = OnceLock::new();
+/// Bytes recorded so far, indexed by `Category`.
+static USED: [AtomicU64; 3] = [const { AtomicU64::new(0) }; 3];
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// The large allocations of a scene, whose approximate sizes are recorded as they
+/// are made. The rest of a scene is usually small next to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Vertex and index buffers of `TriangleMesh`es and their faces.
+    Meshes,
+    /// Decoded `ImageTexture`s with their mip levels.
+    Textures,
+    /// Nodes of `BvhNode` hierarchies.
+    Bvhs,
+}
+
+impl Category {
+    const ALL: [Category; 3] = [Category::Meshes, Category::Textures, Category::Bvhs];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Category::Meshes => "meshes",
+            Category::Textures => "textures",
+            Category::Bvhs => "BVHs",
+        }
+    }
+}
+
+/// Caps the memory recorded for the scene at `bytes`: textures loaded past it are
+/// downscaled to fit, and `check` fails once it is exceeded.
+pub fn set_limit(bytes: u64) {
+    let _ = LIMIT.set(bytes);
+}
+
+pub fn limit() -> Option<u64> {
+    LIMIT.get().copied()
+}
+
+/// Records an allocation of about `bytes` for the scene. Going past the limit is
+/// only reported by `check`.
+pub fn record(category: Category, bytes: usize) {
+    USED[category as usize].fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Records that about `bytes` of an earlier `record` were freed, e.g. by a
+/// structure rebuilt in place.
+pub fn release(category: Category, bytes: usize) {
+    let _ = USED[category as usize].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        Some(used.saturating_sub(bytes as u64))
+    });
+}
+
+pub fn used(category: Category) -> u64 {
+    USED[category as usize].load(Ordering::Relaxed)
+}
+
+pub fn total() -> u64 {
+    Category::ALL.iter().map(|&category| used(category)).sum()
+}
+
+/// Bytes left under the limit, or `None` without one.
+pub fn remaining() -> Option<u64> {
+    limit().map(|limit| limit.saturating_sub(total()))
+}
+
+/// Prints the memory recorded for the scene, by category.
+pub fn report() {
+    let breakdown: Vec<String> = Category::ALL
+        .iter()
+        .map(|&category| format!("{} {:.1}", category.name(), used(category) as f64 / MIB))
+        .collect();
+    let limit = match limit() {
+        Some(limit) => format!(" of {:.1} MiB allowed", limit as f64 / MIB),
+        None => String::new(),
+    };
+    println!(
+        "Scene memory: about {:.1} MiB{} ({} MiB)",
+        total() as f64 / MIB,
+        limit,
+        breakdown.join(", ")
+    );
+}
+
+/// Fails with the breakdown if the scene took more than the limit.
+pub fn check() -> Result<(), String> {
+    match limit() {
+        Some(limit) if total() > limit => Err(format!(
+            "The scene needs about {:.1} MiB, more than the {:.1} MiB allowed by --max-memory (meshes {:.1}, textures {:.1}, BVHs {:.1} MiB); simplify models with --simplify or raise the limit",
+            total() as f64 / MIB,
+            limit as f64 / MIB,
+            used(Category::Meshes) as f64 / MIB,
+            used(Category::Textures) as f64 / MIB,
+            used(Category::Bvhs) as f64 / MIB,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parses a size in bytes with an optional binary unit, e.g. `512M` or `4G`.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last()? {
+        (end, unit) if unit.is_ascii_alphabetic() => (&s[..end], unit.to_ascii_uppercase()),
+        _ => (s, 'B'),
+    };
+    let scale = match unit {
+        'B' => 1u64,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        'T' => 1 << 40,
+        _ => return None,
+    };
+    let value: f64 = number.trim().parse().ok()?;
+    (value.is_finite() && value >= 0.0).then_some((value * scale as f64) as u64)
+}
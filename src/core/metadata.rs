@@ -0,0 +1,104 @@
+use exr::prelude::{AttributeValue, LayerAttributes, Text};
+use image::RgbImage;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Facts about how the image was made, recorded while the scene is built and
+/// rendered: scene, seeds, sample count, integrator, commit, render time.
+static ENTRIES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+/// Whether saved images get a JSON sidecar, set once from the command line.
+static SIDECAR: OnceLock<bool> = OnceLock::new();
+
+/// Records `value` under `key`, replacing an earlier value of the same key. Keys
+/// become PNG text chunk keywords, so keep them short and plain ASCII.
+pub fn record(key: &str, value: impl ToString) {
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    let value = value.to_string();
+    match entries.iter_mut().find(|(k, _)| k == key) {
+        Some(entry) => entry.1 = value,
+        None => entries.push((key.to_string(), value)),
+    }
+}
+
+pub fn entries() -> Vec<(String, String)> {
+    ENTRIES.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Also writes the entries of every saved image to `<image stem>.json` beside it.
+pub fn enable_sidecar() {
+    let _ = SIDECAR.set(true);
+}
+
+/// `s` as a JSON string literal, quoted, with quotes, backslashes and control
+/// characters escaped.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Adds the recorded entries to the header attributes of an OpenEXR layer.
+pub fn add_exr_attributes(attributes: &mut LayerAttributes) {
+    for (key, value) in entries() {
+        attributes.other.insert(
+            Text::from(key.as_str()),
+            AttributeValue::Text(Text::from(value.as_str())),
+        );
+    }
+}
+
+/// Writes the entries to the JSON sidecar of `image_filename` if sidecars are on.
+pub fn save_sidecar(image_filename: &str) {
+    if SIDECAR.get().is_none() {
+        return;
+    }
+    let filename = Path::new(image_filename).with_extension("json");
+    let fields: Vec<String> = entries()
+        .iter()
+        .map(|(key, value)| format!("  {}: {}", json_string(key), json_string(value)))
+        .collect();
+    let json = format!("{{\n{}\n}}\n", fields.join(",\n"));
+    if let Err(e) = std::fs::write(&filename, json) {
+        eprintln!("Error saving metadata to {}: {}", filename.display(), e);
+    }
+}
+
+/// Saves `image`, writing the recorded entries into text chunks when it is a PNG
+/// (other formats are saved without them) and to the sidecar if enabled.
+pub fn save_image(image: &RgbImage, filename: &str) -> Result<(), String> {
+    save_sidecar(filename);
+    let is_png = Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if !is_png {
+        return image.save(filename).map_err(|e| e.to_string());
+    }
+
+    let file = File::create(filename).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (key, value) in entries() {
+        encoder
+            .add_text_chunk(key, value)
+            .map_err(|e| e.to_string())?;
+    }
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,490 @@
+use crate::core::aabb::Aabb;
+use crate::core::assets;
+use crate::core::vec3::{Point3, Vec3};
+use flate2::read::ZlibDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// First seven bytes of every NanoVDB magic number ("NanoVDB0", "NanoVDB1", ...).
+const MAGIC_PREFIX: &[u8; 7] = b"NanoVDB";
+/// Major version of the supported memory layout.
+const MAJOR_VERSION: u32 = 32;
+
+const FILE_HEADER_SIZE: usize = 16;
+const FILE_META_SIZE: usize = 176;
+
+const CODEC_NONE: u16 = 0;
+const CODEC_ZIP: u16 = 1;
+const GRID_TYPE_FLOAT: u32 = 1;
+
+// Byte offsets into the grid blob
+const GRID_MAP: usize = 296;
+const GRID_WORLD_BBOX: usize = 560;
+const GRID_VOXEL_SIZE: usize = 608;
+const GRID_TYPE: usize = 636;
+const TREE: usize = 672;
+const ROOT_SIZE: usize = 64;
+const ROOT_TILE_SIZE: usize = 32;
+
+/// Log2 of the children per axis of the upper and lower internal nodes and of leaves.
+const UPPER_LOG2: u32 = 5;
+const LOWER_LOG2: u32 = 4;
+const LEAF_LOG2: u32 = 3;
+/// Internal nodes: bbox, flags, value mask and child mask, stats, then the table.
+const fn internal_child_mask(log2: u32) -> usize {
+    32 + (1 << (3 * log2)) / 8
+}
+const fn internal_table(log2: u32) -> usize {
+    (32 + 2 * (1usize << (3 * log2)) / 8 + 16).next_multiple_of(32)
+}
+const fn internal_size(log2: u32) -> usize {
+    internal_table(log2) + 8 * (1 << (3 * log2))
+}
+const LEAF_VALUES: usize = 96;
+const LEAF_SIZE: usize = LEAF_VALUES + 4 * (1 << (3 * LEAF_LOG2));
+
+/// A float grid from a NanoVDB file, read from its serialized tree without copying
+/// it into another structure. Values are looked up top-down through the root table,
+/// the upper (4096³) and lower (128³) internal nodes and the 8³ leaves, falling back
+/// to the tile values and the grid's background where no child is allocated.
+#[derive(Debug)]
+pub struct VdbGrid {
+    pub name: String,
+    data: Vec<u8>,
+    root: usize,
+    /// Root tiles by key, to their byte offset.
+    tiles: HashMap<u64, usize>,
+    /// World-to-index linear map, row-major, applied after removing `translation`.
+    inverse_map: [f64; 9],
+    translation: Vec3,
+}
+
+impl VdbGrid {
+    fn from_blob(name: String, data: Vec<u8>) -> Result<Self, String> {
+        if data.len() < TREE + 64 || &data[..7] != MAGIC_PREFIX {
+            return Err(format!("grid '{}' is not a NanoVDB grid", name));
+        }
+        let reader = Reader(&data);
+        if reader.u32(GRID_TYPE) != GRID_TYPE_FLOAT {
+            return Err(format!(
+                "grid '{}' has type {}, only float grids are supported",
+                name,
+                reader.u32(GRID_TYPE)
+            ));
+        }
+
+        let truncated = || format!("grid '{}' is truncated", name);
+        let root = usize::try_from(reader.u64(TREE + 24))
+            .ok()
+            .and_then(|offset| TREE.checked_add(offset))
+            .filter(|root| fits(&data, *root, ROOT_SIZE))
+            .ok_or_else(truncated)?;
+        let table_size = reader.u32(root + 24) as usize;
+        if !fits(&data, root + ROOT_SIZE, table_size * ROOT_TILE_SIZE) {
+            return Err(truncated());
+        }
+        let tiles = (0..table_size)
+            .map(|n| {
+                let tile = root + ROOT_SIZE + n * ROOT_TILE_SIZE;
+                (reader.u64(tile), tile)
+            })
+            .collect();
+
+        let inverse_map = std::array::from_fn(|n| reader.f64(GRID_MAP + 160 + 8 * n));
+        let translation = reader.vec3(GRID_MAP + 232);
+
+        let grid = Self {
+            name,
+            root,
+            tiles,
+            inverse_map,
+            translation,
+            data,
+        };
+        match grid.validate() {
+            Ok(()) => Ok(grid),
+            Err(()) => Err(format!("grid '{}' is truncated", grid.name)),
+        }
+    }
+
+    /// Checks that every node reachable from the root table lies within the data,
+    /// so lookups can read them without bounds errors.
+    fn validate(&self) -> Result<(), ()> {
+        let reader = self.reader();
+        for &tile in self.tiles.values() {
+            let offset = reader.u64(tile + 8) as i64;
+            if offset == 0 {
+                continue;
+            }
+            let upper = self.node_at(self.root, offset, internal_size(UPPER_LOG2))?;
+            self.validate_children(upper, UPPER_LOG2, internal_size(LOWER_LOG2), |lower| {
+                self.validate_children(lower, LOWER_LOG2, LEAF_SIZE, |_| Ok(()))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Checks each child of the internal node at `node`, of `log2` children per
+    /// axis and `child_size` bytes each, then calls `visit` with its offset.
+    fn validate_children(
+        &self,
+        node: usize,
+        log2: u32,
+        child_size: usize,
+        visit: impl Fn(usize) -> Result<(), ()>,
+    ) -> Result<(), ()> {
+        let reader = self.reader();
+        for n in 0..1 << (3 * log2) {
+            let word = reader.u64(node + internal_child_mask(log2) + 8 * (n >> 6));
+            if word >> (n & 63) & 1 == 1 {
+                let offset = reader.u64(node + internal_table(log2) + 8 * n) as i64;
+                visit(self.node_at(node, offset, child_size)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Byte offset of the node `offset` bytes from `node`, if all its `size` bytes
+    /// are within the data.
+    fn node_at(&self, node: usize, offset: i64, size: usize) -> Result<usize, ()> {
+        let child = (node as i64)
+            .checked_add(offset)
+            .and_then(|child| usize::try_from(child).ok())
+            .ok_or(())?;
+        if fits(&self.data, child, size) {
+            Ok(child)
+        } else {
+            Err(())
+        }
+    }
+
+    fn reader(&self) -> Reader<'_> {
+        Reader(&self.data)
+    }
+
+    /// Value outside every allocated node.
+    pub fn background(&self) -> f64 {
+        self.reader().f32(self.root + 28) as f64
+    }
+
+    /// Largest value stored in the grid.
+    pub fn maximum(&self) -> f64 {
+        self.reader().f32(self.root + 36) as f64
+    }
+
+    pub fn voxel_size(&self) -> Vec3 {
+        self.reader().vec3(GRID_VOXEL_SIZE)
+    }
+
+    /// World-space bounds of the active voxels.
+    pub fn world_bbox(&self) -> Aabb {
+        let reader = self.reader();
+        Aabb::new_point(
+            Point3::from(reader.vec3(GRID_WORLD_BBOX)),
+            Point3::from(reader.vec3(GRID_WORLD_BBOX + 24)),
+        )
+    }
+
+    /// Value of voxel `ijk`.
+    pub fn value(&self, ijk: [i32; 3]) -> f32 {
+        let reader = self.reader();
+        let key = (ijk[2] as u32 >> 12) as u64
+            | ((ijk[1] as u32 >> 12) as u64) << 21
+            | ((ijk[0] as u32 >> 12) as u64) << 42;
+        let Some(&tile) = self.tiles.get(&key) else {
+            return reader.f32(self.root + 28);
+        };
+        let child = reader.u64(tile + 8) as i64;
+        if child == 0 {
+            return reader.f32(tile + 20);
+        }
+
+        let upper = (self.root as i64 + child) as usize;
+        let Some(lower) = self.child(upper, UPPER_LOG2, 7, ijk) else {
+            return self.tile_value(upper, UPPER_LOG2, 7, ijk);
+        };
+        let Some(leaf) = self.child(lower, LOWER_LOG2, LEAF_LOG2, ijk) else {
+            return self.tile_value(lower, LOWER_LOG2, LEAF_LOG2, ijk);
+        };
+        let n = ((ijk[0] & 7) << 6 | (ijk[1] & 7) << 3 | (ijk[2] & 7)) as usize;
+        reader.f32(leaf + LEAF_VALUES + 4 * n)
+    }
+
+    /// Table slot of `ijk` in an internal node of `log2` children per axis, each
+    /// spanning `2^child_total` voxels.
+    fn slot(log2: u32, child_total: u32, ijk: [i32; 3]) -> usize {
+        let mask = (1 << (log2 + child_total)) - 1;
+        let [i, j, k] = ijk.map(|c| ((c & mask) >> child_total) as usize);
+        (i << (2 * log2)) | (j << log2) | k
+    }
+
+    fn child(&self, node: usize, log2: u32, child_total: u32, ijk: [i32; 3]) -> Option<usize> {
+        let reader = self.reader();
+        let n = Self::slot(log2, child_total, ijk);
+        let word = reader.u64(node + internal_child_mask(log2) + 8 * (n >> 6));
+        if word >> (n & 63) & 1 == 0 {
+            return None;
+        }
+        let offset = reader.u64(node + internal_table(log2) + 8 * n) as i64;
+        Some((node as i64 + offset) as usize)
+    }
+
+    fn tile_value(&self, node: usize, log2: u32, child_total: u32, ijk: [i32; 3]) -> f32 {
+        let n = Self::slot(log2, child_total, ijk);
+        self.reader().f32(node + internal_table(log2) + 8 * n)
+    }
+
+    /// World-space position to continuous index space, where voxel centers sit on
+    /// integer coordinates.
+    pub fn world_to_index(&self, p: &Point3) -> Vec3 {
+        let d = p - self.translation;
+        let m = &self.inverse_map;
+        Vec3::new(
+            m[0] * d.x + m[1] * d.y + m[2] * d.z,
+            m[3] * d.x + m[4] * d.y + m[5] * d.z,
+            m[6] * d.x + m[7] * d.y + m[8] * d.z,
+        )
+    }
+
+    /// Trilinearly interpolated value at world-space point `p`.
+    pub fn sample(&self, p: &Point3) -> f64 {
+        let x = self.world_to_index(p);
+        let base = [x.x.floor(), x.y.floor(), x.z.floor()];
+        let f = [x.x - base[0], x.y - base[1], x.z - base[2]];
+        let base = base.map(|c| c as i32);
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner >> 2 & 1, corner >> 1 & 1, corner & 1];
+            let weight: f64 = (0..3)
+                .map(|a| if offset[a] == 1 { f[a] } else { 1.0 - f[a] })
+                .product();
+            if weight > 0.0 {
+                let ijk = std::array::from_fn(|a| base[a] + offset[a]);
+                value += weight * self.value(ijk) as f64;
+            }
+        }
+        value
+    }
+}
+
+/// Loads every float grid of a NanoVDB file, looking it up through the asset search
+/// paths. Files may hold several segments of grids, uncompressed or zlib-compressed.
+pub fn load(filename: &str) -> Result<Vec<VdbGrid>, String> {
+    let path = assets::resolve(filename)?;
+    let bytes = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Reads the grids of a NanoVDB file's contents. Sizes in the file are checked
+/// against its length, and decompressed grids are capped at their recorded size.
+fn parse(bytes: &[u8]) -> Result<Vec<VdbGrid>, String> {
+    let section = |offset: usize, size: usize, what: &str| {
+        offset
+            .checked_add(size)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or_else(|| what.to_string())
+    };
+
+    let mut grids = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let header = section(offset, FILE_HEADER_SIZE, "truncated header")?;
+        let reader = Reader(header);
+        if &header[..7] != MAGIC_PREFIX {
+            return Err("not a NanoVDB file".to_string());
+        }
+        if reader.u32(8) >> 21 != MAJOR_VERSION {
+            return Err(format!(
+                "unsupported version {}, expected {}",
+                reader.u32(8) >> 21,
+                MAJOR_VERSION
+            ));
+        }
+        let grid_count = reader.u16(12) as usize;
+        offset += FILE_HEADER_SIZE;
+
+        // All grid descriptions of the segment come first, then all grid blobs
+        let mut metas = Vec::with_capacity(grid_count);
+        for _ in 0..grid_count {
+            let meta = section(offset, FILE_META_SIZE, "truncated grid description")?;
+            let reader = Reader(meta);
+            let grid_size = reader.u64(0);
+            let file_size = reader.u64(8) as usize;
+            let name_size = reader.u32(136) as usize;
+            let codec = reader.u16(168);
+            offset += FILE_META_SIZE;
+            let name = section(offset, name_size, "truncated grid name")?;
+            let name = String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string();
+            offset += name_size;
+            metas.push((name, grid_size, file_size, codec));
+        }
+
+        for (name, grid_size, file_size, codec) in metas {
+            let blob = section(offset, file_size, &format!("grid '{}' is truncated", name))?;
+            offset += file_size;
+            let data = match codec {
+                CODEC_NONE => blob.to_vec(),
+                // A zlib stream prefixed by its compressed size
+                CODEC_ZIP => {
+                    let mut data = Vec::new();
+                    ZlibDecoder::new(blob.get(8..).unwrap_or_default())
+                        .take(grid_size)
+                        .read_to_end(&mut data)
+                        .map_err(|e| format!("grid '{}': {}", name, e))?;
+                    data
+                }
+                other => {
+                    return Err(format!("grid '{}' uses unsupported codec {}", name, other));
+                }
+            };
+            grids.push(VdbGrid::from_blob(name, data)?);
+        }
+    }
+
+    Ok(grids)
+}
+
+/// Whether `size` bytes at `offset` lie within `data`.
+fn fits(data: &[u8], offset: usize, size: usize) -> bool {
+    offset
+        .checked_add(size)
+        .is_some_and(|end| end <= data.len())
+}
+
+/// Little-endian reads at byte offsets, which the caller has checked are within
+/// the data: headers by their size, grid nodes by `VdbGrid::validate` at load.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> [u8; N] {
+        self.0[offset..offset + N].try_into().unwrap()
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes(self.bytes(offset))
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.bytes(offset))
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.bytes(offset))
+    }
+
+    fn f32(&self, offset: usize) -> f32 {
+        f32::from_le_bytes(self.bytes(offset))
+    }
+
+    fn f64(&self, offset: usize) -> f64 {
+        f64::from_le_bytes(self.bytes(offset))
+    }
+
+    fn vec3(&self, offset: usize) -> Vec3 {
+        Vec3::new(
+            self.f64(offset),
+            self.f64(offset + 8),
+            self.f64(offset + 16),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A float grid with one root tile whose child is an upper node without
+    /// children.
+    fn grid_blob() -> Vec<u8> {
+        let root = TREE + 64;
+        let upper = root + ROOT_SIZE + ROOT_TILE_SIZE;
+        let mut data = vec![0; upper + internal_size(UPPER_LOG2)];
+        data[..8].copy_from_slice(b"NanoVDB0");
+        data[GRID_TYPE..GRID_TYPE + 4].copy_from_slice(&GRID_TYPE_FLOAT.to_le_bytes());
+        data[TREE + 24..TREE + 32].copy_from_slice(&((root - TREE) as u64).to_le_bytes());
+        data[root + 24..root + 28].copy_from_slice(&1u32.to_le_bytes());
+        let tile = root + ROOT_SIZE;
+        data[tile + 8..tile + 16].copy_from_slice(&((upper - root) as u64).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn loads_complete_grid() {
+        let grid = VdbGrid::from_blob("density".to_string(), grid_blob()).unwrap();
+        assert_eq!(grid.value([0, 0, 0]), 0.0);
+    }
+
+    #[test]
+    fn truncated_grid_is_an_error() {
+        let data = grid_blob();
+        for len in [TREE + 64, TREE + 64 + ROOT_SIZE, data.len() - 1] {
+            let result = VdbGrid::from_blob("density".to_string(), data[..len].to_vec());
+            assert!(result.is_err(), "grid cut to {len} bytes loaded");
+        }
+    }
+
+    #[test]
+    fn child_out_of_bounds_is_an_error() {
+        let mut data = grid_blob();
+        let tile = TREE + 64 + ROOT_SIZE;
+        data[tile + 8..tile + 16].copy_from_slice(&(-1_000_000i64).to_le_bytes());
+        assert!(VdbGrid::from_blob("density".to_string(), data.clone()).is_err());
+
+        // A lower node past the end of the upper node
+        let tile_child = ROOT_SIZE + ROOT_TILE_SIZE;
+        let upper = TREE + 64 + tile_child;
+        data[tile + 8..tile + 16].copy_from_slice(&(tile_child as u64).to_le_bytes());
+        data[upper + internal_child_mask(UPPER_LOG2)] = 1;
+        let entry = upper + internal_table(UPPER_LOG2);
+        let past_end = (data.len() - upper) as u64;
+        data[entry..entry + 8].copy_from_slice(&past_end.to_le_bytes());
+        assert!(VdbGrid::from_blob("density".to_string(), data).is_err());
+    }
+
+    /// A file header and one grid description, without the name or blob.
+    fn file_prefix(grid_size: u64, file_size: u64, name_size: u32, codec: u16) -> Vec<u8> {
+        let mut bytes = vec![0; FILE_HEADER_SIZE + FILE_META_SIZE];
+        bytes[..8].copy_from_slice(b"NanoVDB0");
+        bytes[8..12].copy_from_slice(&(MAJOR_VERSION << 21).to_le_bytes());
+        bytes[12..14].copy_from_slice(&1u16.to_le_bytes());
+        let meta = &mut bytes[FILE_HEADER_SIZE..];
+        meta[..8].copy_from_slice(&grid_size.to_le_bytes());
+        meta[8..16].copy_from_slice(&file_size.to_le_bytes());
+        meta[136..140].copy_from_slice(&name_size.to_le_bytes());
+        meta[168..170].copy_from_slice(&codec.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn oversized_file_sizes_are_an_error() {
+        let mut bytes = file_prefix(0, u64::MAX, 0, CODEC_NONE);
+        bytes.push(0);
+        assert!(parse(&bytes).is_err());
+
+        let bytes = file_prefix(0, 0, u32::MAX, CODEC_NONE);
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn decompression_stops_at_grid_size() {
+        use flate2::Compression;
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        // A grid followed by a run of zeros far longer than the recorded size
+        let grid = grid_blob();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&grid).unwrap();
+        encoder.write_all(&vec![0; 1 << 24]).unwrap();
+        let stream = encoder.finish().unwrap();
+        let mut bytes = file_prefix(grid.len() as u64, 8 + stream.len() as u64, 0, CODEC_ZIP);
+        bytes.extend_from_slice(&(stream.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&stream);
+
+        let grids = parse(&bytes).unwrap();
+        assert_eq!(grids[0].data, grid);
+    }
+}
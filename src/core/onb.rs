@@ -1,6 +1,9 @@
 use crate::core::vec3::Vec3;
 
 /// Orthonormal Basis
+///
+/// As a shading frame, `w` is the normal, so directions in local coordinates have
+/// `z` = cos θ and the BSDF math needs no dot products: see `cos_theta` and `reflect`.
 #[derive(Debug, Clone, Copy)]
 #[allow(clippy::upper_case_acronyms)]
 pub struct ONB {
@@ -26,6 +29,23 @@ impl ONB {
         }
     }
 
+    /// Constructs an ONB with `w` along the normal and `u` along `tangent`, made
+    /// perpendicular to it, so the frame follows the surface's parameterization.
+    /// Falls back to `build_from_w` where the tangent is zero or along `w`.
+    pub fn build_from_w_and_tangent(w: &Vec3, tangent: &Vec3) -> Self {
+        let unit_w = w.normalize();
+        let u = tangent - unit_w * tangent.dot(&unit_w);
+        let length = u.norm();
+        if length <= 1e-9 * tangent.norm() || length == 0.0 {
+            return Self::build_from_w(w);
+        }
+        let u = u / length;
+
+        Self {
+            axis: [u, unit_w.cross(&u), unit_w],
+        }
+    }
+
     #[inline]
     pub fn u(&self) -> Vec3 {
         self.axis[0]
@@ -46,4 +66,32 @@ impl ONB {
     pub fn local(&self, a: &Vec3) -> Vec3 {
         self.u() * a.x + self.v() * a.y + self.w() * a.z
     }
+
+    /// Transforms a vector from World coordinates to local ONB coordinates, the
+    /// inverse of `local`.
+    #[inline]
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u()), a.dot(&self.v()), a.dot(&self.w()))
+    }
+}
+
+impl Default for ONB {
+    /// The world axes, with `w` along +z.
+    fn default() -> Self {
+        Self {
+            axis: [Vec3::x(), Vec3::y(), Vec3::z()],
+        }
+    }
+}
+
+/// Cosine of the angle between a local direction and the normal.
+#[inline]
+pub fn cos_theta(w: &Vec3) -> f64 {
+    w.z
+}
+
+/// Mirror reflection of a local direction about the normal.
+#[inline]
+pub fn reflect(w: &Vec3) -> Vec3 {
+    Vec3::new(-w.x, -w.y, w.z)
 }
@@ -0,0 +1,562 @@
+use crate::core::assets;
+use crate::core::fnv::Fnv1a;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::smooth_normals::smooth_normals;
+use crate::geometry::triangle_mesh::TriangleMesh;
+use crate::materials::material_trait::Material;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::UNIX_EPOCH;
+
+/// Directory where loaded models are cached, set once from the command line.
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+const CACHE_MAGIC: &[u8; 4] = b"MSH1";
+
+/// Encoding of the body following the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Scalar property types, by their size in bytes and whether they are integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::UInt8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::UInt16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::UInt32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            other => return Err(format!("unknown property type '{}'", other)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// Full-scale value of integer color channels (`uchar` colors run to 255).
+    fn color_scale(self) -> f64 {
+        match self {
+            Self::Int8 | Self::UInt8 => 255.0,
+            Self::Int16 | Self::UInt16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    scalar: Scalar,
+    /// Type of the item count for list properties.
+    list_count: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Loads a PLY mesh (ASCII or binary, either byte order), such as the Stanford scan
+/// models, looking it up through the asset search paths. Vertex normals (`nx`, `ny`,
+/// `nz`), colors (`red`, `green`, `blue`) and texture coordinates (`u`/`v`, `s`/`t`)
+/// are kept when present; polygons are split into triangle fans. Meshes without
+/// normals get them from `smooth_normals` with `crease_angle` in degrees.
+///
+/// With caching enabled, the mesh is read back from the cache instead while the
+/// file is unchanged.
+pub fn load(
+    filename: &str,
+    material: Arc<dyn Material>,
+    crease_angle: f64,
+) -> Result<TriangleMesh, String> {
+    let path = assets::resolve(filename)?;
+    let cache = CACHE_DIR.get().and_then(|dir| {
+        cache_key(&path, crease_angle).map(|key| dir.join(format!("{:016x}.mesh", key)))
+    });
+    if let Some(cache) = &cache
+        && let Ok(bytes) = std::fs::read(cache)
+    {
+        match decode_mesh(&bytes, material.clone()) {
+            Some(mesh) => return Ok(mesh),
+            None => eprintln!("Ignoring corrupt mesh cache {}", cache.display()),
+        }
+    }
+
+    let bytes = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut mesh = parse(&bytes, material).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if mesh.normals().is_none() {
+        mesh = smooth_normals(&mesh, crease_angle)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    // Write then rename, so concurrent renders never read a partial file
+    if let Some(cache) = cache {
+        let temp = cache.with_extension("tmp");
+        if let Err(e) =
+            std::fs::write(&temp, encode_mesh(&mesh)).and_then(|_| std::fs::rename(&temp, &cache))
+        {
+            eprintln!("Cannot write mesh cache {}: {}", cache.display(), e);
+        }
+    }
+    Ok(mesh)
+}
+
+/// Caches every model `load` parses in `dir`, so repeated renders of a large
+/// model skip parsing it and smoothing its normals.
+pub fn enable_cache(dir: &Path) {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("Cannot create mesh cache {}: {}", dir.display(), e);
+        return;
+    }
+    let _ = CACHE_DIR.set(dir.to_path_buf());
+}
+
+/// Hash of what decides the loaded mesh: the file, by its canonical path, size and
+/// modification time, and the crease angle of smoothed normals.
+fn cache_key(path: &Path, crease_angle: f64) -> Option<u64> {
+    let path = path.canonicalize().ok()?;
+    let metadata = std::fs::metadata(&path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    let mut hash = Fnv1a::default();
+    hash.feed(path.to_string_lossy().as_bytes());
+    hash.feed_u64(metadata.len());
+    hash.feed_u64(modified.as_secs());
+    hash.feed_u64(modified.subsec_nanos() as u64);
+    hash.feed_u64(crease_angle.to_bits());
+    Some(hash.finish())
+}
+
+/// Which optional buffers a cached mesh has.
+const CACHE_NORMALS: u8 = 1;
+const CACHE_UVS: u8 = 2;
+const CACHE_COLORS: u8 = 4;
+
+/// Cache file: magic, vertex and face counts, flags, then positions, the flagged
+/// normals, UVs and colors, and indices (little-endian).
+fn encode_mesh(mesh: &TriangleMesh) -> Vec<u8> {
+    let mut bytes = CACHE_MAGIC.to_vec();
+    bytes.extend_from_slice(&(mesh.vertex_count() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(mesh.face_count() as u64).to_le_bytes());
+    let flags = [
+        (mesh.normals().is_some(), CACHE_NORMALS),
+        (mesh.uvs().is_some(), CACHE_UVS),
+        (mesh.colors().is_some(), CACHE_COLORS),
+    ];
+    bytes.push(
+        flags
+            .iter()
+            .filter(|(has, _)| *has)
+            .map(|(_, flag)| flag)
+            .sum(),
+    );
+
+    let mut put = |values: &[f64]| {
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    };
+    for p in mesh.positions() {
+        put(&[p.x, p.y, p.z]);
+    }
+    for n in mesh.normals().unwrap_or_default() {
+        put(&[n.x, n.y, n.z]);
+    }
+    for &(u, v) in mesh.uvs().unwrap_or_default() {
+        put(&[u, v]);
+    }
+    for c in mesh.colors().unwrap_or_default() {
+        put(&[c.x, c.y, c.z]);
+    }
+    for &index in mesh.indices().as_flattened() {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    bytes
+}
+
+/// Reads a mesh written by `encode_mesh`, or None unless the file holds exactly
+/// the buffers its header announces.
+fn decode_mesh(bytes: &[u8], material: Arc<dyn Material>) -> Option<TriangleMesh> {
+    let mut reader = CacheReader(bytes.strip_prefix(CACHE_MAGIC)?);
+    let vertices = usize::try_from(reader.u64()?).ok()?;
+    let faces = usize::try_from(reader.u64()?).ok()?;
+    let flags = reader.take(1)?[0];
+    let has = |flag: u8| flags & flag != 0;
+
+    let positions = reader.f64s::<3>(vertices)?;
+    let normals = if has(CACHE_NORMALS) {
+        Some(reader.f64s::<3>(vertices)?)
+    } else {
+        None
+    };
+    let uvs = if has(CACHE_UVS) {
+        Some(reader.f64s::<2>(vertices)?)
+    } else {
+        None
+    };
+    let colors = if has(CACHE_COLORS) {
+        Some(reader.f64s::<3>(vertices)?)
+    } else {
+        None
+    };
+    let indices = reader.u32s::<3>(faces)?;
+    if !reader.0.is_empty() {
+        return None;
+    }
+
+    let positions = positions.into_iter().map(|[x, y, z]| Point3::new(x, y, z));
+    let mut mesh = TriangleMesh::new(positions.collect(), indices, material).ok()?;
+    if let Some(normals) = normals {
+        mesh = mesh.with_normals(normals.into_iter().map(Vec3::from).collect());
+    }
+    if let Some(uvs) = uvs {
+        mesh = mesh.with_uvs(uvs.into_iter().map(|[u, v]| (u, v)).collect());
+    }
+    if let Some(colors) = colors {
+        mesh = mesh.with_colors(colors.into_iter().map(Color::from).collect());
+    }
+    Some(mesh)
+}
+
+/// Little-endian reads from the front of a cache file, None past its end.
+struct CacheReader<'a>(&'a [u8]);
+
+impl<'a> CacheReader<'a> {
+    fn take(&mut self, size: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = self.0.split_at_checked(size)?;
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// `count` tuples of `N` floats.
+    fn f64s<const N: usize>(&mut self, count: usize) -> Option<Vec<[f64; N]>> {
+        let bytes = self.take(count.checked_mul(8 * N)?)?;
+        let value = |b: &[u8]| f64::from_le_bytes(b.try_into().unwrap());
+        Some(
+            bytes
+                .chunks_exact(8 * N)
+                .map(|tuple| std::array::from_fn(|n| value(&tuple[8 * n..8 * n + 8])))
+                .collect(),
+        )
+    }
+
+    /// `count` tuples of `N` unsigned integers.
+    fn u32s<const N: usize>(&mut self, count: usize) -> Option<Vec<[u32; N]>> {
+        let bytes = self.take(count.checked_mul(4 * N)?)?;
+        let value = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+        Some(
+            bytes
+                .chunks_exact(4 * N)
+                .map(|tuple| std::array::from_fn(|n| value(&tuple[4 * n..4 * n + 4])))
+                .collect(),
+        )
+    }
+}
+
+fn parse(bytes: &[u8], material: Arc<dyn Material>) -> Result<TriangleMesh, String> {
+    let (format, elements, body) = parse_header(bytes)?;
+    let mut body = Body::new(format, body)?;
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in &elements {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| p.list_count.is_none() && names.contains(&p.name.as_str()))
+        };
+        let xyz = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let rgb = [
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+        ];
+        let uv = [
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let face_list = element.properties.iter().position(|p| {
+            p.list_count.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+        });
+
+        let mut values = vec![0.0; element.properties.len()];
+        let mut list = Vec::new();
+        for _ in 0..element.count {
+            for (n, property) in element.properties.iter().enumerate() {
+                match property.list_count {
+                    None => values[n] = body.read(property.scalar)?,
+                    Some(count) => {
+                        let count = body.read(count)? as usize;
+                        let items = (0..count)
+                            .map(|_| body.read(property.scalar))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if Some(n) == face_list {
+                            list = items;
+                        }
+                    }
+                }
+            }
+
+            if element.name == "vertex" {
+                let get = |index: [Option<usize>; 3]| match index {
+                    [Some(a), Some(b), Some(c)] => Some([values[a], values[b], values[c]]),
+                    _ => None,
+                };
+                let [x, y, z] = get(xyz).ok_or("vertices lack x, y or z")?;
+                positions.push(Point3::new(x, y, z));
+                if let Some([x, y, z]) = get(normal) {
+                    normals.push(Vec3::new(x, y, z));
+                }
+                if let Some([r, g, b]) = get(rgb) {
+                    let scale = element.properties[rgb[0].unwrap()].scalar.color_scale();
+                    colors.push(Color::new(r, g, b) / scale);
+                }
+                if let [Some(u), Some(v)] = uv {
+                    uvs.push((values[u], values[v]));
+                }
+            } else if element.name == "face" {
+                // Fan triangulation of convex polygons
+                let corners: Vec<u32> = list.iter().map(|&n| n as u32).collect();
+                for k in 1..corners.len().saturating_sub(1) {
+                    indices.push([corners[0], corners[k], corners[k + 1]]);
+                }
+            }
+        }
+    }
+
+    if positions.is_empty() || indices.is_empty() {
+        return Err("no vertices or faces".to_string());
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices, material)?;
+    if !normals.is_empty() {
+        mesh = mesh.with_normals(normals);
+    }
+    if !colors.is_empty() {
+        mesh = mesh.with_colors(colors);
+    }
+    if !uvs.is_empty() {
+        mesh = mesh.with_uvs(uvs);
+    }
+    Ok(mesh)
+}
+
+/// Reads the header up to `end_header`, returning the body's format, its elements in
+/// order and the bytes that follow.
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, &[u8]), String> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or("not a PLY file (no end_header)")?;
+    let header = String::from_utf8_lossy(&bytes[..end]);
+    // The body starts after the line break ending `end_header`
+    let mut body_start = end + END.len();
+    if bytes.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    if bytes.get(body_start) == Some(&b'\n') {
+        body_start += 1;
+    }
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", name, _version] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    other => return Err(format!("unknown format '{}'", other)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("invalid element count '{}'", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, scalar, name] => elements
+                .last_mut()
+                .ok_or("property before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(scalar)?,
+                    list_count: Some(Scalar::parse(count)?),
+                }),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or("property before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(scalar)?,
+                    list_count: None,
+                }),
+            // Comments, obj_info and blank lines
+            _ => {}
+        }
+    }
+
+    let format = format.ok_or("missing format line")?;
+    Ok((format, elements, &bytes[body_start..]))
+}
+
+/// Sequential reader of body values, as text tokens or binary scalars.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Body<'a> {
+    fn new(format: Format, bytes: &'a [u8]) -> Result<Self, String> {
+        let text = if format == Format::Ascii {
+            std::str::from_utf8(bytes).map_err(|_| "ASCII body is not valid text")?
+        } else {
+            ""
+        };
+        Ok(Self {
+            format,
+            bytes,
+            offset: 0,
+            tokens: text.split_ascii_whitespace(),
+        })
+    }
+
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.tokens.next().ok_or("unexpected end of file")?;
+            return token
+                .parse()
+                .map_err(|_| format!("invalid number '{}'", token));
+        }
+
+        let size = scalar.size();
+        let raw = self
+            .bytes
+            .get(self.offset..self.offset + size)
+            .ok_or("unexpected end of file")?;
+        self.offset += size;
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(raw);
+        if self.format == Format::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+
+        let b = buffer;
+        Ok(match scalar {
+            Scalar::Int8 => b[0] as i8 as f64,
+            Scalar::UInt8 => b[0] as f64,
+            Scalar::Int16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::UInt16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::Int32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::UInt32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::Float32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::Float64 => f64::from_le_bytes(b),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use crate::textures::solid_color::SolidColor;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+            0.5, 0.5, 0.5,
+        ))))
+    }
+
+    fn quad() -> TriangleMesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.25),
+        ];
+        TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], material())
+            .unwrap()
+            .with_normals(vec![Vec3::new(0.0, 0.0, 1.0); 4])
+            .with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+    }
+
+    #[test]
+    fn cached_mesh_reads_back() {
+        let mesh = quad();
+        let read = decode_mesh(&encode_mesh(&mesh), material()).unwrap();
+        assert_eq!(read.positions(), mesh.positions());
+        assert_eq!(read.normals(), mesh.normals());
+        assert_eq!(read.uvs(), mesh.uvs());
+        assert_eq!(read.colors(), None);
+        assert_eq!(read.indices(), mesh.indices());
+    }
+
+    #[test]
+    fn corrupt_cached_mesh_is_rejected() {
+        let bytes = encode_mesh(&quad());
+        assert!(decode_mesh(&bytes[..bytes.len() - 1], material()).is_none());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(decode_mesh(&longer, material()).is_none());
+
+        // A face past the last vertex
+        let mut bytes = bytes;
+        let last = bytes.len() - 4;
+        bytes[last..].copy_from_slice(&9u32.to_le_bytes());
+        assert!(decode_mesh(&bytes, material()).is_none());
+
+        // A vertex count that overflows the buffer sizes
+        let mut bytes = encode_mesh(&quad());
+        bytes[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decode_mesh(&bytes, material()).is_none());
+    }
+}
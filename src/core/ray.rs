@@ -5,16 +5,68 @@ pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
     pub time: f64,
+    /// Offset rays through the neighboring pixels, if tracked.
+    pub differentials: Option<RayDifferentials>,
+}
+
+/// Rays through the next pixel over (x) and down (y) that accompany a camera ray
+/// and its specular bounces, so surfaces know the footprint of a pixel where it
+/// lands: how far apart neighboring pixels' hits are, in space and in texture
+/// coordinates. Diffuse bounces spread light too widely to track and drop them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayDifferentials {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3,
 }
 
 impl Ray {
     #[inline]
     pub const fn new(orig: Point3, dir: Vec3, time: f64) -> Self {
-        Self { orig, dir, time }
+        Self {
+            orig,
+            dir,
+            time,
+            differentials: None,
+        }
+    }
+
+    pub fn with_differentials(mut self, differentials: Option<RayDifferentials>) -> Self {
+        self.differentials = differentials;
+        self
     }
 
     #[inline]
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
     }
+
+    /// Brings the offset rays `scale` times as close to the ray, such as to the
+    /// spacing of the samples rather than the pixels when several are taken per pixel.
+    pub fn scale_differentials(&mut self, scale: f64) {
+        let (orig, dir) = (self.orig, self.dir);
+        if let Some(d) = &mut self.differentials {
+            d.rx_origin = orig + (d.rx_origin - orig) * scale;
+            d.rx_direction = dir + (d.rx_direction - dir) * scale;
+            d.ry_origin = orig + (d.ry_origin - orig) * scale;
+            d.ry_direction = dir + (d.ry_direction - dir) * scale;
+        }
+    }
+
+    /// The ray, with its differentials, under a change of space given by how it
+    /// maps points and vectors. Directions are not renormalized, so `t` carries over.
+    pub fn map(&self, point: impl Fn(&Point3) -> Point3, vector: impl Fn(&Vec3) -> Vec3) -> Ray {
+        Ray {
+            orig: point(&self.orig),
+            dir: vector(&self.dir),
+            time: self.time,
+            differentials: self.differentials.map(|d| RayDifferentials {
+                rx_origin: point(&d.rx_origin),
+                rx_direction: vector(&d.rx_direction),
+                ry_origin: point(&d.ry_origin),
+                ry_direction: vector(&d.ry_direction),
+            }),
+        }
+    }
 }
@@ -0,0 +1,114 @@
+use crate::core::metadata;
+use crate::core::vec3::Color;
+use exr::block::writer::ChunksWriter;
+use exr::block::{BlockIndex, UncompressedBlock};
+use exr::math::{RoundingMode, Vec2};
+use exr::meta::BlockDescription;
+use exr::meta::attribute::{ChannelDescription, LevelMode, LineOrder, SampleType, TileDescription};
+use exr::meta::header::Header;
+use exr::prelude::{Compression, SmallVec, Text};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc;
+
+/// Edge length of the tiles in the file, the OpenEXR default.
+pub const TILE_SIZE: u32 = 64;
+
+/// A rectangle of pixels, clipped to the image at its right and bottom edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Renders a tiled, float RGB OpenEXR image straight to `filename`, calling
+/// `render_tile` on every tile in parallel and writing each one as soon as it is
+/// done, so no more than a few tiles are ever held in memory. `render_tile`
+/// returns the tile's colors row by row. The file is only complete, and readable,
+/// once every tile has been written.
+pub fn render<F>(filename: &str, width: u32, height: u32, render_tile: F) -> Result<(), String>
+where
+    F: Fn(Tile) -> Vec<Color> + Sync,
+{
+    let error = |e: exr::error::Error| format!("{}: {}", filename, e);
+    let channels: SmallVec<[ChannelDescription; 5]> = ["B", "G", "R"]
+        .iter()
+        .map(|&name| ChannelDescription::named(name, SampleType::F32))
+        .collect();
+    let mut header = Header::new(Text::from(""), (width as usize, height as usize), channels)
+        .with_encoding(
+            Compression::ZIP16,
+            BlockDescription::Tiles(TileDescription {
+                tile_size: Vec2(TILE_SIZE as usize, TILE_SIZE as usize),
+                level_mode: LevelMode::Singular,
+                rounding_mode: RoundingMode::Down,
+            }),
+            // Tiles may be written in whatever order they finish
+            LineOrder::Unspecified,
+        );
+    // The header goes first, so only what was recorded before rendering is in it
+    metadata::add_exr_attributes(&mut header.own_attributes);
+
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let file = File::create(filename).map_err(|e| format!("{}: {}", filename, e))?;
+    exr::block::write(
+        BufWriter::new(file),
+        SmallVec::from_elem(header, 1),
+        true,
+        |meta, writer| {
+            // A bounded queue makes renderers wait for the writer rather than pile
+            // up finished tiles
+            let (sender, receiver) = mpsc::sync_channel(2 * rayon::current_num_threads());
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    // Stops early once the writer has failed and hung up
+                    let _ = (0..tiles_x * tiles_y).into_par_iter().try_for_each_with(
+                        sender,
+                        |sender, index| {
+                            let tile = Tile {
+                                x: (index % tiles_x) * TILE_SIZE,
+                                y: (index / tiles_x) * TILE_SIZE,
+                                width: TILE_SIZE.min(width - (index % tiles_x) * TILE_SIZE),
+                                height: TILE_SIZE.min(height - (index / tiles_x) * TILE_SIZE),
+                            };
+                            sender.send((index as usize, tile, render_tile(tile)))
+                        },
+                    );
+                });
+
+                for (index, tile, colors) in receiver {
+                    let block = UncompressedBlock {
+                        index: BlockIndex {
+                            layer: 0,
+                            pixel_position: Vec2(tile.x as usize, tile.y as usize),
+                            pixel_size: Vec2(tile.width as usize, tile.height as usize),
+                            level: Vec2(0, 0),
+                        },
+                        data: block_data(&tile, &colors),
+                    };
+                    writer.write_chunk(index, block.compress_to_chunk(&meta.headers)?)?;
+                }
+                Ok(())
+            })
+        },
+    )
+    .map_err(error)
+}
+
+/// Native-endian samples of a tile as OpenEXR lays them out: row by row, and
+/// within a row all blue values, then green, then red.
+fn block_data(tile: &Tile, colors: &[Color]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(colors.len() * 3 * size_of::<f32>());
+    for row in colors.chunks(tile.width as usize) {
+        for channel in [2, 1, 0] {
+            for color in row {
+                data.extend_from_slice(&(color[channel] as f32).to_ne_bytes());
+            }
+        }
+    }
+    data
+}
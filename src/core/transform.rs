@@ -0,0 +1,177 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Point3, Vec3};
+use crate::sampling::random::degrees_to_radians;
+use nalgebra::{Matrix3, Matrix4, Rotation3, Unit, UnitQuaternion};
+
+/// Affine transform of points, vectors and normals, kept together with its
+/// inverse so both directions cost a matrix product.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Matrix4<f64>,
+    inv: Matrix4<f64>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            m: Matrix4::identity(),
+            inv: Matrix4::identity(),
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            m: Matrix4::new_translation(&offset),
+            inv: Matrix4::new_translation(&-offset),
+        }
+    }
+
+    /// Rotation by `degrees` about `axis`, counter-clockwise looking down the
+    /// axis towards the origin, as `RotateY` turns about the y axis.
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(axis), degrees_to_radians(degrees));
+        let m = rotation.to_homogeneous();
+        Self {
+            m,
+            inv: m.transpose(),
+        }
+    }
+
+    /// Rotation about the origin by a unit quaternion.
+    pub fn from_rotation(rotation: &UnitQuaternion<f64>) -> Self {
+        let m = rotation.to_homogeneous();
+        Self {
+            m,
+            inv: m.transpose(),
+        }
+    }
+
+    /// Scale about the origin by a factor per axis, none of which may be zero.
+    pub fn scaling(factor: Vec3) -> Self {
+        Self {
+            m: Matrix4::new_nonuniform_scaling(&factor),
+            inv: Matrix4::new_nonuniform_scaling(&factor.map(|f| 1.0 / f)),
+        }
+    }
+
+    /// The affine transform of `matrix`, whose last row must be (0, 0, 0, 1), or
+    /// None if it cannot be inverted.
+    pub fn from_matrix(matrix: Matrix4<f64>) -> Option<Self> {
+        Some(Self {
+            m: matrix,
+            inv: matrix.try_inverse()?,
+        })
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            m: next.m * self.m,
+            inv: self.inv * next.inv,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.m
+    }
+
+    fn linear(&self) -> Matrix3<f64> {
+        self.m.fixed_view::<3, 3>(0, 0).into_owned()
+    }
+
+    fn inverse_transpose(&self) -> Matrix3<f64> {
+        self.inv.fixed_view::<3, 3>(0, 0).transpose()
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.m.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.m.transform_vector(v)
+    }
+
+    /// Normals transform by the inverse transpose, which keeps them perpendicular
+    /// to the transformed surface, and come out normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        (self.inverse_transpose() * n).normalize()
+    }
+
+    /// The ray with its origin, direction and differentials transformed. The
+    /// direction is not renormalized, so points along it keep their ray parameter `t`.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        r.map(|p| self.point(p), |v| self.vector(v))
+    }
+
+    /// Moves a hit found along the object-space ray of `r` into the space this
+    /// transform maps to: point, normals, tangents and differentials.
+    pub fn interaction_to_world(&self, r: &Ray, isect: &mut Interaction) {
+        isect.p = self.point(&isect.p);
+        // The inverse transpose keeps the sign of the normals' dot product with the
+        // ray, so the object's face flag still holds, even when mirrored
+        isect.geometry_normal = self.normal(&isect.geometry_normal);
+        // Tangents first, as the shading frame is rebuilt along them
+        isect.dpdu = self.vector(&isect.dpdu);
+        isect.dpdv = self.vector(&isect.dpdv);
+        isect.set_shading_normal(self.normal(&isect.shading_normal));
+        isect.dpdx = self.vector(&isect.dpdx);
+        isect.dpdy = self.vector(&isect.dpdy);
+        isect.wo = -r.dir.normalize();
+    }
+
+    /// Box enclosing the transformed corners of `bbox`. Empty boxes stay empty.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        if bbox.x.min > bbox.x.max || bbox.y.min > bbox.y.max || bbox.z.min > bbox.z.max {
+            return *bbox;
+        }
+        let (xs, ys, zs) = (
+            [bbox.x.min, bbox.x.max],
+            [bbox.y.min, bbox.y.max],
+            [bbox.z.min, bbox.z.max],
+        );
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let p = self.point(&Point3::new(
+                xs[corner & 1],
+                ys[(corner >> 1) & 1],
+                zs[(corner >> 2) & 1],
+            ));
+            min = min.inf(&p);
+            max = max.sup(&p);
+        }
+        Aabb::new_point(min, max)
+    }
+
+    /// Absolute determinant of the linear part, the ratio of volumes.
+    pub fn determinant(&self) -> f64 {
+        self.linear().determinant().abs()
+    }
+
+    /// Ratio of areas of a surface patch with unit normal `n` before the transform.
+    pub fn area_scale(&self, n: &Vec3) -> f64 {
+        self.determinant() * (self.inverse_transpose() * n).norm()
+    }
+
+    /// Ratio of solid angles around the direction `u`, a unit vector before the
+    /// transform: det / |Mu|^3, one for rotations and uniform scales.
+    pub fn solid_angle_scale(&self, u: &Vec3) -> f64 {
+        self.determinant() / self.vector(u).norm().powi(3)
+    }
+}
@@ -1,8 +1,27 @@
+pub mod accelerator;
 pub mod bvh;
+pub mod clip;
 pub mod constant_medium;
+pub mod cylinder;
+pub mod depth_limit;
+pub mod disk;
+pub mod displacement;
+pub mod editable_scene;
+pub mod hit_hook;
 pub mod hittable;
 pub mod hittable_list;
+pub mod kdtree;
+pub mod lattice;
+pub mod material_override;
+pub mod medium;
+pub mod named;
 pub mod quad;
+pub mod scene_graph;
+pub mod simplify;
+pub mod smooth_normals;
 pub mod sphere;
+pub mod sun;
 pub mod transforms;
 pub mod triangle;
+pub mod triangle_mesh;
+pub mod vdb_medium;
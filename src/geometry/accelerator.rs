@@ -0,0 +1,60 @@
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::hittable::Hittable;
+use crate::geometry::kdtree::KdTree;
+use crate::geometry::triangle_mesh::TriangleMesh;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// Accelerator of the scenes' large meshes, set once from the command line.
+static DEFAULT: OnceLock<Accelerator> = OnceLock::new();
+
+/// Structure the scenes' large meshes are put in. Which one traces faster depends
+/// on the layout: kd-trees tend to win on dense, evenly tessellated geometry, BVHs
+/// on scattered objects of very different sizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Accelerator {
+    /// A `BvhNode`, built by the default `BvhBuilder`.
+    #[default]
+    Bvh,
+    /// A `KdTree` split by the surface area heuristic.
+    KdTree,
+}
+
+impl Accelerator {
+    pub fn set_default(accelerator: Accelerator) {
+        let _ = DEFAULT.set(accelerator);
+    }
+
+    pub fn default_accelerator() -> Accelerator {
+        DEFAULT.get().copied().unwrap_or_default()
+    }
+
+    /// Builds the default accelerator over `objects`.
+    pub fn build(objects: Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+        match Self::default_accelerator() {
+            Accelerator::Bvh => Arc::new(BvhNode::new_from_objects(objects)),
+            Accelerator::KdTree => Arc::new(KdTree::new_from_objects(objects)),
+        }
+    }
+
+    /// Builds the default accelerator over the faces of `mesh`, through the BVH
+    /// layout cache when it is a BVH.
+    pub fn build_mesh(mesh: &Arc<TriangleMesh>) -> Arc<dyn Hittable> {
+        match Self::default_accelerator() {
+            Accelerator::Bvh => Arc::new(BvhNode::new_mesh(mesh)),
+            Accelerator::KdTree => Arc::new(KdTree::new_from_objects(mesh.faces())),
+        }
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bvh" => Ok(Self::Bvh),
+            "kdtree" => Ok(Self::KdTree),
+            _ => Err(format!("Unknown accelerator '{}'", s)),
+        }
+    }
+}
@@ -1,58 +1,499 @@
 use crate::core::aabb::Aabb;
+use crate::core::fnv::Fnv1a;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
+use crate::core::memory::{self, Category};
 use crate::core::ray::Ray;
-use crate::geometry::hittable::Hittable;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, SHUTTER, roulette_transmittance};
 use crate::geometry::hittable_list::HittableList;
-use crate::sampling::random::random_int_range;
+use crate::geometry::triangle_mesh::TriangleMesh;
+use crate::sampling::random::{random_double, random_int_range};
+use rayon::prelude::*;
+use std::cell::Cell;
 use std::cmp::Ordering;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// Directory where built BVH layouts are cached, set once from the command line.
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Builder of BVHs constructed without one, set once from the command line.
+static DEFAULT_BUILDER: OnceLock<BvhBuilder> = OnceLock::new();
+/// Meshes of fewer faces build faster than the cache file can be read.
+const CACHE_MIN_OBJECTS: usize = 1000;
+const CACHE_MAGIC: &[u8; 4] = b"BVH2";
+/// Layout token of an interior node; any other token is a primitive index.
+const INTERIOR: u32 = u32::MAX;
+/// Deepest node accepted from a cached layout. Median splits of `u32::MAX`
+/// primitives nest about 33 deep, so deeper layouts are corrupt, and rejecting
+/// them keeps the recursive rebuild from overflowing the stack.
+const MAX_LAYOUT_DEPTH: usize = 64;
+/// Clusters on either side of one that the linear builder looks through for its
+/// best merge; larger windows find better trees, more slowly.
+const LINEAR_SEARCH_RADIUS: usize = 8;
+/// Bits per axis of the Morton codes of the linear builder.
+const MORTON_BITS: u32 = 10;
+
+/// How a `BvhNode` hierarchy is built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BvhBuilder {
+    /// Top down, sorting each node's primitives along a random axis and splitting
+    /// them in half, as in the books.
+    #[default]
+    Median,
+    /// Bottom up from primitives sorted along a Morton curve, repeatedly merging
+    /// clusters with the neighbour that gives them the smallest box (locally
+    /// ordered clustering). Fast to build, for interactive and preview renders.
+    Linear,
+}
+
+impl FromStr for BvhBuilder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(Self::Median),
+            "lbvh" => Ok(Self::Linear),
+            _ => Err(format!("Unknown BVH builder '{}'", s)),
+        }
+    }
+}
+
+thread_local! {
+    /// Traversal work done by this thread since the last `take_traversal_stats`.
+    static TRAVERSAL: Cell<TraversalStats> = const { Cell::new(TraversalStats::new()) };
+}
+
+/// Counts of the bounding box and primitive tests made while traversing BVHs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraversalStats {
+    /// Nodes whose bounding box a ray was tested against.
+    pub node_tests: u64,
+    /// Primitives in leaves whose bounding box the ray entered.
+    pub primitive_tests: u64,
+}
+
+impl TraversalStats {
+    const fn new() -> Self {
+        Self {
+            node_tests: 0,
+            primitive_tests: 0,
+        }
+    }
+}
+
+/// Returns the traversal work done by the calling thread and resets its counters, so
+/// a caller can attribute the tests made between two calls, e.g. to one pixel.
+pub fn take_traversal_stats() -> TraversalStats {
+    TRAVERSAL.take()
+}
+
+/// Records one node test and, if the ray entered the node, the tests of its primitives.
+pub(crate) fn count_traversal(primitives: u64, entered: bool) {
+    TRAVERSAL.with(|stats| {
+        let mut counts = stats.get();
+        counts.node_tests += 1;
+        if entered {
+            counts.primitive_tests += primitives;
+        }
+        stats.set(counts);
+    });
+}
 
 #[derive(Debug)]
 pub struct BvhNode {
     left: Arc<dyn Hittable>,
     right: Arc<dyn Hittable>,
     bbox: Aabb,
+    /// Distinct primitives held by a leaf (1 or 2), or 0 for an interior node.
+    primitives: u8,
+    /// Light sampling weights of the subtrees: their surface area, as for
+    /// `HittableList::add`. The right one is 0 in single-primitive leaves.
+    left_weight: f64,
+    right_weight: f64,
+}
+
+/// Sampling weight of a primitive: its area, or 1 if unknown.
+fn sampling_weight(object: &Arc<dyn Hittable>) -> f64 {
+    let area = object.area();
+    if area > 0.0 { area } else { 1.0 }
+}
+
+/// Approximate bytes of the nodes of a BVH over `objects` primitives: about one
+/// node, behind an `Arc`, per primitive.
+fn memory_size(objects: usize) -> usize {
+    objects * (size_of::<BvhNode>() + 2 * size_of::<usize>())
+}
+
+/// Records the nodes of a BVH over `objects` primitives for memory accounting.
+fn record_memory(objects: usize) {
+    memory::record(Category::Bvhs, memory_size(objects));
+}
+
+/// Takes back what building a BVH over `objects` primitives recorded, for
+/// hierarchies that are replaced while the program runs.
+pub(crate) fn release_memory(objects: usize) {
+    memory::release(Category::Bvhs, memory_size(objects));
 }
 
 impl BvhNode {
+    /// Builds a BVH over the list with the default builder.
     pub fn new(list: &HittableList) -> Self {
         Self::new_from_objects(list.objects.clone())
     }
 
-    pub fn new_from_objects(mut objects: Vec<Arc<dyn Hittable>>) -> Self {
+    /// Builds a BVH over the faces of `mesh` with the default builder, reusing a
+    /// cached layout when caching is enabled.
+    pub fn new_mesh(mesh: &Arc<TriangleMesh>) -> Self {
+        let faces = mesh.faces();
+        match CACHE_DIR.get() {
+            Some(dir)
+                if faces.len() >= CACHE_MIN_OBJECTS
+                    && Self::default_builder() == BvhBuilder::Median =>
+            {
+                Self::new_cached(faces, mesh_key(mesh), dir)
+            }
+            _ => Self::new_from_objects(faces),
+        }
+    }
+
+    /// Builds a BVH over `objects` with the default builder.
+    pub fn new_from_objects(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        Self::with_builder(objects, Self::default_builder())
+    }
+
+    /// Builds a BVH over `objects` with `builder`, never cached.
+    pub fn with_builder(objects: Vec<Arc<dyn Hittable>>, builder: BvhBuilder) -> Self {
+        record_memory(objects.len());
+        if builder == BvhBuilder::Linear && objects.len() > 2 {
+            return Self::build_linear(objects);
+        }
+        let indexed = objects
+            .into_iter()
+            .enumerate()
+            .map(|(index, object)| (index as u32, object))
+            .collect();
+        Self::build(indexed, &mut Vec::new())
+    }
+
+    /// Makes `builder` the one used by `new` and `new_from_objects`. Layouts are
+    /// only cached for the median builder.
+    pub fn set_default_builder(builder: BvhBuilder) {
+        let _ = DEFAULT_BUILDER.set(builder);
+    }
+
+    fn default_builder() -> BvhBuilder {
+        DEFAULT_BUILDER.get().copied().unwrap_or_default()
+    }
+
+    /// Caches built layouts in `dir` for every mesh of at least `CACHE_MIN_OBJECTS`
+    /// faces, so repeated renders of a large model skip the build.
+    pub fn enable_cache(dir: &Path) {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Cannot create BVH cache {}: {}", dir.display(), e);
+            return;
+        }
+        let _ = CACHE_DIR.set(dir.to_path_buf());
+    }
+
+    /// Builds over a mesh's faces, reading the layout from the cache file of the
+    /// mesh's `key` if it has one that fits, and writing it otherwise.
+    fn new_cached(objects: Vec<Arc<dyn Hittable>>, key: u64, dir: &Path) -> Self {
+        record_memory(objects.len());
+        let path = dir.join(format!("{:016x}.bvh", key));
+
+        if let Ok(bytes) = std::fs::read(&path) {
+            match decode_layout(&bytes)
+                .and_then(|tokens| Self::from_cached_layout(&objects, tokens))
+            {
+                Some(node) => return node,
+                None => eprintln!("Ignoring corrupt BVH cache {}", path.display()),
+            }
+        }
+
+        let mut layout = Vec::new();
+        let indexed = objects
+            .into_iter()
+            .enumerate()
+            .map(|(index, object)| (index as u32, object))
+            .collect();
+        let node = Self::build(indexed, &mut layout);
+
+        // Write then rename, so concurrent renders never read a partial file
+        let temp = path.with_extension("tmp");
+        if let Err(e) = std::fs::write(&temp, encode_layout(&layout))
+            .and_then(|_| std::fs::rename(&temp, &path))
+        {
+            eprintln!("Cannot write BVH cache {}: {}", path.display(), e);
+        }
+        node
+    }
+
+    /// Builds the tree over indexed objects, appending its layout in preorder:
+    /// `INTERIOR left right` for interior nodes, two primitive indices for leaves.
+    fn build(mut objects: Vec<(u32, Arc<dyn Hittable>)>, layout: &mut Vec<u32>) -> Self {
         let axis = random_int_range(0, 2) as usize;
-        let comparator =
-            |a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>| Self::box_compare(a, b, axis);
+        let comparator = |a: &(u32, Arc<dyn Hittable>), b: &(u32, Arc<dyn Hittable>)| {
+            Self::box_compare(&a.1, &b.1, axis)
+        };
 
         let object_span = objects.len();
 
-        let (left, right) = if object_span == 1 {
-            (objects[0].clone(), objects[0].clone())
-        } else if object_span == 2 {
-            if comparator(&objects[0], &objects[1]) == Ordering::Less {
-                (objects[0].clone(), objects[1].clone())
-            } else {
-                (objects[1].clone(), objects[0].clone())
-            }
+        let (left, right, primitives, weights) = if object_span <= 2 {
+            let (first, second) =
+                if object_span == 1 || comparator(&objects[0], &objects[1]) == Ordering::Less {
+                    (0, object_span - 1)
+                } else {
+                    (1, 0)
+                };
+            layout.extend([objects[first].0, objects[second].0]);
+            let (left, right) = (objects[first].1.clone(), objects[second].1.clone());
+            let weights = Self::leaf_weights(&left, &right, object_span as u8);
+            (left, right, object_span as u8, weights)
         } else {
             objects.sort_by(comparator);
-            let mid = object_span / 2;
-            let (left_objs, right_objs) = objects.split_at(mid);
+            layout.push(INTERIOR);
+            let right_objs = objects.split_off(object_span / 2);
+            let left = Self::build(objects, layout);
+            let right = Self::build(right_objs, layout);
+            let weights = (left.weight(), right.weight());
             (
-                Arc::new(Self::new_from_objects(left_objs.to_vec())) as Arc<dyn Hittable>,
-                Arc::new(Self::new_from_objects(right_objs.to_vec())) as Arc<dyn Hittable>,
+                Arc::new(left) as Arc<dyn Hittable>,
+                Arc::new(right) as Arc<dyn Hittable>,
+                0,
+                weights,
             )
         };
 
-        let bbox = left.bounding_box().merge(&right.bounding_box());
+        let bbox = left
+            .bounding_box(SHUTTER)
+            .merge(&right.bounding_box(SHUTTER));
+
+        Self {
+            left,
+            right,
+            bbox,
+            primitives,
+            left_weight: weights.0,
+            right_weight: weights.1,
+        }
+    }
+
+    /// Builds the tree bottom up: primitives sorted by the Morton codes of their
+    /// centroids start as clusters, and each round merges every pair of clusters
+    /// that are each other's best partner within `LINEAR_SEARCH_RADIUS` places,
+    /// best meaning the smallest surface area of their joint box. Merged clusters
+    /// keep their place in the order, so neighbours stay close in space.
+    fn build_linear(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let boxes: Vec<Aabb> = objects.iter().map(|o| o.bounding_box(SHUTTER)).collect();
+        let centroids = boxes.iter().fold(Aabb::empty(), |bounds, bbox| {
+            let c = centroid(bbox);
+            bounds.merge(&Aabb::new_point(c, c))
+        });
+        let mut sorted: Vec<(u32, usize)> = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, bbox)| (morton_code(&centroid(bbox), &centroids), index))
+            .collect();
+        sorted.sort_unstable();
+
+        let mut clusters: Vec<(Cluster, Aabb)> = sorted
+            .into_iter()
+            .map(|(_, index)| (Cluster::Primitive(objects[index].clone()), boxes[index]))
+            .collect();
+        while clusters.len() > 1 {
+            let n = clusters.len();
+            // Ties go to the lower pair of indices, so the best pair overall is
+            // always mutual and every round merges at least once
+            let nearest: Vec<usize> = (0..n)
+                .into_par_iter()
+                .map(|i| {
+                    let window = i.saturating_sub(LINEAR_SEARCH_RADIUS)
+                        ..(i + LINEAR_SEARCH_RADIUS + 1).min(n);
+                    window
+                        .filter(|&j| j != i)
+                        .min_by(|&a, &b| {
+                            let cost =
+                                |j: usize| surface_area(&clusters[i].1.merge(&clusters[j].1));
+                            cost(a)
+                                .total_cmp(&cost(b))
+                                .then((i.min(a), i.max(a)).cmp(&(i.min(b), i.max(b))))
+                        })
+                        .unwrap()
+                })
+                .collect();
+
+            let mut pending: Vec<Option<(Cluster, Aabb)>> =
+                clusters.into_iter().map(Some).collect();
+            clusters = Vec::with_capacity(n);
+            for i in 0..n {
+                let j = nearest[i];
+                if nearest[j] != i {
+                    clusters.push(pending[i].take().unwrap());
+                } else if i < j {
+                    let (a, box_a) = pending[i].take().unwrap();
+                    let (b, box_b) = pending[j].take().unwrap();
+                    clusters.push((Cluster::Node(Self::join(a, b)), box_a.merge(&box_b)));
+                }
+            }
+        }
+
+        match clusters.pop().unwrap().0 {
+            Cluster::Node(node) => node,
+            Cluster::Primitive(_) => unreachable!("more than two objects merge into a node"),
+        }
+    }
+
+    /// Node over two clusters: a leaf if both are primitives, else an interior node
+    /// with a lone primitive put in a leaf of its own, as leaves hold only primitives.
+    fn join(a: Cluster, b: Cluster) -> Self {
+        let (left, right, primitives, weights) = match (a, b) {
+            (Cluster::Primitive(left), Cluster::Primitive(right)) => {
+                let weights = Self::leaf_weights(&left, &right, 2);
+                (left, right, 2, weights)
+            }
+            (a, b) => {
+                let (left, right) = (a.into_node(), b.into_node());
+                let weights = (left.weight(), right.weight());
+                (
+                    Arc::new(left) as Arc<dyn Hittable>,
+                    Arc::new(right) as Arc<dyn Hittable>,
+                    0,
+                    weights,
+                )
+            }
+        };
+
+        let bbox = left
+            .bounding_box(SHUTTER)
+            .merge(&right.bounding_box(SHUTTER));
 
-        Self { left, right, bbox }
+        Self {
+            left,
+            right,
+            bbox,
+            primitives,
+            left_weight: weights.0,
+            right_weight: weights.1,
+        }
+    }
+
+    /// Rebuilds a tree from a cached layout, or None unless it is a whole tree,
+    /// no deeper than `MAX_LAYOUT_DEPTH`, holding each of `objects` exactly once.
+    fn from_cached_layout(objects: &[Arc<dyn Hittable>], tokens: Vec<u32>) -> Option<Self> {
+        let mut seen = vec![false; objects.len()];
+        let mut tokens = tokens.into_iter();
+        let node = Self::from_layout(objects, &mut tokens, &mut seen, 0)?;
+        (tokens.next().is_none() && seen.iter().all(|&seen| seen)).then_some(node)
+    }
+
+    /// Rebuilds the subtree at the front of `tokens`, at `depth`, marking the
+    /// primitives it takes in `seen`; None if one is taken twice or does not exist.
+    fn from_layout(
+        objects: &[Arc<dyn Hittable>],
+        tokens: &mut impl Iterator<Item = u32>,
+        seen: &mut [bool],
+        depth: usize,
+    ) -> Option<Self> {
+        if depth > MAX_LAYOUT_DEPTH {
+            return None;
+        }
+        // Draw the split axis a fresh build would, keeping seeded scenes reproducible
+        random_int_range(0, 2);
+
+        let mut take = |index: u32| {
+            let slot = seen.get_mut(index as usize)?;
+            if std::mem::replace(slot, true) {
+                return None;
+            }
+            objects.get(index as usize).cloned()
+        };
+        let (left, right, primitives, weights) = match tokens.next()? {
+            INTERIOR => {
+                let left = Self::from_layout(objects, tokens, seen, depth + 1)?;
+                let right = Self::from_layout(objects, tokens, seen, depth + 1)?;
+                let weights = (left.weight(), right.weight());
+                (
+                    Arc::new(left) as Arc<dyn Hittable>,
+                    Arc::new(right) as Arc<dyn Hittable>,
+                    0,
+                    weights,
+                )
+            }
+            first => {
+                let second = tokens.next()?;
+                let left = take(first)?;
+                // A single-primitive leaf holds it in both slots
+                let right = if second == first {
+                    left.clone()
+                } else {
+                    take(second)?
+                };
+                let primitives = if first == second { 1 } else { 2 };
+                let weights = Self::leaf_weights(&left, &right, primitives);
+                (left, right, primitives, weights)
+            }
+        };
+
+        let bbox = left
+            .bounding_box(SHUTTER)
+            .merge(&right.bounding_box(SHUTTER));
+
+        Some(Self {
+            left,
+            right,
+            bbox,
+            primitives,
+            left_weight: weights.0,
+            right_weight: weights.1,
+        })
+    }
+
+    /// Weights of a leaf's primitives; a single primitive fills both slots but is
+    /// weighted once.
+    fn leaf_weights(
+        left: &Arc<dyn Hittable>,
+        right: &Arc<dyn Hittable>,
+        primitives: u8,
+    ) -> (f64, f64) {
+        let right_weight = if primitives == 2 {
+            sampling_weight(right)
+        } else {
+            0.0
+        };
+        (sampling_weight(left), right_weight)
+    }
+
+    /// Total light sampling weight of the subtree.
+    fn weight(&self) -> f64 {
+        self.left_weight + self.right_weight
+    }
+
+    /// Probability of descending into the left subtree when sampling.
+    fn left_probability(&self) -> f64 {
+        let total = self.weight();
+        if total > 0.0 {
+            self.left_weight / total
+        } else {
+            1.0
+        }
+    }
+
+    /// Picks a subtree proportionally to its weight, returning it with the
+    /// probability of the choice.
+    fn select(&self) -> (&Arc<dyn Hittable>, f64) {
+        let p_left = self.left_probability();
+        if random_double() < p_left {
+            (&self.left, p_left)
+        } else {
+            (&self.right, 1.0 - p_left)
+        }
     }
 
     fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
-        let box_a = a.bounding_box();
-        let box_b = b.bounding_box();
+        let box_a = a.bounding_box(SHUTTER);
+        let box_b = b.bounding_box(SHUTTER);
 
         box_a
             .axis_interval(axis)
@@ -63,28 +504,242 @@ impl BvhNode {
 }
 
 impl Hittable for BvhNode {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
-        if !self.bbox.hit(r, ray_t) {
-            return false;
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let entered = self.bbox.hit(r, ray_t);
+        count_traversal(self.primitives.into(), entered);
+        if !entered {
+            return None;
         }
 
-        let hit_left = self.left.hit(r, ray_t, isect);
+        let left = self.left.hit(r, ray_t);
+        let t_max = left.as_ref().map_or(ray_t.max, |isect| isect.t);
+        self.right.hit(r, Interval::new(ray_t.min, t_max)).or(left)
+    }
 
-        let t_max = if hit_left { isect.t } else { ray_t.max };
-        let right_interval = Interval::new(ray_t.min, t_max);
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let entered = self.bbox.hit(r, ray_t);
+        count_traversal(self.primitives.into(), entered);
+        if !entered {
+            return None;
+        }
 
-        let mut right_isect = Interaction::default();
-        let hit_right = self.right.hit(r, right_interval, &mut right_isect);
+        let left = self.left.intersect_p(r, ray_t);
+        let t_max = left.as_ref().map_or(ray_t.max, |isect| isect.t);
+        self.right
+            .intersect_p(r, Interval::new(ray_t.min, t_max))
+            .or(left)
+    }
 
-        if hit_right {
-            *isect = right_isect;
-            return true;
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        if !self.bbox.hit(r, ray_t) {
+            return Color::new(1.0, 1.0, 1.0);
         }
 
-        hit_left
+        let tr = roulette_transmittance(self.left.transmittance(r, ray_t));
+        if tr == Color::zeros() {
+            return tr;
+        }
+        roulette_transmittance(tr.component_mul(&self.right.transmittance(r, ray_t)))
     }
 
-    fn bounding_box(&self) -> Aabb {
+    fn bounding_box(&self, _time: Interval) -> Aabb {
         self.bbox
     }
+
+    fn area(&self) -> f64 {
+        if self.primitives == 1 {
+            self.left.area()
+        } else {
+            self.left.area() + self.right.area()
+        }
+    }
+
+    fn power(&self) -> Color {
+        if self.primitives == 1 {
+            self.left.power()
+        } else {
+            self.left.power() + self.right.power()
+        }
+    }
+
+    /// Density of the subtrees weighted by their selection probability, skipping
+    /// nodes whose bounding box the direction misses, as their density is zero.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let ray = Ray::new(*origin, *direction, time);
+        if !self.bbox.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
+            return 0.0;
+        }
+
+        let p_left = self.left_probability();
+        let mut pdf = 0.0;
+        if p_left > 0.0 {
+            pdf += p_left * self.left.pdf_value(origin, direction, time);
+        }
+        if p_left < 1.0 {
+            pdf += (1.0 - p_left) * self.right.pdf_value(origin, direction, time);
+        }
+        pdf
+    }
+
+    /// Descends to a primitive proportionally to the subtree weights.
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.select().0.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (child, probability) = self.select();
+        let (isect, pdf) = child.sample_surface(time)?;
+        Some((isect, pdf * probability))
+    }
+}
+
+/// A primitive or subtree being merged by the linear builder.
+enum Cluster {
+    Primitive(Arc<dyn Hittable>),
+    Node(BvhNode),
+}
+
+impl Cluster {
+    fn into_node(self) -> BvhNode {
+        match self {
+            Cluster::Node(node) => node,
+            Cluster::Primitive(object) => {
+                let weights = BvhNode::leaf_weights(&object, &object, 1);
+                BvhNode {
+                    bbox: object.bounding_box(SHUTTER),
+                    left: object.clone(),
+                    right: object,
+                    primitives: 1,
+                    left_weight: weights.0,
+                    right_weight: weights.1,
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn centroid(bbox: &Aabb) -> Point3 {
+    Point3::new(
+        (bbox.x.min + bbox.x.max) / 2.0,
+        (bbox.y.min + bbox.y.max) / 2.0,
+        (bbox.z.min + bbox.z.max) / 2.0,
+    )
+}
+
+pub(crate) fn surface_area(bbox: &Aabb) -> f64 {
+    let (x, y, z) = (bbox.x.size(), bbox.y.size(), bbox.z.size());
+    2.0 * (x * y + y * z + z * x)
+}
+
+/// Position of `p` along a Morton curve through `bounds`, interleaving the top
+/// `MORTON_BITS` bits of its coordinates.
+pub(crate) fn morton_code(p: &Point3, bounds: &Aabb) -> u32 {
+    let cells = (1 << MORTON_BITS) as f64;
+    let mut code = 0;
+    for axis in 0..3 {
+        let interval = bounds.axis_interval(axis);
+        let t = (p[axis] - interval.min) / interval.size();
+        // Unbounded objects have no finite centroid; NaN casts to cell 0
+        let cell = (t * cells).clamp(0.0, cells - 1.0) as u32;
+        for bit in 0..MORTON_BITS {
+            code |= ((cell >> bit) & 1) << (3 * bit + 2 - axis as u32);
+        }
+    }
+    code
+}
+
+/// FNV-1a hash of the mesh's vertex positions and faces, which alone decide the
+/// layout of its BVH.
+fn mesh_key(mesh: &TriangleMesh) -> u64 {
+    let mut hash = Fnv1a::default();
+    hash.feed_u64(mesh.positions().len() as u64);
+    for p in mesh.positions() {
+        hash.feed_u64(p.x.to_bits());
+        hash.feed_u64(p.y.to_bits());
+        hash.feed_u64(p.z.to_bits());
+    }
+    hash.feed_u64(mesh.indices().len() as u64);
+    for face in mesh.indices() {
+        for &index in face {
+            hash.feed_u64(index as u64);
+        }
+    }
+    hash.finish()
+}
+
+/// Cache file: magic, then the layout tokens (little-endian).
+fn encode_layout(layout: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(CACHE_MAGIC.len() + 4 * layout.len());
+    bytes.extend_from_slice(CACHE_MAGIC);
+    for token in layout {
+        bytes.extend_from_slice(&token.to_le_bytes());
+    }
+    bytes
+}
+
+fn decode_layout(bytes: &[u8]) -> Option<Vec<u32>> {
+    let tokens = bytes.strip_prefix(CACHE_MAGIC)?;
+    if tokens.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        tokens
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::textures::solid_color::SolidColor;
+
+    fn spheres(count: usize) -> Vec<Arc<dyn Hittable>> {
+        let material = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        (0..count)
+            .map(|n| {
+                Arc::new(Sphere::new(
+                    Point3::new(n as f64, 0.0, 0.0),
+                    0.4,
+                    material.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn built_layout_is_read_back() {
+        let objects = spheres(7);
+        let mut layout = Vec::new();
+        let indexed = objects.iter().cloned().enumerate();
+        let built = BvhNode::build(indexed.map(|(n, o)| (n as u32, o)).collect(), &mut layout);
+        let read = BvhNode::from_cached_layout(&objects, layout).unwrap();
+        assert_eq!(surface_area(&read.bbox), surface_area(&built.bbox));
+        assert_eq!(read.weight(), built.weight());
+    }
+
+    #[test]
+    fn layouts_not_covering_every_primitive_once_are_rejected() {
+        let objects = spheres(3);
+        // Valid: one interior node over a two-primitive and a one-primitive leaf
+        assert!(BvhNode::from_cached_layout(&objects, vec![INTERIOR, 0, 1, 2, 2]).is_some());
+        // Duplicate, missing, out of range and trailing tokens
+        assert!(BvhNode::from_cached_layout(&objects, vec![INTERIOR, 0, 1, 1, 1]).is_none());
+        assert!(BvhNode::from_cached_layout(&objects, vec![0, 1]).is_none());
+        assert!(BvhNode::from_cached_layout(&objects, vec![INTERIOR, 0, 1, 2, 3]).is_none());
+        assert!(BvhNode::from_cached_layout(&objects, vec![INTERIOR, 0, 1, 2, 2, 0]).is_none());
+    }
+
+    #[test]
+    fn deep_layouts_are_rejected() {
+        let objects = spheres(2);
+        let mut layout = vec![INTERIOR; 100_000];
+        layout.extend([0, 1]);
+        assert!(BvhNode::from_cached_layout(&objects, layout).is_none());
+    }
 }
@@ -0,0 +1,242 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::geometry::material_override::MaterialSpec;
+use crate::materials::material_trait::Material;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Most clipped hits skipped in a row before the ray is considered to miss.
+const MAX_CLIPPED: usize = 64;
+/// Offset past a clipped hit before searching for the next one.
+const CLIP_EPSILON: f64 = 0.0001;
+
+/// Half-space cut away from the scene: everything on the side the normal points to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipPlane {
+    point: Point3,
+    normal: Vec3,
+}
+
+impl ClipPlane {
+    pub fn new(point: Point3, normal: Vec3) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+        }
+    }
+
+    fn clips(&self, p: &Point3) -> bool {
+        (p - self.point).dot(&self.normal) > 0.0
+    }
+
+    /// Ray parameter where `r` crosses the plane, if it is not parallel to it.
+    fn crossing(&self, r: &Ray) -> Option<f64> {
+        let denom = r.dir.dot(&self.normal);
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        Some((self.point - r.orig).dot(&self.normal) / denom)
+    }
+}
+
+impl FromStr for ClipPlane {
+    type Err = String;
+
+    /// `x,y,z,nx,ny,nz`: a point on the plane and the normal towards the removed side.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s
+            .split(',')
+            .map(|c| c.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid clipping plane '{}'", s))?;
+
+        match values[..] {
+            [x, y, z, nx, ny, nz] if (nx, ny, nz) != (0.0, 0.0, 0.0) => {
+                Ok(Self::new(Point3::new(x, y, z), Vec3::new(nx, ny, nz)))
+            }
+            _ => Err(format!("Expected x,y,z,nx,ny,nz but got '{}'", s)),
+        }
+    }
+}
+
+/// Material closing off the cut faces of clipped solids.
+#[derive(Debug, Clone)]
+pub enum CapMaterial {
+    /// The cut solid's own material, so a halved glass ball still refracts as a solid.
+    Own,
+    Replace(MaterialSpec),
+}
+
+impl CapMaterial {
+    pub fn build(&self) -> Option<Arc<dyn Material>> {
+        match self {
+            Self::Own => None,
+            Self::Replace(spec) => Some(spec.build()),
+        }
+    }
+}
+
+impl FromStr for CapMaterial {
+    type Err = String;
+
+    /// `self` or a material such as `lambertian:r,g,b`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "self" => Ok(Self::Own),
+            spec => Ok(Self::Replace(spec.parse()?)),
+        }
+    }
+}
+
+/// Cuts the wrapped object with clipping planes for cutaway renders: hits on the
+/// removed side are skipped, so rays see through to whatever remains. Optionally,
+/// dielectric solids are capped where the planes slice through them, which keeps
+/// glass behaving like a closed volume; open geometry such as walls is never capped.
+#[derive(Debug)]
+pub struct Clipped {
+    object: Arc<dyn Hittable>,
+    planes: Vec<ClipPlane>,
+    caps: bool,
+    cap_material: Option<Arc<dyn Material>>,
+}
+
+impl Clipped {
+    pub fn new(object: Arc<dyn Hittable>, planes: Vec<ClipPlane>) -> Self {
+        Self {
+            object,
+            planes,
+            caps: false,
+            cap_material: None,
+        }
+    }
+
+    /// Caps cut dielectric solids with `material`, or with their own material if `None`.
+    pub fn with_caps(mut self, material: Option<Arc<dyn Material>>) -> Self {
+        self.caps = true;
+        self.cap_material = material;
+        self
+    }
+
+    fn kept(&self, p: &Point3) -> bool {
+        !self.planes.iter().any(|plane| plane.clips(p))
+    }
+
+    /// Closest kept hit along `r` found with `query`, or the cap in front of it.
+    fn closest(
+        &self,
+        r: &Ray,
+        ray_t: Interval,
+        query: impl Fn(&Ray, Interval) -> Option<Interaction>,
+    ) -> Option<Interaction> {
+        let mut search = ray_t;
+        let mut found = None;
+        for _ in 0..MAX_CLIPPED {
+            let Some(isect) = query(r, search) else {
+                break;
+            };
+            if self.kept(&isect.p) {
+                found = Some(isect);
+                break;
+            }
+            search = Interval::new(isect.t + CLIP_EPSILON, ray_t.max);
+        }
+
+        if self.caps {
+            let t_max = found.as_ref().map_or(ray_t.max, |isect| isect.t);
+            if let Some(cap) = self.cap(r, Interval::new(ray_t.min, t_max)) {
+                return Some(cap);
+            }
+        }
+        found
+    }
+
+    /// Closest point within `ray_t` where `r` crosses a cut face of a dielectric solid.
+    fn cap(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut closest: Option<Interaction> = None;
+
+        for (k, plane) in self.planes.iter().enumerate() {
+            let Some(t) = plane.crossing(r) else {
+                continue;
+            };
+            let t_max = closest.as_ref().map_or(ray_t.max, |c| c.t);
+            if !Interval::new(ray_t.min, t_max).surrounds(t) {
+                continue;
+            }
+
+            // The cut face is the part of the plane the other planes keep
+            let p = r.at(t);
+            let cut_away = self
+                .planes
+                .iter()
+                .enumerate()
+                .any(|(n, other)| n != k && other.clips(&p));
+            if cut_away {
+                continue;
+            }
+
+            // Inside a solid when the next surface along the ray is seen from the back
+            let Some(inside) = self
+                .object
+                .hit(r, Interval::new(t, f64::INFINITY))
+                .filter(|inside| !inside.front_face)
+            else {
+                continue;
+            };
+            let Some(material) = inside.material.filter(|m| m.name() == "Dielectric") else {
+                continue;
+            };
+
+            let material = self.cap_material.clone().unwrap_or(material);
+            let mut cap = Interaction::new(p, t, (0.0, 0.0), Some(material));
+            cap.set_face_normal(r, plane.normal);
+            cap.set_differentials(r, &Vec3::zeros(), &Vec3::zeros());
+            cap.max_specular_depth = inside.max_specular_depth;
+            cap.object = inside.object;
+            closest = Some(cap);
+        }
+
+        closest
+    }
+}
+
+impl Hittable for Clipped {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest(r, ray_t, |r, t| self.object.hit(r, t))
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest(r, ray_t, |r, t| self.object.intersect_p(r, t))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.object.sample_surface(time)
+    }
+}
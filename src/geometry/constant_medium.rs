@@ -2,14 +2,20 @@ use crate::core::aabb::Aabb;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::Vec3;
+use crate::core::vec3::{Color, Point3, Vec3};
 use crate::geometry::hittable::Hittable;
+use crate::geometry::medium::Medium;
 use crate::materials::isotropic::Isotropic;
 use crate::materials::material_trait::Material;
 use crate::sampling::random::random_double;
 use crate::textures::texture_trait::Texture;
 use std::sync::Arc;
 
+/// Most boundary crossings followed along one ray.
+const MAX_CROSSINGS: usize = 64;
+/// Offset past a crossing before searching for the next one.
+const CROSSING_EPSILON: f64 = 0.0001;
+
 #[derive(Debug)]
 pub struct ConstantMedium {
     boundary: Arc<dyn Hittable>,
@@ -27,60 +33,115 @@ impl ConstantMedium {
     }
 }
 
-impl Hittable for ConstantMedium {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
-        // Print debugging maybe occasionally useful, but omitted for speed
-        let mut rec1 = Interaction::default();
-        let mut rec2 = Interaction::default();
-
-        if !self.boundary.hit(r, Interval::universe(), &mut rec1) {
-            return false;
-        }
+impl ConstantMedium {
+    /// Walks every boundary crossing along the whole line and pairs them up into the
+    /// spans spent inside, so non-convex boundaries (several spans) and rays starting
+    /// inside (a span beginning behind the origin) are both handled. Calls `visit` with
+    /// each span clipped to `ray_t`, in order, until it returns false.
+    fn for_each_span(&self, r: &Ray, ray_t: Interval, mut visit: impl FnMut(f64, f64) -> bool) {
+        let segment = Interval::new(ray_t.min.max(0.0), ray_t.max);
+        let mut search = Interval::universe();
+        let mut entry: Option<f64> = None;
 
-        if !self
-            .boundary
-            .hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2)
-        {
-            return false;
-        }
+        for _ in 0..MAX_CROSSINGS {
+            let Some(rec) = self.boundary.hit(r, search) else {
+                break;
+            };
+            search = Interval::new(rec.t + CROSSING_EPSILON, f64::INFINITY);
 
-        if rec1.t < ray_t.min {
-            rec1.t = ray_t.min;
-        }
-        if rec2.t > ray_t.max {
-            rec2.t = ray_t.max;
-        }
+            let Some(t_entry) = entry.take() else {
+                if rec.t >= segment.max {
+                    break;
+                }
+                entry = Some(rec.t);
+                continue;
+            };
 
-        if rec1.t >= rec2.t {
-            return false;
-        }
-
-        if rec1.t < 0.0 {
-            rec1.t = 0.0;
+            let t_start = t_entry.max(segment.min);
+            let t_end = rec.t.min(segment.max);
+            if t_start < t_end && !visit(t_start, t_end) {
+                break;
+            }
+            if rec.t >= segment.max {
+                break;
+            }
         }
+    }
+}
 
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
         let ray_length = r.dir.norm();
-        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
 
+        // Free-flight distance, consumed span by span since the medium is homogeneous.
         // Clamp random value to avoid log(0) = -inf, which causes NaNs/Infs
         let rand_val = random_double().max(f64::EPSILON);
-        let hit_distance = self.neg_inv_density * rand_val.ln();
-
-        if hit_distance > distance_inside_boundary {
-            return false;
-        }
+        let mut hit_distance = self.neg_inv_density * rand_val.ln();
 
-        isect.t = rec1.t + hit_distance / ray_length;
-        isect.p = r.at(isect.t);
+        let mut hit_t = None;
+        self.for_each_span(r, ray_t, |t_start, t_end| {
+            let distance_inside_boundary = (t_end - t_start) * ray_length;
+            if hit_distance <= distance_inside_boundary {
+                hit_t = Some(t_start + hit_distance / ray_length);
+                return false;
+            }
+            hit_distance -= distance_inside_boundary;
+            true
+        });
 
+        let t = hit_t?;
+        // Facing the ray, as Interaction::new sets it, is arbitrary too
+        let mut isect = Interaction::new(r.at(t), t, (0.0, 0.0), Some(self.phase_function.clone()));
         isect.geometry_normal = Vec3::new(1.0, 0.0, 0.0); // Arbitrary
-        isect.front_face = true; // Also arbitrary
-        isect.material = Some(self.phase_function.clone());
+        isect.medium = true;
+        Some(isect)
+    }
+
+    /// Media never block shadow rays outright; they attenuate them instead.
+    fn intersect_p(&self, _r: &Ray, _ray_t: Interval) -> Option<Interaction> {
+        None
+    }
+
+    /// Beer-Lambert attenuation over the distance travelled inside the boundary.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let mut distance = 0.0;
+        self.for_each_span(r, ray_t, |t_start, t_end| {
+            distance += t_end - t_start;
+            true
+        });
+
+        let tr = (distance * r.dir.norm() / self.neg_inv_density).exp();
+        Color::new(tr, tr, tr)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.boundary.bounding_box(time)
+    }
+}
+
+impl Medium for ConstantMedium {
+    fn spans(&self, r: &Ray, ray_t: Interval) -> Vec<Interval> {
+        let mut spans = Vec::new();
+        self.for_each_span(r, ray_t, |t_start, t_end| {
+            spans.push(Interval::new(t_start, t_end));
+            true
+        });
+        spans
+    }
+
+    fn density(&self, _p: &Point3) -> f64 {
+        -1.0 / self.neg_inv_density
+    }
+
+    fn majorant(&self) -> f64 {
+        -1.0 / self.neg_inv_density
+    }
 
+    fn homogeneous(&self) -> bool {
         true
     }
 
-    fn bounding_box(&self) -> Aabb {
-        self.boundary.bounding_box()
+    fn phase_function(&self) -> Arc<dyn Material> {
+        self.phase_function.clone()
     }
 }
@@ -0,0 +1,228 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::onb::ONB;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::materials::material_trait::Material;
+use crate::sampling::random::random_double;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Finite cylinder around an axis, for columns, pipes and tubes. Positions along the
+/// axis (`z`) are measured from `center`; the ends are open unless `with_caps` closes
+/// them with disks.
+#[derive(Debug)]
+pub struct Cylinder {
+    center: Point3,
+    radius: f64,
+    z_min: f64,
+    z_max: f64,
+    caps: bool,
+    material: Arc<dyn Material>,
+    uvw: ONB, // w is the cylinder axis
+    bbox: Aabb,
+}
+
+impl Cylinder {
+    /// Cylinder of `radius` along `axis` through `center`, spanning `z_min` to
+    /// `z_max` along it.
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        radius: f64,
+        z_min: f64,
+        z_max: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let uvw = ONB::build_from_w(&axis);
+        let (z_min, z_max) = (z_min.min(z_max), z_min.max(z_max));
+
+        // Bounds of the two end circles, as for `Disk`
+        let n = uvw.w();
+        let extent = Vec3::new(
+            radius * (1.0 - n.x * n.x).max(0.0).sqrt(),
+            radius * (1.0 - n.y * n.y).max(0.0).sqrt(),
+            radius * (1.0 - n.z * n.z).max(0.0).sqrt(),
+        );
+        let bottom = center + z_min * n;
+        let top = center + z_max * n;
+        let bbox = Aabb::new_point(bottom - extent, bottom + extent)
+            .merge(&Aabb::new_point(top - extent, top + extent));
+
+        Self {
+            center,
+            radius,
+            z_min,
+            z_max,
+            caps: false,
+            material,
+            uvw,
+            bbox,
+        }
+    }
+
+    /// Closes both ends with disks, making the cylinder a solid.
+    pub fn with_caps(mut self) -> Self {
+        self.caps = true;
+        self
+    }
+
+    fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(
+            a.dot(&self.uvw.u()),
+            a.dot(&self.uvw.v()),
+            a.dot(&self.uvw.w()),
+        )
+    }
+
+    fn side_area(&self) -> f64 {
+        2.0 * PI * self.radius * (self.z_max - self.z_min)
+    }
+
+    fn cap_area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    /// UVs of the local point `p`: u around the axis, and v along it on the side or
+    /// from the center outwards on the caps.
+    fn uv(&self, p: &Vec3, cap: bool) -> (f64, f64) {
+        let phi = p.y.atan2(p.x) + PI;
+        let u = phi / (2.0 * PI);
+        if cap {
+            (u, (p.x * p.x + p.y * p.y).sqrt() / self.radius)
+        } else {
+            (u, (p.z - self.z_min) / (self.z_max - self.z_min))
+        }
+    }
+
+    /// Closest intersection within `ray_t` as the ray parameter, the local hit
+    /// point, its local outward normal and whether it lies on a cap.
+    fn intersect(&self, r: &Ray, ray_t: Interval) -> Option<(f64, Vec3, Vec3, bool)> {
+        let o = self.to_local(&(r.orig - self.center));
+        let d = self.to_local(&r.dir);
+        let mut closest: Option<(f64, Vec3, Vec3, bool)> = None;
+        let mut consider = |t: f64, p: Vec3, normal: Vec3, cap: bool| {
+            if ray_t.surrounds(t) && closest.is_none_or(|(best, ..)| t < best) {
+                closest = Some((t, p, normal, cap));
+            }
+        };
+
+        // Side: x^2 + y^2 = r^2 within the z range
+        let a = d.x * d.x + d.y * d.y;
+        if a > 0.0 {
+            let half_b = o.x * d.x + o.y * d.y;
+            let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+            let discriminant = half_b * half_b - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                for t in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
+                    let p = o + t * d;
+                    if p.z >= self.z_min && p.z <= self.z_max {
+                        consider(t, p, Vec3::new(p.x, p.y, 0.0) / self.radius, false);
+                    }
+                }
+            }
+        }
+
+        // Caps: disks in the end planes
+        if self.caps && d.z != 0.0 {
+            for (z, normal) in [(self.z_min, -1.0), (self.z_max, 1.0)] {
+                let t = (z - o.z) / d.z;
+                let p = o + t * d;
+                if p.x * p.x + p.y * p.y <= self.radius * self.radius {
+                    consider(t, p, Vec3::new(0.0, 0.0, normal), true);
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let (t, local_p, local_normal, cap) = self.intersect(r, ray_t)?;
+
+        let mut isect = Interaction::new(
+            r.at(t),
+            t,
+            self.uv(&local_p, cap),
+            Some(self.material.clone()),
+        );
+        isect.set_face_normal(r, self.uvw.local(&local_normal));
+        isect.set_differentials(r, &Vec3::zeros(), &Vec3::zeros());
+
+        Some(isect)
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.bbox
+    }
+
+    fn area(&self) -> f64 {
+        if self.caps {
+            self.side_area() + 2.0 * self.cap_area()
+        } else {
+            self.side_area()
+        }
+    }
+
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
+    }
+
+    /// Solid angle density of `random`, which samples the whole surface, so it
+    /// sums over every point of the cylinder along `direction`, hidden ones included.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let ray = Ray::new(*origin, *direction, time);
+        let mut ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut pdf = 0.0;
+        while let Some((t, _, local_normal, _)) = self.intersect(&ray, ray_t) {
+            let normal = self.uvw.local(&local_normal);
+            let distance_squared = t * t * direction.norm_squared();
+            let cosine = (direction.dot(&normal) / direction.norm()).abs();
+            if cosine > 1e-8 {
+                pdf += distance_squared / (cosine * self.area());
+            }
+            ray_t = Interval::new(t, f64::INFINITY);
+        }
+        pdf
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        match self.sample_surface(time) {
+            Some((isect, _)) => (isect.p - *origin).normalize(),
+            None => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+
+    fn sample_surface(&self, _time: f64) -> Option<(Interaction, f64)> {
+        let phi = 2.0 * PI * random_double();
+        let (local_p, local_normal, cap) =
+            if !self.caps || random_double() * self.area() < self.side_area() {
+                let z = self.z_min + random_double() * (self.z_max - self.z_min);
+                let p = Vec3::new(self.radius * phi.cos(), self.radius * phi.sin(), z);
+                (p, Vec3::new(phi.cos(), phi.sin(), 0.0), false)
+            } else {
+                let r = self.radius * random_double().sqrt();
+                let (z, normal) = if random_double() < 0.5 {
+                    (self.z_min, -1.0)
+                } else {
+                    (self.z_max, 1.0)
+                };
+                let p = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                (p, Vec3::new(0.0, 0.0, normal), true)
+            };
+
+        let p = self.center + self.uvw.local(&local_p);
+        let normal = self.uvw.local(&local_normal);
+        let mut isect =
+            Interaction::new(p, 0.0, self.uv(&local_p, cap), Some(self.material.clone()));
+        isect.geometry_normal = normal;
+        isect.set_shading_normal(normal);
+
+        Some((isect, 1.0 / self.area()))
+    }
+}
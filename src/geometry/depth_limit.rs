@@ -0,0 +1,68 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use std::sync::Arc;
+
+/// Limits how many specular bounces in a row a path may take when it reaches the
+/// wrapped object, keeping e.g. nested glassware from bouncing a path around for
+/// the whole global depth. Nested limits take the tighter of the two.
+#[derive(Debug)]
+pub struct DepthLimit {
+    object: Arc<dyn Hittable>,
+    max_specular_depth: u32,
+}
+
+impl DepthLimit {
+    pub fn new(object: Arc<dyn Hittable>, max_specular_depth: u32) -> Self {
+        Self {
+            object,
+            max_specular_depth,
+        }
+    }
+}
+
+impl Hittable for DepthLimit {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(r, ray_t)?;
+        isect.max_specular_depth = Some(match isect.max_specular_depth {
+            Some(inner) => inner.min(self.max_specular_depth),
+            None => self.max_specular_depth,
+        });
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.object.intersect_p(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.object.sample_surface(time)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::onb::ONB;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::materials::material_trait::Material;
+use crate::sampling::random::random_double;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Flat circular disk, mainly used as an area light (softboxes, spots).
+#[derive(Debug)]
+pub struct Disk {
+    center: Point3,
+    radius: f64,
+    material: Arc<dyn Material>,
+    uvw: ONB, // w is the disk normal
+    d: f64,
+    bbox: Aabb,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
+        let uvw = ONB::build_from_w(&normal);
+        let n = uvw.w();
+        let d = n.dot(&center.coords);
+
+        // Extent of the disk along each axis is radius * sin(angle between axis and normal)
+        let extent = Vec3::new(
+            radius * (1.0 - n.x * n.x).max(0.0).sqrt(),
+            radius * (1.0 - n.y * n.y).max(0.0).sqrt(),
+            radius * (1.0 - n.z * n.z).max(0.0).sqrt(),
+        );
+
+        Self {
+            center,
+            radius,
+            material,
+            uvw,
+            d,
+            bbox: Aabb::new_point(center - extent, center + extent),
+        }
+    }
+
+    /// Uniformly samples a point on the disk, returning it with its UV.
+    fn sample_point(&self) -> (Point3, (f64, f64)) {
+        let r = random_double().sqrt();
+        let phi = 2.0 * PI * random_double();
+        let local = Vec3::new(
+            r * self.radius * phi.cos(),
+            r * self.radius * phi.sin(),
+            0.0,
+        );
+
+        let p = self.center + self.uvw.local(&local);
+        let uv = (((phi + PI) / (2.0 * PI)).fract(), r);
+        (p, uv)
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let normal = self.uvw.w();
+        let denom = normal.dot(&r.dir);
+
+        // Ray parallel to the plane, or arriving from behind a culled back face
+        if denom.abs() < 1e-8 || (denom > 0.0 && self.material.cull_backfaces()) {
+            return None;
+        }
+
+        let t = (self.d - normal.dot(&r.orig.coords)) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        let p = r.at(t);
+        let offset = p - self.center;
+        let dist_squared = offset.norm_squared();
+        if dist_squared > self.radius * self.radius {
+            return None;
+        }
+
+        // Polar UVs: u around the disk, v from the center outwards
+        let phi = offset.dot(&self.uvw.v()).atan2(offset.dot(&self.uvw.u())) + PI;
+        let uv = (phi / (2.0 * PI), dist_squared.sqrt() / self.radius);
+
+        let mut isect = Interaction::new(p, t, uv, Some(self.material.clone()));
+        isect.set_face_normal(r, normal);
+        let (x, y) = (offset.dot(&self.uvw.u()), offset.dot(&self.uvw.v()));
+        let dpdu = 2.0 * PI * (x * self.uvw.v() - y * self.uvw.u());
+        let dpdv = offset.try_normalize(0.0).unwrap_or(Vec3::zeros()) * self.radius;
+        isect.set_differentials(r, &dpdu, &dpdv);
+
+        Some(isect)
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.bbox
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let Some(rec) = self.hit(
+            &Ray::new(*origin, *direction, time),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * direction.norm_squared();
+        let cosine = (direction.dot(&rec.geometry_normal) / direction.norm()).abs();
+
+        if cosine < 1e-8 {
+            0.0
+        } else {
+            distance_squared / (cosine * self.area())
+        }
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let (p, _) = self.sample_point();
+        (p - *origin).normalize()
+    }
+
+    fn sample_surface(&self, _time: f64) -> Option<(Interaction, f64)> {
+        let (p, uv) = self.sample_point();
+
+        let mut isect = Interaction::new(p, 0.0, uv, Some(self.material.clone()));
+        isect.geometry_normal = self.uvw.w();
+        isect.set_shading_normal(self.uvw.w());
+
+        Some((isect, 1.0 / self.area()))
+    }
+}
@@ -0,0 +1,268 @@
+use crate::core::camera::Camera;
+use crate::core::interaction::UV_SETS;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::triangle_mesh::TriangleMesh;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Points along each edge at which the displaced surface is compared with the
+/// straight edge, excluding its ends.
+const EDGE_SAMPLES: u32 = 7;
+/// Default cap on subdivision rounds, each splitting a face into up to four.
+const DEFAULT_MAX_LEVEL: u32 = 8;
+
+/// Displacement mapping baked into geometry at load time: the vertices of a mesh
+/// move along their normals by a height texture, so silhouettes and shadows show
+/// the relief that bump mapping only fakes in the shading.
+///
+/// The mesh is tessellated adaptively first. Each round splits the edges along
+/// which the displaced surface strays from the straight edge by more than the
+/// tolerance, so flat areas stay coarse and only detail that the texture actually
+/// has is refined. Edges are split or not as a whole, with the faces on both sides
+/// sharing the new vertex, so the result has no cracks. Relief finer than an
+/// eighth of an edge can be missed, so base meshes should not be much coarser than
+/// the largest features of the height map.
+#[derive(Debug)]
+pub struct Displacement {
+    height: Arc<dyn Texture>,
+    scale: f64,
+    tolerance: f64,
+    camera: Option<Camera>,
+    max_level: u32,
+}
+
+/// A point of the base surface, before displacement.
+#[derive(Clone, Copy)]
+struct Vertex {
+    p: Point3,
+    n: Vec3,
+    uv: (f64, f64),
+    uv_sets: [(f64, f64); UV_SETS - 1],
+    color: Option<Color>,
+}
+
+impl Vertex {
+    fn lerp(a: &Vertex, b: &Vertex, t: f64) -> Vertex {
+        let n = (1.0 - t) * a.n + t * b.n;
+        Vertex {
+            p: Point3::from((1.0 - t) * a.p.coords + t * b.p.coords),
+            n: if n.norm_squared() > 0.0 {
+                n.normalize()
+            } else {
+                a.n
+            },
+            uv: lerp_uv(a.uv, b.uv, t),
+            uv_sets: std::array::from_fn(|set| lerp_uv(a.uv_sets[set], b.uv_sets[set], t)),
+            color: a.color.zip(b.color).map(|(ca, cb)| (1.0 - t) * ca + t * cb),
+        }
+    }
+}
+
+impl Displacement {
+    /// Moves surfaces along their normals by `scale` times the height texture, read
+    /// as the mean of its channels so grayscale height maps work as they are.
+    pub fn new(height: Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            height,
+            scale,
+            tolerance: 0.01 * scale.abs(),
+            camera: None,
+            max_level: DEFAULT_MAX_LEVEL,
+        }
+    }
+
+    /// Largest distance the tessellation may stray from the displaced surface, in
+    /// scene units (default: 1% of the scale).
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Measures the tolerance in pixels of `camera` instead, at the depth of each
+    /// edge: nearby surfaces are refined until their relief is accurate on screen,
+    /// distant ones stay coarse. Surfaces behind the camera are not refined.
+    pub fn with_camera(mut self, camera: &Camera, pixels: f64) -> Self {
+        self.camera = Some(camera.clone());
+        self.tolerance = pixels;
+        self
+    }
+
+    /// Caps the number of subdivision rounds (default 8), bounding the face count
+    /// to 4^`levels` times that of the base mesh.
+    pub fn with_max_level(mut self, levels: u32) -> Self {
+        self.max_level = levels;
+        self
+    }
+
+    fn height(&self, v: &Vertex) -> f64 {
+        let c = self.height.value(&TextureContext::new(v.uv, v.p));
+        (c.x + c.y + c.z) / 3.0
+    }
+
+    fn displaced(&self, v: &Vertex) -> Point3 {
+        v.p + v.n * (self.scale * self.height(v))
+    }
+
+    /// Tolerance at `p`, in scene units, or None where no refinement is needed.
+    fn tolerance_at(&self, p: &Point3) -> Option<f64> {
+        let Some(camera) = &self.camera else {
+            return Some(self.tolerance);
+        };
+        let depth = -(p - camera.center).dot(&camera.w);
+        if depth <= 0.0 {
+            return None;
+        }
+        let pixel = camera.pixel_delta_u.norm() * depth / camera.focus_dist;
+        Some(self.tolerance * pixel)
+    }
+
+    /// Whether the displaced surface along the edge from `a` to `b` strays from the
+    /// straight edge between their displaced ends by more than the tolerance.
+    fn needs_split(&self, a: &Vertex, b: &Vertex) -> bool {
+        let (da, db) = (self.displaced(a), self.displaced(b));
+        (1..=EDGE_SAMPLES).any(|s| {
+            let t = s as f64 / (EDGE_SAMPLES + 1) as f64;
+            let straight = Point3::from((1.0 - t) * da.coords + t * db.coords);
+            let Some(tolerance) = self.tolerance_at(&straight) else {
+                return false;
+            };
+            let exact = self.displaced(&Vertex::lerp(a, b, t));
+            (exact - straight).norm() > tolerance
+        })
+    }
+
+    /// Tessellates and displaces `mesh`, returning a mesh with the same material,
+    /// its UV sets and colors interpolated, and smooth normals of the displaced surface.
+    pub fn apply(&self, mesh: &TriangleMesh) -> Result<TriangleMesh, String> {
+        let normals = match mesh.normals() {
+            Some(normals) => normals.to_vec(),
+            None => vertex_normals(mesh.positions(), mesh.indices()),
+        };
+        let mut vertices: Vec<Vertex> = mesh
+            .positions()
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| Vertex {
+                p,
+                n: normals[i],
+                uv: mesh.uvs().map_or((0.0, 0.0), |uvs| uvs[i]),
+                uv_sets: std::array::from_fn(|set| {
+                    mesh.uv_sets().get(set).map_or((0.0, 0.0), |uvs| uvs[i])
+                }),
+                color: mesh.colors().map(|colors| colors[i]),
+            })
+            .collect();
+        let mut faces = mesh.indices().to_vec();
+
+        for _ in 0..self.max_level {
+            // Decide every edge once, in a fixed order of its ends, so both faces
+            // sharing it agree and reuse the same midpoint
+            let mut midpoints: HashMap<(u32, u32), Option<u32>> = HashMap::new();
+            let mut splits = 0;
+            for face in &faces {
+                for e in 0..3 {
+                    let key = edge_key(face[e], face[(e + 1) % 3]);
+                    midpoints.entry(key).or_insert_with(|| {
+                        let (a, b) = (vertices[key.0 as usize], vertices[key.1 as usize]);
+                        self.needs_split(&a, &b).then(|| {
+                            vertices.push(Vertex::lerp(&a, &b, 0.5));
+                            splits += 1;
+                            (vertices.len() - 1) as u32
+                        })
+                    });
+                }
+            }
+            if splits == 0 {
+                break;
+            }
+
+            faces = faces
+                .iter()
+                .flat_map(|face| split_face(face, |a, b| midpoints[&edge_key(a, b)]))
+                .collect();
+        }
+
+        let positions: Vec<Point3> = vertices.iter().map(|v| self.displaced(v)).collect();
+        let normals = vertex_normals(&positions, &faces);
+        let uvs: Vec<(f64, f64)> = vertices.iter().map(|v| v.uv).collect();
+        let colors: Option<Vec<Color>> = vertices.iter().map(|v| v.color).collect();
+
+        let mut displaced =
+            TriangleMesh::new(positions, faces, mesh.material().clone())?.with_normals(normals);
+        if mesh.uvs().is_some() {
+            displaced = displaced.with_uvs(uvs);
+        }
+        for set in 0..mesh.uv_sets().len() {
+            displaced = displaced.with_uv_set(vertices.iter().map(|v| v.uv_sets[set]).collect());
+        }
+        if let Some(colors) = colors {
+            displaced = displaced.with_colors(colors);
+        }
+        Ok(displaced)
+    }
+}
+
+fn lerp_uv(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    ((1.0 - t) * a.0 + t * b.0, (1.0 - t) * a.1 + t * b.1)
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Splits a face along the edges that have a midpoint, keeping its winding.
+fn split_face(face: &[u32; 3], midpoint: impl Fn(u32, u32) -> Option<u32>) -> Vec<[u32; 3]> {
+    let mids = [0, 1, 2].map(|e| midpoint(face[e], face[(e + 1) % 3]));
+    let corner = |k: usize| face[k % 3];
+
+    match mids.iter().filter(|m| m.is_some()).count() {
+        0 => vec![*face],
+        1 => {
+            // Halve the face from the split edge to the opposite corner
+            let e = mids.iter().position(Option::is_some).unwrap_or(0);
+            let m = mids[e].unwrap_or(face[e]);
+            let (a, b, c) = (corner(e), corner(e + 1), corner(e + 2));
+            vec![[a, m, c], [m, b, c]]
+        }
+        2 => {
+            // Rotate so the edge from c back to a is the one left whole
+            let k = (mids.iter().position(Option::is_none).unwrap_or(0) + 1) % 3;
+            let (a, b, c) = (corner(k), corner(k + 1), corner(k + 2));
+            let (mab, mbc) = (mids[k].unwrap_or(a), mids[(k + 1) % 3].unwrap_or(b));
+            vec![[mab, b, mbc], [a, mab, mbc], [a, mbc, c]]
+        }
+        _ => {
+            let [m0, m1, m2] = mids.map(|m| m.unwrap_or(face[0]));
+            vec![
+                [face[0], m0, m2],
+                [m0, face[1], m1],
+                [m2, m1, face[2]],
+                [m0, m1, m2],
+            ]
+        }
+    }
+}
+
+/// Area-weighted averages of the normals of the faces around each vertex.
+fn vertex_normals(positions: &[Point3], faces: &[[u32; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::zeros(); positions.len()];
+    for face in faces {
+        let [a, b, c] = face.map(|n| positions[n as usize]);
+        // The cross product's length is twice the face area
+        let n = (b - a).cross(&(c - a));
+        for &index in face {
+            normals[index as usize] += n;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| {
+            if n.norm_squared() > 0.0 {
+                n.normalize()
+            } else {
+                Vec3::new(0.0, 1.0, 0.0)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,440 @@
+use crate::core::aabb::Aabb;
+use crate::core::vec3::Vec3;
+use crate::geometry::bvh::{self, BvhNode, centroid, morton_code, surface_area};
+use crate::geometry::hittable::{Hittable, SHUTTER};
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::transforms::translate::Translate;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Objects per bucket: each edit rebuilds the BVH of its bucket, of at most this
+/// many objects, and the top level over the buckets.
+const BUCKET_SIZE: usize = 64;
+
+/// Identifies an object added to an `EditableScene`, stable across edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectHandle(u64);
+
+#[derive(Debug)]
+struct Entry {
+    object: Arc<dyn Hittable>,
+    offset: Vec3,
+    bucket: usize,
+}
+
+impl Entry {
+    /// The object moved by its offset.
+    fn placed(&self) -> Arc<dyn Hittable> {
+        if self.offset == Vec3::zeros() {
+            self.object.clone()
+        } else {
+            Arc::new(Translate::new(self.object.clone(), self.offset))
+        }
+    }
+}
+
+/// Nearby objects sharing a BVH.
+#[derive(Debug, Default)]
+struct Bucket {
+    handles: Vec<u64>,
+    /// Bounds of the objects, grown as they are added and tightened on rebuild.
+    bbox: Aabb,
+    tree: Option<Arc<dyn Hittable>>,
+    /// Objects `tree`'s BVH recorded memory for, 0 unless it is a `BvhNode`.
+    tree_objects: usize,
+    dirty: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_handle: u64,
+    entries: HashMap<u64, Entry>,
+    buckets: Vec<Bucket>,
+    /// Counts edits, so a snapshot built while the scene was edited is not kept.
+    version: u64,
+    /// Hierarchy of the latest snapshot, until the next edit.
+    snapshot: Option<Arc<dyn Hittable>>,
+    /// Buckets the top level of the latest snapshot recorded memory for.
+    snapshot_objects: usize,
+}
+
+/// A BVH over `objects`, or the object itself if there is just one, with the
+/// number of objects the BVH recorded memory for.
+fn build_tree(objects: Vec<Arc<dyn Hittable>>) -> (Option<Arc<dyn Hittable>>, usize) {
+    match objects.len() {
+        0 => (None, 0),
+        1 => (objects.into_iter().next(), 0),
+        n => (Some(Arc::new(BvhNode::new_from_objects(objects))), n),
+    }
+}
+
+/// A scene that can be edited after it is built, for previews and interactive
+/// tools: objects are added, removed, replaced and moved through handles without
+/// rebuilding the whole hierarchy.
+///
+/// Objects are grouped into buckets of nearby objects, each with its own BVH, under
+/// a top-level BVH over the buckets. An edit marks its buckets stale, and the next
+/// `snapshot` rebuilds just those, in parallel, and the small top level. Snapshots
+/// are immutable, so renders in flight keep the scene as it was while other threads
+/// edit it. Moved objects stay in buckets near where they go, but many edits loosen
+/// the grouping; `rebalance` regroups everything.
+///
+/// Light lists are separate: emitters added or moved here must also be given to the
+/// integrator's lights.
+#[derive(Debug, Default)]
+pub struct EditableScene {
+    state: Mutex<State>,
+}
+
+impl EditableScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A scene of the objects of `list`, grouped spatially, with their handles in
+    /// the order of the list.
+    pub fn from_list(list: &HittableList) -> (Self, Vec<ObjectHandle>) {
+        let mut state = State::default();
+        for object in &list.objects {
+            // Grouped by `rebalance` below, all at once
+            let entry = Entry {
+                object: object.clone(),
+                offset: Vec3::zeros(),
+                bucket: 0,
+            };
+            state.entries.insert(state.next_handle, entry);
+            state.next_handle += 1;
+        }
+        let handles = (0..state.next_handle).map(ObjectHandle).collect();
+        let scene = Self {
+            state: Mutex::new(state),
+        };
+        scene.rebalance();
+        (scene, handles)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn add(&self, object: Arc<dyn Hittable>) -> ObjectHandle {
+        let mut state = self.lock();
+        let handle = state.next_handle;
+        state.next_handle += 1;
+        let bbox = object.bounding_box(SHUTTER);
+        let bucket = state.insert(handle, &bbox);
+        state.entries.insert(
+            handle,
+            Entry {
+                object,
+                offset: Vec3::zeros(),
+                bucket,
+            },
+        );
+        ObjectHandle(handle)
+    }
+
+    /// Removes the object, returning it as it was added, or None if the handle is
+    /// not in the scene.
+    pub fn remove(&self, handle: ObjectHandle) -> Option<Arc<dyn Hittable>> {
+        let mut state = self.lock();
+        let entry = state.entries.remove(&handle.0)?;
+        state.detach(handle.0, entry.bucket);
+        Some(entry.object)
+    }
+
+    /// Puts `object` in place of the handle's, keeping its offset. Returns false
+    /// if the handle is not in the scene.
+    pub fn replace(&self, handle: ObjectHandle, object: Arc<dyn Hittable>) -> bool {
+        let mut state = self.lock();
+        let Some(entry) = state.entries.get_mut(&handle.0) else {
+            return false;
+        };
+        entry.object = object;
+        state.relocate(handle.0);
+        true
+    }
+
+    /// Moves the object to `offset` from where it was added. Returns false if the
+    /// handle is not in the scene.
+    pub fn set_offset(&self, handle: ObjectHandle, offset: Vec3) -> bool {
+        let mut state = self.lock();
+        let Some(entry) = state.entries.get_mut(&handle.0) else {
+            return false;
+        };
+        entry.offset = offset;
+        state.relocate(handle.0);
+        true
+    }
+
+    /// The object as placed in the scene, or None if the handle is not in it.
+    pub fn get(&self, handle: ObjectHandle) -> Option<Arc<dyn Hittable>> {
+        self.lock().entries.get(&handle.0).map(Entry::placed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Regroups all objects into buckets along a Morton curve through their
+    /// centroids, as tight as when the scene was first built, at the cost of a
+    /// full rebuild on the next snapshot.
+    pub fn rebalance(&self) {
+        let mut state = self.lock();
+        let boxes: Vec<(u64, Aabb)> = state
+            .entries
+            .iter()
+            .map(|(&handle, entry)| (handle, entry.placed().bounding_box(SHUTTER)))
+            .collect();
+        let centroids = boxes.iter().fold(Aabb::empty(), |bounds, (_, bbox)| {
+            let c = centroid(bbox);
+            bounds.merge(&Aabb::new_point(c, c))
+        });
+        let mut sorted: Vec<(u32, u64, Aabb)> = boxes
+            .into_iter()
+            .map(|(handle, bbox)| (morton_code(&centroid(&bbox), &centroids), handle, bbox))
+            .collect();
+        // Handles break ties, so equal scenes regroup the same way
+        sorted.sort_unstable_by_key(|&(code, handle, _)| (code, handle));
+
+        for bucket in &state.buckets {
+            bvh::release_memory(bucket.tree_objects);
+        }
+        state.buckets = sorted
+            .chunks(BUCKET_SIZE)
+            .map(|chunk| Bucket {
+                handles: chunk.iter().map(|&(_, handle, _)| handle).collect(),
+                bbox: chunk
+                    .iter()
+                    .fold(Aabb::empty(), |bounds, (_, _, bbox)| bounds.merge(bbox)),
+                tree: None,
+                tree_objects: 0,
+                dirty: true,
+            })
+            .collect();
+        for (index, chunk) in sorted.chunks(BUCKET_SIZE).enumerate() {
+            for &(_, handle, _) in chunk {
+                if let Some(entry) = state.entries.get_mut(&handle) {
+                    entry.bucket = index;
+                }
+            }
+        }
+        state.edited();
+    }
+
+    /// The scene as it is now, to render. Rebuilds the buckets edited since the
+    /// last snapshot; without edits, returns the same hierarchy again.
+    ///
+    /// The rebuild runs without holding the scene, so edits from other threads
+    /// go ahead meanwhile. If one lands during it, the snapshot still shows the
+    /// scene as it was when called, but is not kept, and the next call rebuilds.
+    pub fn snapshot(&self) -> Arc<dyn Hittable> {
+        let (version, stale, mut trees) = {
+            let state = self.lock();
+            if let Some(snapshot) = &state.snapshot {
+                return snapshot.clone();
+            }
+            let stale: Vec<(usize, Vec<Arc<dyn Hittable>>)> = state
+                .buckets
+                .iter()
+                .enumerate()
+                .filter(|(_, bucket)| bucket.dirty)
+                .map(|(index, bucket)| {
+                    let objects = bucket
+                        .handles
+                        .iter()
+                        .filter_map(|handle| state.entries.get(handle).map(Entry::placed))
+                        .collect();
+                    (index, objects)
+                })
+                .collect();
+            let trees: Vec<Option<Arc<dyn Hittable>>> = state
+                .buckets
+                .iter()
+                .map(|bucket| bucket.tree.clone())
+                .collect();
+            (state.version, stale, trees)
+        };
+
+        let rebuilt: Vec<_> = stale
+            .into_par_iter()
+            .map(|(index, objects)| {
+                let bbox = objects.iter().fold(Aabb::empty(), |bounds, object| {
+                    bounds.merge(&object.bounding_box(SHUTTER))
+                });
+                let (tree, recorded) = build_tree(objects);
+                (index, bbox, tree, recorded)
+            })
+            .collect();
+        for (index, _, tree, _) in &rebuilt {
+            trees[*index] = tree.clone();
+        }
+        let (top, top_objects) = build_tree(trees.into_iter().flatten().collect());
+        let snapshot = top.unwrap_or_else(|| Arc::new(HittableList::new()));
+
+        let mut state = self.lock();
+        if state.version == version {
+            for (index, bbox, tree, recorded) in rebuilt {
+                let bucket = &mut state.buckets[index];
+                bvh::release_memory(bucket.tree_objects);
+                bucket.bbox = bbox;
+                bucket.tree = tree;
+                bucket.tree_objects = recorded;
+                bucket.dirty = false;
+            }
+            bvh::release_memory(state.snapshot_objects);
+            state.snapshot_objects = top_objects;
+            state.snapshot = Some(snapshot.clone());
+        } else {
+            // Not kept, so not counted: the caller's copy is dropped after its render
+            for (_, _, _, recorded) in rebuilt {
+                bvh::release_memory(recorded);
+            }
+            bvh::release_memory(top_objects);
+        }
+        snapshot
+    }
+}
+
+impl State {
+    /// Puts `handle` in the bucket whose bounds grow least by taking `bbox`, or a
+    /// new bucket if all are full, returning the bucket's index.
+    fn insert(&mut self, handle: u64, bbox: &Aabb) -> usize {
+        let growth =
+            |bucket: &Bucket| surface_area(&bucket.bbox.merge(bbox)) - surface_area(&bucket.bbox);
+        let best = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, bucket)| bucket.handles.len() < BUCKET_SIZE)
+            .min_by(|(_, a), (_, b)| growth(a).total_cmp(&growth(b)))
+            .map(|(index, _)| index);
+        let index = best.unwrap_or_else(|| {
+            self.buckets.push(Bucket::default());
+            self.buckets.len() - 1
+        });
+
+        let bucket = &mut self.buckets[index];
+        bucket.handles.push(handle);
+        bucket.bbox = bucket.bbox.merge(bbox);
+        bucket.dirty = true;
+        self.edited();
+        index
+    }
+
+    fn detach(&mut self, handle: u64, bucket: usize) {
+        if let Some(bucket) = self.buckets.get_mut(bucket) {
+            bucket.handles.retain(|&h| h != handle);
+            bucket.dirty = true;
+        }
+        self.edited();
+    }
+
+    /// Drops the snapshot, which no longer shows the scene.
+    fn edited(&mut self) {
+        self.version += 1;
+        self.snapshot = None;
+    }
+
+    /// Moves a changed object to the bucket that suits its new bounds, possibly
+    /// the one it is in.
+    fn relocate(&mut self, handle: u64) {
+        let Some(entry) = self.entries.get(&handle) else {
+            return;
+        };
+        let (old, bbox) = (entry.bucket, entry.placed().bounding_box(SHUTTER));
+        self.detach(handle, old);
+        let bucket = self.insert(handle, &bbox);
+        if let Some(entry) = self.entries.get_mut(&handle) {
+            entry.bucket = bucket;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::interval::Interval;
+    use crate::core::ray::Ray;
+    use crate::core::vec3::Point3;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::textures::solid_color::SolidColor;
+
+    /// Spheres of radius 0.4 at x = 0, 1, 2, ... along the x axis.
+    fn row_of_spheres(count: usize) -> HittableList {
+        let material = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let mut list = HittableList::new();
+        for n in 0..count {
+            list.add(Arc::new(Sphere::new(
+                Point3::new(n as f64, 0.0, 0.0),
+                0.4,
+                material.clone(),
+            )));
+        }
+        list
+    }
+
+    fn trees(scene: &EditableScene) -> Vec<Option<Arc<dyn Hittable>>> {
+        scene
+            .lock()
+            .buckets
+            .iter()
+            .map(|bucket| bucket.tree.clone())
+            .collect()
+    }
+
+    /// Whether a ray straight down at (x, z) hits the scene.
+    fn hits_at(scene: &dyn Hittable, x: f64, z: f64) -> bool {
+        let ray = Ray::new(Point3::new(x, 10.0, z), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        scene
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .is_some()
+    }
+
+    #[test]
+    fn editing_an_object_rebuilds_only_its_bucket() {
+        let (scene, handles) = EditableScene::from_list(&row_of_spheres(4 * BUCKET_SIZE));
+        let before = scene.snapshot();
+        let trees_before = trees(&scene);
+        assert_eq!(trees_before.len(), 4);
+        assert!(Arc::ptr_eq(&before, &scene.snapshot()));
+        assert!(hits_at(&*before, 10.0, -0.3));
+
+        assert!(scene.set_offset(handles[10], Vec3::new(0.0, 0.0, 0.3)));
+        let dirty: Vec<bool> = scene.lock().buckets.iter().map(|b| b.dirty).collect();
+        assert_eq!(dirty.iter().filter(|&&dirty| dirty).count(), 1);
+
+        let after = scene.snapshot();
+        assert!(!Arc::ptr_eq(&before, &after));
+        for ((old, new), dirty) in trees_before.iter().zip(trees(&scene)).zip(dirty) {
+            let (old, new) = (old.as_ref().unwrap(), new.unwrap());
+            assert_eq!(Arc::ptr_eq(old, &new), !dirty);
+        }
+
+        // Found where it moved to, no longer where it was
+        assert!(hits_at(&*after, 10.0, 0.6));
+        assert!(!hits_at(&*after, 10.0, -0.3));
+        // Earlier snapshots keep the scene as it was
+        assert!(hits_at(&*before, 10.0, -0.3));
+    }
+
+    #[test]
+    fn removed_objects_leave_the_snapshot() {
+        let (scene, handles) = EditableScene::from_list(&row_of_spheres(3));
+        assert!(hits_at(&*scene.snapshot(), 1.0, 0.0));
+        assert!(scene.remove(handles[1]).is_some());
+        assert!(scene.remove(handles[1]).is_none());
+        assert_eq!(scene.len(), 2);
+        let snapshot = scene.snapshot();
+        assert!(!hits_at(&*snapshot, 1.0, 0.0));
+        assert!(hits_at(&*snapshot, 2.0, 0.0));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use std::fmt;
+use std::sync::Arc;
+
+/// User callback run on every intersection with the wrapped object before it is shaded.
+/// It may inspect or modify the `Interaction` (swap the material, record data for a
+/// custom AOV) and returns false to discard the hit, letting the ray continue past it.
+pub type HitCallback = dyn Fn(&Ray, &mut Interaction) -> bool + Send + Sync;
+
+/// Most hits discarded in a row before the ray is considered to miss.
+const MAX_DISCARDS: usize = 64;
+/// Offset past a discarded hit before searching for the next one.
+const DISCARD_EPSILON: f64 = 0.0001;
+
+/// Runs a `HitCallback` on the hits of the wrapped object, which is how library users
+/// customize shading (clipping, cross sections, custom AOVs) without touching the
+/// integrators. Shadow rays see the callback too; media transmittance does not.
+pub struct HitHook {
+    object: Arc<dyn Hittable>,
+    callback: Arc<HitCallback>,
+}
+
+impl HitHook {
+    pub fn new(object: Arc<dyn Hittable>, callback: Arc<HitCallback>) -> Self {
+        Self { object, callback }
+    }
+
+    /// Finds the closest hit accepted by the callback, querying the wrapped object
+    /// with `hit` or `intersect_p`.
+    fn closest_accepted(
+        &self,
+        r: &Ray,
+        ray_t: Interval,
+        query: impl Fn(&Ray, Interval) -> Option<Interaction>,
+    ) -> Option<Interaction> {
+        let mut search = ray_t;
+        for _ in 0..MAX_DISCARDS {
+            let mut isect = query(r, search)?;
+            if (self.callback)(r, &mut isect) {
+                return Some(isect);
+            }
+            search = Interval::new(isect.t + DISCARD_EPSILON, ray_t.max);
+        }
+        None
+    }
+}
+
+impl fmt::Debug for HitHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HitHook")
+            .field("object", &self.object)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hittable for HitHook {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest_accepted(r, ray_t, |r, t| self.object.hit(r, t))
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest_accepted(r, ray_t, |r, t| self.object.intersect_p(r, t))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.object.sample_surface(time)
+    }
+}
@@ -1,26 +1,99 @@
 use crate::core::aabb::Aabb;
-use crate::core::interaction::Interaction;
+use crate::core::interaction::{Interaction, ObjectTag};
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::sampling::random::random_double;
 use std::fmt::Debug;
 
+/// Transmittance below which shadow rays play Russian roulette instead of evaluating
+/// the remaining media.
+const TRANSMITTANCE_RR_THRESHOLD: f64 = 0.1;
+
+/// Interval over which the shutter is open, the times camera rays are cast at.
+/// Acceleration structures bound objects over all of it.
+pub const SHUTTER: Interval = Interval::new(0.0, 1.0);
+
+/// How far through the shutter `time` is, 0 when it opens and 1 when it closes.
+/// Every moving object interpolates its start and end placements by it, so they
+/// agree on where things are at a given ray time.
+pub fn shutter_fraction(time: f64) -> f64 {
+    (time - SHUTTER.min) / SHUTTER.size()
+}
+
 /// Trait representing any object that can be intersected by a ray.
 pub trait Hittable: Send + Sync + Debug {
-    /// Determines if a ray hits the object within the given interval.
-    /// If hit, populates `isect` and returns true.
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool;
+    /// Returns the closest intersection of the ray with the object within the
+    /// given interval, if any.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction>;
 
-    /// Returns the axis-aligned bounding box of the object.
-    fn bounding_box(&self) -> Aabb;
+    /// Shadow ray query: finds the closest surface that blocks light along `r`.
+    /// Participating media and other partly transparent objects are skipped here
+    /// and accounted for by `transmittance` instead.
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.hit(r, ray_t)
+    }
+
+    /// Fraction of light carried unscattered along `r` within `ray_t` by the objects
+    /// that `intersect_p` skips, or an unbiased estimate of it for groups that
+    /// roulette with `roulette_transmittance`. Opaque surfaces transmit everything.
+    fn transmittance(&self, _r: &Ray, _ray_t: Interval) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Returns an axis-aligned box enclosing the object at every instant of `time`,
+    /// usually `SHUTTER`, so moving objects get conservative bounds.
+    fn bounding_box(&self, time: Interval) -> Aabb;
+
+    /// Name and ID the object was given with `Named`, if any.
+    fn object_tag(&self) -> Option<&ObjectTag> {
+        None
+    }
+
+    /// Surface area of the object, or 0 if unknown.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Total power the object emits (its area times the exitance of its material),
+    /// or 0 for objects that do not emit or whose area is unknown.
+    fn power(&self) -> Color {
+        Color::zeros()
+    }
 
     /// Returns the Probability Density Function value for a given direction.
-    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+    /// `time` is the time of the shading point, so moving objects are sampled where they are.
+    /// Wrappers that transform space query their object in object space, scaling the
+    /// density by the Jacobian of the transform when it does not preserve solid angles.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
         0.0
     }
 
     /// Generates a random direction towards this object (for sampling).
-    fn random(&self, _origin: &Point3) -> Vec3 {
+    fn random(&self, _origin: &Point3, _time: f64) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
+
+    /// Samples a point on the surface (for light-driven integrators).
+    /// Returns the surface interaction and its PDF with respect to area,
+    /// or None if the object does not support surface sampling.
+    fn sample_surface(&self, _time: f64) -> Option<(Interaction, f64)> {
+        None
+    }
+}
+
+/// Russian roulette on a partial transmittance product: once it drops below
+/// `TRANSMITTANCE_RR_THRESHOLD`, it survives with probability proportional to its
+/// largest channel, scaled back up to the threshold, or becomes zero so the caller can
+/// skip the media still ahead. The expected value is unchanged.
+pub fn roulette_transmittance(tr: Color) -> Color {
+    let q = tr.max() / TRANSMITTANCE_RR_THRESHOLD;
+    if q <= 0.0 || q >= 1.0 {
+        return tr;
+    }
+    if random_double() < q {
+        tr / q
+    } else {
+        Color::zeros()
+    }
 }
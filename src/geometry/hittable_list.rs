@@ -1,68 +1,190 @@
 use crate::core::aabb::Aabb;
-use crate::core::interaction::Interaction;
+use crate::core::interaction::{Interaction, ObjectTag};
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
-use crate::geometry::hittable::Hittable;
-use crate::sampling::random::random_int_range;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, roulette_transmittance};
+use crate::sampling::random::{random_double, random_int_range};
 use std::sync::Arc;
 
+/// Collection of hittables. When used as a light list, objects are selected
+/// proportionally to per-object weights (surface area by default).
 #[derive(Default, Debug)]
 pub struct HittableList {
     pub objects: Vec<Arc<dyn Hittable>>,
-    bbox: Aabb,
+    weights: Vec<f64>,
+    total_weight: f64,
 }
 
 impl HittableList {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
-            bbox: Aabb::empty(),
+            weights: Vec::new(),
+            total_weight: 0.0,
         }
     }
 
+    /// Adds an object with its surface area as sampling weight.
+    /// Objects of unknown area get unit weight.
     pub fn add(&mut self, object: Arc<dyn Hittable>) {
-        self.bbox = self.bbox.merge(&object.bounding_box());
+        let area = object.area();
+        let weight = if area > 0.0 { area } else { 1.0 };
+        self.add_weighted(object, weight);
+    }
+
+    /// Adds an object with the luminance of its emitted power as sampling weight,
+    /// so bright lights are sampled more often than large dim ones.
+    pub fn add_by_power(&mut self, object: Arc<dyn Hittable>) {
+        let power = object.power();
+        let luminance = 0.2126 * power.x + 0.7152 * power.y + 0.0722 * power.z;
+        self.add_weighted(object, luminance);
+    }
+
+    /// Adds an object with an explicit sampling weight (e.g. emitted power).
+    pub fn add_weighted(&mut self, object: Arc<dyn Hittable>, weight: f64) {
         self.objects.push(object);
+        self.weights.push(weight.max(0.0));
+        self.total_weight += weight.max(0.0);
+    }
+
+    /// Per-object sampling weights, parallel to `objects`.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// Tags of the named objects of the list, in order. Names given inside groups
+    /// are not listed.
+    pub fn tags(&self) -> impl Iterator<Item = &ObjectTag> {
+        self.objects.iter().filter_map(|object| object.object_tag())
+    }
+
+    /// The object of the list named `name`, if any.
+    pub fn find(&self, name: &str) -> Option<&Arc<dyn Hittable>> {
+        self.objects
+            .iter()
+            .find(|object| object.object_tag().is_some_and(|tag| &*tag.name == name))
+    }
+
+    /// The object of the list with ID `id`, e.g. as read from an object ID AOV.
+    pub fn find_id(&self, id: u32) -> Option<&Arc<dyn Hittable>> {
+        self.objects
+            .iter()
+            .find(|object| object.object_tag().is_some_and(|tag| tag.id == id))
+    }
+
+    /// Probability of selecting object `index` when sampling the list.
+    fn selection_probability(&self, index: usize) -> f64 {
+        if self.weights.len() != self.objects.len() || self.total_weight <= 0.0 {
+            // Objects pushed directly into `objects` have no weights; fall back to uniform
+            return 1.0 / self.objects.len() as f64;
+        }
+        self.weights()[index] / self.total_weight
+    }
+
+    /// Selects an object index proportionally to its weight.
+    fn select(&self) -> Option<usize> {
+        let int_size = self.objects.len();
+        if int_size == 0 {
+            return None;
+        }
+
+        if self.weights.len() != int_size || self.total_weight <= 0.0 {
+            return Some(random_int_range(0, (int_size - 1) as i32) as usize);
+        }
+
+        let target = random_double() * self.total_weight;
+        let mut accum = 0.0;
+        for (index, weight) in self.weights.iter().enumerate() {
+            accum += weight;
+            if target < accum {
+                return Some(index);
+            }
+        }
+        // Guard against round-off at the upper end
+        self.weights.iter().rposition(|&w| w > 0.0)
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
-        let mut temp_isect = Interaction::default();
-        let mut hit_anything = false;
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut closest = None;
+        let mut closest_so_far = ray_t.max;
+
+        for object in &self.objects {
+            if let Some(isect) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = isect.t;
+                closest = Some(isect);
+            }
+        }
+
+        closest
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut closest = None;
         let mut closest_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_isect) {
-                hit_anything = true;
-                closest_so_far = temp_isect.t;
-                *isect = temp_isect.clone();
+            if let Some(isect) = object.intersect_p(r, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = isect.t;
+                closest = Some(isect);
             }
         }
 
-        hit_anything
+        closest
     }
 
-    fn bounding_box(&self) -> Aabb {
-        self.bbox
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let mut tr = Color::new(1.0, 1.0, 1.0);
+        for object in &self.objects {
+            tr = roulette_transmittance(tr.component_mul(&object.transmittance(r, ray_t)));
+            if tr == Color::zeros() {
+                break;
+            }
+        }
+        tr
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.objects.iter().fold(Aabb::empty(), |bbox, object| {
+            bbox.merge(&object.bounding_box(time))
+        })
+    }
+
+    fn area(&self) -> f64 {
+        self.objects.iter().map(|obj| obj.area()).sum()
+    }
+
+    fn power(&self) -> Color {
+        self.objects.iter().map(|obj| obj.power()).sum()
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        let weight = 1.0 / self.objects.len() as f64;
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         self.objects
             .iter()
-            .map(|obj| weight * obj.pdf_value(origin, direction))
+            .enumerate()
+            .map(|(index, obj)| {
+                let probability = self.selection_probability(index);
+                if probability > 0.0 {
+                    probability * obj.pdf_value(origin, direction, time)
+                } else {
+                    0.0
+                }
+            })
             .sum()
     }
 
-    fn random(&self, origin: &Point3) -> Vec3 {
-        let int_size = self.objects.len();
-        if int_size == 0 {
-            return Vec3::new(1.0, 0.0, 0.0);
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        match self.select() {
+            Some(index) => self.objects[index].random(origin, time),
+            None => Vec3::new(1.0, 0.0, 0.0),
         }
+    }
 
-        let index = random_int_range(0, (int_size - 1) as i32) as usize;
-        self.objects[index].random(origin)
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let index = self.select()?;
+        let (isect, pdf) = self.objects[index].sample_surface(time)?;
+        Some((isect, pdf * self.selection_probability(index)))
     }
 }
@@ -0,0 +1,353 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::memory::{self, Category};
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::bvh::count_traversal;
+use crate::geometry::hittable::{Hittable, SHUTTER, roulette_transmittance};
+use crate::geometry::hittable_list::HittableList;
+use std::sync::Arc;
+
+/// Relative cost of testing a primitive against stepping through a node, as in
+/// PBRT: splits must save a lot of primitive tests to pay off.
+const INTERSECT_COST: f64 = 80.0;
+const TRAVERSAL_COST: f64 = 1.0;
+/// Discount on the cost of splits that leave one side empty, which rays cross
+/// for free.
+const EMPTY_BONUS: f64 = 0.5;
+/// Leaves are made once a node holds this few primitives.
+const MAX_LEAF_PRIMITIVES: usize = 1;
+/// Splits allowed to cost more than the leaf they replace along one path, in the
+/// hope that further splits recover it.
+const MAX_BAD_REFINES: u32 = 3;
+/// Slots of the per-ray mailbox of primitives already tested.
+const MAILBOX_SLOTS: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+enum KdNode {
+    /// Splits space at `split` along `axis`. The child below is the next node.
+    Interior { axis: u8, split: f64, above: u32 },
+    /// Primitives `indices[start..start + count]`.
+    Leaf { start: u32, count: u32 },
+}
+
+/// Primitive extent along the split axis, its start sorting before ends at the
+/// same position.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    t: f64,
+    end: bool,
+    primitive: u32,
+}
+
+/// kd-tree over a list of primitives, an alternative to `BvhNode` to compare on a
+/// given scene: space is split by axis-aligned planes chosen with the surface
+/// area heuristic, and primitives straddling a plane are referenced from both
+/// sides. Rays visit leaves front to back and stop at the first leaf past the
+/// closest hit; a small mailbox per ray keeps straddling primitives from being
+/// tested twice.
+///
+/// Built once over the bounds at `SHUTTER`. As a light list it samples primitives
+/// by area like a `HittableList`, evaluating every light's density.
+#[derive(Debug)]
+pub struct KdTree {
+    primitives: HittableList,
+    indices: Vec<u32>,
+    nodes: Vec<KdNode>,
+    bounds: Aabb,
+}
+
+impl KdTree {
+    pub fn new(list: &HittableList) -> Self {
+        Self::new_from_objects(list.objects.clone())
+    }
+
+    pub fn new_from_objects(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let boxes: Vec<Aabb> = objects.iter().map(|o| o.bounding_box(SHUTTER)).collect();
+        let bounds = boxes
+            .iter()
+            .fold(Aabb::empty(), |bounds, bbox| bounds.merge(bbox));
+        let mut primitives = HittableList::new();
+        for object in objects {
+            primitives.add(object);
+        }
+
+        let mut tree = Self {
+            primitives,
+            indices: Vec::new(),
+            nodes: Vec::new(),
+            bounds,
+        };
+        let all: Vec<u32> = (0..boxes.len() as u32).collect();
+        let max_depth = (8.0 + 1.3 * (boxes.len().max(1) as f64).log2()).round() as u32;
+        tree.build(bounds, &all, &boxes, max_depth, 0);
+        memory::record(
+            Category::Bvhs,
+            tree.nodes.len() * size_of::<KdNode>() + tree.indices.len() * size_of::<u32>(),
+        );
+        tree
+    }
+
+    /// Appends the subtree over `primitives` within `bounds`.
+    fn build(
+        &mut self,
+        bounds: Aabb,
+        primitives: &[u32],
+        boxes: &[Aabb],
+        depth: u32,
+        bad_refines: u32,
+    ) {
+        let leaf_cost = INTERSECT_COST * primitives.len() as f64;
+        let split = if primitives.len() <= MAX_LEAF_PRIMITIVES || depth == 0 {
+            None
+        } else {
+            Self::best_split(&bounds, primitives, boxes)
+        };
+        // Worse splits are still taken a few times, unless far worse on a small node
+        let split = split.filter(|&(_, _, cost, _)| {
+            !(cost > 4.0 * leaf_cost && primitives.len() < 16)
+                && (cost <= leaf_cost || bad_refines < MAX_BAD_REFINES)
+        });
+
+        let Some((axis, position, cost, edges)) = split else {
+            self.nodes.push(KdNode::Leaf {
+                start: self.indices.len() as u32,
+                count: primitives.len() as u32,
+            });
+            self.indices.extend_from_slice(primitives);
+            return;
+        };
+        let bad_refines = bad_refines + (cost > leaf_cost) as u32;
+
+        // Primitives starting before the split go below, those ending after it above
+        let below: Vec<u32> = edges
+            .iter()
+            .filter(|e| !e.end && e.t < position)
+            .map(|e| e.primitive)
+            .collect();
+        let above: Vec<u32> = edges
+            .iter()
+            .filter(|e| e.end && e.t > position)
+            .map(|e| e.primitive)
+            .collect();
+        let (mut below_bounds, mut above_bounds) = (bounds, bounds);
+        match axis {
+            0 => (below_bounds.x.max, above_bounds.x.min) = (position, position),
+            1 => (below_bounds.y.max, above_bounds.y.min) = (position, position),
+            _ => (below_bounds.z.max, above_bounds.z.min) = (position, position),
+        }
+
+        let node = self.nodes.len();
+        self.nodes.push(KdNode::Leaf { start: 0, count: 0 });
+        self.build(below_bounds, &below, boxes, depth - 1, bad_refines);
+        let above_node = self.nodes.len() as u32;
+        self.build(above_bounds, &above, boxes, depth - 1, bad_refines);
+        self.nodes[node] = KdNode::Interior {
+            axis: axis as u8,
+            split: position,
+            above: above_node,
+        };
+    }
+
+    /// Cheapest split of the node by the surface area heuristic, trying the axis
+    /// of largest extent first: its axis, position and cost, with the sorted edges
+    /// along the axis. None if no plane falls inside the node.
+    fn best_split(
+        bounds: &Aabb,
+        primitives: &[u32],
+        boxes: &[Aabb],
+    ) -> Option<(usize, f64, f64, Vec<Edge>)> {
+        let extent = [bounds.x.size(), bounds.y.size(), bounds.z.size()];
+        let area = 2.0 * (extent[0] * extent[1] + extent[1] * extent[2] + extent[2] * extent[0]);
+        if !area.is_finite() || area <= 0.0 {
+            return None;
+        }
+
+        let mut axes = [0, 1, 2];
+        axes.sort_by(|&a, &b| extent[b].total_cmp(&extent[a]));
+        for axis in axes {
+            let interval = bounds.axis_interval(axis);
+            let (d0, d1) = (extent[(axis + 1) % 3], extent[(axis + 2) % 3]);
+            let mut edges: Vec<Edge> = primitives
+                .iter()
+                .flat_map(|&primitive| {
+                    let extent = boxes[primitive as usize].axis_interval(axis);
+                    [(extent.min, false), (extent.max, true)].map(|(t, end)| Edge {
+                        t,
+                        end,
+                        primitive,
+                    })
+                })
+                .collect();
+            edges.sort_by(|a, b| a.t.total_cmp(&b.t).then(a.end.cmp(&b.end)));
+
+            let mut best: Option<(f64, f64)> = None;
+            let (mut below, mut above) = (0, primitives.len());
+            for edge in &edges {
+                if edge.end {
+                    above -= 1;
+                }
+                if edge.t > interval.min && edge.t < interval.max {
+                    let below_area = 2.0 * (d0 * d1 + (edge.t - interval.min) * (d0 + d1));
+                    let above_area = 2.0 * (d0 * d1 + (interval.max - edge.t) * (d0 + d1));
+                    let bonus = if below == 0 || above == 0 {
+                        EMPTY_BONUS
+                    } else {
+                        0.0
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECT_COST
+                            * (1.0 - bonus)
+                            * (below_area * below as f64 + above_area * above as f64)
+                            / area;
+                    if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                        best = Some((cost, edge.t));
+                    }
+                }
+                if !edge.end {
+                    below += 1;
+                }
+            }
+
+            if let Some((cost, position)) = best {
+                return Some((axis, position, cost, edges));
+            }
+        }
+        None
+    }
+
+    /// Walks the leaves `r` passes through within `ray_t` front to back, calling
+    /// `visit` once per primitive in them. `visit` returns the distance beyond
+    /// which nothing more is wanted, ending the walk before any leaf past it.
+    fn traverse(&self, r: &Ray, ray_t: Interval, mut visit: impl FnMut(&Arc<dyn Hittable>) -> f64) {
+        let entered = self.bounds.clip(r, ray_t);
+        count_traversal(0, entered.is_some());
+        let Some(Interval {
+            min: mut t_min,
+            max: mut t_max,
+        }) = entered
+        else {
+            return;
+        };
+
+        let mut mailbox = [u32::MAX; MAILBOX_SLOTS];
+        let mut stack: Vec<(usize, f64, f64)> = Vec::new();
+        let mut node = 0;
+        let mut wanted = ray_t.max;
+        loop {
+            if wanted < t_min {
+                return;
+            }
+            match self.nodes[node] {
+                KdNode::Interior { axis, split, above } => {
+                    count_traversal(0, true);
+                    let axis = axis as usize;
+                    let t_plane = (split - r.orig[axis]) / r.dir[axis];
+                    let below_first =
+                        r.orig[axis] < split || (r.orig[axis] == split && r.dir[axis] <= 0.0);
+                    let (first, second) = if below_first {
+                        (node + 1, above as usize)
+                    } else {
+                        (above as usize, node + 1)
+                    };
+
+                    if t_plane > t_max || t_plane <= 0.0 || t_plane.is_nan() {
+                        node = first;
+                    } else if t_plane < t_min {
+                        node = second;
+                    } else {
+                        stack.push((second, t_plane, t_max));
+                        node = first;
+                        t_max = t_plane;
+                    }
+                }
+                KdNode::Leaf { start, count } => {
+                    count_traversal(count as u64, true);
+                    for &index in &self.indices[start as usize..(start + count) as usize] {
+                        let slot = &mut mailbox[index as usize % MAILBOX_SLOTS];
+                        if *slot == index {
+                            continue;
+                        }
+                        *slot = index;
+                        wanted = visit(&self.primitives.objects[index as usize]);
+                    }
+                    match stack.pop() {
+                        Some((next, next_min, next_max)) => {
+                            (node, t_min, t_max) = (next, next_min, next_max);
+                        }
+                        None => return,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Closest intersection by `query`, either `hit` or `intersect_p`. Primitives
+    /// are tested over the whole remaining interval, not just their leaf, so a
+    /// hit beyond the leaf is kept and its primitive need not be tested again.
+    fn closest(
+        &self,
+        r: &Ray,
+        ray_t: Interval,
+        query: impl Fn(&Arc<dyn Hittable>, Interval) -> Option<Interaction>,
+    ) -> Option<Interaction> {
+        let mut closest = None;
+        let mut closest_so_far = ray_t.max;
+        self.traverse(r, ray_t, |object| {
+            if let Some(isect) = query(object, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = isect.t;
+                closest = Some(isect);
+            }
+            closest_so_far
+        });
+        closest
+    }
+}
+
+impl Hittable for KdTree {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest(r, ray_t, |object, ray_t| object.hit(r, ray_t))
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.closest(r, ray_t, |object, ray_t| object.intersect_p(r, ray_t))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let mut tr = Color::new(1.0, 1.0, 1.0);
+        self.traverse(r, ray_t, |object| {
+            tr = roulette_transmittance(tr.component_mul(&object.transmittance(r, ray_t)));
+            if tr == Color::zeros() {
+                f64::NEG_INFINITY
+            } else {
+                ray_t.max
+            }
+        });
+        tr
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.bounds
+    }
+
+    fn area(&self) -> f64 {
+        self.primitives.area()
+    }
+
+    fn power(&self) -> Color {
+        self.primitives.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.primitives.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.primitives.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.primitives.sample_surface(time)
+    }
+}
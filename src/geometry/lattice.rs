@@ -0,0 +1,147 @@
+use crate::core::onb::ONB;
+use crate::core::vec3::{Point3, Vec3};
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::hittable::Hittable;
+use crate::geometry::transforms::rotate::RotateY;
+use crate::geometry::transforms::translate::Translate;
+use crate::sampling::random::{random_double, random_double_range};
+use std::sync::Arc;
+
+/// Where one instance goes: its position, the surface normal there (up for grids
+/// and curves), its rotation about the vertical axis in degrees, and its index.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    pub position: Point3,
+    pub normal: Vec3,
+    pub rotation: f64,
+    pub index: usize,
+}
+
+/// Procedural layout of instances, such as a field of boxes, pebbles strewn over
+/// the ground or posts along a path. A generator lays out the placements, builders
+/// vary them, and `instantiate` puts an object at each, under one BVH.
+///
+/// Instances are rotated about the vertical axis only, so they stay upright on
+/// sloped surfaces; objects that should lean with the surface can use the normal.
+#[derive(Debug, Clone)]
+pub struct Lattice {
+    placements: Vec<Placement>,
+}
+
+impl Lattice {
+    fn new(placements: impl Iterator<Item = (Point3, Vec3, f64)>) -> Self {
+        Self {
+            placements: placements
+                .enumerate()
+                .map(|(index, (position, normal, rotation))| Placement {
+                    position,
+                    normal,
+                    rotation,
+                    index,
+                })
+                .collect(),
+        }
+    }
+
+    /// `columns` by `rows` placements from `origin`, a step of `u` between columns
+    /// and `v` between rows, in column-major order. Their normal is that of the
+    /// grid's plane, on its upper side.
+    pub fn grid(origin: Point3, u: Vec3, v: Vec3, columns: u32, rows: u32) -> Self {
+        let mut normal = u.cross(&v).try_normalize(0.0).unwrap_or(Vec3::y());
+        if normal.y < 0.0 {
+            normal = -normal;
+        }
+        Self::new((0..columns).flat_map(move |i| {
+            (0..rows).map(move |j| (origin + i as f64 * u + j as f64 * v, normal, 0.0))
+        }))
+    }
+
+    /// `count` placements uniformly distributed over the area of `surface`, with
+    /// their surface normals.
+    pub fn on_surface(surface: &dyn Hittable, count: usize) -> Self {
+        Self::new(
+            (0..count)
+                .filter_map(|_| surface.sample_surface(0.0))
+                .map(|(isect, _)| (isect.p, isect.geometry_normal, 0.0)),
+        )
+    }
+
+    /// `count` placements evenly spaced along the polyline through `points`, ends
+    /// included, each turned to face along the curve with its +x axis.
+    pub fn along_curve(points: &[Point3], count: usize) -> Self {
+        let lengths: Vec<f64> = points.windows(2).map(|w| (w[1] - w[0]).norm()).collect();
+        let total: f64 = lengths.iter().sum();
+        if points.len() < 2 || total <= 0.0 {
+            return Self::new(points.iter().take(count).map(|&p| (p, Vec3::y(), 0.0)));
+        }
+
+        Self::new((0..count).map(|k| {
+            let mut distance = total * k as f64 / (count.max(2) - 1) as f64;
+            // Find the segment the distance falls on, clamping round-off to the last
+            let mut segment = 0;
+            while segment + 1 < lengths.len() && distance > lengths[segment] {
+                distance -= lengths[segment];
+                segment += 1;
+            }
+            let (a, b) = (points[segment], points[segment + 1]);
+            let t = if lengths[segment] > 0.0 {
+                (distance / lengths[segment]).min(1.0)
+            } else {
+                0.0
+            };
+            // RotateY by theta turns +x towards (cos theta, 0, -sin theta)
+            let tangent = b - a;
+            let heading = (-tangent.z).atan2(tangent.x).to_degrees();
+            (a + t * tangent, Vec3::y(), heading)
+        }))
+    }
+
+    /// Moves each placement by a random offset of up to `amount` along each of two
+    /// directions perpendicular to its normal, breaking up the regularity of grids.
+    pub fn with_jitter(mut self, amount: f64) -> Self {
+        for placement in &mut self.placements {
+            let uvw = ONB::build_from_w(&placement.normal);
+            placement.position += random_double_range(-amount, amount) * uvw.u()
+                + random_double_range(-amount, amount) * uvw.v();
+        }
+        self
+    }
+
+    /// Adds a uniformly random rotation about the vertical axis to each placement.
+    pub fn with_random_rotation(mut self) -> Self {
+        for placement in &mut self.placements {
+            placement.rotation += 360.0 * random_double();
+        }
+        self
+    }
+
+    pub fn placements(&self) -> &[Placement] {
+        &self.placements
+    }
+
+    pub fn len(&self) -> usize {
+        self.placements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    /// Builds an object for each placement with `make`, modeled around the origin,
+    /// rotates and moves it into place, and collects them under a BVH. `make` sees
+    /// the placement, so it can vary the size or material of each instance.
+    pub fn instantiate(&self, mut make: impl FnMut(&Placement) -> Arc<dyn Hittable>) -> BvhNode {
+        let instances = self
+            .placements
+            .iter()
+            .map(|placement| {
+                let mut object = make(placement);
+                if placement.rotation != 0.0 {
+                    object = Arc::new(RotateY::new(object, placement.rotation));
+                }
+                Arc::new(Translate::new(object, placement.position.coords)) as Arc<dyn Hittable>
+            })
+            .collect();
+        BvhNode::new_from_objects(instances)
+    }
+}
@@ -0,0 +1,303 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::{Interaction, MaterialParams};
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::material_trait::Material;
+use crate::materials::metal::Metal;
+use crate::materials::params;
+use crate::materials::shading_graph::ShadingGraph;
+use crate::textures::debug::{NormalTexture, StripesTexture, UvCheckerTexture};
+use crate::textures::solid_color::SolidColor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Tiles per side of the `uv-checker` debug material.
+const DEFAULT_UV_TILES: u32 = 8;
+
+/// Replacement material given on the command line.
+#[derive(Debug, Clone)]
+pub enum MaterialSpec {
+    /// Matte mid gray, for checking geometry and lighting.
+    Clay,
+    Lambertian(Color),
+    Metal(Color, f64),
+    Dielectric(f64),
+    /// Numbered UV checker grid with the given tiles per side.
+    UvChecker(u32),
+    /// Shading normals as colors.
+    Normals,
+    /// World-position stripes with the given period.
+    Stripes(f64),
+    /// Material compiled from a `ShadingGraph` file.
+    Graph(Arc<dyn Material>),
+}
+
+impl MaterialSpec {
+    pub fn build(&self) -> Arc<dyn Material> {
+        match *self {
+            Self::Graph(ref material) => material.clone(),
+            Self::Clay => Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+            Self::Lambertian(albedo) => {
+                Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))
+            }
+            Self::Metal(albedo, fuzz) => Arc::new(Metal::new(albedo, fuzz)),
+            Self::Dielectric(ior) => Arc::new(Dielectric::new(ior)),
+            Self::UvChecker(tiles) => {
+                Arc::new(Lambertian::new(Arc::new(UvCheckerTexture::new(tiles))))
+            }
+            Self::Normals => Arc::new(Lambertian::new(Arc::new(NormalTexture))),
+            Self::Stripes(period) => {
+                Arc::new(Lambertian::new(Arc::new(StripesTexture::new(period))))
+            }
+        }
+    }
+}
+
+impl FromStr for MaterialSpec {
+    type Err = String;
+
+    /// `clay`, `lambertian:r,g,b`, `metal:r,g,b,fuzz`, `dielectric:ior`, the debug
+    /// textures `uv-checker[:tiles]`, `normals` and `stripes:period`, or
+    /// `graph:<file>[#node]` for a node of a shading graph, by default its output.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(source) = s.strip_prefix("graph:") {
+            let (filename, node) = match source.split_once('#') {
+                Some((filename, node)) => (filename, Some(node.to_string())),
+                None => (source, None),
+            };
+            let graph = ShadingGraph::load(filename)?;
+            let node = node
+                .or_else(|| graph.output().map(str::to_string))
+                .ok_or_else(|| format!("{}: The graph defines no material", filename))?;
+            return Ok(Self::Graph(
+                graph
+                    .material(&node)
+                    .map_err(|e| format!("{}: {}", filename, e))?,
+            ));
+        }
+
+        let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+        let values: Vec<f64> = params
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .map(|p| p.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid material parameters '{}'", params))?;
+
+        match (kind, &values[..]) {
+            ("clay", []) => Ok(Self::Clay),
+            ("lambertian", &[r, g, b]) => Ok(Self::Lambertian(albedo(r, g, b)?)),
+            ("metal", &[r, g, b, fuzz]) => Ok(Self::Metal(
+                albedo(r, g, b)?,
+                params::check_unit("fuzz", fuzz)?,
+            )),
+            ("dielectric", &[ior]) => Ok(Self::Dielectric(params::check_ior("IOR", ior)?)),
+            ("uv-checker", []) => Ok(Self::UvChecker(DEFAULT_UV_TILES)),
+            ("uv-checker", &[tiles]) if tiles >= 1.0 => Ok(Self::UvChecker(tiles as u32)),
+            ("normals", []) => Ok(Self::Normals),
+            ("stripes", &[period]) if period > 0.0 => Ok(Self::Stripes(period)),
+            _ => Err(format!(
+                "Unknown material '{}'. Available: clay, lambertian:r,g,b, metal:r,g,b,fuzz, dielectric:ior, uv-checker[:tiles], normals, stripes:period, graph:file[#node]",
+                s
+            )),
+        }
+    }
+}
+
+/// Reflectance given on the command line, rejected if outside [0, 1].
+fn albedo(r: f64, g: f64, b: f64) -> Result<Color, String> {
+    Ok(Color::new(
+        params::check_unit("albedo", r)?,
+        params::check_unit("albedo", g)?,
+        params::check_unit("albedo", b)?,
+    ))
+}
+
+/// `<target>=<material>`: replaces every material whose type name matches `target`
+/// (case-insensitive), or every surface material for `*`.
+#[derive(Debug, Clone)]
+pub struct OverrideRule {
+    target: String,
+    spec: MaterialSpec,
+}
+
+impl OverrideRule {
+    /// The clay render: all surfaces become matte gray, lights and volumes stay.
+    pub fn clay() -> Self {
+        Self {
+            target: "*".to_string(),
+            spec: MaterialSpec::Clay,
+        }
+    }
+
+    fn matches(&self, material: &dyn Material) -> bool {
+        let name = material.name();
+        if self.target == "*" {
+            // Lights keep lighting the scene, and a surface material inside a volume
+            // would turn fog into solid specks
+            return !matches!(name, "DiffuseLight" | "Isotropic");
+        }
+        self.target.eq_ignore_ascii_case(name)
+    }
+}
+
+impl FromStr for OverrideRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, spec) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <material type>=<material> but got '{}'", s))?;
+        Ok(Self {
+            target: target.trim().to_string(),
+            spec: spec.trim().parse()?,
+        })
+    }
+}
+
+/// Wraps an object and swaps the materials it reports at hit points.
+/// Earlier rules take precedence.
+#[derive(Debug)]
+pub struct MaterialOverride {
+    object: Arc<dyn Hittable>,
+    rules: Vec<(OverrideRule, Arc<dyn Material>)>,
+}
+
+impl MaterialOverride {
+    pub fn new(object: Arc<dyn Hittable>, rules: &[OverrideRule]) -> Self {
+        Self {
+            object,
+            rules: rules
+                .iter()
+                .map(|rule| (rule.clone(), rule.spec.build()))
+                .collect(),
+        }
+    }
+}
+
+impl MaterialOverride {
+    /// Replaces the material at `isect` by the first matching rule's.
+    fn apply(&self, isect: &mut Interaction) {
+        if let Some(material) = &isect.material
+            && let Some((_, replacement)) = self
+                .rules
+                .iter()
+                .find(|(rule, _)| rule.matches(&**material))
+        {
+            isect.material = Some(replacement.clone());
+        }
+    }
+}
+
+impl Hittable for MaterialOverride {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(r, ray_t)?;
+        self.apply(&mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(r, ray_t)?;
+        // Overrides may change what a shadow ray sees, e.g. a light turned into clay
+        self.apply(&mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.object.sample_surface(time)
+    }
+}
+
+/// Adjusts the materials of the wrapped object per instance: tints reflectance,
+/// scales roughness and emission, leaving the shared materials themselves alone.
+/// Nested adjustments multiply.
+#[derive(Debug)]
+pub struct ParameterOverride {
+    object: Arc<dyn Hittable>,
+    params: MaterialParams,
+}
+
+impl ParameterOverride {
+    pub fn new(object: Arc<dyn Hittable>, params: MaterialParams) -> Self {
+        Self { object, params }
+    }
+
+    fn apply(&self, isect: &mut Interaction) {
+        isect.material_params = isect.material_params.compose(&self.params);
+    }
+}
+
+impl Hittable for ParameterOverride {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(r, ray_t)?;
+        self.apply(&mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(r, ray_t)?;
+        // Shadow rays reaching a light must see its scaled emission
+        self.apply(&mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power() * self.params.emission_scale
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        self.apply(&mut isect);
+        Some((isect, pdf))
+    }
+}
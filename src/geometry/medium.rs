@@ -0,0 +1,233 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, SHUTTER, roulette_transmittance};
+use crate::materials::material_trait::Material;
+use crate::sampling::random::random_double;
+use std::sync::Arc;
+
+/// A participating medium that `OverlappingMedia` can combine with others.
+pub trait Medium: Hittable {
+    /// Parts of `ray_t` along `r` spent inside the medium, in order.
+    fn spans(&self, r: &Ray, ray_t: Interval) -> Vec<Interval>;
+
+    /// Extinction per unit length at `p`, a point inside one of the spans.
+    fn density(&self, p: &Point3) -> f64;
+
+    /// Upper bound of `density` anywhere in the medium.
+    fn majorant(&self) -> f64;
+
+    /// Whether `density` is the same everywhere inside the medium.
+    fn homogeneous(&self) -> bool {
+        false
+    }
+
+    fn phase_function(&self) -> Arc<dyn Material>;
+}
+
+/// How the densities of media present at the same point combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapRule {
+    /// Extinctions add up, as for independent particles sharing the space. This is
+    /// also what separate media in a `HittableList` do.
+    Additive,
+    /// Only the media of the highest priority present at a point count, so a volume
+    /// can replace, rather than thicken, the fog or water it sits in.
+    Priority,
+    /// The densest medium at a point wins, as when unioning simulation caches.
+    Maximum,
+}
+
+/// Several possibly overlapping media resolved by an `OverlapRule`.
+///
+/// Rays are split at every boundary crossing of the members into segments, each
+/// tracked against the sum of the majorants of the members covering it. Segments
+/// covered only by homogeneous media are sampled in closed form.
+#[derive(Debug)]
+pub struct OverlappingMedia {
+    media: Vec<(Arc<dyn Medium>, i32)>,
+    rule: OverlapRule,
+    bbox: Aabb,
+}
+
+impl OverlappingMedia {
+    pub fn new(rule: OverlapRule) -> Self {
+        Self {
+            media: Vec::new(),
+            rule,
+            bbox: Aabb::empty(),
+        }
+    }
+
+    /// Adds a medium; `priority` only matters for `OverlapRule::Priority`, where
+    /// higher values win and equal ones add up.
+    pub fn add(&mut self, medium: Arc<dyn Medium>, priority: i32) {
+        self.bbox = self.bbox.merge(&medium.bounding_box(SHUTTER));
+        self.media.push((medium, priority));
+    }
+
+    /// Segments of `ray_t` between consecutive boundary crossings, each with the
+    /// members whose spans cover it.
+    fn segments(&self, r: &Ray, ray_t: Interval) -> Vec<(Interval, Vec<usize>)> {
+        let spans: Vec<Vec<Interval>> = self
+            .media
+            .iter()
+            .map(|(medium, _)| medium.spans(r, ray_t))
+            .collect();
+
+        let mut cuts: Vec<f64> = spans
+            .iter()
+            .flatten()
+            .flat_map(|span| [span.min, span.max])
+            .collect();
+        cuts.sort_by(f64::total_cmp);
+        cuts.dedup();
+
+        cuts.windows(2)
+            .filter_map(|pair| {
+                let middle = 0.5 * (pair[0] + pair[1]);
+                let covering: Vec<usize> = (0..self.media.len())
+                    .filter(|&n| spans[n].iter().any(|span| span.contains(middle)))
+                    .collect();
+                (!covering.is_empty()).then(|| (Interval::new(pair[0], pair[1]), covering))
+            })
+            .collect()
+    }
+
+    /// Resolved extinction at `p` from the `covering` members, and the member whose
+    /// phase function scatters there, chosen in proportion to its share.
+    fn resolve(&self, p: &Point3, covering: &[usize]) -> (f64, Option<usize>) {
+        let densities: Vec<(usize, f64)> = covering
+            .iter()
+            .map(|&n| (n, self.media[n].0.density(p)))
+            .filter(|&(_, density)| density > 0.0)
+            .collect();
+
+        let counted: Vec<(usize, f64)> = match self.rule {
+            OverlapRule::Additive => densities,
+            OverlapRule::Priority => {
+                let top = densities.iter().map(|&(n, _)| self.media[n].1).max();
+                densities
+                    .into_iter()
+                    .filter(|&(n, _)| Some(self.media[n].1) == top)
+                    .collect()
+            }
+            OverlapRule::Maximum => densities
+                .into_iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .into_iter()
+                .collect(),
+        };
+
+        let total: f64 = counted.iter().map(|&(_, density)| density).sum();
+        let mut pick = random_double() * total;
+        let chosen = counted.iter().find_map(|&(n, density)| {
+            pick -= density;
+            (pick <= 0.0).then_some(n)
+        });
+        (total, chosen.or(counted.last().map(|&(n, _)| n)))
+    }
+
+    fn majorant(&self, covering: &[usize]) -> f64 {
+        covering.iter().map(|&n| self.media[n].0.majorant()).sum()
+    }
+
+    fn homogeneous(&self, covering: &[usize]) -> bool {
+        covering.iter().all(|&n| self.media[n].0.homogeneous())
+    }
+}
+
+impl Hittable for OverlappingMedia {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let ray_length = r.dir.norm();
+        let mut collision = None;
+
+        'segments: for (segment, covering) in self.segments(r, ray_t) {
+            if self.homogeneous(&covering) {
+                let (density, member) = self.resolve(&r.at(segment.min), &covering);
+                if density <= 0.0 {
+                    continue;
+                }
+                let distance = -random_double().max(f64::EPSILON).ln() / density;
+                let t = segment.min + distance / ray_length;
+                if t < segment.max {
+                    collision = member.map(|n| (t, n));
+                    break;
+                }
+                continue;
+            }
+
+            // Delta tracking against the members' combined majorant
+            let majorant = self.majorant(&covering);
+            if majorant <= 0.0 {
+                continue;
+            }
+            let mut t = segment.min;
+            loop {
+                let distance = -(1.0 - random_double()).ln() / majorant;
+                t += distance / ray_length;
+                if t >= segment.max {
+                    break;
+                }
+                let (density, member) = self.resolve(&r.at(t), &covering);
+                if random_double() * majorant < density {
+                    collision = member.map(|n| (t, n));
+                    break 'segments;
+                }
+            }
+        }
+
+        let (t, member) = collision?;
+        let phase_function = self.media[member].0.phase_function();
+        // Facing the ray, as Interaction::new sets it, is arbitrary too
+        let mut isect = Interaction::new(r.at(t), t, (0.0, 0.0), Some(phase_function));
+        isect.geometry_normal = Vec3::new(1.0, 0.0, 0.0); // Arbitrary
+        isect.medium = true;
+        Some(isect)
+    }
+
+    /// Media never block shadow rays outright; they attenuate them instead.
+    fn intersect_p(&self, _r: &Ray, _ray_t: Interval) -> Option<Interaction> {
+        None
+    }
+
+    /// Beer-Lambert attenuation through homogeneous segments and ratio tracking
+    /// through the others.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let ray_length = r.dir.norm();
+        let mut tr = Color::new(1.0, 1.0, 1.0);
+
+        for (segment, covering) in self.segments(r, ray_t) {
+            if self.homogeneous(&covering) {
+                let (density, _) = self.resolve(&r.at(segment.min), &covering);
+                tr *= (-density * segment.size() * ray_length).exp();
+                continue;
+            }
+
+            let majorant = self.majorant(&covering);
+            if majorant <= 0.0 {
+                continue;
+            }
+            let mut t = segment.min;
+            loop {
+                let distance = -(1.0 - random_double()).ln() / majorant;
+                t += distance / ray_length;
+                if t >= segment.max {
+                    break;
+                }
+                let (density, _) = self.resolve(&r.at(t), &covering);
+                tr = roulette_transmittance(tr * (1.0 - density / majorant).max(0.0));
+                if tr == Color::zeros() {
+                    return tr;
+                }
+            }
+        }
+        tr
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,94 @@
+use crate::core::aabb::Aabb;
+use crate::core::camera::Camera;
+use crate::core::interaction::{Interaction, ObjectTag};
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use std::sync::Arc;
+
+/// Gives the wrapped primitive or group a name and numeric ID, recorded in the
+/// `Interaction` of every hit so integrators and tools can tell objects apart.
+/// Nested names keep the innermost, so a named part inside a named group reports
+/// the part.
+#[derive(Debug)]
+pub struct Named {
+    object: Arc<dyn Hittable>,
+    tag: ObjectTag,
+}
+
+impl Named {
+    pub fn new(object: Arc<dyn Hittable>, id: u32, name: &str) -> Self {
+        Self {
+            object,
+            tag: ObjectTag {
+                id,
+                name: name.into(),
+            },
+        }
+    }
+
+    fn tag(&self, isect: &mut Interaction) {
+        if isect.object.is_none() {
+            isect.object = Some(self.tag.clone());
+        }
+    }
+}
+
+impl Hittable for Named {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(r, ray_t)?;
+        self.tag(&mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(r, ray_t)?;
+        self.tag(&mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn object_tag(&self) -> Option<&ObjectTag> {
+        Some(&self.tag)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        self.tag(&mut isect);
+        Some((isect, pdf))
+    }
+}
+
+/// The surface seen through the center of pixel (i, j), or None where the pixel
+/// shows the background. Its `object` holds the tag of the named object under the
+/// pixel, if any.
+pub fn pick(world: &dyn Hittable, camera: &Camera, i: u32, j: u32) -> Option<Interaction> {
+    world.hit(
+        &camera.center_ray(i, j),
+        Interval::new(0.001, f64::INFINITY),
+    )
+}
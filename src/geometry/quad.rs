@@ -2,13 +2,75 @@ use crate::core::aabb::Aabb;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
-use crate::geometry::hittable::Hittable;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, shutter_fraction};
 use crate::geometry::hittable_list::HittableList;
 use crate::materials::material_trait::Material;
 use crate::sampling::random::random_double;
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+/// Region of the parallelogram spanned by `u` and `v` that a `Quad` covers, in
+/// the plane coordinates (a, b) in [0, 1]^2 that are also its UVs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuadShape {
+    /// The whole parallelogram.
+    Parallelogram,
+    /// The half on the side of `q`: a triangle with corners q, q + u and q + v.
+    Triangle,
+    /// The ellipse inscribed in the parallelogram, a disk if `u` and `v` are
+    /// perpendicular and of equal length.
+    Ellipse,
+    /// The inscribed ellipse without a concentric hole, `inner` times its size.
+    Annulus { inner: f64 },
+}
+
+impl QuadShape {
+    /// Whether the point at plane coordinates (a, b) is inside the shape.
+    fn contains(&self, a: f64, b: f64) -> bool {
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return false;
+        }
+        // Squared distance from the center, 1 on the inscribed ellipse
+        let r2 = || (2.0 * a - 1.0).powi(2) + (2.0 * b - 1.0).powi(2);
+        match *self {
+            Self::Parallelogram => true,
+            Self::Triangle => a + b <= 1.0,
+            Self::Ellipse => r2() <= 1.0,
+            Self::Annulus { inner } => (inner * inner..=1.0).contains(&r2()),
+        }
+    }
+
+    /// Fraction of the parallelogram's area the shape covers.
+    fn coverage(&self) -> f64 {
+        match *self {
+            Self::Parallelogram => 1.0,
+            Self::Triangle => 0.5,
+            Self::Ellipse => PI / 4.0,
+            Self::Annulus { inner } => PI / 4.0 * (1.0 - inner * inner),
+        }
+    }
+
+    /// Uniformly distributed plane coordinates inside the shape.
+    fn sample(&self) -> (f64, f64) {
+        let (a, b) = (random_double(), random_double());
+        let on_ring = |inner: f64| {
+            // Radius by the inverse of the area within it, so area is uniform
+            let r = (inner * inner + a * (1.0 - inner * inner)).sqrt();
+            let phi = 2.0 * PI * b;
+            (0.5 + 0.5 * r * phi.cos(), 0.5 + 0.5 * r * phi.sin())
+        };
+        match *self {
+            Self::Parallelogram => (a, b),
+            // Points past the diagonal fold back onto the triangle
+            Self::Triangle if a + b > 1.0 => (1.0 - a, 1.0 - b),
+            Self::Triangle => (a, b),
+            Self::Ellipse => on_ring(0.0),
+            Self::Annulus { inner } => on_ring(inner),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Quad {
     q: Point3,
@@ -20,6 +82,8 @@ pub struct Quad {
     d: f64,
     w: Vec3,
     area: f64,
+    motion: Vec3, // Offset at time 1, for motion blur
+    shape: QuadShape,
 }
 
 impl Quad {
@@ -44,64 +108,117 @@ impl Quad {
             d,
             w,
             area,
+            motion: Vec3::zeros(),
+            shape: QuadShape::Parallelogram,
         }
     }
 
-    fn is_interior(&self, a: f64, b: f64, isect: &mut Interaction) -> bool {
-        // Given the hit point in plane coordinates, return false if it is outside the
-        // primitive. Otherwise set the hit record UV coordinates and return true.
-        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
-            return false;
+    /// Covers only `shape` of the parallelogram, e.g. for round or triangular lights
+    /// and decals. Sampling and area follow the shape.
+    pub fn with_shape(mut self, shape: QuadShape) -> Self {
+        let shape = match shape {
+            QuadShape::Annulus { inner } if !(0.0..1.0).contains(&inner) => {
+                eprintln!(
+                    "Warning: annulus inner size {} is outside [0, 1), using a full ellipse",
+                    inner
+                );
+                QuadShape::Ellipse
+            }
+            shape => shape,
+        };
+        let parallelogram = self.u.cross(&self.v).norm();
+        self.area = parallelogram * shape.coverage();
+        if shape == QuadShape::Triangle {
+            let (q, u, v) = (self.q, self.u, self.v);
+            self.bbox = Aabb::new_point(q, q + u).merge(&Aabb::new_point(q, q + v));
         }
+        self.shape = shape;
+        self
+    }
+
+    /// Moves the quad by `offset` in a straight line over the shutter, from where
+    /// it was built when the shutter opens, as `Sphere::new_moving` does its center.
+    pub fn with_motion(mut self, offset: Vec3) -> Self {
+        self.motion = offset;
+        self
+    }
+
+    fn offset(&self, time: f64) -> Vec3 {
+        self.motion * shutter_fraction(time)
+    }
 
-        isect.uv = (a, b);
-        true
+    /// Whether the face turned toward `origin` emits, so that light sampling skips
+    /// quads seen from their dark side.
+    fn lit_from(&self, origin: &Point3, time: f64) -> bool {
+        let in_front = self.normal.dot(&(origin - (self.q + self.offset(time)))) > 0.0;
+        self.material.emission_side().emits(in_front)
+    }
+
+    fn is_interior(&self, a: f64, b: f64) -> bool {
+        // Given the hit point in plane coordinates, return false if it is outside the
+        // primitive. The coordinates are then the hit's UVs.
+        self.shape.contains(a, b)
     }
 }
 
 impl Hittable for Quad {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
         let denom = self.normal.dot(&r.dir);
 
-        // Ray parallel to the plane
-        if denom.abs() < 1e-8 {
-            return false;
+        // Ray parallel to the plane, or arriving from behind a culled back face
+        if denom.abs() < 1e-8 || (denom > 0.0 && self.material.cull_backfaces()) {
+            return None;
         }
 
-        let t = (self.d - self.normal.dot(&r.orig.coords)) / denom;
+        // Where the quad is at the ray's time
+        let offset = self.offset(r.time);
+        let t = (self.d + self.normal.dot(&offset) - self.normal.dot(&r.orig.coords)) / denom;
         if !ray_t.contains(t) {
-            return false;
+            return None;
         }
 
         let intersection = r.at(t);
-        let planar_hitpt_vector = intersection - self.q;
+        let planar_hitpt_vector = intersection - (self.q + offset);
         let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
         let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
 
-        if !self.is_interior(alpha, beta, isect) {
-            return false;
+        if !self.is_interior(alpha, beta) {
+            return None;
         }
 
         // Hit confirmed
-        isect.t = t;
-        isect.p = intersection;
-        isect.material = Some(self.material.clone());
+        let mut isect =
+            Interaction::new(intersection, t, (alpha, beta), Some(self.material.clone()));
         isect.set_face_normal(r, self.normal);
+        isect.set_differentials(r, &self.u, &self.v);
+
+        Some(isect)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        if self.motion == Vec3::zeros() {
+            return self.bbox;
+        }
+        // The quad moves linearly, so the boxes at both ends bound the sweep
+        (self.bbox + self.offset(time.min)).merge(&(self.bbox + self.offset(time.max)))
+    }
 
-        true
+    fn area(&self) -> f64 {
+        self.area
     }
 
-    fn bounding_box(&self) -> Aabb {
-        self.bbox
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        let mut rec = Interaction::default();
-        if !self.hit(
-            &Ray::new(*origin, *direction, 0.0),
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let Some(rec) = self.hit(
+            &Ray::new(*origin, *direction, time),
             Interval::new(0.001, f64::INFINITY),
-            &mut rec,
-        ) {
+        ) else {
+            return 0.0;
+        };
+        if !self.material.emission_side().emits(rec.front_face) {
             return 0.0;
         }
 
@@ -115,11 +232,28 @@ impl Hittable for Quad {
         }
     }
 
-    fn random(&self, origin: &Point3) -> Vec3 {
-        let p = self.q + (random_double() * self.u) + (random_double() * self.v);
+    /// Toward a uniformly sampled point, or zero, for no sample, from the side
+    /// that does not emit.
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if !self.lit_from(origin, time) {
+            return Vec3::zeros();
+        }
+        let (a, b) = self.shape.sample();
+        let p = self.q + self.offset(time) + (a * self.u) + (b * self.v);
         // Normalize the return vector to ensure consistency with PDF expectations
         (p - *origin).normalize()
     }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (a, b) = self.shape.sample();
+        let p = self.q + self.offset(time) + (a * self.u) + (b * self.v);
+
+        let mut isect = Interaction::new(p, 0.0, (a, b), Some(self.material.clone()));
+        isect.geometry_normal = self.normal;
+        isect.set_shading_normal(self.normal);
+
+        Some((isect, 1.0 / self.area))
+    }
 }
 
 /// Helper to create a box (6 quads)
@@ -172,3 +306,39 @@ pub fn box_new(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
 
     sides
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::vec3::Vec3Ext;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::material_trait::EmissionSide;
+    use crate::textures::solid_color::SolidColor;
+
+    #[test]
+    fn one_sided_quads_are_sampled_only_from_their_lit_side() {
+        let (front, back) = (Point3::new(0.5, 0.5, 2.0), Point3::new(0.5, 0.5, -2.0));
+        let cases = [
+            (EmissionSide::Front, true, false),
+            (EmissionSide::Back, false, true),
+            (EmissionSide::Both, true, true),
+        ];
+        for (side, from_front, from_back) in cases {
+            let light = DiffuseLight::new(Arc::new(SolidColor::new(Color::new(1.0, 1.0, 1.0))));
+            // Facing +z
+            let quad = Quad::new(
+                Point3::origin(),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Arc::new(light.with_side(side)),
+            );
+            for (origin, lit) in [(front, from_front), (back, from_back)] {
+                let direction = quad.random(&origin, 0.0);
+                assert_eq!(!direction.near_zero(), lit, "{side:?} from {origin}");
+                let toward = Point3::new(0.5, 0.5, 0.0) - origin;
+                let pdf = quad.pdf_value(&origin, &toward, 0.0);
+                assert_eq!(pdf > 0.0, lit, "{side:?} from {origin}: pdf {pdf}");
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::core::transform::Transform;
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::hittable::Hittable;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::named::Named;
+use crate::geometry::transforms::animated::{AnimatedTransform, Keyframe};
+use crate::geometry::transforms::instance::Instance;
+use std::sync::Arc;
+
+/// A named group of objects and child nodes placed relative to its parent, so an
+/// assembly such as a lamp (stand, bulb, shade) is moved, rotated or animated as
+/// one unit by the transform of its node. Nodes are addressed by their path of
+/// names from the root, e.g. `lamp/shade`.
+///
+/// The graph only exists while a scene is built: `flatten` composes the transforms
+/// down the tree and places each node's objects once, as an `Instance` of a BVH over
+/// them. A node moving over the shutter carries its whole subtree along in an
+/// `AnimatedTransform`, inside which its children are flattened the same way.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    name: String,
+    start: Keyframe,
+    end: Keyframe,
+    objects: Vec<Arc<dyn Hittable>>,
+    lights: Vec<Arc<dyn Hittable>>,
+    children: Vec<SceneNode>,
+}
+
+/// The placed objects of a flattened graph.
+#[derive(Debug, Default)]
+pub struct FlatScene {
+    pub world: HittableList,
+    /// Placed emitters, also part of `world`, each on its own for light sampling.
+    pub lights: Vec<Arc<dyn Hittable>>,
+}
+
+impl SceneNode {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            start: Keyframe::default(),
+            end: Keyframe::default(),
+            objects: Vec::new(),
+            lights: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Places the node relative to its parent.
+    pub fn with_transform(mut self, keyframe: Keyframe) -> Self {
+        self.set_transform(keyframe);
+        self
+    }
+
+    /// Moves the node relative to its parent between two keyframes over the shutter.
+    pub fn with_motion(mut self, start: Keyframe, end: Keyframe) -> Self {
+        self.set_motion(start, end);
+        self
+    }
+
+    /// Adds an object, in the node's space.
+    pub fn with_object(mut self, object: Arc<dyn Hittable>) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    /// Adds an emitter, in the node's space, which is also reported among the
+    /// flattened lights.
+    pub fn with_light(mut self, light: Arc<dyn Hittable>) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    pub fn with_child(mut self, child: SceneNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn set_transform(&mut self, keyframe: Keyframe) {
+        self.set_motion(keyframe, keyframe);
+    }
+
+    pub fn set_motion(&mut self, start: Keyframe, end: Keyframe) {
+        self.start = start;
+        self.end = end;
+    }
+
+    /// The descendant at `path`, names separated by `/`, relative to this node.
+    pub fn find(&self, path: &str) -> Option<&SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter().find(|child| child.name == name)
+            })
+    }
+
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter_mut().find(|child| child.name == name)
+            })
+    }
+
+    fn is_moving(&self) -> bool {
+        self.start != self.end
+    }
+
+    /// Places every object of the graph in world space. Each node's placement is
+    /// named after its path and numbered in depth-first order from 1, for object
+    /// AOVs and picking.
+    pub fn flatten(&self) -> FlatScene {
+        let mut flat = FlatScene::default();
+        let mut id = 0;
+        self.flatten_into(&Transform::identity(), "", &mut id, &mut flat);
+        flat
+    }
+
+    fn flatten_into(
+        &self,
+        parent: &Transform,
+        parent_path: &str,
+        id: &mut u32,
+        flat: &mut FlatScene,
+    ) {
+        let path = if parent_path.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}/{}", parent_path, self.name)
+        };
+        if !self.is_moving() {
+            let to_world = self.start.transform().then(parent);
+            self.flatten_contents(&to_world, &path, id, flat);
+            return;
+        }
+
+        // The subtree is flattened in the node's own space and moved as a whole,
+        // then placed by the static transform above it
+        let mut local = FlatScene::default();
+        self.flatten_contents(&Transform::identity(), &path, id, &mut local);
+        let moving = |objects: Vec<Arc<dyn Hittable>>| {
+            let group = place(objects, &Transform::identity());
+            place(
+                vec![Arc::new(AnimatedTransform::new(
+                    group, self.start, self.end,
+                ))],
+                parent,
+            )
+        };
+        for light in local.lights {
+            flat.lights.push(moving(vec![light]));
+        }
+        if !local.world.objects.is_empty() {
+            flat.world.add(moving(local.world.objects));
+        }
+    }
+
+    fn flatten_contents(
+        &self,
+        to_world: &Transform,
+        path: &str,
+        id: &mut u32,
+        flat: &mut FlatScene,
+    ) {
+        *id += 1;
+        if !self.objects.is_empty() {
+            let group = place(self.objects.clone(), to_world);
+            flat.world.add(Arc::new(Named::new(group, *id, path)));
+        }
+        for light in &self.lights {
+            let light: Arc<dyn Hittable> =
+                Arc::new(Named::new(place(vec![light.clone()], to_world), *id, path));
+            flat.world.add(light.clone());
+            flat.lights.push(light);
+        }
+        for child in &self.children {
+            child.flatten_into(to_world, path, id, flat);
+        }
+    }
+}
+
+/// `objects` under `to_world` as one group: a lone object stays as it is under the
+/// identity, and anything else is put in a BVH, instanced unless the identity.
+fn place(mut objects: Vec<Arc<dyn Hittable>>, to_world: &Transform) -> Arc<dyn Hittable> {
+    let identity = *to_world == Transform::identity();
+    if identity && objects.len() == 1 {
+        return objects.remove(0);
+    }
+    let bvh = Arc::new(BvhNode::new_from_objects(objects));
+    if identity {
+        bvh
+    } else {
+        Arc::new(Instance::new(bvh, *to_world))
+    }
+}
@@ -0,0 +1,363 @@
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::triangle_mesh::{TriangleMesh, face_normal};
+use nalgebra::Matrix3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Weight of the planes that hold open boundaries in place, relative to those of
+/// faces, so holes and UV seams keep their outline.
+const BOUNDARY_WEIGHT: f64 = 100.0;
+/// Determinant below which a quadric is treated as singular, its minimum being a
+/// line or plane rather than a point.
+const SINGULAR_DETERMINANT: f64 = 1e-12;
+
+/// Sum of squared distances to a set of planes, as x^T A x + 2 b^T x + c.
+#[derive(Debug, Clone, Copy)]
+struct Quadric {
+    a: Matrix3<f64>,
+    b: Vec3,
+    c: f64,
+}
+
+impl Quadric {
+    fn zero() -> Self {
+        Self {
+            a: Matrix3::zeros(),
+            b: Vec3::zeros(),
+            c: 0.0,
+        }
+    }
+
+    /// Squared distance to the plane through `p` with unit normal `n`, times `weight`.
+    fn plane(n: &Vec3, p: &Point3, weight: f64) -> Self {
+        let d = -n.dot(&p.coords);
+        Self {
+            a: weight * n * n.transpose(),
+            b: weight * d * n,
+            c: weight * d * d,
+        }
+    }
+
+    fn add(&self, other: &Quadric) -> Self {
+        Self {
+            a: self.a + other.a,
+            b: self.b + other.b,
+            c: self.c + other.c,
+        }
+    }
+
+    fn error(&self, p: &Point3) -> f64 {
+        let x = p.coords;
+        x.dot(&(self.a * x)) + 2.0 * self.b.dot(&x) + self.c
+    }
+
+    /// The point of least error, if unique.
+    fn minimizer(&self) -> Option<Point3> {
+        if self.a.determinant().abs() < SINGULAR_DETERMINANT {
+            return None;
+        }
+        self.a
+            .try_inverse()
+            .map(|inverse| Point3::from(-(inverse * self.b)))
+    }
+}
+
+/// Collapse of the edge between two vertices into `target`, valid while neither
+/// vertex has changed since it was evaluated.
+struct Collapse {
+    cost: f64,
+    vertices: [u32; 2],
+    versions: [u32; 2],
+    target: Point3,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost.total_cmp(&other.cost) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Reversed, so the heap yields the cheapest collapse first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Simplifies `mesh` to at most `target_faces` faces by quadric error metrics
+/// (Garland and Heckbert): edges are collapsed cheapest first into the point
+/// closest to the planes of the faces they gathered, so flat regions thin out
+/// while creases and silhouettes keep their shape. Collapses that would fold a
+/// face over are skipped, so the result may keep more faces than asked for.
+/// Normals, UV sets and colors are interpolated along each collapsed edge.
+pub fn simplify(mesh: &TriangleMesh, target_faces: usize) -> Result<TriangleMesh, String> {
+    let mut positions = mesh.positions().to_vec();
+    let mut normals = mesh.normals().map(<[Vec3]>::to_vec);
+    let mut uvs = mesh.uvs().map(<[(f64, f64)]>::to_vec);
+    let mut uv_sets = mesh.uv_sets().to_vec();
+    let mut colors = mesh.colors().map(<[Color]>::to_vec);
+    let mut faces = mesh.indices().to_vec();
+
+    let mut alive = vec![true; faces.len()];
+    let mut live_faces = faces.len();
+    let mut removed = vec![false; positions.len()];
+    let mut versions = vec![0u32; positions.len()];
+    let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+    let mut quadrics = vec![Quadric::zero(); positions.len()];
+
+    // Each vertex starts with the planes of the faces around it
+    let mut edge_faces: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        // Degenerate faces add no plane but can still collapse away
+        let plane = face_normal(&positions, face)
+            .try_normalize(0.0)
+            .map_or(Quadric::zero(), |n| {
+                Quadric::plane(&n, &positions[face[0] as usize], 1.0)
+            });
+        for e in 0..3 {
+            let v = face[e] as usize;
+            quadrics[v] = quadrics[v].add(&plane);
+            vertex_faces[v].push(f);
+            edge_faces
+                .entry(edge_key(face[e], face[(e + 1) % 3]))
+                .or_default()
+                .push(f);
+        }
+    }
+
+    // Boundary edges get a plane through them, perpendicular to their face
+    for (&(a, b), adjacent) in &edge_faces {
+        if let [f] = adjacent[..]
+            && let Some(n) = face_normal(&positions, &faces[f]).try_normalize(0.0)
+            && let Some(m) = (positions[b as usize] - positions[a as usize])
+                .cross(&n)
+                .try_normalize(0.0)
+        {
+            let plane = Quadric::plane(&m, &positions[a as usize], BOUNDARY_WEIGHT);
+            quadrics[a as usize] = quadrics[a as usize].add(&plane);
+            quadrics[b as usize] = quadrics[b as usize].add(&plane);
+        }
+    }
+
+    let evaluate =
+        |positions: &[Point3], quadrics: &[Quadric], versions: &[u32], a: u32, b: u32| {
+            let (pa, pb) = (positions[a as usize], positions[b as usize]);
+            let q = quadrics[a as usize].add(&quadrics[b as usize]);
+            let midpoint = Point3::from(0.5 * (pa.coords + pb.coords));
+
+            // Nearly singular quadrics can put their minimum far off; stay near the edge
+            let reach = (pb - pa).norm();
+            let optimum = q.minimizer().filter(|p| (p - midpoint).norm() <= reach);
+            let (cost, target) = optimum
+                .into_iter()
+                .chain([midpoint, pa, pb])
+                .map(|p| (q.error(&p), p))
+                .min_by(|x, y| x.0.total_cmp(&y.0))
+                .unwrap_or((0.0, midpoint));
+            Collapse {
+                cost,
+                vertices: [a, b],
+                versions: [versions[a as usize], versions[b as usize]],
+                target,
+            }
+        };
+
+    let mut heap: BinaryHeap<Collapse> = edge_faces
+        .keys()
+        .map(|&(a, b)| evaluate(&positions, &quadrics, &versions, a, b))
+        .collect();
+
+    while live_faces > target_faces {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+        let [a, b] = collapse.vertices;
+        let (ai, bi) = (a as usize, b as usize);
+        if removed[ai] || removed[bi] || collapse.versions != [versions[ai], versions[bi]] {
+            continue;
+        }
+        if !keeps_manifold(&faces, &alive, &vertex_faces[ai], &vertex_faces[bi], a, b)
+            || folds(
+                &positions,
+                &faces,
+                &alive,
+                &vertex_faces[ai],
+                a,
+                b,
+                &collapse.target,
+            )
+            || folds(
+                &positions,
+                &faces,
+                &alive,
+                &vertex_faces[bi],
+                b,
+                a,
+                &collapse.target,
+            )
+        {
+            continue;
+        }
+
+        // Keep a, moved to the target, with attributes from where it falls on the edge
+        let edge = positions[bi] - positions[ai];
+        let t = if edge.norm_squared() > 0.0 {
+            ((collapse.target - positions[ai]).dot(&edge) / edge.norm_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        positions[ai] = collapse.target;
+        if let Some(normals) = &mut normals {
+            let n = (1.0 - t) * normals[ai] + t * normals[bi];
+            normals[ai] = n.try_normalize(0.0).unwrap_or(normals[ai]);
+        }
+        for uvs in uvs.iter_mut().chain(&mut uv_sets) {
+            let (ua, ub) = (uvs[ai], uvs[bi]);
+            uvs[ai] = ((1.0 - t) * ua.0 + t * ub.0, (1.0 - t) * ua.1 + t * ub.1);
+        }
+        if let Some(colors) = &mut colors {
+            colors[ai] = (1.0 - t) * colors[ai] + t * colors[bi];
+        }
+        quadrics[ai] = quadrics[ai].add(&quadrics[bi]);
+        removed[bi] = true;
+        versions[ai] += 1;
+
+        // Faces on the edge vanish, the others of b now use a
+        for f in std::mem::take(&mut vertex_faces[bi]) {
+            if !alive[f] {
+                continue;
+            }
+            if faces[f].contains(&a) {
+                alive[f] = false;
+                live_faces -= 1;
+            } else {
+                for index in &mut faces[f] {
+                    if *index == b {
+                        *index = a;
+                    }
+                }
+                vertex_faces[ai].push(f);
+            }
+        }
+        vertex_faces[ai].retain(|&f| alive[f]);
+
+        let neighbors: HashSet<u32> = vertex_faces[ai]
+            .iter()
+            .flat_map(|&f| faces[f])
+            .filter(|&v| v != a)
+            .collect();
+        for n in neighbors {
+            heap.push(evaluate(&positions, &quadrics, &versions, a, n));
+        }
+    }
+
+    // Keep the vertices still in use, renumbered in order
+    let mut remap = vec![u32::MAX; positions.len()];
+    let mut kept = Vec::new();
+    let indices: Vec<[u32; 3]> = faces
+        .iter()
+        .zip(&alive)
+        .filter(|(_, alive)| **alive)
+        .map(|(face, _)| {
+            face.map(|v| {
+                if remap[v as usize] == u32::MAX {
+                    remap[v as usize] = kept.len() as u32;
+                    kept.push(v as usize);
+                }
+                remap[v as usize]
+            })
+        })
+        .collect();
+
+    let mut simplified = TriangleMesh::new(
+        kept.iter().map(|&v| positions[v]).collect(),
+        indices,
+        mesh.material().clone(),
+    )?;
+    if let Some(normals) = normals {
+        simplified = simplified.with_normals(kept.iter().map(|&v| normals[v]).collect());
+    }
+    if let Some(uvs) = uvs {
+        simplified = simplified.with_uvs(kept.iter().map(|&v| uvs[v]).collect());
+    }
+    for uvs in uv_sets {
+        simplified = simplified.with_uv_set(kept.iter().map(|&v| uvs[v]).collect());
+    }
+    if let Some(colors) = colors {
+        simplified = simplified.with_colors(kept.iter().map(|&v| colors[v]).collect());
+    }
+    Ok(simplified)
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Whether moving `vertex` to `target` turns any of its faces, other than those
+/// shared with `other`, over or to zero area.
+fn folds(
+    positions: &[Point3],
+    faces: &[[u32; 3]],
+    alive: &[bool],
+    around: &[usize],
+    vertex: u32,
+    other: u32,
+    target: &Point3,
+) -> bool {
+    around.iter().any(|&f| {
+        if !alive[f] || faces[f].contains(&other) {
+            return false;
+        }
+        let before = face_normal(positions, &faces[f]);
+        if before == Vec3::zeros() {
+            return false;
+        }
+        let [a, b, c] = faces[f].map(|v| {
+            if v == vertex {
+                *target
+            } else {
+                positions[v as usize]
+            }
+        });
+        let after = (b - a).cross(&(c - a));
+        after.dot(&before) <= 0.0
+    })
+}
+
+/// Whether collapsing the edge from `a` to `b` keeps the surface manifold: the two
+/// may share no neighbors but the far corners of the faces on the edge, or else the
+/// collapse would pinch the surface or fold it onto itself.
+fn keeps_manifold(
+    faces: &[[u32; 3]],
+    alive: &[bool],
+    around_a: &[usize],
+    around_b: &[usize],
+    a: u32,
+    b: u32,
+) -> bool {
+    let neighbors = |around: &[usize], v: u32| -> HashSet<u32> {
+        around
+            .iter()
+            .filter(|&&f| alive[f])
+            .flat_map(|&f| faces[f])
+            .filter(|&n| n != v)
+            .collect()
+    };
+    let corners: HashSet<u32> = around_a
+        .iter()
+        .filter(|&&f| alive[f] && faces[f].contains(&b))
+        .flat_map(|&f| faces[f])
+        .filter(|&n| n != a && n != b)
+        .collect();
+    neighbors(around_a, a)
+        .intersection(&neighbors(around_b, b))
+        .all(|n| corners.contains(n))
+}
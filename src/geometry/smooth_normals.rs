@@ -0,0 +1,73 @@
+use crate::core::vec3::Vec3;
+use crate::geometry::triangle_mesh::{TriangleMesh, face_normal};
+use crate::sampling::random::degrees_to_radians;
+use std::collections::HashMap;
+
+/// Generates vertex normals for `mesh`, smooth across edges where faces meet at
+/// less than `crease_angle` degrees and sharp where they meet at more: each corner
+/// of a face averages the normals of the faces around its vertex that are within
+/// the angle of its own, weighted by area. Vertices whose corners end up with
+/// different normals are split, copying their UV sets and colors, so hard edges
+/// stay hard. An angle of 0 keeps every face flat and 180 smooths everything.
+/// Normals the mesh already has are replaced.
+pub fn smooth_normals(mesh: &TriangleMesh, crease_angle: f64) -> Result<TriangleMesh, String> {
+    let positions = mesh.positions();
+    let faces = mesh.indices();
+    let cos_crease = degrees_to_radians(crease_angle.clamp(0.0, 180.0)).cos();
+
+    let weighted: Vec<Vec3> = faces.iter().map(|f| face_normal(positions, f)).collect();
+    let unit: Vec<Vec3> = weighted
+        .iter()
+        .map(|n| n.try_normalize(0.0).unwrap_or(Vec3::zeros()))
+        .collect();
+    let mut around = vec![Vec::new(); positions.len()];
+    for (f, face) in faces.iter().enumerate() {
+        for &v in face {
+            around[v as usize].push(f);
+        }
+    }
+
+    // Corners of a vertex in the same smoothing group sum the same faces in the
+    // same order, so their normals match bit for bit and share one new vertex
+    let mut split: HashMap<(u32, [u64; 3]), u32> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut normals = Vec::new();
+    let indices = faces
+        .iter()
+        .enumerate()
+        .map(|(f, face)| {
+            face.map(|v| {
+                let n = around[v as usize]
+                    .iter()
+                    .filter(|&&g| g == f || unit[f].dot(&unit[g]) >= cos_crease)
+                    .fold(Vec3::zeros(), |sum, &g| sum + weighted[g])
+                    .try_normalize(0.0)
+                    .unwrap_or(unit[f]);
+                *split
+                    .entry((v, n.map(f64::to_bits).into()))
+                    .or_insert_with(|| {
+                        kept.push(v as usize);
+                        normals.push(n);
+                        (kept.len() - 1) as u32
+                    })
+            })
+        })
+        .collect();
+
+    let mut smoothed = TriangleMesh::new(
+        kept.iter().map(|&v| positions[v]).collect(),
+        indices,
+        mesh.material().clone(),
+    )?
+    .with_normals(normals);
+    if let Some(uvs) = mesh.uvs() {
+        smoothed = smoothed.with_uvs(kept.iter().map(|&v| uvs[v]).collect());
+    }
+    for uvs in mesh.uv_sets() {
+        smoothed = smoothed.with_uv_set(kept.iter().map(|&v| uvs[v]).collect());
+    }
+    if let Some(colors) = mesh.colors() {
+        smoothed = smoothed.with_colors(kept.iter().map(|&v| colors[v]).collect());
+    }
+    Ok(smoothed)
+}
@@ -6,8 +6,8 @@ use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::onb::ONB;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3, Vec3Ext};
-use crate::geometry::hittable::Hittable;
+use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
+use crate::geometry::hittable::{Hittable, shutter_fraction};
 use crate::materials::material_trait::Material;
 
 #[derive(Debug)]
@@ -30,6 +30,9 @@ impl Sphere {
         }
     }
 
+    /// Sphere whose center moves in a straight line from `center1` when the
+    /// shutter opens to `center2` when it closes. Every method places it at the
+    /// time of the ray or sample at hand.
     pub fn new_moving(
         center1: Point3,
         center2: Point3,
@@ -47,7 +50,7 @@ impl Sphere {
 
     fn center(&self, time: f64) -> Point3 {
         if self.is_moving {
-            self.center + self.center_vec * time
+            self.center + self.center_vec * shutter_fraction(time)
         } else {
             self.center
         }
@@ -58,10 +61,23 @@ impl Sphere {
         let phi = (-p.z).atan2(p.x) + PI;
         (phi / (2.0 * PI), theta / PI)
     }
+
+    /// Partial derivatives of the surface point by u and v where the outward unit
+    /// normal is `n`, zero at the poles where v alone does not determine them.
+    fn partials(&self, n: &Vec3) -> (Vec3, Vec3) {
+        let sin_theta = (n.x * n.x + n.z * n.z).sqrt();
+        if sin_theta < 1e-9 {
+            return (Vec3::zeros(), Vec3::zeros());
+        }
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(n.z, 0.0, -n.x);
+        let dpdv =
+            PI * self.radius * Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta);
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
         let center = self.center(r.time); // Use time-varying center
         let oc = r.orig - center;
         let a = r.dir.norm_squared();
@@ -70,7 +86,7 @@ impl Hittable for Sphere {
         let discriminant = half_b * half_b - a * c;
 
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
 
         let sqrtd = discriminant.sqrt();
@@ -78,7 +94,7 @@ impl Hittable for Sphere {
         if !ray_t.surrounds(root) {
             root = (-half_b + sqrtd) / a;
             if !ray_t.surrounds(root) {
-                return false;
+                return None;
             }
         }
 
@@ -87,47 +103,148 @@ impl Hittable for Sphere {
         let outward_normal = (p - center) / self.radius;
         let (u, v) = Self::get_sphere_uv(&Point3::from(outward_normal));
 
-        *isect = Interaction::new(p, t, (u, v), Some(self.material.clone()));
+        let mut isect = Interaction::new(p, t, (u, v), Some(self.material.clone()));
         isect.set_face_normal(r, outward_normal);
+        let (dpdu, dpdv) = self.partials(&outward_normal);
+        isect.set_differentials(r, &dpdu, &dpdv);
 
-        true
+        Some(isect)
     }
 
-    fn bounding_box(&self) -> Aabb {
+    fn bounding_box(&self, time: Interval) -> Aabb {
         let rvec = Vec3::new(self.radius, self.radius, self.radius);
-        let box1 = Aabb::new_point(self.center - rvec, self.center + rvec);
+        let start = self.center(time.min);
+        let box1 = Aabb::new_point(start - rvec, start + rvec);
 
         if self.is_moving {
-            let center2 = self.center + self.center_vec;
-            let box2 = Aabb::new_point(center2 - rvec, center2 + rvec);
+            // The center moves linearly, so the boxes at both ends bound the sweep
+            let end = self.center(time.max);
+            let box2 = Aabb::new_point(end - rvec, end + rvec);
             box1.merge(&box2)
         } else {
             box1
         }
     }
 
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
+    }
+
     // Usually for lights
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        // Importance sampling currently assumes static sphere for simplicity
-        // or effectively samples at time=0. A full implementation would sample time.
-        let mut dummy = Interaction::default();
-        let test_ray = Ray::new(*origin, *direction, 0.0);
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        // Sample the sphere where it is at the shading point's time
+        let test_ray = Ray::new(*origin, *direction, time);
 
-        if !self.hit(&test_ray, Interval::new(0.001, f64::INFINITY), &mut dummy) {
+        let Some(rec) = self.hit(&test_ray, Interval::new(0.001, f64::INFINITY)) else {
             return 0.0;
+        };
+        // The outside faces the front, the inside the back
+        if !self.material.emission_side().emits(rec.front_face) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center(time) - origin).norm_squared();
+        if distance_squared <= self.radius * self.radius {
+            // Inside, as for dome lights: the sphere surrounds every direction, sampled
+            // uniformly
+            return 1.0 / (4.0 * PI);
         }
 
-        let cos_theta_max =
-            (1.0 - self.radius.powi(2) / (self.center - origin).norm_squared()).sqrt();
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared).sqrt();
         let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
 
         1.0 / solid_angle
     }
 
-    fn random(&self, origin: &Point3) -> Vec3 {
-        let direction = self.center - origin;
+    /// Uniform over the cone of directions subtended by the sphere, or over all
+    /// directions from inside it. Zero, for no sample, if the face seen from
+    /// `origin` does not emit.
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let direction = self.center(time) - origin;
         let dist_sq = direction.norm_squared();
+        let inside = dist_sq <= self.radius * self.radius;
+        if !self.material.emission_side().emits(!inside) {
+            return Vec3::zeros();
+        }
+        if inside {
+            return Vec3::random_unit_vector();
+        }
         let uvw = ONB::build_from_w(&direction);
         uvw.local(&Vec3::random_to_sphere(self.radius, dist_sq))
     }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let outward_normal = Vec3::random_unit_vector();
+        let p = self.center(time) + self.radius * outward_normal;
+        let uv = Self::get_sphere_uv(&Point3::from(outward_normal));
+
+        let mut isect = Interaction::new(p, 0.0, uv, Some(self.material.clone()));
+        isect.geometry_normal = outward_normal;
+        isect.set_shading_normal(outward_normal);
+
+        Some((isect, 1.0 / self.area()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::material_trait::EmissionSide;
+    use crate::textures::solid_color::SolidColor;
+
+    #[test]
+    fn light_samples_follow_fast_moving_sphere() {
+        let (start, end, radius) = (
+            Point3::new(-20.0, 0.0, -5.0),
+            Point3::new(20.0, 3.0, -5.0),
+            0.5,
+        );
+        let light = DiffuseLight::new(Arc::new(SolidColor::new(Color::new(4.0, 4.0, 4.0))));
+        let sphere = Sphere::new_moving(start, end, radius, Arc::new(light));
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        for time in [0.0, 0.25, 0.5, 0.9, 1.0] {
+            let center = start + (end - start) * time;
+            for _ in 0..100 {
+                let direction = sphere.random(&origin, time);
+                let ray = Ray::new(origin, direction, time);
+                let isect = sphere
+                    .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                    .unwrap_or_else(|| panic!("sample at time {time} missed the sphere"));
+                assert!(((isect.p - center).norm() - radius).abs() < 1e-6);
+
+                let pdf = sphere.pdf_value(&origin, &direction, time);
+                assert!(pdf.is_finite() && pdf > 0.0, "pdf {pdf} at time {time}");
+            }
+        }
+    }
+
+    #[test]
+    fn one_sided_spheres_are_sampled_only_where_they_emit() {
+        let emitter = |side| {
+            let light = DiffuseLight::new(Arc::new(SolidColor::new(Color::new(1.0, 1.0, 1.0))));
+            Arc::new(light.with_side(side))
+        };
+        let (outside, inside) = (Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.2, 0.0));
+        let cases = [
+            (EmissionSide::Front, true, false),
+            (EmissionSide::Back, false, true),
+            (EmissionSide::Both, true, true),
+        ];
+        for (side, from_outside, from_inside) in cases {
+            let sphere = Sphere::new(Point3::origin(), 1.0, emitter(side));
+            for (origin, lit) in [(outside, from_outside), (inside, from_inside)] {
+                let direction = sphere.random(&origin, 0.0);
+                assert_eq!(!direction.near_zero(), lit, "{side:?} from {origin}");
+                let toward = Point3::origin() - origin + Vec3::new(0.0, 0.1, 0.0);
+                let pdf = sphere.pdf_value(&origin, &toward, 0.0);
+                assert_eq!(pdf > 0.0, lit, "{side:?} from {origin}: pdf {pdf}");
+            }
+        }
+    }
 }
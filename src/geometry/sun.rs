@@ -0,0 +1,89 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::geometry::sphere::Sphere;
+use crate::materials::material_trait::Material;
+use crate::textures::sky::SunPath;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Sun following a `SunPath`: a sphere of `radius` at `distance` from the origin
+/// toward the sun at the time of each ray or sample, so it rises and sets over a
+/// sequence of frames.
+#[derive(Debug)]
+pub struct Sun {
+    path: Arc<SunPath>,
+    distance: f64,
+    radius: f64,
+    material: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Sun {
+    pub fn new(
+        path: Arc<SunPath>,
+        distance: f64,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        // The arc of the path, bounded by its ends and, if it crosses the horizon,
+        // the point of longest horizontal reach
+        let (low, high) = path.elevation_range();
+        let rvec = Vec3::new(radius, radius, radius);
+        let at = |elevation: f64| {
+            let center = Point3::origin() + distance * path.direction_at(elevation);
+            Aabb::new_point(center - rvec, center + rvec)
+        };
+        let mut bbox = at(low).merge(&at(high));
+        if low < 0.0 && high > 0.0 {
+            bbox = bbox.merge(&at(0.0));
+        }
+
+        Self {
+            path,
+            distance,
+            radius,
+            material,
+            bbox,
+        }
+    }
+
+    /// The sun where it is at `time`.
+    fn sphere(&self, time: f64) -> Sphere {
+        let center = Point3::origin() + self.distance * self.path.direction(time);
+        Sphere::new(center, self.radius, self.material.clone())
+    }
+}
+
+impl Hittable for Sun {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        self.sphere(r.time).hit(r, ray_t)
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.bbox
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.sphere(time).pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.sphere(time).random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.sphere(time).sample_surface(time)
+    }
+}
@@ -1,2 +1,108 @@
+pub mod animated;
+pub mod instance;
 pub mod rotate;
+pub mod scale;
 pub mod translate;
+
+#[cfg(test)]
+mod tests {
+    use super::instance::Instance;
+    use super::rotate::RotateY;
+    use super::scale::Scale;
+    use super::translate::Translate;
+    use crate::core::interval::Interval;
+    use crate::core::ray::Ray;
+    use crate::core::transform::Transform;
+    use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
+    use crate::geometry::bvh::BvhNode;
+    use crate::geometry::hittable::Hittable;
+    use crate::geometry::quad::Quad;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::textures::solid_color::SolidColor;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    fn emitter() -> Arc<DiffuseLight> {
+        Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(Color::new(
+            4.0, 4.0, 4.0,
+        )))))
+    }
+
+    /// A quad light facing -z, toward the origins of the tests.
+    fn quad_light() -> Arc<dyn Hittable> {
+        Arc::new(Quad::new(
+            Point3::new(-1.0, -1.0, 1.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            emitter(),
+        ))
+    }
+
+    fn sphere_light() -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(Point3::new(0.0, 0.0, 2.0), 1.0, emitter()))
+    }
+
+    /// Directions sampled toward `light` from `origin` hit it with a positive
+    /// density, and the density integrates to 1 over the sphere of directions.
+    fn check_light_sampling(light: &dyn Hittable, origin: Point3) {
+        let time = 0.5;
+        for _ in 0..1000 {
+            let direction = light.random(&origin, time);
+            let ray = Ray::new(origin, direction, time);
+            assert!(
+                light
+                    .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                    .is_some(),
+                "sampled direction {direction:?} missed"
+            );
+            assert!(light.pdf_value(&origin, &direction, time) > 0.0);
+        }
+
+        let samples = 100_000;
+        let total: f64 = (0..samples)
+            .map(|_| light.pdf_value(&origin, &Vec3::random_unit_vector(), time))
+            .sum();
+        let integral = total / samples as f64 * 4.0 * PI;
+        assert!(
+            (integral - 1.0).abs() < 0.05,
+            "density integrates to {integral}"
+        );
+    }
+
+    #[test]
+    fn translated_lights_sample_correctly() {
+        let offset = Vec3::new(0.5, -0.3, 0.4);
+        let origin = Point3::new(0.2, 0.1, -0.3);
+        check_light_sampling(&Translate::new(quad_light(), offset), origin);
+        check_light_sampling(&Translate::new(sphere_light(), offset), origin);
+    }
+
+    #[test]
+    fn rotated_lights_sample_correctly() {
+        // Turned toward the origin, off to the side of it
+        let origin = Point3::new(-0.5, 0.2, 0.3);
+        check_light_sampling(&RotateY::new(quad_light(), 30.0), origin);
+        check_light_sampling(&RotateY::new(sphere_light(), 60.0), origin);
+    }
+
+    #[test]
+    fn scaled_lights_sample_correctly() {
+        let factor = Vec3::new(2.0, 1.0, 0.5);
+        let origin = Point3::new(0.3, -0.2, 0.0);
+        check_light_sampling(&Scale::new(quad_light(), factor), origin);
+        check_light_sampling(&Scale::new(sphere_light(), factor), origin);
+    }
+
+    #[test]
+    fn instanced_lights_sample_correctly() {
+        let to_world = Transform::scaling(Vec3::new(1.5, 0.8, 1.0))
+            .then(&Transform::rotation(Vec3::new(1.0, 1.0, 0.0), 25.0))
+            .then(&Transform::translation(Vec3::new(0.2, 0.0, 0.5)));
+        let origin = Point3::new(0.1, 0.1, -0.2);
+        for light in [quad_light(), sphere_light()] {
+            let bvh = Arc::new(BvhNode::new_from_objects(vec![light]));
+            check_light_sampling(&Instance::new(bvh, to_world), origin);
+        }
+    }
+}
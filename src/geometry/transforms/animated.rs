@@ -0,0 +1,193 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::transform::Transform;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, SHUTTER, shutter_fraction};
+use crate::sampling::random::degrees_to_radians;
+use nalgebra::{Unit, UnitQuaternion};
+use std::sync::Arc;
+
+/// Times the transform is sampled at across an interval to bound the object.
+const BOUND_STEPS: usize = 32;
+
+/// Placement of an object at one instant: scaled about the origin, then rotated
+/// about it, then translated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub translation: Vec3,
+    pub rotation: UnitQuaternion<f64>,
+    pub scale: Vec3,
+}
+
+impl Default for Keyframe {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Keyframe {
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            translation: offset,
+            ..Self::default()
+        }
+    }
+
+    /// Rotation by `degrees` about `axis`, in the same sense as `Transform::rotation`.
+    pub fn with_rotation(mut self, axis: Vec3, degrees: f64) -> Self {
+        self.rotation = UnitQuaternion::from_axis_angle(
+            &Unit::new_normalize(axis),
+            degrees_to_radians(degrees),
+        );
+        self
+    }
+
+    /// Scale by a factor per axis, none of which may be zero.
+    pub fn with_scale(mut self, factor: Vec3) -> Self {
+        self.scale = factor;
+        self
+    }
+
+    /// The keyframe a fraction `t` of the way to `next`: translation and scale
+    /// interpolate linearly, rotation along the shorter arc between the two.
+    pub fn lerp(&self, next: &Keyframe, t: f64) -> Self {
+        Self {
+            translation: self.translation.lerp(&next.translation, t),
+            // Opposite quaternions are the same rotation, with no arc to follow
+            rotation: self
+                .rotation
+                .try_slerp(&next.rotation, t, 1e-9)
+                .unwrap_or(self.rotation),
+            scale: self.scale.lerp(&next.scale, t),
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::scaling(self.scale)
+            .then(&Transform::from_rotation(&self.rotation))
+            .then(&Transform::translation(self.translation))
+    }
+}
+
+/// Moves an object between two keyframes over the shutter, at the start and end
+/// of `SHUTTER`, so any object can be motion blurred by its transform rather
+/// than only a `Sphere` built with `new_moving`. Each ray sees the object as
+/// placed at its time. Rotations take the shorter way round, so keyframes must
+/// be less than half a turn apart.
+///
+/// Rays are transformed into object space without renormalizing, so `t` is the
+/// same in both spaces, as for `Instance`.
+#[derive(Debug)]
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    start: Keyframe,
+    end: Keyframe,
+}
+
+impl AnimatedTransform {
+    pub fn new(object: Arc<dyn Hittable>, start: Keyframe, end: Keyframe) -> Self {
+        Self { object, start, end }
+    }
+
+    /// Object to world transform at `time`, held at the keyframes outside the shutter.
+    pub fn transform_at(&self, time: f64) -> Transform {
+        let t = shutter_fraction(time).clamp(0.0, 1.0);
+        self.start.lerp(&self.end, t).transform()
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let to_world = self.transform_at(r.time);
+        let mut isect = self.object.hit(&to_world.inverse().ray(r), ray_t)?;
+        to_world.interaction_to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let to_world = self.transform_at(r.time);
+        let mut isect = self.object.intersect_p(&to_world.inverse().ray(r), ray_t)?;
+        to_world.interaction_to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let to_object = self.transform_at(r.time).inverse();
+        self.object.transmittance(&to_object.ray(r), ray_t)
+    }
+
+    /// The object's bounds placed at evenly spaced times across `time`, padded by
+    /// how far a corner can stray from the chord of its arc between two of them.
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        let bbox = self.object.bounding_box(time);
+        if bbox.x.min > bbox.x.max || bbox.y.min > bbox.y.max || bbox.z.min > bbox.z.max {
+            return bbox;
+        }
+
+        let mut bounds = Aabb::empty();
+        for step in 0..=BOUND_STEPS {
+            let t = time.min + time.size() * step as f64 / BOUND_STEPS as f64;
+            bounds = bounds.merge(&self.transform_at(t).bounding_box(&bbox));
+        }
+
+        let corner = Vec3::new(
+            bbox.x.min.abs().max(bbox.x.max.abs()),
+            bbox.y.min.abs().max(bbox.y.max.abs()),
+            bbox.z.min.abs().max(bbox.z.max.abs()),
+        );
+        let stretch = self.start.scale.abs().max().max(self.end.scale.abs().max());
+        let step_angle = self.start.rotation.angle_to(&self.end.rotation) / BOUND_STEPS as f64;
+        let sag = corner.norm() * stretch * (1.0 - (step_angle / 2.0).cos());
+        Aabb::new(
+            bounds.x.expand(2.0 * sag),
+            bounds.y.expand(2.0 * sag),
+            bounds.z.expand(2.0 * sag),
+        )
+    }
+
+    /// Area at the middle of the shutter, estimated as for `Instance`.
+    fn area(&self) -> f64 {
+        let to_world = self.transform_at(SHUTTER.min + SHUTTER.size() / 2.0);
+        self.object.area() * to_world.determinant().powf(2.0 / 3.0)
+    }
+
+    fn power(&self) -> Color {
+        let to_world = self.transform_at(SHUTTER.min + SHUTTER.size() / 2.0);
+        self.object.power() * to_world.determinant().powf(2.0 / 3.0)
+    }
+
+    /// As for `Instance`, with the object placed at `time`.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let to_world = self.transform_at(time);
+        let to_object = to_world.inverse();
+        let local_origin = to_object.point(origin);
+        let Some(u) = to_object.vector(direction).try_normalize(0.0) else {
+            return 0.0;
+        };
+        self.object.pdf_value(&local_origin, &u, time) / to_world.solid_angle_scale(&u)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let to_world = self.transform_at(time);
+        let local_origin = to_world.inverse().point(origin);
+        to_world.vector(&self.object.random(&local_origin, time))
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let to_world = self.transform_at(time);
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        let stretch = to_world.area_scale(&isect.geometry_normal.normalize());
+
+        isect.p = to_world.point(&isect.p);
+        isect.geometry_normal = to_world.normal(&isect.geometry_normal);
+        isect.set_shading_normal(to_world.normal(&isect.shading_normal));
+
+        Some((isect, pdf / stretch))
+    }
+}
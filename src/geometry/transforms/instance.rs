@@ -0,0 +1,97 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::transform::Transform;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::hittable::Hittable;
+use std::sync::Arc;
+
+/// One placement of a shared BVH, such as a tree of a forest or a copy of a
+/// cluster, under any affine transform. Instances of the same BVH share its
+/// geometry and acceleration structure; only the transform is stored per copy.
+/// Put instances into a `BvhNode` of their own for a two-level hierarchy: the top
+/// level over instance bounds, the shared bottom level in object space.
+///
+/// Rays are transformed into object space without renormalizing, so `t` is the
+/// same in both spaces.
+#[derive(Debug)]
+pub struct Instance {
+    object: Arc<BvhNode>,
+    to_world: Transform,
+    to_object: Transform,
+}
+
+impl Instance {
+    pub fn new(object: Arc<BvhNode>, to_world: Transform) -> Self {
+        Self {
+            object,
+            to_world,
+            to_object: to_world.inverse(),
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.to_world
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(&self.to_object.ray(r), ray_t)?;
+        self.to_world.interaction_to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(&self.to_object.ray(r), ray_t)?;
+        self.to_world.interaction_to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(&self.to_object.ray(r), ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.to_world.bounding_box(&self.object.bounding_box(time))
+    }
+
+    /// Exact for rigid transforms and uniform scales. Otherwise estimated as for
+    /// `Scale`, by the determinant to the power 2/3.
+    fn area(&self) -> f64 {
+        self.object.area() * self.to_world.determinant().powf(2.0 / 3.0)
+    }
+
+    fn power(&self) -> Color {
+        self.object.power() * self.to_world.determinant().powf(2.0 / 3.0)
+    }
+
+    /// The object's density at the direction in object space, divided by the
+    /// growth of solid angle around it.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let local_origin = self.to_object.point(origin);
+        let Some(u) = self.to_object.vector(direction).try_normalize(0.0) else {
+            return 0.0;
+        };
+        self.object.pdf_value(&local_origin, &u, time) / self.to_world.solid_angle_scale(&u)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let local_origin = self.to_object.point(origin);
+        self.to_world
+            .vector(&self.object.random(&local_origin, time))
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        let stretch = self.to_world.area_scale(&isect.geometry_normal.normalize());
+
+        isect.p = self.to_world.point(&isect.p);
+        isect.geometry_normal = self.to_world.normal(&isect.geometry_normal);
+        isect.set_shading_normal(self.to_world.normal(&isect.shading_normal));
+
+        Some((isect, pdf / stretch))
+    }
+}
@@ -2,7 +2,7 @@ use crate::core::aabb::Aabb;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
+use crate::core::vec3::{Color, Point3, Vec3};
 use crate::geometry::hittable::Hittable;
 use crate::sampling::random::degrees_to_radians;
 use std::sync::Arc;
@@ -12,15 +12,79 @@ pub struct RotateY {
     object: Arc<dyn Hittable>,
     sin_theta: f64,
     cos_theta: f64,
-    bbox: Aabb,
 }
 
 impl RotateY {
     pub fn new(object: Arc<dyn Hittable>, angle: f64) -> Self {
         let radians = degrees_to_radians(angle);
-        let sin_theta = radians.sin();
-        let cos_theta = radians.cos();
-        let bbox = object.bounding_box();
+        Self {
+            object,
+            sin_theta: radians.sin(),
+            cos_theta: radians.cos(),
+        }
+    }
+
+    /// Changes ray from world space to object space.
+    fn to_object(&self, r: &Ray) -> Ray {
+        r.map(
+            |p| Point3::from(self.rotate_to_object(p.coords)),
+            |v| self.rotate_to_object(*v),
+        )
+    }
+
+    /// Rotates a vector from world space to object space.
+    fn rotate_to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    /// Rotates a vector from object space to world space.
+    fn rotate_to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    /// Changes intersection point and normals from object space to world space.
+    fn to_world(&self, r: &Ray, isect: &mut Interaction) {
+        isect.p = Point3::from(self.rotate_to_world(isect.p.coords));
+        // Rotations preserve which side the ray arrived from, so the object's face flag
+        // and the orientation of both normals still hold
+        isect.geometry_normal = self.rotate_to_world(isect.geometry_normal);
+        // Tangents first, as the shading frame is rebuilt along them
+        isect.dpdu = self.rotate_to_world(isect.dpdu);
+        isect.dpdv = self.rotate_to_world(isect.dpdv);
+        isect.set_shading_normal(self.rotate_to_world(isect.shading_normal));
+        isect.dpdx = self.rotate_to_world(isect.dpdx);
+        isect.dpdy = self.rotate_to_world(isect.dpdy);
+        isect.wo = -r.dir.normalize();
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(&self.to_object(r), ray_t)?;
+        self.to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(&self.to_object(r), ray_t)?;
+        self.to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(&self.to_object(r), ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        let bbox = self.object.bounding_box(time);
 
         let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
         let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
@@ -33,10 +97,7 @@ impl RotateY {
                     let y = j as f64 * bbox.y.max + (1 - j) as f64 * bbox.y.min;
                     let z = k as f64 * bbox.z.max + (1 - k) as f64 * bbox.z.min;
 
-                    let newx = cos_theta * x + sin_theta * z;
-                    let newz = -sin_theta * x + cos_theta * z;
-
-                    let tester = Vec3::new(newx, y, newz);
+                    let tester = self.rotate_to_world(Vec3::new(x, y, z));
 
                     min.x = min.x.min(tester.x);
                     max.x = max.x.max(tester.x);
@@ -48,56 +109,20 @@ impl RotateY {
             }
         }
 
-        Self {
-            object,
-            sin_theta,
-            cos_theta,
-            bbox: Aabb::new_point(min, max),
-        }
+        Aabb::new_point(min, max)
     }
-}
-
-impl Hittable for RotateY {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
-        // Change ray from world space to object space
-        let mut origin = r.orig;
-        let mut direction = r.dir;
-
-        origin.x = self.cos_theta * r.orig.x - self.sin_theta * r.orig.z;
-        origin.z = self.sin_theta * r.orig.x + self.cos_theta * r.orig.z;
-
-        direction.x = self.cos_theta * r.dir.x - self.sin_theta * r.dir.z;
-        direction.z = self.sin_theta * r.dir.x + self.cos_theta * r.dir.z;
 
-        let rotated_r = Ray::new(origin, direction, r.time);
-
-        if !self.object.hit(&rotated_r, ray_t, isect) {
-            return false;
-        }
-
-        // Change intersection point and normal from object space to world space
-        let mut p = isect.p;
-        p.x = self.cos_theta * isect.p.x + self.sin_theta * isect.p.z;
-        p.z = -self.sin_theta * isect.p.x + self.cos_theta * isect.p.z;
-
-        let mut normal = isect.geometry_normal;
-        normal.x =
-            self.cos_theta * isect.geometry_normal.x + self.sin_theta * isect.geometry_normal.z;
-        normal.z =
-            -self.sin_theta * isect.geometry_normal.x + self.cos_theta * isect.geometry_normal.z;
-
-        isect.p = p;
-        // Update shading normal and face flags using the new world-space geometry normal
-        isect.set_face_normal(r, normal);
-
-        true
+    fn area(&self) -> f64 {
+        self.object.area()
     }
 
-    fn bounding_box(&self) -> Aabb {
-        self.bbox
+    fn power(&self) -> Color {
+        self.object.power()
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    /// Rotations preserve solid angles and areas, so the object's densities carry
+    /// over unchanged once the query is rotated into object space.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         // Rotate ray to object space
         let mut origin_rot = *origin;
         origin_rot.x = self.cos_theta * origin.x - self.sin_theta * origin.z;
@@ -107,22 +132,29 @@ impl Hittable for RotateY {
         dir_rot.x = self.cos_theta * direction.x - self.sin_theta * direction.z;
         dir_rot.z = self.sin_theta * direction.x + self.cos_theta * direction.z;
 
-        self.object.pdf_value(&origin_rot, &dir_rot)
+        self.object.pdf_value(&origin_rot, &dir_rot, time)
     }
 
-    fn random(&self, origin: &Point3) -> Vec3 {
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
         // Transform user viewpoint to object space
         let mut origin_rot = *origin;
         origin_rot.x = self.cos_theta * origin.x - self.sin_theta * origin.z;
         origin_rot.z = self.sin_theta * origin.x + self.cos_theta * origin.z;
 
-        let local_dir = self.object.random(&origin_rot);
+        let local_dir = self.object.random(&origin_rot, time);
 
         // Rotate random direction back to world space
-        let mut world_dir = local_dir;
-        world_dir.x = self.cos_theta * local_dir.x + self.sin_theta * local_dir.z;
-        world_dir.z = -self.sin_theta * local_dir.x + self.cos_theta * local_dir.z;
+        self.rotate_to_world(local_dir)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+
+        // Rotate sampled point and normals back to world space
+        isect.p = Point3::from(self.rotate_to_world(isect.p.coords));
+        isect.geometry_normal = self.rotate_to_world(isect.geometry_normal);
+        isect.set_shading_normal(self.rotate_to_world(isect.shading_normal));
 
-        world_dir
+        Some((isect, pdf))
     }
 }
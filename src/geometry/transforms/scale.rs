@@ -0,0 +1,148 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use std::sync::Arc;
+
+/// Scales an object about the origin by a factor per axis, so one shared object
+/// can be instanced at different sizes and proportions. Negative factors mirror
+/// it. Factors must not be zero.
+///
+/// Rays are scaled into object space without renormalizing their direction, so
+/// the ray parameter `t` is the same in both spaces. Participating media measure
+/// distances in object space, so their density scales with the object.
+#[derive(Debug)]
+pub struct Scale {
+    object: Arc<dyn Hittable>,
+    factor: Vec3,
+}
+
+impl Scale {
+    pub fn new(object: Arc<dyn Hittable>, factor: Vec3) -> Self {
+        Self { object, factor }
+    }
+
+    /// Scales by the same factor along every axis.
+    pub fn uniform(object: Arc<dyn Hittable>, factor: f64) -> Self {
+        Self::new(object, Vec3::new(factor, factor, factor))
+    }
+
+    /// Determinant of the scale, the ratio of volumes.
+    fn determinant(&self) -> f64 {
+        (self.factor.x * self.factor.y * self.factor.z).abs()
+    }
+
+    /// Changes ray from world space to object space.
+    fn to_object(&self, r: &Ray) -> Ray {
+        r.map(
+            |p| Point3::from(p.coords.component_div(&self.factor)),
+            |v| v.component_div(&self.factor),
+        )
+    }
+
+    /// Normals transform by the inverse transpose, which for a scale is the
+    /// inverse factor, and need renormalizing.
+    fn normal_to_world(&self, n: Vec3) -> Vec3 {
+        n.component_div(&self.factor).normalize()
+    }
+
+    /// Changes intersection point and normals from object space to world space.
+    fn to_world(&self, r: &Ray, isect: &mut Interaction) {
+        isect.p = Point3::from(isect.p.coords.component_mul(&self.factor));
+        // The inverse transpose keeps the sign of the normals' dot product with the
+        // ray, so the object's face flag still holds, even when mirrored
+        isect.geometry_normal = self.normal_to_world(isect.geometry_normal);
+        // Tangents first, as the shading frame is rebuilt along them
+        isect.dpdu = isect.dpdu.component_mul(&self.factor);
+        isect.dpdv = isect.dpdv.component_mul(&self.factor);
+        isect.set_shading_normal(self.normal_to_world(isect.shading_normal));
+        isect.dpdx = isect.dpdx.component_mul(&self.factor);
+        isect.dpdy = isect.dpdy.component_mul(&self.factor);
+        isect.wo = -r.dir.normalize();
+    }
+}
+
+impl Hittable for Scale {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.hit(&self.to_object(r), ray_t)?;
+        self.to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let mut isect = self.object.intersect_p(&self.to_object(r), ray_t)?;
+        self.to_world(r, &mut isect);
+        Some(isect)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(&self.to_object(r), ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        let bbox = self.object.bounding_box(time);
+        // Mirroring swaps the ends of an axis; empty ones stay empty
+        let scaled = |axis: Interval, factor: f64| {
+            if axis.min > axis.max {
+                return axis;
+            }
+            let (a, b) = (axis.min * factor, axis.max * factor);
+            Interval::new(a.min(b), a.max(b))
+        };
+        Aabb::new(
+            scaled(bbox.x, self.factor.x),
+            scaled(bbox.y, self.factor.y),
+            scaled(bbox.z, self.factor.z),
+        )
+    }
+
+    /// Exact for uniform scales. Otherwise the stretch of each patch depends on
+    /// its orientation, and the area is estimated as that of a surface facing
+    /// every way equally, scaled by the determinant to the power 2/3.
+    fn area(&self) -> f64 {
+        self.object.area() * self.determinant().powf(2.0 / 3.0)
+    }
+
+    fn power(&self) -> Color {
+        self.object.power() * self.determinant().powf(2.0 / 3.0)
+    }
+
+    /// Scales do not preserve solid angles: a direction u in object space maps to
+    /// Su, and solid angle around it grows by det(S) / |Su|^3, so the object's
+    /// density is divided by that factor.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let local_origin = Point3::from(origin.coords.component_div(&self.factor));
+        let Some(u) = direction.component_div(&self.factor).try_normalize(0.0) else {
+            return 0.0;
+        };
+        let stretch = u.component_mul(&self.factor).norm();
+        self.object.pdf_value(&local_origin, &u, time) * stretch.powi(3) / self.determinant()
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let local_origin = Point3::from(origin.coords.component_div(&self.factor));
+        self.object
+            .random(&local_origin, time)
+            .component_mul(&self.factor)
+    }
+
+    /// A patch with unit normal n in object space grows in area by det(S) |S^-1 n|,
+    /// which divides the object's area density.
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        let stretch = self.determinant()
+            * isect
+                .geometry_normal
+                .normalize()
+                .component_div(&self.factor)
+                .norm();
+
+        isect.p = Point3::from(isect.p.coords.component_mul(&self.factor));
+        isect.geometry_normal = self.normal_to_world(isect.geometry_normal);
+        isect.set_shading_normal(self.normal_to_world(isect.shading_normal));
+
+        Some((isect, pdf / stretch))
+    }
+}
@@ -2,7 +2,7 @@ use crate::core::aabb::Aabb;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
+use crate::core::vec3::{Color, Point3, Vec3};
 use crate::geometry::hittable::Hittable;
 use std::sync::Arc;
 
@@ -10,48 +10,75 @@ use std::sync::Arc;
 pub struct Translate {
     object: Arc<dyn Hittable>,
     offset: Vec3,
-    bbox: Aabb,
 }
 
 impl Translate {
     pub fn new(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
-        let bbox = object.bounding_box() + offset;
-        Self {
-            object,
-            offset,
-            bbox,
-        }
+        Self { object, offset }
+    }
+
+    /// Changes ray from world space to object space.
+    fn to_object(&self, r: &Ray) -> Ray {
+        r.map(|p| p - self.offset, |v| *v)
     }
 }
 
 impl Hittable for Translate {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
         // Move ray backwards to object space
-        let offset_r = Ray::new(r.orig - self.offset, r.dir, r.time);
+        let offset_r = self.to_object(r);
 
-        if !self.object.hit(&offset_r, ray_t, isect) {
-            return false;
-        }
+        let mut isect = self.object.hit(&offset_r, ray_t)?;
 
         // Move intersection point forward to world space
         isect.p += self.offset;
 
-        // Normal and wo do not change with translation
+        // Normals, wo and the footprint do not change with translation
+
+        Some(isect)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let offset_r = self.to_object(r);
+
+        let mut isect = self.object.intersect_p(&offset_r, ray_t)?;
+        isect.p += self.offset;
+        Some(isect)
+    }
 
-        true
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let offset_r = self.to_object(r);
+        self.object.transmittance(&offset_r, ray_t)
     }
 
-    fn bounding_box(&self) -> Aabb {
-        self.bbox
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time) + self.offset
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    /// Translations preserve solid angles and areas, so the object's densities
+    /// carry over unchanged from the offset origin.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         // Look from the perspective of the object
-        self.object.pdf_value(&(*origin - self.offset), direction)
+        self.object
+            .pdf_value(&(*origin - self.offset), direction, time)
     }
 
-    fn random(&self, origin: &Point3) -> Vec3 {
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
         // Look from the perspective of the object
-        self.object.random(&(*origin - self.offset))
+        self.object.random(&(*origin - self.offset), time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (mut isect, pdf) = self.object.sample_surface(time)?;
+        isect.p += self.offset;
+        Some((isect, pdf))
     }
 }
@@ -2,9 +2,10 @@ use crate::core::aabb::Aabb;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Point3, Vec3};
-use crate::geometry::hittable::Hittable;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, shutter_fraction};
 use crate::materials::material_trait::Material;
+use crate::sampling::random::random_double;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -17,6 +18,8 @@ pub struct Triangle {
     uv1: (f64, f64),
     uv2: (f64, f64),
     normal: Vec3, // Pre-computed face normal
+    normals: Option<[Vec3; 3]>,
+    motion: Vec3, // Offset at time 1, for motion blur
 }
 
 impl Triangle {
@@ -34,49 +37,78 @@ impl Triangle {
             uv1: (1.0, 0.0),
             uv2: (0.0, 1.0),
             normal,
+            normals: None,
+            motion: Vec3::zeros(),
         }
     }
 
+    /// Moves the triangle by `offset` in a straight line over the shutter, from
+    /// where it was built when the shutter opens, as `Sphere::new_moving` does
+    /// its center.
+    pub fn with_motion(mut self, offset: Vec3) -> Self {
+        self.motion = offset;
+        self
+    }
+
+    /// Vertices where the triangle is at `time`.
+    fn vertices_at(&self, time: f64) -> [Point3; 3] {
+        let offset = self.motion * shutter_fraction(time);
+        [self.v0 + offset, self.v1 + offset, self.v2 + offset]
+    }
+
+    /// Uniformly samples a point on the triangle where it is at `time`, returning
+    /// it with its interpolated UV.
+    fn sample_point(&self, time: f64) -> (Point3, (f64, f64)) {
+        let [b0, b1, b2] = sample_barycentric();
+
+        let [v0, v1, v2] = self.vertices_at(time);
+        let p = Point3::from(b0 * v0.coords + b1 * v1.coords + b2 * v2.coords);
+        let uv = (
+            b0 * self.uv0.0 + b1 * self.uv1.0 + b2 * self.uv2.0,
+            b0 * self.uv0.1 + b1 * self.uv1.1 + b2 * self.uv2.1,
+        );
+        (p, uv)
+    }
+
     pub fn with_uvs(mut self, uv0: (f64, f64), uv1: (f64, f64), uv2: (f64, f64)) -> Self {
         self.uv0 = uv0;
         self.uv1 = uv1;
         self.uv2 = uv2;
         self
     }
-}
 
-impl Hittable for Triangle {
-    fn hit(&self, r: &Ray, ray_t: Interval, isect: &mut Interaction) -> bool {
-        let edge1 = self.v1 - self.v0;
-        let edge2 = self.v2 - self.v0;
-        let h = r.dir.cross(&edge2);
-        let a = edge1.dot(&h);
-
-        // Parallel check using epsilon
-        if a.abs() < 1e-8 {
-            return false;
-        }
-
-        let f = 1.0 / a;
-        let s = r.orig - self.v0;
-        let u = f * s.dot(&h);
+    /// Per-vertex normals, interpolated into the shading normal so that meshes built
+    /// from triangles shade smoothly instead of faceted.
+    pub fn with_normals(mut self, n0: Vec3, n1: Vec3, n2: Vec3) -> Self {
+        self.normals = Some([n0, n1, n2]);
+        self
+    }
 
-        if !(0.0..=1.0).contains(&u) {
-            return false;
-        }
+    /// Barycentric interpolation of the vertex normals, if any.
+    fn shading_normal(&self, b: [f64; 3]) -> Option<Vec3> {
+        let [n0, n1, n2] = self.normals?;
+        let n = b[0] * n0 + b[1] * n1 + b[2] * n2;
+        (n.norm_squared() > 0.0).then(|| n.normalize())
+    }
 
-        let q = s.cross(&edge1);
-        let v = f * r.dir.dot(&q);
+    pub fn vertices(&self) -> [Point3; 3] {
+        [self.v0, self.v1, self.v2]
+    }
 
-        if v < 0.0 || u + v > 1.0 {
-            return false;
-        }
+    pub fn uvs(&self) -> [(f64, f64); 3] {
+        [self.uv0, self.uv1, self.uv2]
+    }
 
-        let t = f * edge2.dot(&q);
+    /// Face normal, following the winding v0 -> v1 -> v2.
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
 
-        if !ray_t.contains(t) {
-            return false;
-        }
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let cull_back = self.material.cull_backfaces();
+        let (t, u, v) = intersect(&self.vertices_at(r.time), r, ray_t, cull_back)?;
 
         // --- Intersection confirmed ---
         let intersection_point = r.at(t);
@@ -87,29 +119,146 @@ impl Hittable for Triangle {
         let tex_u = w * self.uv0.0 + u * self.uv1.0 + v * self.uv2.0;
         let tex_v = w * self.uv0.1 + u * self.uv1.1 + v * self.uv2.1;
 
-        *isect = Interaction::new(
+        let mut isect = Interaction::new(
             intersection_point,
             t,
             (tex_u, tex_v),
             Some(self.material.clone()),
         );
         isect.set_face_normal(r, self.normal);
+        let (dpdu, dpdv) = uv_partials(&[self.v0, self.v1, self.v2], &self.uvs());
+        isect.set_differentials(r, &dpdu, &dpdv);
+        // Vertex normals bend shading, on the side the ray arrived from
+        if let Some(n) = self.shading_normal([w, u, v]) {
+            isect.set_shading_normal(n);
+        }
+
+        Some(isect)
+    }
+
+    fn area(&self) -> f64 {
+        0.5 * (self.v1 - self.v0).cross(&(self.v2 - self.v0)).norm()
+    }
 
-        true
+    fn power(&self) -> Color {
+        self.material.power() * self.area()
     }
 
-    fn bounding_box(&self) -> Aabb {
-        let min_x = self.v0.x.min(self.v1.x).min(self.v2.x);
-        let min_y = self.v0.y.min(self.v1.y).min(self.v2.y);
-        let min_z = self.v0.z.min(self.v1.z).min(self.v2.z);
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let Some(rec) = self.hit(
+            &Ray::new(*origin, *direction, time),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
 
-        let max_x = self.v0.x.max(self.v1.x).max(self.v2.x);
-        let max_y = self.v0.y.max(self.v1.y).max(self.v2.y);
-        let max_z = self.v0.z.max(self.v1.z).max(self.v2.z);
+        // Convert the uniform area density to solid angle
+        let distance_squared = rec.t * rec.t * direction.norm_squared();
+        let cosine = (direction.dot(&rec.geometry_normal) / direction.norm()).abs();
 
-        Aabb::new_point(
-            Point3::new(min_x, min_y, min_z),
-            Point3::new(max_x, max_y, max_z),
-        )
+        if cosine < 1e-8 {
+            0.0
+        } else {
+            distance_squared / (cosine * self.area())
+        }
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let (p, _) = self.sample_point(time);
+        (p - *origin).normalize()
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        let (p, uv) = self.sample_point(time);
+
+        let mut isect = Interaction::new(p, 0.0, uv, Some(self.material.clone()));
+        isect.geometry_normal = self.normal;
+        isect.set_shading_normal(self.normal);
+
+        Some((isect, 1.0 / self.area()))
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        // The triangle moves linearly, so the boxes at both ends bound the sweep
+        bounds(&self.vertices_at(time.min)).merge(&bounds(&self.vertices_at(time.max)))
+    }
+}
+
+/// Möller-Trumbore intersection of `r` with the triangle `vertices`, returning the
+/// ray parameter and the barycentric weights of the second and third vertices.
+/// With `cull_back`, misses triangles whose winding faces away from the ray.
+pub(crate) fn intersect(
+    vertices: &[Point3; 3],
+    r: &Ray,
+    ray_t: Interval,
+    cull_back: bool,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+    let h = r.dir.cross(&edge2);
+    let a = edge1.dot(&h);
+
+    // Parallel check using epsilon; `a` is negative for back faces
+    if a.abs() < 1e-8 || (cull_back && a < 0.0) {
+        return None;
+    }
+
+    let f = 1.0 / a;
+    let s = r.orig - vertices[0];
+    let u = f * s.dot(&h);
+
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(&edge1);
+    let v = f * r.dir.dot(&q);
+
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = f * edge2.dot(&q);
+
+    ray_t.contains(t).then_some((t, u, v))
+}
+
+/// Partial derivatives of the points of a triangle by its texture coordinates, or
+/// zero if the UVs are degenerate.
+pub(crate) fn uv_partials(vertices: &[Point3; 3], uvs: &[(f64, f64); 3]) -> (Vec3, Vec3) {
+    let (du02, dv02) = (uvs[0].0 - uvs[2].0, uvs[0].1 - uvs[2].1);
+    let (du12, dv12) = (uvs[1].0 - uvs[2].0, uvs[1].1 - uvs[2].1);
+    let det = du02 * dv12 - dv02 * du12;
+    if det.abs() < 1e-12 {
+        return (Vec3::zeros(), Vec3::zeros());
     }
+    let (dp02, dp12) = (vertices[0] - vertices[2], vertices[1] - vertices[2]);
+    (
+        (dv12 * dp02 - dv02 * dp12) / det,
+        (du02 * dp12 - du12 * dp02) / det,
+    )
+}
+
+/// Uniformly distributed barycentric coordinates.
+pub(crate) fn sample_barycentric() -> [f64; 3] {
+    // Square-root warp of the unit square onto barycentric coordinates
+    let su = random_double().sqrt();
+    let b1 = 1.0 - su;
+    let b2 = random_double() * su;
+    [1.0 - b1 - b2, b1, b2]
+}
+
+pub(crate) fn bounds(v: &[Point3; 3]) -> Aabb {
+    Aabb::new_point(
+        Point3::new(
+            v[0].x.min(v[1].x).min(v[2].x),
+            v[0].y.min(v[1].y).min(v[2].y),
+            v[0].z.min(v[1].z).min(v[2].z),
+        ),
+        Point3::new(
+            v[0].x.max(v[1].x).max(v[2].x),
+            v[0].y.max(v[1].y).max(v[2].y),
+            v[0].z.max(v[1].z).max(v[2].z),
+        ),
+    )
 }
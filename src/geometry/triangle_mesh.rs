@@ -0,0 +1,311 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::{Interaction, UV_SETS, UvSets};
+use crate::core::interval::Interval;
+use crate::core::memory::{self, Category};
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::geometry::triangle::{bounds, intersect, sample_barycentric, uv_partials};
+use crate::materials::material_trait::Material;
+use std::sync::Arc;
+
+/// Indexed triangle mesh: vertex positions, optional per-vertex normals, UV sets
+/// and colors in flat buffers shared by all faces. Large models cost one `Point3`
+/// per vertex plus three indices per face, instead of three points and three UVs
+/// per `Triangle`.
+///
+/// Faces are turned into `Hittable`s by `faces`, each a reference to the mesh and
+/// a face index, ready to be put into a `BvhNode`.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    uv_sets: Vec<Vec<(f64, f64)>>,
+    colors: Option<Vec<Color>>,
+    indices: Vec<[u32; 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl TriangleMesh {
+    /// Mesh of the triangles `indices` into `positions`, wound counter-clockwise
+    /// around their outward normals. Fails on out-of-range indices.
+    pub fn new(
+        positions: Vec<Point3>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Result<Self, String> {
+        if let Some(face) = indices
+            .iter()
+            .position(|face| face.iter().any(|&n| n as usize >= positions.len()))
+        {
+            return Err(format!(
+                "face {} references a vertex beyond the {} given",
+                face,
+                positions.len()
+            ));
+        }
+        Ok(Self {
+            positions,
+            normals: None,
+            uvs: None,
+            uv_sets: Vec::new(),
+            colors: None,
+            indices,
+            material,
+        })
+    }
+
+    /// Per-vertex normals, interpolated into the shading normal. Ignored unless
+    /// there is one per vertex.
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        self.normals = (normals.len() == self.positions.len()).then_some(normals);
+        self
+    }
+
+    /// Per-vertex texture coordinates. Ignored unless there is one per vertex.
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        self.uvs = (uvs.len() == self.positions.len()).then_some(uvs);
+        self
+    }
+
+    /// A further set of per-vertex texture coordinates, such as a lightmap's, numbered
+    /// from 1 in the order added and chosen by textures with `UvSet`. Ignored unless
+    /// there is one per vertex, and beyond `UV_SETS` sets in all.
+    pub fn with_uv_set(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        if uvs.len() == self.positions.len() && self.uv_sets.len() < UV_SETS - 1 {
+            self.uv_sets.push(uvs);
+        }
+        self
+    }
+
+    /// Per-vertex colors, interpolated into `Interaction::vertex_color` for the
+    /// `VertexColor` texture. Ignored unless there is one per vertex.
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        self.colors = (colors.len() == self.positions.len()).then_some(colors);
+        self
+    }
+
+    pub fn has_colors(&self) -> bool {
+        self.colors.is_some()
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> Option<&[Vec3]> {
+        self.normals.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[(f64, f64)]> {
+        self.uvs.as_deref()
+    }
+
+    /// The UV sets added with `with_uv_set`, from set 1 on.
+    pub fn uv_sets(&self) -> &[Vec<(f64, f64)>] {
+        &self.uv_sets
+    }
+
+    pub fn colors(&self) -> Option<&[Color]> {
+        self.colors.as_deref()
+    }
+
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    pub fn material(&self) -> &Arc<dyn Material> {
+        &self.material
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// One `Hittable` per face, sharing this mesh's buffers. The mesh is recorded
+    /// for memory accounting here, where it enters a scene.
+    pub fn faces(self: &Arc<Self>) -> Vec<Arc<dyn Hittable>> {
+        memory::record(Category::Meshes, self.memory_size());
+        (0..self.indices.len() as u32)
+            .map(|face| {
+                Arc::new(MeshTriangle {
+                    mesh: self.clone(),
+                    face,
+                }) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    /// Approximate bytes taken by the buffers and the faces.
+    fn memory_size(&self) -> usize {
+        let vertex = size_of::<Point3>()
+            + self.normals.as_ref().map_or(0, |_| size_of::<Vec3>())
+            + self.uvs.as_ref().map_or(0, |_| size_of::<(f64, f64)>())
+            + self.uv_sets.len() * size_of::<(f64, f64)>()
+            + self.colors.as_ref().map_or(0, |_| size_of::<Color>());
+        // A face is an index triple, a `MeshTriangle` behind an `Arc` and a pointer to it
+        let face = size_of::<[u32; 3]>()
+            + size_of::<MeshTriangle>()
+            + 2 * size_of::<usize>()
+            + size_of::<Arc<dyn Hittable>>();
+        self.positions.len() * vertex + self.indices.len() * face
+    }
+
+    fn vertices(&self, face: u32) -> [Point3; 3] {
+        self.indices[face as usize].map(|n| self.positions[n as usize])
+    }
+
+    /// UVs of the corners of `face`, or the same fixed parameterization as
+    /// `Triangle` without UVs.
+    fn corner_uvs(&self, face: u32) -> [(f64, f64); 3] {
+        match &self.uvs {
+            Some(uvs) => self.indices[face as usize].map(|n| uvs[n as usize]),
+            None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        }
+    }
+
+    /// Barycentric interpolation of the UVs of `face`.
+    fn uv(&self, face: u32, b: [f64; 3]) -> (f64, f64) {
+        let corners = self.corner_uvs(face);
+        (
+            b[0] * corners[0].0 + b[1] * corners[1].0 + b[2] * corners[2].0,
+            b[0] * corners[0].1 + b[1] * corners[1].1 + b[2] * corners[2].1,
+        )
+    }
+
+    /// Barycentric interpolation of the further UV sets of `face`.
+    fn uv_sets_at(&self, face: u32, b: [f64; 3]) -> UvSets {
+        let mut sets = [None; UV_SETS - 1];
+        for (set, uvs) in sets.iter_mut().zip(&self.uv_sets) {
+            let [c0, c1, c2] = self.indices[face as usize].map(|n| uvs[n as usize]);
+            *set = Some((
+                b[0] * c0.0 + b[1] * c1.0 + b[2] * c2.0,
+                b[0] * c0.1 + b[1] * c1.1 + b[2] * c2.1,
+            ));
+        }
+        sets
+    }
+
+    /// Barycentric interpolation of the vertex colors of `face`, if any.
+    fn color(&self, face: u32, b: [f64; 3]) -> Option<Color> {
+        let colors = self.colors.as_ref()?;
+        let [c0, c1, c2] = self.indices[face as usize].map(|n| colors[n as usize]);
+        Some(b[0] * c0 + b[1] * c1 + b[2] * c2)
+    }
+
+    /// Barycentric interpolation of the vertex normals of `face`, if any.
+    fn shading_normal(&self, face: u32, b: [f64; 3]) -> Option<Vec3> {
+        let normals = self.normals.as_ref()?;
+        let [n0, n1, n2] = self.indices[face as usize].map(|n| normals[n as usize]);
+        let n = b[0] * n0 + b[1] * n1 + b[2] * n2;
+        (n.norm_squared() > 0.0).then(|| n.normalize())
+    }
+}
+
+/// Unnormalized normal of a face of the vertices `positions`, twice its area
+/// long, for the mesh processing passes.
+pub(crate) fn face_normal(positions: &[Point3], face: &[u32; 3]) -> Vec3 {
+    let [a, b, c] = face.map(|v| positions[v as usize]);
+    (b - a).cross(&(c - a))
+}
+
+/// Face of a `TriangleMesh`.
+#[derive(Debug)]
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: u32,
+}
+
+impl MeshTriangle {
+    fn face_normal(&self, v: &[Point3; 3]) -> Vec3 {
+        (v[1] - v[0]).cross(&(v[2] - v[0])).normalize()
+    }
+
+    fn interaction(&self, p: Point3, t: f64, b: [f64; 3]) -> Interaction {
+        let mut isect = Interaction::new(
+            p,
+            t,
+            self.mesh.uv(self.face, b),
+            Some(self.mesh.material.clone()),
+        );
+        isect.uv_sets = self.mesh.uv_sets_at(self.face, b);
+        isect.vertex_color = self.mesh.color(self.face, b);
+        isect
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        let v = self.mesh.vertices(self.face);
+        let cull_back = self.mesh.material.cull_backfaces();
+        let (t, b1, b2) = intersect(&v, r, ray_t, cull_back)?;
+        let b = [1.0 - b1 - b2, b1, b2];
+
+        let mut isect = self.interaction(r.at(t), t, b);
+        isect.set_face_normal(r, self.face_normal(&v));
+        let (dpdu, dpdv) = uv_partials(&v, &self.mesh.corner_uvs(self.face));
+        isect.set_differentials(r, &dpdu, &dpdv);
+        // Vertex normals bend shading, on the side the ray arrived from
+        if let Some(n) = self.mesh.shading_normal(self.face, b) {
+            isect.set_shading_normal(n);
+        }
+
+        Some(isect)
+    }
+
+    fn area(&self) -> f64 {
+        let v = self.mesh.vertices(self.face);
+        0.5 * (v[1] - v[0]).cross(&(v[2] - v[0])).norm()
+    }
+
+    fn power(&self) -> Color {
+        self.mesh.material.power() * self.area()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let Some(rec) = self.hit(
+            &Ray::new(*origin, *direction, time),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        // Convert the uniform area density to solid angle
+        let distance_squared = rec.t * rec.t * direction.norm_squared();
+        let cosine = (direction.dot(&rec.geometry_normal) / direction.norm()).abs();
+
+        if cosine < 1e-8 {
+            0.0
+        } else {
+            distance_squared / (cosine * self.area())
+        }
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let v = self.mesh.vertices(self.face);
+        let b = sample_barycentric();
+        let p = Point3::from(b[0] * v[0].coords + b[1] * v[1].coords + b[2] * v[2].coords);
+        (p - *origin).normalize()
+    }
+
+    fn sample_surface(&self, _time: f64) -> Option<(Interaction, f64)> {
+        let v = self.mesh.vertices(self.face);
+        let b = sample_barycentric();
+        let p = Point3::from(b[0] * v[0].coords + b[1] * v[1].coords + b[2] * v[2].coords);
+
+        let mut isect = self.interaction(p, 0.0, b);
+        isect.geometry_normal = self.face_normal(&v);
+        isect.set_shading_normal(isect.geometry_normal);
+
+        Some((isect, 1.0 / self.area()))
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        bounds(&self.mesh.vertices(self.face))
+    }
+}
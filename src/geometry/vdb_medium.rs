@@ -0,0 +1,306 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::nanovdb::VdbGrid;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, roulette_transmittance};
+use crate::geometry::medium::Medium;
+use crate::materials::fire::Fire;
+use crate::materials::henyey_greenstein::HenyeyGreenstein;
+use crate::materials::isotropic::Isotropic;
+use crate::materials::material_trait::Material;
+use crate::sampling::random::random_double;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture_trait::Texture;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Single-scattering albedo of cloud droplets: they absorb almost nothing.
+const CLOUD_ALBEDO: f64 = 0.99;
+/// Henyey-Greenstein asymmetry of cloud droplets, strongly forward scattering.
+const CLOUD_G: f64 = 0.85;
+/// Single-scattering albedo of the soot in fire media.
+const SOOT_ALBEDO: f64 = 0.5;
+/// Most octaves of the multiple scattering approximation.
+const MAX_OCTAVES: usize = 8;
+
+/// Spatially varying density of a heterogeneous medium, or another scalar field
+/// over it such as temperature.
+pub trait DensityField: Send + Sync + Debug {
+    fn density(&self, p: &Point3) -> f64;
+
+    /// Upper bound of `density` anywhere in the bounding box.
+    fn maximum(&self) -> f64;
+
+    /// Region outside which the density is taken to be zero.
+    fn bounding_box(&self) -> Aabb;
+}
+
+/// The sum of the trilinearly interpolated values of several grids, so a coarse
+/// background grid can be combined with finer detail grids, each with its own voxel
+/// size and transform. Background values only apply inside the union of the grids'
+/// active voxels.
+#[derive(Debug)]
+pub struct GridSum {
+    grids: Vec<VdbGrid>,
+    maximum: f64,
+    bbox: Aabb,
+}
+
+impl GridSum {
+    pub fn new(grids: Vec<VdbGrid>) -> Self {
+        let bbox = grids
+            .iter()
+            .map(VdbGrid::world_bbox)
+            .reduce(|a, b| a.merge(&b))
+            .unwrap_or_else(Aabb::empty);
+        let maximum = grids
+            .iter()
+            .map(|grid| grid.maximum().max(grid.background()).max(0.0))
+            .sum();
+        Self {
+            grids,
+            maximum,
+            bbox,
+        }
+    }
+}
+
+impl DensityField for GridSum {
+    fn density(&self, p: &Point3) -> f64 {
+        self.grids.iter().map(|grid| grid.sample(p)).sum()
+    }
+
+    fn maximum(&self) -> f64 {
+        self.maximum
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Wrenninge's approximation of high-order scattering in dense, bright media: shadow
+/// rays sum `octaves` transmittances, octave `i` seeing the extinction scaled by
+/// `extinction^i` and contributing `contribution^i`. Light thus reaches deep into a
+/// cloud as it does after many forward scattering events, without tracing them.
+#[derive(Debug, Clone, Copy)]
+pub struct MultipleScattering {
+    pub octaves: u32,
+    pub extinction: f64,
+    pub contribution: f64,
+}
+
+impl Default for MultipleScattering {
+    fn default() -> Self {
+        Self {
+            octaves: 4,
+            extinction: 0.25,
+            contribution: 0.5,
+        }
+    }
+}
+
+/// Heterogeneous participating medium whose density comes from NanoVDB grids, such
+/// as smoke and cloud assets exported from production tools, or from any other
+/// `DensityField`.
+///
+/// The extinction at a point is the field's density times `density_scale`. Free
+/// flights are sampled by delta tracking and shadow rays estimate transmittance by
+/// ratio tracking, both against the field's maximum.
+#[derive(Debug)]
+pub struct VdbMedium {
+    field: Arc<dyn DensityField>,
+    density_scale: f64,
+    /// Upper bound of the extinction anywhere in the medium.
+    majorant: f64,
+    phase_function: Arc<dyn Material>,
+    multiple_scattering: Option<MultipleScattering>,
+}
+
+impl VdbMedium {
+    /// Isotropic medium with the summed density of `grids` (see `GridSum`).
+    pub fn new(grids: Vec<VdbGrid>, density_scale: f64, texture: Arc<dyn Texture>) -> Self {
+        Self::from_field(Arc::new(GridSum::new(grids)), density_scale, texture)
+    }
+
+    pub fn from_field(
+        field: Arc<dyn DensityField>,
+        density_scale: f64,
+        texture: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            majorant: density_scale * field.maximum(),
+            field,
+            density_scale,
+            phase_function: Arc::new(Isotropic::new(texture)),
+            multiple_scattering: None,
+        }
+    }
+
+    /// Fire or explosion from a simulation cache: extinction from the summed
+    /// `density` grids and blackbody emission from the summed `temperature` grids,
+    /// through a `Fire` phase function with grey soot.
+    pub fn fire(
+        density: Vec<VdbGrid>,
+        temperature: Vec<VdbGrid>,
+        density_scale: f64,
+        temperature_scale: f64,
+        intensity: f64,
+    ) -> Self {
+        let albedo = Arc::new(SolidColor::new(Color::repeat(SOOT_ALBEDO)));
+        let fire = Fire::new(albedo.clone(), Arc::new(GridSum::new(temperature)))
+            .with_temperature_scale(temperature_scale)
+            .with_intensity(intensity);
+        Self::new(density, density_scale, albedo).with_phase_function(Arc::new(fire))
+    }
+
+    pub fn with_phase_function(mut self, phase_function: Arc<dyn Material>) -> Self {
+        self.phase_function = phase_function;
+        self
+    }
+
+    pub fn with_multiple_scattering(mut self, multiple_scattering: MultipleScattering) -> Self {
+        self.multiple_scattering = Some(multiple_scattering);
+        self
+    }
+
+    /// Cumulus look: white, nearly lossless forward scattering, optionally with the
+    /// default `MultipleScattering` approximation for bright, soft interiors.
+    pub fn with_cloud_preset(self, multiple_scattering: bool) -> Self {
+        let medium = self.with_phase_function(Arc::new(HenyeyGreenstein::new(
+            Arc::new(SolidColor::new(Color::repeat(CLOUD_ALBEDO))),
+            CLOUD_G,
+        )));
+        if multiple_scattering {
+            medium.with_multiple_scattering(MultipleScattering::default())
+        } else {
+            medium
+        }
+    }
+
+    pub fn density(&self, p: &Point3) -> f64 {
+        self.density_scale * self.field.density(p)
+    }
+
+    /// Calls `visit` with the ray parameter and extinction of each tentative collision
+    /// inside the medium, in order, until it returns false.
+    fn track(&self, r: &Ray, ray_t: Interval, mut visit: impl FnMut(f64, f64) -> bool) {
+        if self.majorant <= 0.0 {
+            return;
+        }
+        let Some(inside) = self.field.bounding_box().clip(r, ray_t) else {
+            return;
+        };
+
+        let ray_length = r.dir.norm();
+        let mut t = inside.min;
+        loop {
+            let distance = -(1.0 - random_double()).ln() / self.majorant;
+            t += distance / ray_length;
+            if t >= inside.max || !visit(t, self.density(&r.at(t))) {
+                return;
+            }
+        }
+    }
+
+    /// Ratio tracking of every octave of `multiple_scattering` at once.
+    fn octave_transmittance(&self, r: &Ray, ray_t: Interval, ms: &MultipleScattering) -> Color {
+        let octaves = (ms.octaves as usize).clamp(1, MAX_OCTAVES);
+        let mut tr = [0.0; MAX_OCTAVES];
+        let mut weight = 1.0;
+        for octave in tr.iter_mut().take(octaves) {
+            *octave = weight;
+            weight *= ms.contribution;
+        }
+
+        self.track(r, ray_t, |_, density| {
+            let ratio = (density / self.majorant).min(1.0);
+            let mut scale = 1.0;
+            for octave in tr.iter_mut().take(octaves) {
+                *octave *= 1.0 - scale * ratio;
+                scale *= ms.extinction;
+            }
+
+            // Roulette the octaves together, on their sum
+            let total: f64 = tr.iter().sum();
+            let survivor = roulette_transmittance(Color::repeat(total)).x;
+            if survivor == 0.0 {
+                return false;
+            }
+            tr.iter_mut().for_each(|octave| *octave *= survivor / total);
+            true
+        });
+
+        Color::repeat(tr.iter().sum())
+    }
+}
+
+impl Hittable for VdbMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        // Delta tracking: a tentative collision is real with probability density / majorant
+        let mut hit_t = None;
+        self.track(r, ray_t, |t, density| {
+            if random_double() * self.majorant < density {
+                hit_t = Some(t);
+                return false;
+            }
+            true
+        });
+
+        let t = hit_t?;
+        // Facing the ray, as Interaction::new sets it, is arbitrary too
+        let mut isect = Interaction::new(r.at(t), t, (0.0, 0.0), Some(self.phase_function.clone()));
+        isect.geometry_normal = Vec3::new(1.0, 0.0, 0.0); // Arbitrary
+        isect.medium = true;
+        Some(isect)
+    }
+
+    /// Media never block shadow rays outright; they attenuate them instead.
+    fn intersect_p(&self, _r: &Ray, _ray_t: Interval) -> Option<Interaction> {
+        None
+    }
+
+    /// Ratio tracking: every tentative collision scales the estimate by the
+    /// probability that it is fictitious.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        if let Some(ms) = &self.multiple_scattering {
+            return self.octave_transmittance(r, ray_t, ms);
+        }
+
+        let mut tr = Color::new(1.0, 1.0, 1.0);
+        self.track(r, ray_t, |_, density| {
+            tr = roulette_transmittance(tr * (1.0 - density / self.majorant).max(0.0));
+            tr != Color::zeros()
+        });
+        tr
+    }
+
+    fn bounding_box(&self, _time: Interval) -> Aabb {
+        self.field.bounding_box()
+    }
+}
+
+/// In `OverlappingMedia` the multiple scattering approximation does not apply.
+impl Medium for VdbMedium {
+    fn spans(&self, r: &Ray, ray_t: Interval) -> Vec<Interval> {
+        self.field
+            .bounding_box()
+            .clip(r, ray_t)
+            .into_iter()
+            .collect()
+    }
+
+    fn density(&self, p: &Point3) -> f64 {
+        VdbMedium::density(self, p)
+    }
+
+    fn majorant(&self) -> f64 {
+        self.majorant
+    }
+
+    fn phase_function(&self) -> Arc<dyn Material> {
+        self.phase_function.clone()
+    }
+}
@@ -1,2 +1,9 @@
+pub mod budget;
+pub mod contact_sheet;
 pub mod integrator_trait;
+pub mod light_tracer;
+pub mod monitor;
+pub mod path_record;
 pub mod path_tracer;
+pub mod probe_baker;
+pub mod uv_baker;
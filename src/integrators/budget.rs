@@ -0,0 +1,167 @@
+use crate::core::aabb::Aabb;
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::metadata;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Rays traced by this thread and not yet charged to a `BudgetTracker`.
+    static RAYS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Hard limits on a render, for unattended jobs such as CI: once either is
+/// reached, the integrator stops taking samples and saves the image it has, so a
+/// misconfigured job ends with partial output instead of running for days.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderBudget {
+    /// Rays of every kind: camera, bounce, shadow and AOV rays.
+    pub max_rays: Option<u64>,
+    /// Wall-clock time from the start of the render.
+    pub max_time: Option<Duration>,
+}
+
+impl RenderBudget {
+    pub fn is_limited(&self) -> bool {
+        self.max_rays.is_some() || self.max_time.is_some()
+    }
+}
+
+/// Spending against a `RenderBudget` during one render, shared by its threads.
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budget: RenderBudget,
+    start: Instant,
+    rays: AtomicU64,
+    samples: AtomicU64,
+    exhausted: AtomicBool,
+}
+
+impl BudgetTracker {
+    pub fn start(budget: RenderBudget) -> Self {
+        Self {
+            budget,
+            start: Instant::now(),
+            rays: AtomicU64::new(0),
+            samples: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Charges the rays this thread traced since it last did and, if the budget
+    /// still allows, books `samples` more samples or paths, returning whether it
+    /// did. Threads call it once per sample rather than per ray, so they do not
+    /// contend for the shared counts.
+    pub fn charge(&self, samples: u64) -> bool {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return false;
+        }
+        let rays = self.rays.fetch_add(RAYS.take(), Ordering::Relaxed);
+        let over = self.budget.max_rays.is_some_and(|max| rays >= max)
+            || self
+                .budget
+                .max_time
+                .is_some_and(|max| self.start.elapsed() >= max);
+        if over {
+            self.exhausted.store(true, Ordering::Relaxed);
+            return false;
+        }
+        self.samples.fetch_add(samples, Ordering::Relaxed);
+        true
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Rays charged so far.
+    pub fn rays(&self) -> u64 {
+        self.rays.load(Ordering::Relaxed)
+    }
+
+    /// Samples or paths booked so far.
+    pub fn samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Warns that the image is partial if the budget ran out, with how many of
+    /// the `planned` samples were taken, which is also stamped in its metadata.
+    pub fn report(&self, planned: u64) {
+        if !self.exhausted() {
+            return;
+        }
+        eprintln!(
+            "Render budget exhausted after {} rays in {:.2?}: {} of {} samples ({:.1}%) taken, the image is partial",
+            self.rays(),
+            self.elapsed(),
+            self.samples(),
+            planned,
+            100.0 * self.samples() as f64 / planned.max(1) as f64
+        );
+        metadata::record(
+            "samples taken",
+            format!("{} of {} (budget exhausted)", self.samples(), planned),
+        );
+    }
+}
+
+/// Counts every ray traced against `object`, the scene, for `BudgetTracker::charge`.
+/// Shadow rays count once though they query both `intersect_p` and `transmittance`.
+#[derive(Debug)]
+pub struct RayCounter<'a> {
+    object: &'a dyn Hittable,
+}
+
+impl<'a> RayCounter<'a> {
+    pub fn new(object: &'a dyn Hittable) -> Self {
+        Self { object }
+    }
+}
+
+impl Hittable for RayCounter<'_> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        RAYS.set(RAYS.get() + 1);
+        self.object.hit(r, ray_t)
+    }
+
+    fn intersect_p(&self, r: &Ray, ray_t: Interval) -> Option<Interaction> {
+        RAYS.set(RAYS.get() + 1);
+        self.object.intersect_p(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(r, ray_t)
+    }
+
+    fn bounding_box(&self, time: Interval) -> Aabb {
+        self.object.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn power(&self) -> Color {
+        self.object.power()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.object.random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(Interaction, f64)> {
+        self.object.sample_surface(time)
+    }
+}
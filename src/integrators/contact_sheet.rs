@@ -0,0 +1,162 @@
+use crate::core::camera::Camera;
+use crate::core::film::Film;
+use crate::core::metadata;
+use crate::geometry::hittable::Hittable;
+use crate::integrators::path_tracer::PathTracer;
+use image::{Rgb, RgbImage};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Thumbnail width unless set with `with_thumbnail_width`.
+const DEFAULT_WIDTH: u32 = 240;
+/// Samples per thumbnail pixel unless set with `with_samples`.
+const DEFAULT_SAMPLES: u32 = 16;
+/// Bounces per thumbnail path at most, whatever the scene asks for.
+const MAX_DEPTH: u32 = 16;
+/// Pixels between thumbnails and around the sheet.
+const GAP: u32 = 4;
+const BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+
+/// Quick thumbnails of several scenes laid out on one grid image, to check at a
+/// glance that a change left every scene looking as it should. Each scene is
+/// rendered through its own camera at a low resolution and sample count, then
+/// dropped; the sheet is written once all are added, in the order they were,
+/// left to right and top to bottom. Thumbnails are not labelled: the order is
+/// printed and recorded in the image metadata.
+pub struct ContactSheet {
+    output_filename: String,
+    path_tracer: PathTracer,
+    thumbnail_width: u32,
+    samples_per_pixel: u32,
+    thumbnails: Vec<(String, RgbImage)>,
+}
+
+impl ContactSheet {
+    /// `path_tracer` renders the thumbnails, with its light sampling and roulette
+    /// settings.
+    pub fn new(output_filename: &str, path_tracer: PathTracer) -> Self {
+        Self {
+            output_filename: output_filename.to_string(),
+            path_tracer,
+            thumbnail_width: DEFAULT_WIDTH,
+            samples_per_pixel: DEFAULT_SAMPLES,
+            thumbnails: Vec::new(),
+        }
+    }
+
+    pub fn with_thumbnail_width(mut self, width: u32) -> Self {
+        self.thumbnail_width = width.max(1);
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples_per_pixel = samples.max(1);
+        self
+    }
+
+    /// Renders a thumbnail of the scene seen by `camera`, keeping its framing and
+    /// aspect ratio.
+    pub fn add(
+        &mut self,
+        name: &str,
+        world: &dyn Hittable,
+        lights: Option<Arc<dyn Hittable>>,
+        camera: &Camera,
+    ) {
+        let mut camera = camera.clone();
+        camera.image_width = self.thumbnail_width;
+        camera.samples_per_pixel = self.samples_per_pixel;
+        camera.max_depth = camera.max_depth.min(MAX_DEPTH);
+        camera.initialize();
+
+        let start_time = std::time::Instant::now();
+        let (width, height) = (camera.image_width, camera.image_height);
+        let pixels: Vec<_> = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let (i, j) = (index % width, index / width);
+                (0..camera.samples_per_pixel)
+                    .map(|_| {
+                        self.path_tracer.radiance(
+                            &camera.get_ray(i, j),
+                            camera.max_depth,
+                            world,
+                            lights.as_ref(),
+                            &camera.background,
+                        )
+                    })
+                    .filter(|sample| sample.iter().all(|c| c.is_finite()))
+                    .sum()
+            })
+            .collect();
+
+        let mut film = Film::new(width, height)
+            .with_exposure(camera.exposure)
+            .with_color_space(camera.color_space)
+            .with_lut(camera.lut.clone());
+        for (index, color) in pixels.into_iter().enumerate() {
+            film.set(index as u32 % width, index as u32 / width, color);
+        }
+        println!(
+            "  {}: {}x{} thumbnail in {:.2?}",
+            name,
+            width,
+            height,
+            start_time.elapsed()
+        );
+        self.thumbnails
+            .push((name.to_string(), film.to_image(camera.samples_per_pixel)));
+    }
+
+    /// Lays the thumbnails out on a roughly square grid, in cells as tall as the
+    /// tallest of them, and saves the sheet.
+    pub fn save(&self) {
+        if self.thumbnails.is_empty() {
+            eprintln!("No scenes rendered, so no contact sheet to save");
+            return;
+        }
+
+        let count = self.thumbnails.len() as u32;
+        let columns = (count as f64).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+        let cell_width = self.thumbnail_width;
+        let cell_height = self
+            .thumbnails
+            .iter()
+            .map(|(_, thumbnail)| thumbnail.height())
+            .max()
+            .unwrap_or(1);
+
+        let mut sheet = RgbImage::from_pixel(
+            GAP + columns * (cell_width + GAP),
+            GAP + rows * (cell_height + GAP),
+            BACKGROUND,
+        );
+        for (n, (_, thumbnail)) in self.thumbnails.iter().enumerate() {
+            let (column, row) = (n as u32 % columns, n as u32 / columns);
+            // Shorter thumbnails are centered vertically in their cell
+            let x = GAP + column * (cell_width + GAP);
+            let y = GAP + row * (cell_height + GAP) + (cell_height - thumbnail.height()) / 2;
+            for (i, j, pixel) in thumbnail.enumerate_pixels() {
+                sheet.put_pixel(x + i, y + j, *pixel);
+            }
+        }
+
+        let names: Vec<&str> = self
+            .thumbnails
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        println!(
+            "Contact sheet of {} scenes, {} per row: {}",
+            count,
+            columns,
+            names.join(", ")
+        );
+        metadata::record("contact sheet", names.join(", "));
+        match metadata::save_image(&sheet, &self.output_filename) {
+            Ok(_) => println!("Image saved to {}", self.output_filename),
+            Err(e) => eprintln!("Error saving image: {}", e),
+        }
+    }
+}
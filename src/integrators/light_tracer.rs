@@ -0,0 +1,264 @@
+use crate::core::camera::Camera;
+use crate::core::film::Film;
+use crate::core::interval::Interval;
+use crate::core::metadata;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::{Hittable, SHUTTER};
+use crate::integrators::budget::{BudgetTracker, RayCounter, RenderBudget};
+use crate::integrators::integrator_trait::Integrator;
+use crate::materials::material_trait::{EmissionSide, ScatterRecord};
+use crate::sampling::pdf::{CosinePDF, PDF};
+use crate::sampling::random::random_double;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Light tracer (particle tracing).
+/// Paths start on the light sources and every non-specular vertex is connected
+/// to the camera, splatting its contribution onto the film. Caustics seen
+/// directly by the camera converge much faster than with the path tracer.
+/// The camera is treated as a pinhole, so defocus blur is ignored.
+pub struct LightTracer {
+    output_filename: String,
+    budget: RenderBudget,
+}
+
+impl LightTracer {
+    pub fn new(output_filename: &str) -> Self {
+        Self {
+            output_filename: output_filename.to_string(),
+            budget: RenderBudget::default(),
+        }
+    }
+
+    /// Stops tracing paths once `budget` is spent and saves the partial image,
+    /// brightened to make up for the paths not traced.
+    pub fn with_budget(mut self, budget: RenderBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Connects a surface point to the camera.
+    /// Returns the film index, the ray towards the camera and the camera
+    /// importance times the geometry term at the camera end, dimmed by the media in between.
+    fn connect_to_camera(
+        &self,
+        p: &Point3,
+        time: f64,
+        world: &dyn Hittable,
+        camera: &Camera,
+    ) -> Option<(usize, Ray, Color)> {
+        let (i, j) = camera.project(p)?;
+
+        let to_camera = camera.center - p;
+        let dist = to_camera.norm();
+        let dir = to_camera / dist;
+
+        // Shadow ray towards the lens
+        let ray = Ray::new(*p, dir, time);
+        let unoccluded = Interval::new(0.001, dist - 0.001);
+        if world.intersect_p(&ray, unoccluded).is_some() {
+            return None;
+        }
+
+        let cos_camera = dir.dot(&camera.w);
+        let factor = world.transmittance(&ray, unoccluded)
+            * (camera.importance(cos_camera) * cos_camera / (dist * dist));
+
+        let index = (j * camera.image_width + i) as usize;
+        Some((index, ray, factor))
+    }
+
+    /// Traces a single light path and splats its contributions onto `film`.
+    fn trace_path(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        camera: &Camera,
+        film: &mut Film,
+    ) {
+        let time = SHUTTER.min + SHUTTER.size() * random_double();
+        let (light_isect, pdf_area) = match lights.sample_surface(time) {
+            Some(sample) => sample,
+            None => return,
+        };
+        let light_material = match &light_isect.material {
+            Some(m) => m,
+            None => return,
+        };
+
+        let (u, v) = light_isect.uv;
+        let outward = light_isect.geometry_normal;
+        let side = light_material.emission_side();
+
+        // Radiance leaving the light towards `direction`, seen as a ray arriving from it
+        // on the face that direction points away from
+        let le = |direction: &Vec3| {
+            let mut face = light_isect.clone();
+            face.front_face = direction.dot(&outward) > 0.0;
+            face.geometry_normal = if face.front_face { outward } else { -outward };
+            let incoming = Ray::new(light_isect.p + *direction, -*direction, time);
+            light_material.emitted(&incoming, &face, u, v, &light_isect.p)
+        };
+
+        // Light seen directly by the camera
+        if let Some((index, to_camera, factor)) =
+            self.connect_to_camera(&light_isect.p, time, world, camera)
+        {
+            let cos_light = outward.dot(&to_camera.dir);
+            if cos_light != 0.0 && side.emits(cos_light > 0.0) {
+                film.splat(
+                    index,
+                    le(&to_camera.dir).component_mul(&factor) * cos_light.abs() / pdf_area,
+                );
+            }
+        }
+
+        // Cosine-weighted emission direction, from either face with equal probability
+        // when both emit
+        let front = match side {
+            EmissionSide::Front => true,
+            EmissionSide::Back => false,
+            EmissionSide::Both => random_double() < 0.5,
+        };
+        let normal = if front { outward } else { -outward };
+        let emission_pdf = CosinePDF::new(&normal);
+        let direction = emission_pdf.generate();
+        let pdf_dir = emission_pdf.value(&direction) / side.faces();
+        if pdf_dir < 1e-5 {
+            return;
+        }
+
+        let direction = direction.normalize();
+        let cos_emit = direction.dot(&normal);
+        let mut beta = le(&direction) * cos_emit / (pdf_area * pdf_dir);
+        let mut ray = Ray::new(light_isect.p, direction, time);
+
+        for _ in 0..camera.max_depth {
+            let Some(isect) = world.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+                break;
+            };
+
+            let material = match &isect.material {
+                Some(m) => m.clone(),
+                None => break,
+            };
+
+            let mut srec = ScatterRecord::default();
+            if !material.scatter(&ray, &isect, &mut srec) {
+                break;
+            }
+
+            // Specular vertices cannot be connected to a pinhole camera
+            if srec.skip_pdf {
+                beta = beta.component_mul(&srec.attenuation);
+                ray = srec.skip_pdf_ray;
+                continue;
+            }
+
+            if let Some((index, to_camera, factor)) =
+                self.connect_to_camera(&isect.p, ray.time, world, camera)
+            {
+                let scattering_pdf = material.scattering_pdf(&ray, &isect, &to_camera);
+                let contribution =
+                    beta.component_mul(&srec.attenuation).component_mul(&factor) * scattering_pdf;
+                film.splat(index, contribution);
+            }
+
+            let p = srec.pdf_ptr.unwrap();
+            let scattered_direction = p.generate();
+            let pdf_val = p.value(&scattered_direction);
+            if pdf_val < 1e-5 {
+                break;
+            }
+
+            let scattered_ray = Ray::new(isect.p, scattered_direction, ray.time);
+            let scattering_pdf = material.scattering_pdf(&ray, &isect, &scattered_ray);
+            beta = beta.component_mul(&srec.attenuation) * scattering_pdf / pdf_val;
+            ray = scattered_ray;
+        }
+    }
+}
+
+impl Integrator for LightTracer {
+    fn render(&self, world: &dyn Hittable, lights: Option<Arc<dyn Hittable>>, camera: &Camera) {
+        let width = camera.image_width;
+        let height = camera.image_height;
+
+        let lights = match lights {
+            Some(l) => l,
+            None => {
+                eprintln!("Light tracing requires at least one light; nothing to render.");
+                return;
+            }
+        };
+
+        println!(
+            "Light tracing {}x{} image with {} paths per pixel...",
+            width, height, camera.samples_per_pixel
+        );
+
+        // One batch per image row worth of paths keeps progress updates cheap
+        let paths_per_batch = width as u64 * camera.samples_per_pixel as u64;
+        let progress_bar = ProgressBar::new(height as u64);
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+                )
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        let start_time = std::time::Instant::now();
+
+        // Under a budget, paths are booked an image row's width at a time
+        let budget = self
+            .budget
+            .is_limited()
+            .then(|| BudgetTracker::start(self.budget));
+        let counted = RayCounter::new(world);
+        let world: &dyn Hittable = if budget.is_some() { &counted } else { world };
+
+        let film = (0..height)
+            .into_par_iter()
+            .fold(
+                || Film::new(width, height),
+                |mut film, _| {
+                    for _ in 0..camera.samples_per_pixel {
+                        if budget
+                            .as_ref()
+                            .is_some_and(|budget| !budget.charge(width as u64))
+                        {
+                            break;
+                        }
+                        for _ in 0..width {
+                            self.trace_path(world, &*lights, camera, &mut film);
+                        }
+                    }
+                    progress_bar.inc(1);
+                    film
+                },
+            )
+            .reduce(|| Film::new(width, height), Film::merge);
+
+        progress_bar.finish_with_message("Done");
+        println!("Render complete in {:.2?}", start_time.elapsed());
+        metadata::record("render time", format!("{:.2?}", start_time.elapsed()));
+
+        // Splats are averaged over the paths planned, so a partial render makes up
+        // for the paths it did not trace
+        let mut exposure = camera.exposure;
+        if let Some(budget) = &budget {
+            budget.report(paths_per_batch * height as u64);
+            if budget.exhausted() && budget.samples() > 0 {
+                exposure *= (paths_per_batch * height as u64) as f64 / budget.samples() as f64;
+            }
+        }
+        film.with_exposure(exposure)
+            .with_color_space(camera.color_space)
+            .with_lut(camera.lut.clone())
+            .save(&self.output_filename, camera.samples_per_pixel);
+    }
+}
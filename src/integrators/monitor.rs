@@ -0,0 +1,241 @@
+use image::{ImageFormat, RgbImage};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Settings that only change how the samples are turned into an image, so they
+/// can be adjusted while the render runs without invalidating any samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    /// Linear scale applied to radiance.
+    pub exposure: f64,
+    /// Largest value of a channel after exposure, if clamped.
+    pub clamp: Option<f64>,
+}
+
+/// Live state of a progressive render, shared between the integrator and a
+/// monitoring server: progress, the preview after the latest round of samples,
+/// and the post-processing settings, which apply to the next preview and to the
+/// saved image.
+#[derive(Debug)]
+pub struct RenderMonitor {
+    start: Instant,
+    samples_planned: AtomicU64,
+    samples_done: AtomicU64,
+    finished: AtomicBool,
+    /// Latest preview, encoded as PNG.
+    preview: Mutex<Option<Vec<u8>>>,
+    settings: Mutex<PostSettings>,
+}
+
+impl RenderMonitor {
+    pub fn new(settings: PostSettings) -> Self {
+        Self {
+            start: Instant::now(),
+            samples_planned: AtomicU64::new(0),
+            samples_done: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+            preview: Mutex::new(None),
+            settings: Mutex::new(settings),
+        }
+    }
+
+    /// Starts counting towards `planned` camera samples, e.g. for the next frame.
+    pub fn begin(&self, planned: u64) {
+        self.samples_planned.store(planned, Ordering::Relaxed);
+        self.samples_done.store(0, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, samples: u64) {
+        self.samples_done.fetch_add(samples, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_preview(&self, image: &RgbImage) {
+        let mut png = Cursor::new(Vec::new());
+        if image.write_to(&mut png, ImageFormat::Png).is_ok() {
+            *self.preview.lock().unwrap_or_else(|e| e.into_inner()) = Some(png.into_inner());
+        }
+    }
+
+    /// Latest preview as PNG, or None before the first round of samples is done.
+    pub fn preview(&self) -> Option<Vec<u8>> {
+        self.preview
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn settings(&self) -> PostSettings {
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Applies `key=value` pairs of a query string: `exposure=<scale>` and
+    /// `clamp=<max>`, or `clamp=none` to stop clamping.
+    pub fn update(&self, query: &str) -> Result<PostSettings, String> {
+        let mut settings = self.settings();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let parse = |value: &str| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .ok_or_else(|| format!("Invalid {} '{}'", key, value))
+            };
+            match key {
+                "exposure" => settings.exposure = parse(value)?,
+                "clamp" if value == "none" => settings.clamp = None,
+                "clamp" => settings.clamp = Some(parse(value)?),
+                _ => {
+                    return Err(format!(
+                        "Unknown setting '{}'. Available: exposure, clamp",
+                        key
+                    ));
+                }
+            }
+        }
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+        Ok(settings)
+    }
+
+    /// Progress and settings as a JSON object.
+    pub fn status_json(&self) -> String {
+        let planned = self.samples_planned.load(Ordering::Relaxed);
+        let done = self.samples_done.load(Ordering::Relaxed);
+        let settings = self.settings();
+        format!(
+            "{{\"samples_done\": {}, \"samples_planned\": {}, \"progress\": {:.4}, \"elapsed\": {:.1}, \"finished\": {}, \"exposure\": {}, \"clamp\": {}}}",
+            done,
+            planned,
+            done as f64 / planned.max(1) as f64,
+            self.start.elapsed().as_secs_f64(),
+            self.finished.load(Ordering::Relaxed),
+            settings.exposure,
+            settings
+                .clamp
+                .map_or("null".to_string(), |clamp| clamp.to_string())
+        )
+    }
+}
+
+/// Serves `monitor` over HTTP at `address` (e.g. `127.0.0.1:8080`) from a
+/// background thread, until the process exits:
+///
+/// - `GET /status`: progress and settings as JSON
+/// - `GET /preview.png`: the latest preview
+/// - `GET` or `POST /settings?exposure=2&clamp=10`: adjusts the settings
+///
+/// Requests are handled one at a time; this is for watching a long render, not
+/// for serving the public.
+#[cfg(feature = "http")]
+pub fn serve(monitor: std::sync::Arc<RenderMonitor>, address: &str) -> Result<(), String> {
+    let listener = std::net::TcpListener::bind(address)
+        .map_err(|e| format!("Cannot listen on {}: {}", address, e))?;
+    println!("Monitoring render at http://{}/status", address);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = respond(&monitor, stream) {
+                eprintln!("Monitor request failed: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "http"))]
+pub fn serve(_monitor: std::sync::Arc<RenderMonitor>, _address: &str) -> Result<(), String> {
+    Err("Monitoring needs a build with the http feature: cargo build --features http".to_string())
+}
+
+/// Most bytes of request line and headers read from a client before it is
+/// turned away.
+#[cfg(feature = "http")]
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// How long a client may take to send its request or read the response before
+/// the connection is dropped, so a stalled client cannot block the server.
+#[cfg(feature = "http")]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Answers one request on `stream`, reading only its request line and headers.
+#[cfg(feature = "http")]
+fn respond(monitor: &RenderMonitor, mut stream: std::net::TcpStream) -> std::io::Result<()> {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers end at an empty line; running out of input first means the client
+    // hung up or sent more than the limit
+    let mut complete = request_line.ends_with('\n');
+    let mut header = String::new();
+    while complete {
+        header.clear();
+        let read = reader.read_line(&mut header)?;
+        if !header.ends_with('\n') {
+            complete = false;
+        } else if read <= 2 {
+            break;
+        }
+    }
+    let too_large = !complete && reader.get_ref().limit() == 0;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (status, content_type, body) = match (method, path) {
+        _ if too_large => (
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            b"Request too large\n".to_vec(),
+        ),
+        _ if !complete => (
+            "400 Bad Request",
+            "text/plain",
+            b"Incomplete request\n".to_vec(),
+        ),
+        ("GET", "/" | "/status") => (
+            "200 OK",
+            "application/json",
+            monitor.status_json().into_bytes(),
+        ),
+        ("GET", "/preview.png") => match monitor.preview() {
+            Some(png) => ("200 OK", "image/png", png),
+            None => (
+                "503 Service Unavailable",
+                "text/plain",
+                b"No preview yet\n".to_vec(),
+            ),
+        },
+        ("GET" | "POST", "/settings") => match monitor.update(query) {
+            Ok(_) => (
+                "200 OK",
+                "application/json",
+                monitor.status_json().into_bytes(),
+            ),
+            Err(e) => (
+                "400 Bad Request",
+                "text/plain",
+                format!("{}\n", e).into_bytes(),
+            ),
+        },
+        _ => ("404 Not Found", "text/plain", b"Not found\n".to_vec()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
@@ -0,0 +1,332 @@
+use crate::core::metadata::json_string;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use std::fmt::Write as _;
+
+/// What happened to the path at a recorded vertex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathEvent {
+    /// The ray left the scene and picked up the background.
+    Escaped,
+    /// The surface does not scatter (lights, or rays absorbed by a medium).
+    Absorbed,
+    /// Perfectly specular reflection or refraction.
+    Specular,
+    /// A sampled (diffuse or glossy) bounce.
+    Diffuse,
+    /// A specular bounce widened into a cone by path regularization.
+    Regularized,
+    /// A dielectric surface inside a dielectric of higher priority, which the path
+    /// passed straight through.
+    Passed,
+    /// Russian roulette, a degenerate sample or an object's depth limit ended the path here.
+    Terminated,
+}
+
+/// One vertex of a recorded path: the ray that reached it and what it found there.
+#[derive(Debug, Clone)]
+pub struct PathVertex {
+    /// Vertex whose scattered ray led here. Splitting turns a path into a tree.
+    pub parent: Option<usize>,
+    pub bounce: u32,
+    pub origin: Point3,
+    pub direction: Vec3,
+    /// Hit point, `None` when the ray escaped.
+    pub hit: Option<Point3>,
+    pub event: PathEvent,
+    /// Type name of the material at the hit point, e.g. `Lambertian`.
+    pub material: Option<String>,
+    /// Path throughput arriving at this vertex.
+    pub beta: Color,
+    /// Emission picked up here, after MIS weighting.
+    pub emitted: Color,
+    /// MIS weight applied to `emitted` (1 when emission is not shared with light sampling).
+    pub emission_weight: f64,
+    /// Light reached by next event estimation from this vertex.
+    pub direct: Color,
+    /// Light sampling density of the next event estimation direction.
+    pub light_pdf: f64,
+    /// MIS weight applied to `direct`.
+    pub direct_weight: f64,
+    /// Density of the sampled scattered direction, 0 for specular events.
+    pub scatter_pdf: f64,
+    /// Radiance returned from this vertex towards its parent.
+    pub radiance: Color,
+}
+
+/// All vertices traced for one camera sample.
+#[derive(Debug, Clone, Default)]
+pub struct PathRecord {
+    pub pixel: (u32, u32),
+    pub vertices: Vec<PathVertex>,
+}
+
+impl PathRecord {
+    pub fn new(i: u32, j: u32) -> Self {
+        Self {
+            pixel: (i, j),
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Opens a vertex for `ray` and returns its index.
+    pub fn push(&mut self, parent: Option<usize>, bounce: u32, ray: &Ray, beta: Color) -> usize {
+        self.vertices.push(PathVertex {
+            parent,
+            bounce,
+            origin: ray.orig,
+            direction: ray.dir,
+            hit: None,
+            event: PathEvent::Escaped,
+            material: None,
+            beta,
+            emitted: Color::zeros(),
+            emission_weight: 1.0,
+            direct: Color::zeros(),
+            light_pdf: 0.0,
+            direct_weight: 0.0,
+            scatter_pdf: 0.0,
+            radiance: Color::zeros(),
+        });
+        self.vertices.len() - 1
+    }
+
+    /// Vertex where a non-finite value first appeared: its radiance is not finite
+    /// although everything returned by its children is.
+    pub fn non_finite_origin(&self) -> Option<&PathVertex> {
+        let finite = |c: &Color| c.x.is_finite() && c.y.is_finite() && c.z.is_finite();
+        self.vertices.iter().enumerate().find_map(|(k, v)| {
+            let children_finite = self
+                .vertices
+                .iter()
+                .filter(|child| child.parent == Some(k))
+                .all(|child| finite(&child.radiance));
+            (!finite(&v.radiance) && children_finite).then_some(v)
+        })
+    }
+}
+
+/// Non-finite samples sharing an origin, with the first one found.
+struct OriginGroup {
+    bounce: u32,
+    event: PathEvent,
+    material: String,
+    count: usize,
+    pixel: (u32, u32),
+    hit: Option<Point3>,
+}
+
+/// Summarizes samples with non-finite radiance, grouped by where the value originated.
+pub fn print_non_finite_report(records: &[PathRecord]) {
+    if records.is_empty() {
+        println!("NaN check: no non-finite samples");
+        return;
+    }
+
+    let mut pixels: Vec<(u32, u32)> = records.iter().map(|r| r.pixel).collect();
+    pixels.sort_unstable();
+    pixels.dedup();
+    println!(
+        "NaN check: {} non-finite samples in {} pixels (painted magenta)",
+        records.len(),
+        pixels.len()
+    );
+
+    let mut groups: Vec<OriginGroup> = Vec::new();
+    for record in records {
+        let Some(v) = record.non_finite_origin() else {
+            continue;
+        };
+        let material = v.material.as_deref().unwrap_or("background");
+        match groups
+            .iter_mut()
+            .find(|g| g.bounce == v.bounce && g.event == v.event && g.material == material)
+        {
+            Some(group) => group.count += 1,
+            None => groups.push(OriginGroup {
+                bounce: v.bounce,
+                event: v.event,
+                material: material.to_string(),
+                count: 1,
+                pixel: record.pixel,
+                hit: v.hit,
+            }),
+        }
+    }
+    groups.sort_by_key(|g| std::cmp::Reverse(g.count));
+
+    for g in groups {
+        let location = g.hit.map_or(String::new(), |p| {
+            format!(" at ({:.3}, {:.3}, {:.3})", p.x, p.y, p.z)
+        });
+        println!(
+            "  {} x bounce {} {:?} on {} (first: pixel ({}, {}){})",
+            g.count, g.bounce, g.event, g.material, g.pixel.0, g.pixel.1, location
+        );
+    }
+}
+
+/// Prints the recorded paths bounce by bounce, children indented under their parent,
+/// followed by the mean radiance and the brightest sample.
+pub fn print_report(records: &[PathRecord]) {
+    let mut mean = Color::zeros();
+    let mut brightest: Option<(usize, f64)> = None;
+
+    for (n, record) in records.iter().enumerate() {
+        let radiance = record
+            .vertices
+            .first()
+            .map_or(Color::zeros(), |v| v.radiance);
+        println!(
+            "Pixel ({}, {}) sample {}: radiance {}",
+            record.pixel.0,
+            record.pixel.1,
+            n,
+            format_color(&radiance)
+        );
+        for (k, v) in record.vertices.iter().enumerate() {
+            print_vertex(k, v, "  ".repeat(v.bounce as usize + 1));
+        }
+
+        mean += radiance / records.len() as f64;
+        let luminance = radiance.dot(&Vec3::new(0.2126, 0.7152, 0.0722));
+        if !luminance.is_finite() || brightest.is_none_or(|(_, l)| luminance > l) {
+            brightest = Some((n, luminance));
+        }
+    }
+
+    println!("Mean radiance {}", format_color(&mean));
+    if let Some((n, luminance)) = brightest {
+        println!("Brightest sample {} (luminance {:.4})", n, luminance);
+    }
+}
+
+fn print_vertex(index: usize, v: &PathVertex, indent: String) {
+    let parent = v.parent.map_or("camera".to_string(), |p| format!("#{}", p));
+    let location = v.hit.map_or(String::new(), |p| {
+        format!(" at ({:.3}, {:.3}, {:.3})", p.x, p.y, p.z)
+    });
+    println!(
+        "{}#{} bounce {} from {}: {:?}{}",
+        indent, index, v.bounce, parent, v.event, location
+    );
+    if let Some(material) = &v.material {
+        println!("{}   material {}", indent, material);
+    }
+    println!(
+        "{}   beta {}  emitted {} (MIS {:.4})",
+        indent,
+        format_color(&v.beta),
+        format_color(&v.emitted),
+        v.emission_weight
+    );
+    if v.light_pdf > 0.0 {
+        println!(
+            "{}   direct {}  light pdf {:.4}  (MIS {:.4})",
+            indent,
+            format_color(&v.direct),
+            v.light_pdf,
+            v.direct_weight
+        );
+    }
+    if v.scatter_pdf > 0.0 {
+        println!("{}   scatter pdf {:.4}", indent, v.scatter_pdf);
+    }
+    println!("{}   returns {}", indent, format_color(&v.radiance));
+}
+
+fn format_color(c: &Color) -> String {
+    format!("({:.4}, {:.4}, {:.4})", c.x, c.y, c.z)
+}
+
+/// Writes the recorded paths as JSON, one object per path with its vertices in trace order.
+pub fn save_json(records: &[PathRecord], filename: &str) {
+    let mut json = String::from("{\n  \"paths\": [\n");
+
+    for (n, record) in records.iter().enumerate() {
+        let _ = writeln!(
+            json,
+            "    {{\n      \"pixel\": [{}, {}],\n      \"vertices\": [",
+            record.pixel.0, record.pixel.1
+        );
+        for (k, v) in record.vertices.iter().enumerate() {
+            let _ = write!(
+                json,
+                "        {{\"parent\": {}, \"bounce\": {}, \"event\": \"{:?}\", \"origin\": {}, \"direction\": {}, \"hit\": {}, \"material\": {}, \"beta\": {}, \"emitted\": {}, \"emission_weight\": {}, \"direct\": {}, \"light_pdf\": {}, \"direct_weight\": {}, \"scatter_pdf\": {}, \"radiance\": {}}}",
+                v.parent.map_or("null".to_string(), |p| p.to_string()),
+                v.bounce,
+                v.event,
+                json_vec(&v.origin.coords),
+                json_vec(&v.direction),
+                v.hit.map_or("null".to_string(), |p| json_vec(&p.coords)),
+                v.material
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+                json_vec(&v.beta),
+                json_vec(&v.emitted),
+                json_number(v.emission_weight),
+                json_vec(&v.direct),
+                json_number(v.light_pdf),
+                json_number(v.direct_weight),
+                json_number(v.scatter_pdf),
+                json_vec(&v.radiance),
+            );
+            json.push_str(if k + 1 < record.vertices.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        json.push_str("      ]\n    }");
+        json.push_str(if n + 1 < records.len() { ",\n" } else { "\n" });
+    }
+
+    json.push_str("  ]\n}\n");
+    match std::fs::write(filename, json) {
+        Ok(_) => println!("Path dump saved to {}", filename),
+        Err(e) => eprintln!("Error saving path dump: {}", e),
+    }
+}
+
+/// Writes the recorded paths as OBJ polylines, one object per path.
+/// Escaped rays are drawn with length `miss_length`.
+pub fn save_obj(records: &[PathRecord], filename: &str, miss_length: f64) {
+    let mut obj = String::from("# Recorded light paths\n");
+    let mut next_index = 1;
+
+    for (n, record) in records.iter().enumerate() {
+        let _ = writeln!(obj, "o path{}_{}_{}", n, record.pixel.0, record.pixel.1);
+        for v in &record.vertices {
+            let end = v
+                .hit
+                .unwrap_or(v.origin + v.direction.normalize() * miss_length);
+            let _ = writeln!(obj, "v {} {} {}", v.origin.x, v.origin.y, v.origin.z);
+            let _ = writeln!(obj, "v {} {} {}", end.x, end.y, end.z);
+            let _ = writeln!(obj, "l {} {}", next_index, next_index + 1);
+            next_index += 2;
+        }
+    }
+
+    match std::fs::write(filename, obj) {
+        Ok(_) => println!("Path polylines saved to {}", filename),
+        Err(e) => eprintln!("Error saving path polylines: {}", e),
+    }
+}
+
+/// JSON has no NaN or infinity, which are exactly what a path dump may need to show.
+fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        format!("\"{}\"", x)
+    }
+}
+
+fn json_vec(v: &Vec3) -> String {
+    format!(
+        "[{}, {}, {}]",
+        json_number(v.x),
+        json_number(v.y),
+        json_number(v.z)
+    )
+}
@@ -1,140 +1,1405 @@
 use crate::core::camera::Camera;
+use crate::core::color_space::{OutputColorSpace, TransferFunction};
+use crate::core::cryptomatte::{self, Cryptomatte};
+use crate::core::film::Film;
+use crate::core::glare::Glare;
+use crate::core::heatmap::Heatmap;
 use crate::core::interaction::Interaction;
 use crate::core::interval::Interval;
+use crate::core::lens_effects::LensEffects;
+use crate::core::metadata;
 use crate::core::ray::Ray;
-use crate::core::vec3::Color;
+use crate::core::tiled_exr;
+use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
+use crate::geometry::bvh::{self, TraversalStats};
 use crate::geometry::hittable::Hittable;
+use crate::integrators::budget::{BudgetTracker, RayCounter, RenderBudget};
 use crate::integrators::integrator_trait::Integrator;
-use crate::materials::material_trait::ScatterRecord;
-use crate::sampling::pdf::{HittablePDF, MixturePDF, PDF};
-use image::{ImageBuffer, Rgb, RgbImage};
+use crate::integrators::monitor::RenderMonitor;
+use crate::integrators::path_record::{self, PathEvent, PathRecord, PathVertex};
+use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::sampling::pdf::{ConePDF, CosinePDF, HittablePDF, MixturePDF, PDF, power_heuristic};
+use crate::sampling::random::{degrees_to_radians, random_double, sample_seed, seed_thread};
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+/// Russian roulette starts after this many bounces by default.
+pub const DEFAULT_RR_START_BOUNCE: u32 = 3;
+/// Default upper bound on the number of branches at a split event.
+pub const DEFAULT_MAX_SPLITS: u32 = 4;
+
+/// Auto-exposure preview size, samples and depth: enough to gauge the light level.
+const PREVIEW_WIDTH: u32 = 64;
+const PREVIEW_SAMPLES: u32 = 4;
+const PREVIEW_DEPTH: u32 = 8;
+/// Target of the log-average luminance (Reinhard's key value).
+const MIDDLE_GRAY: f64 = 0.18;
+/// Camera samples per pixel measuring Cryptomatte coverage, at most.
+const CRYPTOMATTE_SAMPLES: u32 = 64;
+/// Rec. 709 luminance weights.
+const LUMINANCE: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+/// Start of a checkpoint file, followed by its format version.
+const CHECKPOINT_MAGIC: &[u8; 4] = b"RTCK";
+const CHECKPOINT_VERSION: u32 = 1;
+/// Checkpointed renders take at most this fraction of the samples per round, so
+/// an interruption loses little work.
+const CHECKPOINT_ROUNDS: u32 = 16;
+
+/// Strategy for combining light and BSDF sampling at non-specular vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightSampling {
+    /// One-sample 50/50 mixture of light and BSDF directions (the books' approach).
+    Mixture,
+    /// Next event estimation with a shadow ray plus BSDF sampling, combined with
+    /// the power heuristic. Specular vertices rely on BSDF sampling alone.
+    Mis,
+}
+
+impl std::str::FromStr for LightSampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mixture" => Ok(Self::Mixture),
+            "mis" => Ok(Self::Mis),
+            _ => Err(format!(
+                "Unknown light sampling '{}'. Available: mixture, mis",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct PathTracer {
     output_filename: String,
+    light_sampling: LightSampling,
+    ao_distance: Option<f64>,
+    rr_start_bounce: u32,
+    max_splits: u32,
+    regularization_angle: f64,
+    nan_check: bool,
+    seed: Option<u64>,
+    /// Index of the progressive pass rendered, offsetting the sample streams.
+    pass: u64,
+    glare: Option<Glare>,
+    lens_effects: Option<LensEffects>,
+    bvh_heatmap: bool,
+    /// Exposure offsets in stops of the extra copies of the beauty image.
+    exposure_bracket: Vec<f64>,
+    bounce_aovs: bool,
+    cryptomatte: bool,
+    budget: RenderBudget,
+    tiled_exr: bool,
+    checkpoints: bool,
+    monitor: Option<Arc<RenderMonitor>>,
+}
+
+/// AOVs the radiance of a sample is split into by the number of bounces of the light
+/// path: emitters seen directly and light scattered once, light scattered twice, and
+/// everything longer. They add up to the beauty image.
+const BOUNCE_AOVS: [&str; 3] = ["direct", "indirect1", "indirect2plus"];
+
+/// How the ray being traced was sampled at the previous vertex, which decides the
+/// MIS weight of the emission it finds.
+#[derive(Debug, Clone, Copy)]
+enum Sampled {
+    /// Camera rays, and BSDF samples with no competing light sampling strategy
+    /// (mixture mode, or a scene without lights): emission counts fully.
+    Unweighted,
+    /// A delta (perfectly specular) lobe. Light sampling can never produce such a
+    /// direction, so the BSDF sample alone accounts for the emission it reaches.
+    Delta,
+    /// A BSDF density at `origin`, combined with light sampling by the power heuristic.
+    Density { origin: Point3, pdf: f64 },
+}
+
+/// Dielectric interiors a path can be nested in at once; deeper ones are ignored.
+const MAX_NESTING: usize = 8;
+
+/// The interior of a dielectric a path has entered.
+#[derive(Debug, Clone, Copy)]
+struct Interior {
+    /// Address of the material, telling interiors apart.
+    id: usize,
+    ior: f64,
+    absorption: Color,
+    priority: u32,
+}
+
+impl Interior {
+    /// The interior of `material`, if it is a dielectric.
+    fn of(material: &dyn Material) -> Option<Self> {
+        Some(Self {
+            id: material as *const dyn Material as *const () as usize,
+            ior: material.ior()?,
+            absorption: material.absorption().unwrap_or_else(Color::zeros),
+            priority: material.priority(),
+        })
+    }
+}
+
+/// The dielectric interiors enclosing a path, in the order entered (Schmidt and
+/// Budge, "Simple Nested Dielectrics in Ray Traced Images", 2002). Where several
+/// overlap, the one of highest priority, the last entered among equals, fills
+/// the space.
+#[derive(Debug, Clone, Copy, Default)]
+struct Interiors {
+    entries: [Option<Interior>; MAX_NESTING],
+    len: usize,
+}
+
+impl Interiors {
+    fn iter(&self) -> impl Iterator<Item = &Interior> {
+        self.entries[..self.len].iter().flatten()
+    }
+
+    /// The interior filling the space the path travels through, excluding `skip`.
+    fn filling(&self, skip: Option<&Interior>) -> Option<&Interior> {
+        self.iter()
+            .filter(|interior| skip.is_none_or(|skip| interior.id != skip.id))
+            .max_by_key(|interior| interior.priority)
+    }
+
+    /// Whether the surfaces of `interior` lie within one of higher priority, so
+    /// paths pass them by.
+    fn hides(&self, interior: &Interior) -> bool {
+        self.filling(Some(interior))
+            .is_some_and(|other| other.priority > interior.priority)
+    }
+
+    /// Index of refraction of the space around `interior`: air unless it is
+    /// nested in another.
+    fn outside_ior(&self, interior: &Interior) -> f64 {
+        self.filling(Some(interior)).map_or(1.0, |other| other.ior)
+    }
+
+    fn absorption(&self) -> Color {
+        self.filling(None)
+            .map_or_else(Color::zeros, |interior| interior.absorption)
+    }
+
+    /// Notes the path entering or leaving `interior` through one of its surfaces.
+    /// Leaving an interior never entered, as when the camera starts inside, is a
+    /// no-op.
+    fn cross(&mut self, interior: Interior, entering: bool) {
+        if entering {
+            if self.len < MAX_NESTING {
+                self.entries[self.len] = Some(interior);
+                self.len += 1;
+            }
+        } else if let Some(index) = self.entries[..self.len]
+            .iter()
+            .rposition(|entry| entry.is_some_and(|entry| entry.id == interior.id))
+        {
+            self.entries[index..self.len].rotate_left(1);
+            self.len -= 1;
+            self.entries[self.len] = None;
+        }
+    }
+}
+
+/// Per-path state threaded through the recursive estimator.
+#[derive(Debug, Clone, Copy)]
+struct PathState<'a> {
+    /// Remaining bounces before the path is cut off.
+    depth: u32,
+    /// Bounces taken so far.
+    bounce: u32,
+    /// Path throughput up to the current vertex.
+    beta: Color,
+    /// Whether the path has already passed a non-specular vertex.
+    after_diffuse: bool,
+    /// Specular bounces since the last non-specular vertex.
+    specular_depth: u32,
+    /// How the incoming ray was sampled.
+    sampled: Sampled,
+    /// Dielectrics the ray travels inside.
+    interiors: Interiors,
+    /// Recorder for path dumps, with the index of the current vertex (or, before the
+    /// vertex is opened, of its parent).
+    record: Option<&'a RefCell<PathRecord>>,
+    vertex: Option<usize>,
+    /// Radiance of the sample so far, binned into the `BOUNCE_AOVS`.
+    bounces: Option<&'a RefCell<[Color; 3]>>,
+}
+
+impl<'a> PathState<'a> {
+    fn new(max_depth: u32) -> Self {
+        Self {
+            depth: max_depth,
+            bounce: 0,
+            beta: Color::new(1.0, 1.0, 1.0),
+            after_diffuse: false,
+            specular_depth: 0,
+            sampled: Sampled::Unweighted,
+            interiors: Interiors::default(),
+            record: None,
+            vertex: None,
+            bounces: None,
+        }
+    }
+
+    fn with_record(mut self, record: &'a RefCell<PathRecord>) -> Self {
+        self.record = Some(record);
+        self
+    }
+
+    fn with_bounces(mut self, bounces: &'a RefCell<[Color; 3]>) -> Self {
+        self.bounces = Some(bounces);
+        self
+    }
+
+    /// Bins `radiance`, reaching the current vertex after `extra` more bounces, into
+    /// the AOV of the path's length; a no-op unless the bounce AOVs are on.
+    fn bin(&self, radiance: &Color, extra: u32) {
+        if let Some(bounces) = self.bounces {
+            let aov = (self.bounce + extra).saturating_sub(1).min(2) as usize;
+            bounces.borrow_mut()[aov] += self.beta.component_mul(radiance);
+        }
+    }
+
+    /// Updates the current vertex of a recorded path; a no-op otherwise.
+    fn note(&self, f: impl FnOnce(&mut PathVertex)) {
+        if let (Some(record), Some(index)) = (self.record, self.vertex) {
+            f(&mut record.borrow_mut().vertices[index]);
+        }
+    }
+
+    /// Updates the interiors the ray leaving `isect` towards `direction` travels
+    /// through: entering the material's after refracting into it, leaving it after
+    /// refracting out, unchanged after reflecting or at surfaces rays never enter.
+    fn crossing(mut self, isect: &Interaction, material: &dyn Material, direction: &Vec3) -> Self {
+        if let Some(interior) = Interior::of(material)
+            && direction.dot(&isect.geometry_normal) < 0.0
+        {
+            self.interiors.cross(interior, isect.front_face);
+        }
+        self
+    }
+
+    /// State after a bounce through a sampled (`diffuse`) or delta lobe.
+    fn next(&self, weight: &Color, diffuse: bool) -> Self {
+        Self {
+            depth: self.depth - 1,
+            bounce: self.bounce + 1,
+            beta: self.beta.component_mul(weight),
+            after_diffuse: self.after_diffuse || diffuse,
+            specular_depth: if diffuse { 0 } else { self.specular_depth + 1 },
+            sampled: if diffuse {
+                Sampled::Unweighted
+            } else {
+                Sampled::Delta
+            },
+            interiors: self.interiors,
+            record: self.record,
+            vertex: self.vertex,
+            bounces: self.bounces,
+        }
+    }
 }
 
 impl PathTracer {
     pub fn new(output_filename: &str) -> Self {
         Self {
             output_filename: output_filename.to_string(),
+            light_sampling: LightSampling::Mis,
+            ao_distance: None,
+            rr_start_bounce: DEFAULT_RR_START_BOUNCE,
+            max_splits: DEFAULT_MAX_SPLITS,
+            regularization_angle: 0.0,
+            nan_check: false,
+            seed: None,
+            pass: 0,
+            glare: None,
+            lens_effects: None,
+            bvh_heatmap: false,
+            exposure_bracket: Vec::new(),
+            bounce_aovs: false,
+            cryptomatte: false,
+            budget: RenderBudget::default(),
+            tiled_exr: false,
+            checkpoints: false,
+            monitor: None,
         }
     }
 
+    /// Writes the images to `output_filename` instead, e.g. for each frame of a
+    /// sequence rendered with the same settings.
+    pub fn with_output(mut self, output_filename: &str) -> Self {
+        self.output_filename = output_filename.to_string();
+        self
+    }
+
+    pub fn with_light_sampling(mut self, light_sampling: LightSampling) -> Self {
+        self.light_sampling = light_sampling;
+        self
+    }
+
+    /// Enables path regularization: after the first non-specular bounce, perfectly
+    /// specular lobes are widened into a cone of half-angle `angle` (degrees), so
+    /// light sampling can reach light-specular-diffuse (caustic) paths.
+    pub fn with_regularization(mut self, angle: f64) -> Self {
+        self.regularization_angle = degrees_to_radians(angle);
+        self
+    }
+
+    /// Configures Russian roulette and path splitting.
+    /// Roulette starts after `rr_start_bounce` bounces; `max_splits = 1` disables splitting.
+    pub fn with_roulette(mut self, rr_start_bounce: u32, max_splits: u32) -> Self {
+        self.rr_start_bounce = rr_start_bounce;
+        self.max_splits = max_splits.max(1);
+        self
+    }
+
+    /// Enables the ambient occlusion AOV, counting occluders up to `distance`.
+    pub fn with_ao(mut self, distance: f64) -> Self {
+        self.ao_distance = Some(distance);
+        self
+    }
+
+    /// Records where non-finite samples originate instead of silently dropping them.
+    /// Affected pixels are painted magenta and a report is printed after the render.
+    pub fn with_nan_check(mut self) -> Self {
+        self.nan_check = true;
+        self
+    }
+
+    /// Makes renders reproducible: every sample draws from its own stream, seeded from
+    /// `seed`, the pixel and the sample index, so the result is bit-identical
+    /// regardless of the number of threads.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Renders progressive pass `pass`: samples `pass * spp` to `(pass + 1) * spp - 1`
+    /// of each pixel's sequence rather than the first `spp`. With a seed, the streams
+    /// of a pass depend only on the seed, the pixel and the pass, so passes rendered
+    /// later, e.g. after a cancelled run, continue the sequence instead of repeating
+    /// the samples of earlier passes, and averaging passes 0 to N - 1 matches one
+    /// render with N times the samples.
+    pub fn with_pass(mut self, pass: u64) -> Self {
+        self.pass = pass;
+        self
+    }
+
+    /// Adds starburst glare around directly visible lights, also saving the light AOV
+    /// it is computed from.
+    pub fn with_glare(mut self, glare: Glare) -> Self {
+        self.glare = Some(glare);
+        self
+    }
+
+    /// Applies chromatic aberration and vignetting to the beauty image before it is saved.
+    pub fn with_lens_effects(mut self, lens_effects: LensEffects) -> Self {
+        self.lens_effects = Some(lens_effects);
+        self
+    }
+
+    /// Also saves the beauty image at each exposure offset of `stops` (EV, e.g.
+    /// -2, 0 and +2), as `<name>_ev-2.png` and so on, to check the dynamic range
+    /// without rendering again.
+    pub fn with_exposure_bracket(mut self, stops: Vec<f64>) -> Self {
+        self.exposure_bracket = stops;
+        self
+    }
+
+    /// Also saves the radiance split by path length into the `BOUNCE_AOVS`, as
+    /// `<name>_direct.png`, `<name>_indirect1.png` and `<name>_indirect2plus.png`.
+    pub fn with_bounce_aovs(mut self) -> Self {
+        self.bounce_aovs = true;
+        self
+    }
+
+    /// Also saves Cryptomatte object and material mattes, keyed by the names of
+    /// `Named` objects and of material types, to `<name>_cryptomatte.exr`.
+    pub fn with_cryptomatte(mut self) -> Self {
+        self.cryptomatte = true;
+        self
+    }
+
+    /// Saves heatmaps of the BVH node and primitive tests per sample of every pixel,
+    /// counting all rays the pixel traces (camera, bounce, shadow and AOV rays).
+    pub fn with_bvh_heatmap(mut self) -> Self {
+        self.bvh_heatmap = true;
+        self
+    }
+
+    /// Stops taking samples once `budget` is spent and saves the partial image,
+    /// each pixel averaging the samples it got. Samples are taken in rounds over
+    /// the whole image, so it is evenly noisy rather than partly black.
+    pub fn with_budget(mut self, budget: RenderBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Writes the beauty image as a tiled float EXR, `<output>.exr`, tile by tile
+    /// as they finish, instead of keeping films of the whole image: for renders too
+    /// large for memory. Values are linear. AOVs and post effects need the whole
+    /// image and are skipped, and under a budget, tiles not reached stay black.
+    pub fn with_tiled_exr(mut self) -> Self {
+        self.tiled_exr = true;
+        self
+    }
+
+    /// Saves the pixel sums to `<output>.ckpt` after every round of samples and
+    /// resumes from that file if a render of the same size and samples left one
+    /// behind, so an interrupted render picks up where it stopped. The checkpoint
+    /// is removed once the images are saved; tiled EXR renders take none.
+    pub fn with_checkpoints(mut self) -> Self {
+        self.checkpoints = true;
+        self
+    }
+
+    /// Reports progress to `monitor` and renders in rounds, leaving a preview in it
+    /// after each, and saves the image with its post-processing settings as they
+    /// are when the render ends.
+    pub fn with_monitor(mut self, monitor: Arc<RenderMonitor>) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Radiance arriving along `ray`, for integrators that generate their own rays.
+    pub fn radiance(
+        &self,
+        ray: &Ray,
+        max_depth: u32,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        background: &Color,
+    ) -> Color {
+        self.li(ray, PathState::new(max_depth), world, lights, background)
+    }
+
+    /// Cosine-sampled ambient occlusion at the primary hit.
+    /// Returns 1 for unoccluded (or missed) rays and 0 when an occluder lies within `distance`.
+    fn ambient_occlusion(&self, ray: &Ray, world: &dyn Hittable, distance: f64) -> f64 {
+        let Some(isect) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) else {
+            return 1.0;
+        };
+
+        let direction = CosinePDF::new(&isect.geometry_normal).generate();
+        if direction.near_zero() {
+            return 1.0;
+        }
+
+        let occlusion_ray = Ray::new(isect.p, direction.normalize(), ray.time);
+        if world
+            .hit(&occlusion_ray, Interval::new(0.001, distance))
+            .is_some()
+        {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Emission of the surface seen directly along a camera ray, for the light AOV.
+    fn visible_emission(&self, ray: &Ray, world: &dyn Hittable) -> Color {
+        let Some(isect) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) else {
+            return Color::zeros();
+        };
+        match &isect.material {
+            Some(m) => m.emitted(ray, &isect, isect.uv.0, isect.uv.1, &isect.p),
+            None => Color::zeros(),
+        }
+    }
+
+    /// Traces `count` camera samples through pixel (i, j), recording every vertex.
+    pub fn record_paths(
+        &self,
+        i: u32,
+        j: u32,
+        count: u32,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        camera: &Camera,
+    ) -> Vec<PathRecord> {
+        (0..count)
+            .map(|_| {
+                let record = RefCell::new(PathRecord::new(i, j));
+                let state = PathState::new(camera.max_depth).with_record(&record);
+                self.li(
+                    &camera.get_ray(i, j),
+                    state,
+                    world,
+                    lights,
+                    &camera.background,
+                );
+                record.into_inner()
+            })
+            .collect()
+    }
+
     /// Li (Incoming Light)
     fn li(
         &self,
         ray: &Ray,
-        depth: u32,
+        mut state: PathState,
         world: &dyn Hittable,
         lights: Option<&Arc<dyn Hittable>>,
         background: &Color,
     ) -> Color {
-        // Stop recursion
-        if depth == 0 {
+        let Some(record) = state.record else {
+            return self.li_vertex(ray, state, world, lights, background);
+        };
+        if state.depth == 0 {
             return Color::zeros();
         }
 
-        let mut isect = Interaction::default();
+        let index = record
+            .borrow_mut()
+            .push(state.vertex, state.bounce, ray, state.beta);
+        state.vertex = Some(index);
+        let radiance = self.li_vertex(ray, state, world, lights, background);
+        record.borrow_mut().vertices[index].radiance = radiance;
+        radiance
+    }
+
+    /// Evaluates the vertex found along `ray` and recurses along the scattered rays.
+    fn li_vertex(
+        &self,
+        ray: &Ray,
+        state: PathState,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        background: &Color,
+    ) -> Color {
+        // Stop recursion
+        if state.depth == 0 {
+            return Color::zeros();
+        }
 
         // Ray intersection test
-        if !world.hit(ray, Interval::new(0.001, f64::INFINITY), &mut isect) {
+        let Some(isect) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) else {
+            state.note(|v| v.emitted = *background);
+            state.bin(background, 0);
             return *background;
+        };
+
+        // Beer-Lambert absorption of the segment inside a dielectric
+        let absorption = state.interiors.absorption();
+        if absorption == Color::zeros() {
+            return self.shade(ray, &isect, state, world, lights, background);
         }
+        let transmittance = (-absorption * isect.t * ray.dir.norm()).map(f64::exp);
+        let mut state = state;
+        state.beta = state.beta.component_mul(&transmittance);
+        self.shade(ray, &isect, state, world, lights, background)
+            .component_mul(&transmittance)
+    }
 
+    /// Shades the vertex `isect` found along `ray`.
+    fn shade(
+        &self,
+        ray: &Ray,
+        isect: &Interaction,
+        state: PathState,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        background: &Color,
+    ) -> Color {
         let material = match &isect.material {
             Some(m) => m,
-            None => return Color::new(1.0, 0.0, 1.0),
+            None => {
+                state.bin(&Color::new(1.0, 0.0, 1.0), 0);
+                return Color::new(1.0, 0.0, 1.0);
+            }
         };
+        state.note(|v| {
+            v.hit = Some(isect.p);
+            v.material = Some(material.name().to_string());
+        });
 
-        let emission = material.emitted(ray, &isect, isect.uv.0, isect.uv.1, &isect.p);
+        // Surfaces of dielectrics inside one of higher priority are not there: the
+        // path goes on straight through, only noting that it entered or left
+        let interior = Interior::of(&**material);
+        if let Some(interior) = interior
+            && state.interiors.hides(&interior)
+        {
+            state.note(|v| v.event = PathEvent::Passed);
+            let mut next = state;
+            next.interiors.cross(interior, isect.front_face);
+            let ray = Ray {
+                orig: isect.p,
+                ..*ray
+            };
+            return self.li(&ray, next, world, lights, background);
+        }
+        // and the others refract relative to the dielectric around them
+        let nested;
+        let isect = match interior.map(|interior| state.interiors.outside_ior(&interior)) {
+            Some(outside_ior) if outside_ior != 1.0 => {
+                nested = Interaction {
+                    outside_ior,
+                    ..isect.clone()
+                };
+                &nested
+            }
+            _ => isect,
+        };
+
+        let mut emission = material.emitted(ray, isect, isect.uv.0, isect.uv.1, &isect.p);
+
+        // Emitters reached by BSDF sampling share their contribution with light sampling;
+        // after delta lobes and unweighted samples it counts fully, as does the emission
+        // of media, which light sampling never reaches
+        if let (Sampled::Density { origin, pdf }, Some(light_objects)) = (state.sampled, lights)
+            && emission != Color::zeros()
+            && !isect.medium
+        {
+            let light_pdf = light_objects.pdf_value(&origin, &ray.dir, ray.time);
+            let weight = power_heuristic(pdf, light_pdf);
+            emission *= weight;
+            state.note(|v| v.emission_weight = weight);
+        }
+        state.note(|v| v.emitted = emission);
+        state.bin(&emission, 0);
 
         let mut srec = ScatterRecord::default();
-        if !material.scatter(ray, &isect, &mut srec) {
+        if !material.scatter(ray, isect, &mut srec) {
+            state.note(|v| v.event = PathEvent::Absorbed);
             return emission;
         }
 
-        if srec.skip_pdf {
-            return emission
-                + srec.attenuation.component_mul(&self.li(
-                    &srec.skip_pdf_ray,
-                    depth - 1,
-                    world,
-                    lights,
-                    background,
-                ));
+        // Regularized specular vertices are treated as glossy and join light sampling below
+        let regularize = srec.skip_pdf && state.after_diffuse && self.regularization_angle > 0.0;
+
+        if srec.skip_pdf && !regularize {
+            // Objects may cut long specular chains (e.g. nested glass) short
+            if let Some(limit) = isect.max_specular_depth
+                && state.specular_depth >= limit
+            {
+                state.note(|v| v.event = PathEvent::Terminated);
+                return emission;
+            }
+
+            state.note(|v| v.event = PathEvent::Specular);
+            // Split high-throughput specular/glossy events into several branches
+            let splits = self.split_count(&state.beta.component_mul(&srec.attenuation));
+            let mut sum = Color::zeros();
+
+            for n in 0..splits {
+                // Every extra branch draws a fresh specular sample
+                if n > 0 {
+                    srec = ScatterRecord::default();
+                    if !material.scatter(ray, isect, &mut srec) {
+                        continue;
+                    }
+                }
+
+                let weight = srec.attenuation / splits as f64;
+                if let Some((next, compensation)) =
+                    self.continue_path(state, &weight, false, isect.medium)
+                {
+                    let next = next.crossing(isect, &**material, &srec.skip_pdf_ray.dir);
+                    let sample_color = self.li(&srec.skip_pdf_ray, next, world, lights, background);
+                    sum += weight.component_mul(&sample_color) * compensation;
+                }
+            }
+
+            return emission + sum;
         }
 
-        let p: Arc<dyn PDF> = if let Some(light_objects) = lights {
-            let light_pdf = Arc::new(HittablePDF::new(light_objects.clone(), isect.p));
-            let mat_pdf = srec.pdf_ptr.unwrap();
-            Arc::new(MixturePDF::new(light_pdf, mat_pdf))
-        } else {
-            srec.pdf_ptr.unwrap()
+        state.note(|v| {
+            v.event = if regularize {
+                PathEvent::Regularized
+            } else {
+                PathEvent::Diffuse
+            }
+        });
+        let lobe = regularize.then(|| {
+            Arc::new(ConePDF::new(
+                &srec.skip_pdf_ray.dir,
+                self.regularization_angle,
+            ))
+        });
+        let mat_pdf: Arc<dyn PDF> = match &lobe {
+            Some(lobe) => lobe.clone(),
+            None => srec.pdf_ptr.unwrap(),
+        };
+
+        let scattering_pdf_of = |direction: &Vec3| match &lobe {
+            Some(lobe) => lobe.value(direction),
+            None => {
+                let scattered = Ray::new(isect.p, *direction, ray.time);
+                material.scattering_pdf(ray, isect, &scattered)
+            }
+        };
+
+        let mut direct = Color::zeros();
+        let p: Arc<dyn PDF> = match (self.light_sampling, lights) {
+            (LightSampling::Mixture, Some(light_objects)) => {
+                let light_pdf =
+                    Arc::new(HittablePDF::new(light_objects.clone(), isect.p, ray.time));
+                Arc::new(MixturePDF::new(light_pdf, mat_pdf))
+            }
+            (LightSampling::Mis, Some(light_objects)) => {
+                direct = self.sample_light(
+                    ray,
+                    isect,
+                    light_objects,
+                    world,
+                    &*mat_pdf,
+                    &srec.attenuation,
+                    &scattering_pdf_of,
+                    &state,
+                );
+                mat_pdf
+            }
+            (_, None) => mat_pdf,
         };
 
         let scattered_direction = p.generate();
         let scattered_ray = Ray::new(isect.p, scattered_direction, ray.time);
 
-        let pdf_val = p.value(&scattered_direction);
+        // Light sampling yields no direction toward emitters seen from their dark side
+        let pdf_val = if scattered_direction.near_zero() {
+            0.0
+        } else {
+            p.value(&scattered_direction)
+        };
+        state.note(|v| v.scatter_pdf = pdf_val);
 
         if pdf_val < 1e-5 {
-            return emission;
+            state.note(|v| v.event = PathEvent::Terminated);
+            return emission + direct;
+        }
+
+        let scattering_pdf = scattering_pdf_of(&scattered_direction);
+        let weight = srec.attenuation * scattering_pdf / pdf_val;
+
+        let (mut next, compensation) = match self.continue_path(state, &weight, true, isect.medium)
+        {
+            Some(result) => result,
+            None => {
+                state.note(|v| v.event = PathEvent::Terminated);
+                return emission + direct;
+            }
+        };
+
+        if self.light_sampling == LightSampling::Mis && lights.is_some() {
+            next.sampled = Sampled::Density {
+                origin: isect.p,
+                pdf: pdf_val,
+            };
+        }
+
+        let next = next.crossing(isect, &**material, &scattered_direction);
+        let sample_color = self.li(&scattered_ray, next, world, lights, background);
+
+        emission + direct + weight.component_mul(&sample_color) * compensation
+    }
+
+    /// Next event estimation: samples a direction towards the lights, traces it
+    /// and returns the MIS-weighted radiance emitted by whatever it reaches.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_light(
+        &self,
+        ray: &Ray,
+        isect: &Interaction,
+        lights: &Arc<dyn Hittable>,
+        world: &dyn Hittable,
+        mat_pdf: &dyn PDF,
+        attenuation: &Color,
+        scattering_pdf_of: &dyn Fn(&Vec3) -> f64,
+        state: &PathState,
+    ) -> Color {
+        let direction = lights.random(&isect.p, ray.time);
+        if direction.near_zero() {
+            return Color::zeros();
+        }
+
+        let light_pdf = lights.pdf_value(&isect.p, &direction, ray.time);
+        state.note(|v| v.light_pdf = light_pdf);
+        if light_pdf < 1e-5 {
+            return Color::zeros();
+        }
+
+        let scattering_pdf = scattering_pdf_of(&direction);
+        if scattering_pdf <= 0.0 {
+            return Color::zeros();
         }
 
-        let scattering_pdf = material.scattering_pdf(ray, &isect, &scattered_ray);
+        // The first opaque surface along the shadow ray decides what is seen,
+        // dimmed by the media in front of it
+        let shadow_ray = Ray::new(isect.p, direction, ray.time);
+        let Some(light_isect) = world.intersect_p(&shadow_ray, Interval::new(0.001, f64::INFINITY))
+        else {
+            return Color::zeros();
+        };
+        let transmittance = world.transmittance(&shadow_ray, Interval::new(0.001, light_isect.t));
 
-        let sample_color = self.li(&scattered_ray, depth - 1, world, lights, background);
+        let le = match &light_isect.material {
+            Some(m) => m.emitted(
+                &shadow_ray,
+                &light_isect,
+                light_isect.uv.0,
+                light_isect.uv.1,
+                &light_isect.p,
+            ),
+            None => return Color::zeros(),
+        };
 
-        emission + srec.attenuation.component_mul(&sample_color) * scattering_pdf / pdf_val
+        let weight = power_heuristic(light_pdf, mat_pdf.value(&direction));
+        let direct =
+            attenuation.component_mul(&le).component_mul(&transmittance) * scattering_pdf * weight
+                / light_pdf;
+        state.note(|v| {
+            v.direct = direct;
+            v.direct_weight = weight;
+        });
+        state.bin(&direct, 1);
+        direct
     }
 
-    fn calculate_pixel_color(
+    /// Advances the path by one bounce with the given vertex `weight`, applying
+    /// Russian roulette on the max-component throughput. Scattering events in media
+    /// (`in_medium`) roulette from the first bounce: dense media produce long chains of
+    /// such events, each dimming the path by the albedo, that are rarely worth tracing.
+    /// Returns the next state and the compensation factor, or None if the path is terminated.
+    fn continue_path<'a>(
+        &self,
+        state: PathState<'a>,
+        weight: &Color,
+        diffuse: bool,
+        in_medium: bool,
+    ) -> Option<(PathState<'a>, f64)> {
+        let mut next = state.next(weight, diffuse);
+        if next.bounce < self.rr_start_bounce && !in_medium {
+            return Some((next, 1.0));
+        }
+
+        let q = next.beta.max().min(1.0);
+        if q <= 0.0 || random_double() >= q {
+            return None;
+        }
+
+        next.beta /= q;
+        Some((next, 1.0 / q))
+    }
+
+    /// Fraction of pixel (i, j) covered by each named object and by each material,
+    /// from the surfaces first seen by its camera samples. With a seed, the samples
+    /// are the first camera rays of the beauty render, so mattes line up with it.
+    fn coverage(
         &self,
         i: u32,
         j: u32,
         world: &dyn Hittable,
+        camera: &Camera,
+    ) -> (BTreeMap<String, f64>, BTreeMap<String, f64>) {
+        let samples = camera.samples_per_pixel.clamp(1, CRYPTOMATTE_SAMPLES);
+        let weight = 1.0 / samples as f64;
+        let mut objects = BTreeMap::new();
+        let mut materials = BTreeMap::new();
+        for sample in 0..samples {
+            if let Some(seed) = self.seed {
+                let pixel = j as u64 * camera.image_width as u64 + i as u64;
+                let first_sample = self.pass * camera.samples_per_pixel as u64;
+                seed_thread(sample_seed(seed, pixel, first_sample + sample as u64));
+            }
+
+            let Some(isect) = world.hit(&camera.get_ray(i, j), Interval::new(0.001, f64::INFINITY))
+            else {
+                continue;
+            };
+            if let Some(tag) = &isect.object {
+                *objects.entry(tag.name.to_string()).or_insert(0.0) += weight;
+            }
+            if let Some(material) = &isect.material {
+                *materials.entry(material.name().to_string()).or_insert(0.0) += weight;
+            }
+        }
+        (objects, materials)
+    }
+
+    /// Renders the beauty image straight into a tiled EXR, each tile taking all its
+    /// samples before it is written.
+    fn render_tiled_exr(
+        &self,
+        world: &dyn Hittable,
         lights: Option<&Arc<dyn Hittable>>,
         camera: &Camera,
-    ) -> Color {
-        let mut pixel_color = Color::zeros();
-        for _ in 0..camera.samples_per_pixel {
+    ) {
+        let (width, height) = (camera.image_width, camera.image_height);
+        let filename = match self.output_filename.rsplit_once('.') {
+            Some((stem, _)) => format!("{}.exr", stem),
+            None => format!("{}.exr", self.output_filename),
+        };
+        if self.ao_distance.is_some()
+            || self.bounce_aovs
+            || self.cryptomatte
+            || self.bvh_heatmap
+            || self.glare.is_some()
+            || self.lens_effects.is_some()
+            || !self.exposure_bracket.is_empty()
+        {
+            eprintln!(
+                "Tiled EXR output holds the beauty image only: AOVs, glare, lens effects and exposure brackets are skipped"
+            );
+        }
+        println!(
+            "Rendering {}x{} image with {} SPP into {}...",
+            width, height, camera.samples_per_pixel, filename
+        );
+
+        let samples_per_pixel = camera.samples_per_pixel;
+        let progress_bar =
+            samples_progress_bar(width as u64 * height as u64 * samples_per_pixel as u64);
+        let start_time = std::time::Instant::now();
+        let budget = self
+            .budget
+            .is_limited()
+            .then(|| BudgetTracker::start(self.budget));
+        let counted = RayCounter::new(world);
+        let world: &dyn Hittable = if budget.is_some() { &counted } else { world };
+
+        // EXR holds scene-referred values: the output primaries apply, the transfer
+        // function and LUT, meant for display, do not
+        let color_space = OutputColorSpace {
+            transfer: TransferFunction::Linear,
+            ..camera.color_space
+        };
+        let non_finite_samples = Mutex::new(Vec::new());
+        let result = tiled_exr::render(&filename, width, height, |tile| {
+            let mut colors = Vec::with_capacity((tile.width * tile.height) as usize);
+            for j in tile.y..tile.y + tile.height {
+                for i in tile.x..tile.x + tile.width {
+                    let sums = self.calculate_pixel_color(
+                        (i, j),
+                        world,
+                        lights,
+                        camera,
+                        0..samples_per_pixel,
+                        budget.as_ref(),
+                    );
+                    let color = if !sums.non_finite.is_empty() {
+                        non_finite_samples.lock().unwrap().extend(sums.non_finite);
+                        Color::new(1.0, 0.0, 1.0)
+                    } else if sums.taken > 0 {
+                        sums.color * camera.exposure / sums.taken as f64
+                    } else {
+                        Color::zeros()
+                    };
+                    colors.push(color_space.encode(color));
+                }
+            }
+            progress_bar.inc(colors.len() as u64 * samples_per_pixel as u64);
+            colors
+        });
+
+        progress_bar.finish_with_message("Done");
+        println!("Render complete in {:.2?}", start_time.elapsed());
+        metadata::record("render time", format!("{:.2?}", start_time.elapsed()));
+        if let Some(budget) = &budget {
+            budget.report(width as u64 * height as u64 * samples_per_pixel as u64);
+        }
+        if self.nan_check {
+            path_record::print_non_finite_report(&non_finite_samples.into_inner().unwrap());
+        }
+        metadata::save_sidecar(&filename);
+        match result {
+            Ok(()) => println!("Image saved to {}", filename),
+            Err(e) => eprintln!("Error saving image: {}", e),
+        }
+    }
+
+    /// Measures coverage over the whole image and writes the object and material
+    /// mattes.
+    fn save_cryptomatte(&self, world: &dyn Hittable, camera: &Camera) {
+        let (width, height) = (camera.image_width, camera.image_height);
+        let coverages: Vec<_> = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let (i, j) = (index % width, index / width);
+                (i, j, self.coverage(i, j, world, camera))
+            })
+            .collect();
+
+        let mut objects = Cryptomatte::new("CryptoObject", width, height);
+        let mut materials = Cryptomatte::new("CryptoMaterial", width, height);
+        for (i, j, (object_coverage, material_coverage)) in coverages {
+            objects.set(i, j, &object_coverage);
+            materials.set(i, j, &material_coverage);
+        }
+
+        let filename = match self.output_filename.rsplit_once('.') {
+            Some((stem, _)) => format!("{}_cryptomatte.exr", stem),
+            None => format!("{}_cryptomatte.exr", self.output_filename),
+        };
+        let counts = (objects.name_count(), materials.name_count());
+        match cryptomatte::save(&[objects, materials], &filename) {
+            Ok(()) => println!(
+                "Cryptomatte saved to {} ({} objects, {} materials)",
+                filename, counts.0, counts.1
+            ),
+            Err(e) => eprintln!("Error saving Cryptomatte: {}", e),
+        }
+    }
+
+    /// Number of branches to trace at a specular event with throughput `beta`.
+    /// Paths carrying most of their energy are split, and every branch carries
+    /// `1 / splits` of it, so splitting cannot cascade along a path.
+    fn split_count(&self, beta: &Color) -> u32 {
+        let q = beta.max();
+        if !q.is_finite() || q <= 0.0 {
+            return 1;
+        }
+        ((q * self.max_splits as f64) as u32).clamp(1, self.max_splits)
+    }
+
+    /// Exposure that maps the log-average luminance of a quick low-resolution preview
+    /// to middle gray, for scenes whose light levels are not known in advance.
+    pub fn estimate_exposure(
+        &self,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        camera: &Camera,
+    ) -> f64 {
+        let mut preview = camera.clone();
+        preview.image_width = PREVIEW_WIDTH.min(camera.image_width);
+        preview.samples_per_pixel = PREVIEW_SAMPLES;
+        preview.max_depth = camera.max_depth.min(PREVIEW_DEPTH);
+        preview.initialize();
+
+        let pixels: Vec<(u32, u32)> = (0..preview.image_height)
+            .flat_map(|j| (0..preview.image_width).map(move |i| (i, j)))
+            .collect();
+        let luminances: Vec<f64> = pixels
+            .par_iter()
+            .map(|&(i, j)| {
+                let sums = self.calculate_pixel_color(
+                    (i, j),
+                    world,
+                    lights,
+                    &preview,
+                    0..PREVIEW_SAMPLES,
+                    None,
+                );
+                (sums.color / PREVIEW_SAMPLES as f64).dot(&LUMINANCE)
+            })
+            .collect();
+
+        // Black pixels (empty background, unlit voids) would drag the log-average
+        // towards zero, so only lit pixels are metered
+        let lit: Vec<f64> = luminances
+            .into_iter()
+            .filter(|&l| l > 0.0 && l.is_finite())
+            .collect();
+        if lit.is_empty() {
+            return 1.0;
+        }
+        let log_average = (lit.iter().map(|l| l.ln()).sum::<f64>() / lit.len() as f64).exp();
+        MIDDLE_GRAY / log_average
+    }
+
+    /// Sums the radiance, AO and directly visible emission of the pixel's `samples`,
+    /// numbered within the pass, with the BVH traversal work when the heatmap is on
+    /// and the recorded paths of non-finite samples when the NaN check is on.
+    /// Samples stop early once the `budget` runs out.
+    fn calculate_pixel_color(
+        &self,
+        (i, j): (u32, u32),
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        camera: &Camera,
+        samples: Range<u32>,
+        budget: Option<&BudgetTracker>,
+    ) -> PixelSamples {
+        if self.bvh_heatmap {
+            bvh::take_traversal_stats();
+        }
+        let mut sums = PixelSamples::default();
+        let first_sample = self.pass * camera.samples_per_pixel as u64;
+        for sample in samples {
+            if budget.is_some_and(|budget| !budget.charge(1)) {
+                break;
+            }
+            sums.taken += 1;
+            if let Some(seed) = self.seed {
+                let pixel = j as u64 * camera.image_width as u64 + i as u64;
+                seed_thread(sample_seed(seed, pixel, first_sample + sample as u64));
+            }
+
             let r = camera.get_ray(i, j);
-            let sample_color = self.li(&r, camera.max_depth, world, lights, &camera.background);
+            let bounces = RefCell::new([Color::zeros(); 3]);
+            let mut state = PathState::new(camera.max_depth);
+            if self.bounce_aovs {
+                state = state.with_bounces(&bounces);
+            }
+            let record = RefCell::new(PathRecord::new(i, j));
+            let sample_color = if self.nan_check {
+                self.li(
+                    &r,
+                    state.with_record(&record),
+                    world,
+                    lights,
+                    &camera.background,
+                )
+            } else {
+                self.li(&r, state, world, lights, &camera.background)
+            };
 
             if sample_color.x.is_finite()
                 && sample_color.y.is_finite()
                 && sample_color.z.is_finite()
             {
-                pixel_color += sample_color;
+                sums.color += sample_color;
+                for (sum, bin) in sums.bounces.iter_mut().zip(bounces.into_inner()) {
+                    *sum += bin;
+                }
+            } else if self.nan_check {
+                sums.non_finite.push(record.into_inner());
+            }
+
+            if let Some(distance) = self.ao_distance {
+                sums.ao += self.ambient_occlusion(&r, world, distance);
+            }
+            if self.glare.is_some() {
+                sums.light += self.visible_emission(&r, world);
             }
         }
-        pixel_color
+        if self.bvh_heatmap {
+            sums.traversal = bvh::take_traversal_stats();
+        }
+        sums
     }
 }
 
+/// Sums over samples of one pixel, as returned by `calculate_pixel_color`.
+#[derive(Debug, Default)]
+struct PixelSamples {
+    color: Color,
+    bounces: [Color; 3],
+    ao: f64,
+    light: Color,
+    traversal: TraversalStats,
+    non_finite: Vec<PathRecord>,
+    /// Samples taken, fewer than asked for once the budget runs out.
+    taken: u32,
+}
+
+impl PixelSamples {
+    /// Factor bringing the sums up to the full count of samples the films divide
+    /// by, for pixels cut short by the budget; 0 for pixels without samples.
+    fn scale(&self, samples_per_pixel: u32) -> f64 {
+        if self.taken > 0 {
+            samples_per_pixel as f64 / self.taken as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn add(&mut self, other: PixelSamples) {
+        self.color += other.color;
+        for (sum, bin) in self.bounces.iter_mut().zip(other.bounces) {
+            *sum += bin;
+        }
+        self.ao += other.ao;
+        self.light += other.light;
+        self.traversal.node_tests += other.traversal.node_tests;
+        self.traversal.primitive_tests += other.traversal.primitive_tests;
+        self.non_finite.extend(other.non_finite);
+        self.taken += other.taken;
+    }
+
+    /// Writes the sums for a checkpoint. Non-finite sample records are not kept,
+    /// only the samples counted into the sums.
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let colors = [
+            &self.color,
+            &self.bounces[0],
+            &self.bounces[1],
+            &self.bounces[2],
+            &self.light,
+        ];
+        for value in colors.into_iter().flat_map(|c| c.iter()).chain([&self.ao]) {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&self.taken.to_le_bytes())?;
+        out.write_all(&self.traversal.node_tests.to_le_bytes())?;
+        out.write_all(&self.traversal.primitive_tests.to_le_bytes())
+    }
+
+    /// Reads sums written by `write`.
+    fn read(input: &mut impl Read) -> io::Result<Self> {
+        let mut color = || -> io::Result<Color> {
+            Ok(Color::new(
+                read_f64(input)?,
+                read_f64(input)?,
+                read_f64(input)?,
+            ))
+        };
+        let color_sum = color()?;
+        let bounces = [color()?, color()?, color()?];
+        let light = color()?;
+        Ok(Self {
+            color: color_sum,
+            bounces,
+            light,
+            ao: read_f64(input)?,
+            taken: read_u32(input)?,
+            traversal: TraversalStats {
+                node_tests: read_u64(input)?,
+                primitive_tests: read_u64(input)?,
+            },
+            non_finite: Vec::new(),
+        })
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(input: &mut impl Read) -> io::Result<f64> {
+    read_u64(input).map(f64::from_bits)
+}
+
+/// Checkpoint kept while rendering to `output_filename`: `frame_0001.png` is
+/// checkpointed to `frame_0001.ckpt`.
+pub fn checkpoint_filename(output_filename: &str) -> String {
+    Path::new(output_filename)
+        .with_extension("ckpt")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Saves the sums of every pixel, holding the first `done` samples each, to a
+/// temporary file first so that an interruption never leaves a truncated checkpoint.
+fn save_checkpoint(
+    filename: &str,
+    camera: &Camera,
+    done: u32,
+    pixels: &[PixelSamples],
+) -> io::Result<()> {
+    let temporary = format!("{}.tmp", filename);
+    let mut out = BufWriter::new(File::create(&temporary)?);
+    out.write_all(CHECKPOINT_MAGIC)?;
+    let header = [
+        CHECKPOINT_VERSION,
+        camera.image_width,
+        camera.image_height,
+        camera.samples_per_pixel,
+        done,
+    ];
+    for value in header {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    for pixel in pixels {
+        pixel.write(&mut out)?;
+    }
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(&temporary, filename)
+}
+
+/// Sums restored from a checkpoint and the samples per pixel they hold, or None
+/// if there is no readable checkpoint of a render with the camera's size and samples.
+fn load_checkpoint(filename: &str, camera: &Camera) -> Option<(u32, Vec<PixelSamples>)> {
+    let mut input = BufReader::new(File::open(filename).ok()?);
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic).ok()?;
+    let header = (0..5)
+        .map(|_| read_u32(&mut input))
+        .collect::<io::Result<Vec<_>>>()
+        .ok()?;
+    let expected = [
+        CHECKPOINT_VERSION,
+        camera.image_width,
+        camera.image_height,
+        camera.samples_per_pixel,
+    ];
+    if &magic != CHECKPOINT_MAGIC || header[..4] != expected {
+        eprintln!("Ignoring checkpoint {} of a different render", filename);
+        return None;
+    }
+    let pixels = (0..camera.image_width as usize * camera.image_height as usize)
+        .map(|_| PixelSamples::read(&mut input))
+        .collect::<io::Result<Vec<_>>>();
+    match pixels {
+        Ok(pixels) => Some((header[4], pixels)),
+        Err(e) => {
+            eprintln!("Ignoring unreadable checkpoint {}: {}", filename, e);
+            None
+        }
+    }
+}
+
+/// Ranges of samples rendered over the whole image in turn: all of them at once,
+/// or under a budget, doubling rounds (1, 1, 2, 4, ...), so a render cut short
+/// still covers the image evenly, only with fewer samples. Monitored renders use
+/// the same rounds for their previews, and checkpointed renders double too, up
+/// to `CHECKPOINT_ROUNDS`-th of the samples per round.
+fn sample_rounds(samples_per_pixel: u32, progressive: bool, checkpointed: bool) -> Vec<Range<u32>> {
+    let mut rounds = Vec::new();
+    if !progressive && !checkpointed {
+        rounds.push(0..samples_per_pixel);
+        return rounds;
+    }
+    let longest = if checkpointed {
+        samples_per_pixel.div_ceil(CHECKPOINT_ROUNDS)
+    } else {
+        samples_per_pixel
+    };
+    let mut start = 0;
+    while start < samples_per_pixel {
+        let end = (2 * start)
+            .clamp(start + 1, samples_per_pixel)
+            .min(start + longest);
+        rounds.push(start..end);
+        start = end;
+    }
+    rounds
+}
+
 impl Integrator for PathTracer {
     fn render(&self, world: &dyn Hittable, lights: Option<Arc<dyn Hittable>>, camera: &Camera) {
+        if self.tiled_exr {
+            self.render_tiled_exr(world, lights.as_ref(), camera);
+            return;
+        }
+
         let width = camera.image_width;
         let height = camera.image_height;
-        let mut img: RgbImage = ImageBuffer::new(width, height);
+        let new_film = || {
+            Film::new(width, height)
+                .with_exposure(camera.exposure)
+                .with_color_space(camera.color_space)
+                .with_lut(camera.lut.clone())
+        };
+        let mut film = new_film();
+        let mut ao_film = Film::new(width, height).with_color_space(camera.color_space);
+        let mut light_film = new_film();
+        let mut bounce_films = [(); 3].map(|_| new_film());
+        let mut node_heatmap = Heatmap::new(width, height);
+        let mut primitive_heatmap = Heatmap::new(width, height);
 
         println!(
             "Rendering {}x{} image with {} SPP...",
             width, height, camera.samples_per_pixel
         );
 
-        let progress_bar = ProgressBar::new((width * height) as u64);
-        progress_bar.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
-                )
-                .unwrap()
-                .progress_chars("#>-"),
-        );
+        // Progress counts camera samples, advanced once per finished tile so that
+        // threads do not contend for the bar on every pixel
+        let samples_per_pixel = camera.samples_per_pixel as u64;
+        let progress_bar = samples_progress_bar(width as u64 * height as u64 * samples_per_pixel);
 
         // Tile-based rendering for parallel efficiency and progress updates
         let tile_size = 16;
@@ -144,53 +1409,233 @@ impl Integrator for PathTracer {
 
         let start_time = std::time::Instant::now();
 
-        let render_results: Vec<(u32, u32, Rgb<u8>)> = (0..total_tiles)
-            .into_par_iter()
-            .flat_map(|tile_idx| {
-                let tile_x = (tile_idx % num_tiles_x) * tile_size;
-                let tile_y = (tile_idx / num_tiles_x) * tile_size;
-
-                let mut tile_pixels = Vec::new();
-
-                for j in tile_y..std::cmp::min(tile_y + tile_size, height) {
-                    for i in tile_x..std::cmp::min(tile_x + tile_size, width) {
-                        let color =
-                            self.calculate_pixel_color(i, j, world, lights.as_ref(), camera);
-                        tile_pixels.push((i, j, color_to_rgb(color, camera.samples_per_pixel)));
-                        progress_bar.inc(1);
+        // Rays are only counted under a budget, which costs a thread-local increment each
+        let budget = self
+            .budget
+            .is_limited()
+            .then(|| BudgetTracker::start(self.budget));
+        let counted = RayCounter::new(world);
+        let world: &dyn Hittable = if budget.is_some() { &counted } else { world };
+
+        let checkpoint = self
+            .checkpoints
+            .then(|| checkpoint_filename(&self.output_filename));
+        let (mut done, mut pixels) = checkpoint
+            .as_deref()
+            .and_then(|filename| load_checkpoint(filename, camera))
+            .unwrap_or_else(|| {
+                let pixels = (0..width as usize * height as usize)
+                    .map(|_| PixelSamples::default())
+                    .collect();
+                (0, pixels)
+            });
+        if let Some(monitor) = &self.monitor {
+            monitor.begin(width as u64 * height as u64 * samples_per_pixel);
+        }
+        if done > 0 {
+            println!("Resuming from checkpoint with {} SPP done", done);
+            progress_bar.inc(width as u64 * height as u64 * done as u64);
+            if let Some(monitor) = &self.monitor {
+                monitor.advance(width as u64 * height as u64 * done as u64);
+            }
+        }
+        let rounds = sample_rounds(
+            camera.samples_per_pixel,
+            budget.is_some() || self.monitor.is_some(),
+            self.checkpoints,
+        );
+        for round in rounds.into_iter().filter(move |round| round.start >= done) {
+            let round_size = round.len() as u64;
+            let round_results: Vec<_> = (0..total_tiles)
+                .into_par_iter()
+                .flat_map(|tile_idx| {
+                    let tile_x = (tile_idx % num_tiles_x) * tile_size;
+                    let tile_y = (tile_idx / num_tiles_x) * tile_size;
+
+                    let mut tile_pixels = Vec::new();
+
+                    for j in tile_y..std::cmp::min(tile_y + tile_size, height) {
+                        for i in tile_x..std::cmp::min(tile_x + tile_size, width) {
+                            let sums = self.calculate_pixel_color(
+                                (i, j),
+                                world,
+                                lights.as_ref(),
+                                camera,
+                                round.clone(),
+                                budget.as_ref(),
+                            );
+                            tile_pixels.push((i, j, sums));
+                        }
+                    }
+                    progress_bar.inc(tile_pixels.len() as u64 * round_size);
+                    if let Some(monitor) = &self.monitor {
+                        monitor.advance(tile_pixels.len() as u64 * round_size);
                     }
+                    tile_pixels
+                })
+                .collect();
+            for (i, j, sums) in round_results {
+                pixels[film.index(i, j)].add(sums);
+            }
+            if let Some(monitor) = &self.monitor {
+                let settings = monitor.settings();
+                let mut preview = new_film()
+                    .with_exposure(settings.exposure)
+                    .with_clamp(settings.clamp);
+                for (index, sums) in pixels.iter().enumerate() {
+                    let (i, j) = (index as u32 % width, index as u32 / width);
+                    preview.set(i, j, sums.color * sums.scale(camera.samples_per_pixel));
                 }
-                tile_pixels
-            })
-            .collect();
+                monitor.set_preview(&preview.to_image(camera.samples_per_pixel));
+            }
+            if budget.as_ref().is_some_and(BudgetTracker::exhausted) {
+                break;
+            }
+            // A round cut short by the budget is not saved, as its pixels hold
+            // differing numbers of samples
+            done = round.end;
+            if let Some(filename) = &checkpoint
+                && let Err(e) = save_checkpoint(filename, camera, done, &pixels)
+            {
+                eprintln!("Error saving checkpoint {}: {}", filename, e);
+            }
+        }
 
         progress_bar.finish_with_message("Done");
         println!("Render complete in {:.2?}", start_time.elapsed());
+        metadata::record("render time", format!("{:.2?}", start_time.elapsed()));
+        if let Some(budget) = &budget {
+            budget.report(width as u64 * height as u64 * samples_per_pixel);
+        }
+
+        let mut non_finite_samples = Vec::new();
+        let mut bounce_totals = [0.0; 3];
+        for (index, sums) in pixels.into_iter().enumerate() {
+            let (i, j) = (index as u32 % width, index as u32 / width);
+            // Pixels cut short by the budget are scaled up, as the films divide by
+            // the full count of samples
+            let samples = sums.taken.max(1) as f64;
+            let scale = sums.scale(camera.samples_per_pixel);
+            if sums.non_finite.is_empty() {
+                film.set(i, j, sums.color * scale);
+            } else {
+                film.set(i, j, Color::new(1.0, 0.0, 1.0) * samples_per_pixel as f64);
+                non_finite_samples.extend(sums.non_finite);
+            }
+            for (n, bin) in sums.bounces.iter().enumerate() {
+                bounce_films[n].set(i, j, *bin * scale);
+                bounce_totals[n] += bin.dot(&LUMINANCE) * scale;
+            }
+            let ao = sums.ao * scale;
+            ao_film.set(i, j, Color::new(ao, ao, ao));
+            light_film.set(i, j, sums.light * scale);
+            node_heatmap.set(i, j, sums.traversal.node_tests as f64 / samples);
+            primitive_heatmap.set(i, j, sums.traversal.primitive_tests as f64 / samples);
+        }
 
-        for (i, j, pixel) in render_results {
-            img.put_pixel(i, j, pixel);
+        if self.nan_check {
+            path_record::print_non_finite_report(&non_finite_samples);
         }
 
-        match img.save(&self.output_filename) {
-            Ok(_) => println!("Image saved to {}", self.output_filename),
-            Err(e) => eprintln!("Error saving image: {}", e),
+        if let Some(glare) = &self.glare {
+            glare.apply(&mut film, &light_film);
+            light_film.save(
+                &aov_filename(&self.output_filename, "light"),
+                camera.samples_per_pixel,
+            );
+        }
+
+        if let Some(lens_effects) = &self.lens_effects {
+            lens_effects.apply(&mut film, camera);
+        }
+
+        if let Some(monitor) = &self.monitor {
+            let settings = monitor.settings();
+            film = film
+                .with_exposure(settings.exposure)
+                .with_clamp(settings.clamp);
+            monitor.set_preview(&film.to_image(camera.samples_per_pixel));
+            monitor.finish();
+        }
+        film.save(&self.output_filename, camera.samples_per_pixel);
+        if let Some(filename) = &checkpoint
+            && done == camera.samples_per_pixel
+        {
+            let _ = std::fs::remove_file(filename);
+        }
+        for stop in &self.exposure_bracket {
+            film.clone()
+                .with_exposure(camera.exposure * 2f64.powf(*stop))
+                .save(
+                    &aov_filename(&self.output_filename, &format!("ev{:+}", stop)),
+                    camera.samples_per_pixel,
+                );
+        }
+
+        if self.ao_distance.is_some() {
+            ao_film.save(
+                &aov_filename(&self.output_filename, "ao"),
+                camera.samples_per_pixel,
+            );
+        }
+
+        if self.cryptomatte {
+            self.save_cryptomatte(world, camera);
+        }
+
+        if self.bounce_aovs {
+            // Energy shares make integrators comparable bounce by bounce
+            let total: f64 = bounce_totals.iter().sum();
+            let shares: Vec<String> = BOUNCE_AOVS
+                .iter()
+                .zip(bounce_totals)
+                .map(|(name, luminance)| {
+                    format!(
+                        "{} {:.1}%",
+                        name,
+                        100.0 * luminance / total.max(f64::MIN_POSITIVE)
+                    )
+                })
+                .collect();
+            println!("Radiance by bounces: {}", shares.join(", "));
+            for (name, bounce_film) in BOUNCE_AOVS.iter().zip(&bounce_films) {
+                bounce_film.save(
+                    &aov_filename(&self.output_filename, name),
+                    camera.samples_per_pixel,
+                );
+            }
+        }
+
+        if self.bvh_heatmap {
+            println!(
+                "BVH tests per sample: {:.1} nodes, {:.1} primitives on average",
+                node_heatmap.mean(),
+                primitive_heatmap.mean()
+            );
+            node_heatmap.save(&aov_filename(&self.output_filename, "bvh_nodes"));
+            primitive_heatmap.save(&aov_filename(&self.output_filename, "bvh_primitives"));
         }
     }
 }
 
-fn color_to_rgb(color: Color, samples_per_pixel: u32) -> Rgb<u8> {
-    let scale = 1.0 / samples_per_pixel as f64;
-    let r = (linear_to_gamma(color.x * scale)).clamp(0.0, 0.999);
-    let g = (linear_to_gamma(color.y * scale)).clamp(0.0, 0.999);
-    let b = (linear_to_gamma(color.z * scale)).clamp(0.0, 0.999);
-
-    Rgb([(r * 256.0) as u8, (g * 256.0) as u8, (b * 256.0) as u8])
+/// Progress bar counting camera samples.
+fn samples_progress_bar(samples: u64) -> ProgressBar {
+    let progress_bar = ProgressBar::new(samples);
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% {human_pos}/{human_len} samples ({per_sec}, ETA {eta})",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    progress_bar
 }
 
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    if linear_component > 0.0 {
-        linear_component.sqrt()
-    } else {
-        0.0
+/// Derives an AOV output name, e.g. `cornell_box.png` -> `cornell_box_ao.png`.
+pub fn aov_filename(output_filename: &str, aov: &str) -> String {
+    match output_filename.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}_{}.{}", stem, aov, ext),
+        None => format!("{}_{}", output_filename, aov),
     }
 }
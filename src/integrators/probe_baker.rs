@@ -0,0 +1,226 @@
+use crate::core::camera::Camera;
+use crate::core::film::Film;
+use crate::core::metadata::json_string;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::integrators::integrator_trait::Integrator;
+use crate::integrators::path_tracer::{PathTracer, aov_filename};
+use crate::sampling::random::random_double;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+/// Cube faces in OpenGL order: +X, -X, +Y, -Y, +Z, -Z.
+const FACES: usize = 6;
+/// Cell (column, row) of each face in the unfolded horizontal cross.
+const CROSS_CELLS: [(u32, u32); FACES] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+/// Convolution of radiance SH bands with the clamped cosine (Ramamoorthi & Hanrahan).
+const IRRADIANCE_BANDS: [f64; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+
+/// Light probe baker.
+/// Renders a radiance cubemap at each probe position and projects it onto order-2
+/// spherical harmonics of irradiance, as used by real-time engines for ambient lighting.
+/// Cubemaps are saved as horizontal crosses (`<output>_probe<n>.png`, faces of
+/// `image_width / 4` pixels) and the SH coefficients go to `<output>_probes.json`.
+pub struct ProbeBaker {
+    output_filename: String,
+    positions: Vec<Point3>,
+    path_tracer: PathTracer,
+}
+
+impl ProbeBaker {
+    /// `path_tracer` evaluates the probe rays, so its light sampling and roulette
+    /// settings apply to the probes as well.
+    pub fn new(output_filename: &str, positions: Vec<Point3>, path_tracer: PathTracer) -> Self {
+        Self {
+            output_filename: output_filename.to_string(),
+            positions,
+            path_tracer,
+        }
+    }
+
+    /// Renders texel `index` of the six faces, returning the summed radiance samples.
+    fn render_texel(
+        &self,
+        position: &Point3,
+        index: u32,
+        size: u32,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        camera: &Camera,
+    ) -> Color {
+        let (face, i, j) = texel_coords(index, size);
+        let mut sum = Color::zeros();
+        for _ in 0..camera.samples_per_pixel {
+            let s = 2.0 * (i as f64 + random_double()) / size as f64 - 1.0;
+            let t = 2.0 * (j as f64 + random_double()) / size as f64 - 1.0;
+            let ray = Ray::new(*position, face_direction(face, s, t), random_double());
+
+            let sample = self.path_tracer.radiance(
+                &ray,
+                camera.max_depth,
+                world,
+                lights,
+                &camera.background,
+            );
+            if sample.x.is_finite() && sample.y.is_finite() && sample.z.is_finite() {
+                sum += sample;
+            }
+        }
+        sum
+    }
+}
+
+impl Integrator for ProbeBaker {
+    fn render(&self, world: &dyn Hittable, lights: Option<Arc<dyn Hittable>>, camera: &Camera) {
+        let size = (camera.image_width / 4).max(1);
+        let texels_per_probe = FACES as u32 * size * size;
+
+        println!(
+            "Rendering {} light probes ({}x{} faces) with {} SPP...",
+            self.positions.len(),
+            size,
+            size,
+            camera.samples_per_pixel
+        );
+
+        let progress_bar = ProgressBar::new(self.positions.len() as u64 * texels_per_probe as u64);
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+                )
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        let start_time = std::time::Instant::now();
+        let mut json = String::from("{\n  \"probes\": [\n");
+
+        for (n, position) in self.positions.iter().enumerate() {
+            let texels: Vec<Color> = (0..texels_per_probe)
+                .into_par_iter()
+                .map(|index| {
+                    let color =
+                        self.render_texel(position, index, size, world, lights.as_ref(), camera);
+                    progress_bar.inc(1);
+                    color
+                })
+                .collect();
+
+            // Unfold into a horizontal cross
+            let mut film = Film::new(4 * size, 3 * size).with_color_space(camera.color_space);
+            for (index, color) in texels.iter().enumerate() {
+                let (face, i, j) = texel_coords(index as u32, size);
+                let (column, row) = CROSS_CELLS[face];
+                film.set(column * size + i, row * size + j, *color);
+            }
+            let cubemap = aov_filename(&self.output_filename, &format!("probe{}", n));
+            film.save(&cubemap, camera.samples_per_pixel);
+
+            let sh = project_irradiance(&texels, size, camera.samples_per_pixel);
+            let coefficients: Vec<String> = sh
+                .iter()
+                .map(|c| format!("[{:.6}, {:.6}, {:.6}]", c.x, c.y, c.z))
+                .collect();
+            let _ = write!(
+                json,
+                "    {{\n      \"position\": [{}, {}, {}],\n      \"cubemap\": {},\n      \"sh_irradiance\": [{}]\n    }}{}\n",
+                position.x,
+                position.y,
+                position.z,
+                json_string(&cubemap),
+                coefficients.join(", "),
+                if n + 1 < self.positions.len() {
+                    ","
+                } else {
+                    ""
+                }
+            );
+        }
+
+        progress_bar.finish_with_message("Done");
+        println!("Render complete in {:.2?}", start_time.elapsed());
+
+        json.push_str("  ]\n}\n");
+        let json_filename = match self.output_filename.rsplit_once('.') {
+            Some((stem, _)) => format!("{}_probes.json", stem),
+            None => format!("{}_probes.json", self.output_filename),
+        };
+        match std::fs::write(&json_filename, json) {
+            Ok(_) => println!("Probe data saved to {}", json_filename),
+            Err(e) => eprintln!("Error saving probe data: {}", e),
+        }
+    }
+}
+
+/// Splits a texel index into (face, column, row).
+fn texel_coords(index: u32, size: u32) -> (usize, u32, u32) {
+    (
+        (index / (size * size)) as usize,
+        index % size,
+        (index / size) % size,
+    )
+}
+
+/// World-space direction through the point (s, t) in [-1, 1]^2 of a cube face,
+/// with s running left to right and t top to bottom as seen from the center.
+fn face_direction(face: usize, s: f64, t: f64) -> Vec3 {
+    let direction = match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+/// Real spherical harmonics up to order 2, in the usual (l, m) order.
+fn sh_basis(d: &Vec3) -> [f64; 9] {
+    [
+        0.282095,
+        0.488603 * d.y,
+        0.488603 * d.z,
+        0.488603 * d.x,
+        1.092548 * d.x * d.y,
+        1.092548 * d.y * d.z,
+        0.315392 * (3.0 * d.z * d.z - 1.0),
+        1.092548 * d.x * d.z,
+        0.546274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+/// Projects the summed cubemap radiance onto SH and convolves it into irradiance.
+/// Evaluating the result with `sh_basis` gives the irradiance for a normal direction.
+fn project_irradiance(texels: &[Color], size: u32, samples_per_pixel: u32) -> [Color; 9] {
+    let mut sh = [Color::zeros(); 9];
+    let scale = 1.0 / samples_per_pixel as f64;
+
+    for (index, radiance) in texels.iter().enumerate() {
+        let (face, i, j) = texel_coords(index as u32, size);
+        let s = 2.0 * (i as f64 + 0.5) / size as f64 - 1.0;
+        let t = 2.0 * (j as f64 + 0.5) / size as f64 - 1.0;
+
+        // Solid angle subtended by the texel
+        let d_omega = 4.0 / (size * size) as f64 / (1.0 + s * s + t * t).powf(1.5);
+        let basis = sh_basis(&face_direction(face, s, t));
+        for (coefficient, y) in sh.iter_mut().zip(basis) {
+            *coefficient += radiance * (scale * y * d_omega);
+        }
+    }
+
+    for (k, coefficient) in sh.iter_mut().enumerate() {
+        let band = match k {
+            0 => 0,
+            1..=3 => 1,
+            _ => 2,
+        };
+        *coefficient *= IRRADIANCE_BANDS[band];
+    }
+    sh
+}
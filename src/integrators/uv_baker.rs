@@ -0,0 +1,285 @@
+use crate::core::camera::Camera;
+use crate::core::film::Film;
+use crate::core::interval::Interval;
+use crate::core::metadata;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::geometry::triangle::Triangle;
+use crate::geometry::triangle_mesh::{TriangleMesh, face_normal};
+use crate::integrators::integrator_trait::Integrator;
+use crate::integrators::path_tracer::{PathTracer, aov_filename};
+use crate::sampling::pdf::{CosinePDF, PDF};
+use crate::sampling::random::random_double;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Texels of gutter filled around each UV island, hiding seams under bilinear filtering.
+const DILATION_PASSES: u32 = 2;
+
+/// A triangle to bake: its corners, their texture coordinates and its normal.
+#[derive(Debug, Clone, Copy)]
+pub struct BakeFace {
+    pub vertices: [Point3; 3],
+    pub uvs: [(f64, f64); 3],
+    pub normal: Vec3,
+}
+
+impl BakeFace {
+    pub fn from_triangle(triangle: &Triangle) -> Self {
+        Self {
+            vertices: triangle.vertices(),
+            uvs: triangle.uvs(),
+            normal: triangle.normal(),
+        }
+    }
+
+    /// The faces of `mesh`, which must have UVs to lay them out in the atlas.
+    /// Faces of zero area, which have no normal, are left out.
+    pub fn from_mesh(mesh: &TriangleMesh) -> Result<Vec<Self>, String> {
+        let uvs = mesh
+            .uvs()
+            .ok_or("the mesh has no UVs to bake into; export it with texture coordinates")?;
+        Ok(mesh
+            .indices()
+            .iter()
+            .filter_map(|face| {
+                let normal = face_normal(mesh.positions(), face).try_normalize(0.0)?;
+                Some(Self {
+                    vertices: face.map(|n| mesh.positions()[n as usize]),
+                    uvs: face.map(|n| uvs[n as usize]),
+                    normal,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Texture-space baker.
+/// Renders the lighting arriving at a triangle mesh into its UV atlas instead of a
+/// camera image. Each texel stores the mean incident radiance over the cosine-weighted
+/// hemisphere, i.e. irradiance / pi: what a white diffuse surface would reflect.
+/// The camera only supplies the atlas size (`image_width`), samples, depth and background.
+pub struct UvBaker {
+    output_filename: String,
+    faces: Vec<BakeFace>,
+    path_tracer: PathTracer,
+    ao_distance: Option<f64>,
+}
+
+/// A texel covered by the mesh, with the surface point and normal it maps to.
+struct Texel {
+    i: u32,
+    j: u32,
+    p: Point3,
+    normal: Vec3,
+}
+
+impl UvBaker {
+    /// `path_tracer` evaluates the gathered rays, so its light sampling and roulette
+    /// settings apply to the bake as well.
+    pub fn new(output_filename: &str, faces: Vec<BakeFace>, path_tracer: PathTracer) -> Self {
+        Self {
+            output_filename: output_filename.to_string(),
+            faces,
+            path_tracer,
+            ao_distance: None,
+        }
+    }
+
+    /// Also bakes an ambient occlusion map, counting occluders up to `distance`.
+    pub fn with_ao(mut self, distance: f64) -> Self {
+        self.ao_distance = Some(distance);
+        self
+    }
+
+    /// Finds the texels whose centers fall inside each triangle's UV footprint.
+    fn rasterize(&self, size: u32) -> Vec<Texel> {
+        let mut texels = Vec::new();
+
+        for face in &self.faces {
+            let [v0, v1, v2] = face.vertices;
+            let [t0, t1, t2] = face.uvs;
+
+            // Texel-space corners; rows run top to bottom, so v is flipped
+            let to_texel = |(u, v): (f64, f64)| (u * size as f64, (1.0 - v) * size as f64);
+            let (a, b, c) = (to_texel(t0), to_texel(t1), to_texel(t2));
+
+            let area = (b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1);
+            if area.abs() < 1e-12 {
+                continue;
+            }
+
+            let min_i = a.0.min(b.0).min(c.0).floor().max(0.0) as u32;
+            let max_i = (a.0.max(b.0).max(c.0).ceil() as u32).min(size);
+            let min_j = a.1.min(b.1).min(c.1).floor().max(0.0) as u32;
+            let max_j = (a.1.max(b.1).max(c.1).ceil() as u32).min(size);
+
+            for j in min_j..max_j {
+                for i in min_i..max_i {
+                    let (x, y) = (i as f64 + 0.5, j as f64 + 0.5);
+
+                    // Barycentric coordinates of the texel center
+                    let w1 = ((x - a.0) * (c.1 - a.1) - (c.0 - a.0) * (y - a.1)) / area;
+                    let w2 = ((b.0 - a.0) * (y - a.1) - (x - a.0) * (b.1 - a.1)) / area;
+                    let w0 = 1.0 - w1 - w2;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+
+                    let p = Point3::from(w0 * v0.coords + w1 * v1.coords + w2 * v2.coords);
+                    texels.push(Texel {
+                        i,
+                        j,
+                        p,
+                        normal: face.normal,
+                    });
+                }
+            }
+        }
+
+        texels
+    }
+
+    /// Gathers lighting (and optionally occlusion) over the hemisphere above a texel.
+    fn bake_texel(
+        &self,
+        texel: &Texel,
+        world: &dyn Hittable,
+        lights: Option<&Arc<dyn Hittable>>,
+        camera: &Camera,
+    ) -> (Color, f64) {
+        let pdf = CosinePDF::new(&texel.normal);
+        // Lift the origin off the surface so gathered rays do not hit the mesh itself
+        let origin = texel.p + texel.normal * 1e-4;
+
+        let mut radiance = Color::zeros();
+        let mut ao = 0.0;
+        for _ in 0..camera.samples_per_pixel {
+            let direction = pdf.generate().normalize();
+            let ray = Ray::new(origin, direction, random_double());
+
+            // Cosine sampling cancels the cosine term: the estimator is the plain radiance
+            let sample = self.path_tracer.radiance(
+                &ray,
+                camera.max_depth,
+                world,
+                lights,
+                &camera.background,
+            );
+            if sample.x.is_finite() && sample.y.is_finite() && sample.z.is_finite() {
+                radiance += sample;
+            }
+
+            if let Some(distance) = self.ao_distance
+                && world.hit(&ray, Interval::new(0.001, distance)).is_none()
+            {
+                ao += 1.0;
+            }
+        }
+
+        (radiance, ao)
+    }
+}
+
+impl Integrator for UvBaker {
+    fn render(&self, world: &dyn Hittable, lights: Option<Arc<dyn Hittable>>, camera: &Camera) {
+        let size = camera.image_width;
+        let texels = self.rasterize(size);
+
+        println!(
+            "Baking {} triangles into a {}x{} atlas ({} texels) with {} SPP...",
+            self.faces.len(),
+            size,
+            size,
+            texels.len(),
+            camera.samples_per_pixel
+        );
+
+        let progress_bar = ProgressBar::new(texels.len() as u64);
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+                )
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        let start_time = std::time::Instant::now();
+
+        let results: Vec<(Color, f64)> = texels
+            .par_iter()
+            .map(|texel| {
+                let result = self.bake_texel(texel, world, lights.as_ref(), camera);
+                progress_bar.inc(1);
+                result
+            })
+            .collect();
+
+        progress_bar.finish_with_message("Done");
+        println!("Bake complete in {:.2?}", start_time.elapsed());
+        metadata::record("render time", format!("{:.2?}", start_time.elapsed()));
+
+        let mut film = Film::new(size, size).with_color_space(camera.color_space);
+        let mut ao_film = Film::new(size, size).with_color_space(camera.color_space);
+        let mut covered = vec![false; (size * size) as usize];
+        for (texel, (color, ao)) in texels.iter().zip(results) {
+            film.set(texel.i, texel.j, color);
+            ao_film.set(texel.i, texel.j, Color::new(ao, ao, ao));
+            covered[film.index(texel.i, texel.j)] = true;
+        }
+
+        let mut ao_covered = covered.clone();
+        dilate(&mut film, &mut covered);
+        film.save(&self.output_filename, camera.samples_per_pixel);
+
+        if self.ao_distance.is_some() {
+            dilate(&mut ao_film, &mut ao_covered);
+            ao_film.save(
+                &aov_filename(&self.output_filename, "ao"),
+                camera.samples_per_pixel,
+            );
+        }
+    }
+}
+
+/// Grows the baked islands outwards by averaging covered neighbours into empty texels.
+fn dilate(film: &mut Film, covered: &mut [bool]) {
+    let (width, height) = (film.width as i64, film.height as i64);
+
+    for _ in 0..DILATION_PASSES {
+        let mut filled = Vec::new();
+
+        for j in 0..height {
+            for i in 0..width {
+                if covered[film.index(i as u32, j as u32)] {
+                    continue;
+                }
+
+                let mut sum = Color::zeros();
+                let mut count = 0;
+                for (di, dj) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (ni, nj) = (i + di, j + dj);
+                    if ni < 0 || nj < 0 || ni >= width || nj >= height {
+                        continue;
+                    }
+                    if covered[film.index(ni as u32, nj as u32)] {
+                        sum += film.get(ni as u32, nj as u32);
+                        count += 1;
+                    }
+                }
+
+                if count > 0 {
+                    filled.push((i as u32, j as u32, sum / count as f64));
+                }
+            }
+        }
+
+        for (i, j, color) in filled {
+            film.set(i, j, color);
+            covered[film.index(i, j)] = true;
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod core;
+pub mod geometry;
+pub mod integrators;
+pub mod materials;
+pub mod sampling;
+pub mod scenes;
+pub mod textures;
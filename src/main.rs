@@ -1,52 +1,633 @@
-mod core;
-mod geometry;
-mod integrators;
-mod materials;
-mod sampling;
-mod scenes;
-mod textures;
-
-use crate::geometry::hittable::Hittable;
-use crate::integrators::integrator_trait::Integrator;
-use crate::integrators::path_tracer::PathTracer;
-use crate::scenes::{cornell_box, final_scene, many_balls};
+mod cli;
+
+use crate::cli::Options;
+use raytracing_rust::core::animation;
+use raytracing_rust::core::assets;
+use raytracing_rust::core::camera::Camera;
+use raytracing_rust::core::glare::Glare;
+use raytracing_rust::core::image_compare;
+use raytracing_rust::core::lens_effects::LensEffects;
+use raytracing_rust::core::lut::Lut3d;
+use raytracing_rust::core::memory;
+use raytracing_rust::core::metadata;
+use raytracing_rust::geometry::accelerator::Accelerator;
+use raytracing_rust::geometry::bvh::BvhNode;
+use raytracing_rust::geometry::clip::Clipped;
+use raytracing_rust::geometry::hittable::{Hittable, SHUTTER};
+use raytracing_rust::geometry::hittable_list::HittableList;
+use raytracing_rust::geometry::material_override::{MaterialOverride, OverrideRule};
+use raytracing_rust::geometry::named;
+use raytracing_rust::integrators::budget::RenderBudget;
+use raytracing_rust::integrators::contact_sheet::ContactSheet;
+use raytracing_rust::integrators::integrator_trait::Integrator;
+use raytracing_rust::integrators::light_tracer::LightTracer;
+use raytracing_rust::integrators::monitor::{self, PostSettings, RenderMonitor};
+use raytracing_rust::integrators::path_record;
+use raytracing_rust::integrators::path_tracer::{PathTracer, aov_filename, checkpoint_filename};
+use raytracing_rust::integrators::probe_baker::ProbeBaker;
+use raytracing_rust::integrators::uv_baker::{BakeFace, UvBaker};
+use raytracing_rust::sampling::random;
+use raytracing_rust::scenes::cornell_box::{CornellBoxConfig, CornellContents};
+use raytracing_rust::scenes::scatter::ScatterBuilder;
+use raytracing_rust::scenes::volume::VolumeStyle;
+use raytracing_rust::scenes::{
+    city, clouds, cornell_box, final_scene, many_balls, ply, pool, showcase, studio, terrain,
+    volume,
+};
 use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let scene_name = args.get(1).map(String::as_str).unwrap_or("many_balls");
-
-    let (world, lights, camera) = match scene_name {
-        "many_balls" => {
-            println!("Loading Book 1 Final Scene (Random Spheres)...");
-            many_balls::build_many_balls(1200, 10000, 75)
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
         }
-        "cornell_box" => {
-            println!("Loading Book 3 Cornell Box (Glass Sphere)...");
-            cornell_box::build_cornell_box(1200, 10000, 75)
+    };
+    let scene_name = options.scene.as_str();
+
+    // Compare mode: measure the difference between two renders instead of rendering
+    if let Some((reference, test)) = &options.compare {
+        match image_compare::compare_files(reference, test) {
+            Ok(comparison) => {
+                println!("{}", comparison.summary());
+                comparison.difference.save(&aov_filename(test, "flip"));
+            }
+            Err(e) => eprintln!("{}", e),
         }
-        "final_scene" => {
-            println!("Loading Book 2 Final Scene...");
-            // High resolution render settings from book
-            final_scene::build_final_scene(1200, 10000, 75)
+        return;
+    }
+
+    assets::set_search_paths(options.asset_dirs.iter().map(PathBuf::from).collect());
+    if let Some(dir) = &options.bvh_cache {
+        raytracing_rust::core::ply::enable_cache(Path::new(dir));
+        BvhNode::enable_cache(Path::new(dir));
+    }
+    BvhNode::set_default_builder(options.bvh_builder);
+    Accelerator::set_default(options.accelerator);
+    if options.metadata_json {
+        metadata::enable_sidecar();
+    }
+    if let Some(bytes) = options.max_memory {
+        memory::set_limit(bytes);
+    }
+    let lut = match options.lut.as_deref().map(Lut3d::load).transpose() {
+        Ok(lut) => lut.map(Arc::new),
+        Err(e) => {
+            eprintln!("Cannot load LUT: {}", e);
+            return;
         }
-        _ => {
-            eprintln!(
-                "Unknown scene '{}'. Available: many_balls, cornell_box, final_scene",
-                scene_name
-            );
+    };
+
+    // Random scenes are generated on this thread, so seeding it makes them reproducible
+    if let Some(seed) = options.seed {
+        random::seed_thread(seed);
+    }
+    if let Some(seed) = options.scatter_seed {
+        ScatterBuilder::set_default_seed(seed);
+    }
+    let integrator_name = options.integrator.as_str();
+
+    if options.contact_sheet {
+        render_contact_sheet(&options);
+        return;
+    }
+
+    let (world, lights, mut camera) = match load_scene(scene_name, &options) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("{}", e);
             return;
         }
     };
 
+    // Objects mode: list the named objects instead of rendering
+    if options.list_objects {
+        let mut any = false;
+        for tag in world.tags() {
+            let bbox = world
+                .find_id(tag.id)
+                .map(|object| object.bounding_box(SHUTTER));
+            print!("{:>4}  {}", tag.id, tag.name);
+            if let Some(bbox) = bbox {
+                print!(
+                    "  ({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3})",
+                    bbox.x.min, bbox.y.min, bbox.z.min, bbox.x.max, bbox.y.max, bbox.z.max
+                );
+            }
+            println!();
+            any = true;
+        }
+        if !any {
+            println!("The scene has no named objects");
+        }
+        return;
+    }
+
+    memory::report();
+    if let Err(e) = memory::check() {
+        eprintln!("{}", e);
+        return;
+    }
+
+    // Explicit overrides take precedence over the clay render
+    let mut overrides = options.material_overrides.clone();
+    if options.clay {
+        overrides.push(OverrideRule::clay());
+    }
+    let world: Arc<dyn Hittable> = if overrides.is_empty() {
+        world
+    } else {
+        Arc::new(MaterialOverride::new(world, &overrides))
+    };
+    let world: Arc<dyn Hittable> = if options.clip_planes.is_empty() {
+        world
+    } else {
+        let mut clipped = Clipped::new(world, options.clip_planes.clone());
+        if let Some(cap) = &options.clip_cap {
+            clipped = clipped.with_caps(cap.build());
+        }
+        Arc::new(clipped)
+    };
+
     let filename = format!("{}.png", scene_name);
-    let integrator = PathTracer::new(&filename);
+    // Each progressive pass gets its own image, e.g. `cornell_box_pass1.png`
+    let beauty_filename = match options.pass {
+        Some(pass) => aov_filename(&filename, &format!("pass{}", pass)),
+        None => filename.clone(),
+    };
+    let mut path_tracer = PathTracer::new(&beauty_filename)
+        .with_light_sampling(options.light_sampling)
+        .with_roulette(options.rr_start_bounce, options.max_splits);
+    if let Some(seed) = options.seed {
+        path_tracer = path_tracer.with_seed(seed);
+    }
+    if let Some(pass) = options.pass {
+        path_tracer = path_tracer.with_pass(pass);
+    }
+    if let Some(angle) = options.regularization_angle {
+        path_tracer = path_tracer.with_regularization(angle);
+    }
 
     let lights_opt = if lights.objects.is_empty() {
         None
     } else {
-        Some(lights as std::sync::Arc<dyn Hittable>)
+        Some(lights as Arc<dyn Hittable>)
+    };
+
+    if options.auto_frame {
+        camera.frame_bounds(&world.bounding_box(SHUTTER));
+        println!(
+            "Auto-framed: looking from ({:.2}, {:.2}, {:.2}) at ({:.2}, {:.2}, {:.2})",
+            camera.lookfrom.x,
+            camera.lookfrom.y,
+            camera.lookfrom.z,
+            camera.lookat.x,
+            camera.lookat.y,
+            camera.lookat.z
+        );
+    }
+    camera.color_space = options.color_space.unwrap_or_default();
+    if let Some(gamma) = options.gamma {
+        camera.color_space = camera.color_space.with_gamma(gamma);
+    }
+    camera.lut = lut;
+    if options.auto_exposure {
+        camera.exposure = path_tracer.estimate_exposure(&*world, lights_opt.as_ref(), &camera);
+        println!(
+            "Auto exposure: x{:.3} ({:+.2} EV)",
+            camera.exposure,
+            camera.exposure.log2()
+        );
+    }
+
+    // Pick mode: report what is under a pixel instead of rendering
+    if let Some((i, j)) = options.pick {
+        if i >= camera.image_width || j >= camera.image_height {
+            eprintln!(
+                "Pixel {},{} is outside the {}x{} image",
+                i, j, camera.image_width, camera.image_height
+            );
+            return;
+        }
+        match named::pick(&*world, &camera, i, j) {
+            Some(isect) => {
+                match &isect.object {
+                    Some(tag) => println!("Object: {} (id {})", tag.name, tag.id),
+                    None => println!("Object: unnamed"),
+                }
+                if let Some(material) = &isect.material {
+                    println!("Material: {}", material.name());
+                }
+                println!(
+                    "Point: ({:.3}, {:.3}, {:.3}) at distance {:.3}",
+                    isect.p.x,
+                    isect.p.y,
+                    isect.p.z,
+                    isect.t * camera.center_ray(i, j).dir.norm()
+                );
+            }
+            None => println!("Background"),
+        }
+        return;
+    }
+
+    // Path dump and inspect modes: record a few paths through the chosen pixels
+    // instead of rendering
+    let traced_pixels = match options.inspect {
+        Some(pixel) => vec![pixel],
+        None => options.dump_pixels.clone(),
+    };
+    if !traced_pixels.is_empty() {
+        if let Some((i, j)) = traced_pixels
+            .iter()
+            .find(|&&(i, j)| i >= camera.image_width || j >= camera.image_height)
+        {
+            eprintln!(
+                "Pixel {},{} is outside the {}x{} image",
+                i, j, camera.image_width, camera.image_height
+            );
+            return;
+        }
+        let records: Vec<_> = traced_pixels
+            .iter()
+            .flat_map(|&(i, j)| {
+                path_tracer.record_paths(
+                    i,
+                    j,
+                    options.dump_count,
+                    &*world,
+                    lights_opt.as_ref(),
+                    &camera,
+                )
+            })
+            .collect();
+        if options.inspect.is_some() {
+            path_record::print_report(&records);
+            return;
+        }
+
+        let stem = format!("{}_paths", scene_name);
+        path_record::save_json(&records, &format!("{}.json", stem));
+        // Escaped rays are drawn as long as the camera's viewing distance
+        let miss_length = (camera.lookat - camera.lookfrom).norm();
+        path_record::save_obj(&records, &format!("{}.obj", stem), miss_length);
+        return;
+    }
+
+    metadata::record("scene", scene_name);
+    metadata::record("integrator", integrator_name);
+    if let Some(seed) = options.seed {
+        metadata::record("seed", seed);
+    }
+    if let Some(pass) = options.pass {
+        metadata::record("pass", pass);
+    }
+    metadata::record("samples per pixel", camera.samples_per_pixel);
+    metadata::record("max depth", camera.max_depth);
+    metadata::record("commit", env!("RAYTRACER_COMMIT"));
+    metadata::record("command line", args[1..].join(" "));
+
+    let budget = RenderBudget {
+        max_rays: options.max_rays,
+        max_time: options.max_time.map(Duration::from_secs_f64),
+    };
+    let integrator: Box<dyn Integrator> = match integrator_name {
+        "path" => {
+            path_tracer = path_tracer.with_budget(budget);
+            if let Some(address) = &options.http {
+                let monitor = Arc::new(RenderMonitor::new(PostSettings {
+                    exposure: camera.exposure,
+                    clamp: None,
+                }));
+                if let Err(e) = monitor::serve(monitor.clone(), address) {
+                    eprintln!("{}", e);
+                    return;
+                }
+                path_tracer = path_tracer.with_monitor(monitor);
+            }
+            if let Some(distance) = options.ao_distance {
+                path_tracer = path_tracer.with_ao(distance);
+            }
+            if options.bounce_aovs {
+                path_tracer = path_tracer.with_bounce_aovs();
+            }
+            if options.cryptomatte {
+                path_tracer = path_tracer.with_cryptomatte();
+            }
+            if options.nan_check {
+                path_tracer = path_tracer.with_nan_check();
+            }
+            if options.tiled_exr {
+                path_tracer = path_tracer.with_tiled_exr();
+            }
+            if options.bvh_heatmap {
+                path_tracer = path_tracer.with_bvh_heatmap();
+            }
+            if let Some(strength) = options.glare {
+                path_tracer = path_tracer.with_glare(Glare::new(strength));
+            }
+            if !options.bracket.is_empty() {
+                path_tracer = path_tracer.with_exposure_bracket(options.bracket.clone());
+            }
+            if options.chromatic_aberration != 0.0 || options.vignetting != 0.0 {
+                path_tracer = path_tracer.with_lens_effects(
+                    LensEffects::new()
+                        .with_chromatic_aberration(options.chromatic_aberration)
+                        .with_vignetting(options.vignetting),
+                );
+            }
+            if let Some(frames) = options.frames {
+                if options.tiled_exr {
+                    eprintln!("Frame sequences cannot be rendered as tiled EXRs");
+                    return;
+                }
+                let frame_filenames = render_frames(
+                    &path_tracer,
+                    frames,
+                    &beauty_filename,
+                    &*world,
+                    lights_opt,
+                    &camera,
+                );
+                if let Some(format) = options.animation {
+                    let output = aov_filename(&beauty_filename, "animation");
+                    let output = Path::new(&output).with_extension(format.extension());
+                    let output = output.to_string_lossy();
+                    match animation::assemble(&frame_filenames, &output, format, options.fps) {
+                        Ok(()) => println!("Animation saved to {}", output),
+                        Err(e) => eprintln!("Error assembling animation: {}", e),
+                    }
+                }
+                return;
+            }
+            Box::new(path_tracer)
+        }
+        "light" => Box::new(LightTracer::new(&filename).with_budget(budget)),
+        "bake" => {
+            // The model given with --ply, else the Cornell box's mesh
+            let faces = match &options.ply {
+                Some(file) => {
+                    println!("Loading bake target ({})...", file);
+                    match ply::load_model(file, options.simplify, options.crease_angle)
+                        .and_then(|mesh| BakeFace::from_mesh(&mesh))
+                    {
+                        Ok(faces) => faces,
+                        Err(e) => {
+                            eprintln!("Cannot bake {}: {}", file, e);
+                            return;
+                        }
+                    }
+                }
+                None if scene_name == "cornell_box"
+                    && options.cornell_contents == CornellContents::Mesh =>
+                {
+                    cornell_box::mesh_triangles()
+                        .iter()
+                        .map(|triangle| BakeFace::from_triangle(triangle))
+                        .collect()
+                }
+                None => {
+                    eprintln!(
+                        "The bake integrator needs a mesh with UVs: pass one with --ply <file.ply>, or use 'cornell_box bake --contents mesh'"
+                    );
+                    return;
+                }
+            };
+            let mut baker = UvBaker::new(&aov_filename(&filename, "bake"), faces, path_tracer);
+            if let Some(distance) = options.ao_distance {
+                baker = baker.with_ao(distance);
+            }
+            Box::new(baker)
+        }
+        "probe" => {
+            // Without explicit probes, place one at the point the camera looks at
+            let positions = if options.probes.is_empty() {
+                vec![camera.lookat]
+            } else {
+                options.probes.clone()
+            };
+            Box::new(ProbeBaker::new(&filename, positions, path_tracer))
+        }
+        _ => {
+            eprintln!(
+                "Unknown integrator '{}'. Available: path, light, bake, probe",
+                integrator_name
+            );
+            return;
+        }
     };
 
     integrator.render(&*world, lights_opt, &camera);
 }
+
+/// Renders `frames` frames, each exposing its slice of the shutter, into
+/// `<name>_frame0000.png` and so on. Frames already saved are skipped and a frame
+/// left unfinished resumes from its checkpoint, so a sequence can be rerun after
+/// an interruption. Returns the names of all frames, in order.
+fn render_frames(
+    path_tracer: &PathTracer,
+    frames: u32,
+    filename: &str,
+    world: &dyn Hittable,
+    lights: Option<Arc<dyn Hittable>>,
+    camera: &Camera,
+) -> Vec<String> {
+    let mut frame_filenames = Vec::new();
+    for frame in 0..frames {
+        let frame_filename = aov_filename(filename, &format!("frame{:04}", frame));
+        frame_filenames.push(frame_filename.clone());
+        if Path::new(&frame_filename).exists()
+            && !Path::new(&checkpoint_filename(&frame_filename)).exists()
+        {
+            println!("Skipping frame {}: {} exists", frame, frame_filename);
+            continue;
+        }
+        println!("Frame {} of {}", frame + 1, frames);
+        metadata::record("frame", frame);
+        path_tracer
+            .clone()
+            .with_output(&frame_filename)
+            .with_checkpoints()
+            .render(world, lights.clone(), &camera.frame(frame, frames));
+    }
+    frame_filenames
+}
+
+/// Renders a thumbnail of every scene that can be built with the given options
+/// into `contact_sheet.png`, skipping those that cannot, e.g. `ply` without a model.
+fn render_contact_sheet(options: &Options) {
+    let mut path_tracer = PathTracer::new("contact_sheet.png")
+        .with_light_sampling(options.light_sampling)
+        .with_roulette(options.rr_start_bounce, options.max_splits);
+    if let Some(angle) = options.regularization_angle {
+        path_tracer = path_tracer.with_regularization(angle);
+    }
+    let mut sheet = ContactSheet::new("contact_sheet.png", path_tracer);
+
+    for scene_name in SCENES {
+        let (world, lights, camera) = match load_scene(scene_name, options) {
+            Ok(scene) => scene,
+            Err(e) => {
+                println!("  Skipping {}: {}", scene_name, e);
+                continue;
+            }
+        };
+        let lights = if lights.objects.is_empty() {
+            None
+        } else {
+            Some(lights as Arc<dyn Hittable>)
+        };
+        sheet.add(scene_name, &*world, lights, &camera);
+    }
+    sheet.save();
+}
+
+/// Scenes the renderer can build, by name.
+const SCENES: [&str; 11] = [
+    "city",
+    "clouds",
+    "cornell_box",
+    "final_scene",
+    "many_balls",
+    "ply",
+    "pool",
+    "showcase",
+    "studio",
+    "terrain",
+    "volume",
+];
+
+/// Builds the scene `scene_name` as configured by the command line.
+fn load_scene(
+    scene_name: &str,
+    options: &Options,
+) -> Result<(Arc<HittableList>, Arc<HittableList>, Camera), String> {
+    let scene = match scene_name {
+        "many_balls" => {
+            println!("Loading Book 1 Final Scene (Random Spheres)...");
+            many_balls::build_many_balls(1200, 10000, 75)
+        }
+        "city" => {
+            println!(
+                "Loading City Scene ({:?} light selection)...",
+                options.light_selection
+            );
+            city::build_city(options.light_selection, 1200, 10000, 75)
+        }
+        "clouds" => {
+            println!(
+                "Loading Clouds Scene ({} scattering)...",
+                if options.single_scattering {
+                    "single"
+                } else {
+                    "multiple"
+                }
+            );
+            clouds::build_clouds(options.single_scattering, 1200, 10000, 75)
+        }
+        "cornell_box" => {
+            println!(
+                "Loading Cornell Box ({:?}{})...",
+                options.cornell_contents,
+                if options.smoke { ", smoke" } else { "" }
+            );
+            let mut config = CornellBoxConfig::default()
+                .with_contents(options.cornell_contents)
+                .with_smoke(options.smoke);
+            if let Some((scale, radiance)) = options.cornell_light {
+                config = config.with_light(scale, radiance);
+            }
+            if let Some((left, right)) = options.cornell_walls {
+                config = config.with_walls(left, right);
+            }
+            cornell_box::build_cornell_box(&config, 1200, 10000, 75)
+        }
+        "final_scene" => {
+            println!("Loading Book 2 Final Scene...");
+            // High resolution render settings from book
+            final_scene::build_final_scene(1200, 10000, 75)
+        }
+        "pool" => {
+            println!("Loading Pool Scene...");
+            if options.regularization_angle.is_none() {
+                println!("  Caustics on the pool floor converge slowly; try --regularize 5");
+            }
+            pool::build_pool(1200, 10000, 75)
+        }
+        "showcase" => {
+            println!(
+                "Loading Material Showcase ({:?})...",
+                options.material_family
+            );
+            showcase::build_showcase(options.material_family, 1200, 10000, 75)
+        }
+        "ply" => {
+            let Some(file) = &options.ply else {
+                return Err("The ply scene needs a PLY model: --ply <file.ply>".to_string());
+            };
+            println!("Loading PLY Scene ({})...", file);
+            ply::build_ply(
+                file,
+                options.simplify,
+                options.crease_angle,
+                1200,
+                10000,
+                75,
+            )
+            .map_err(|e| format!("Cannot load model: {}", e))?
+        }
+        "studio" => {
+            println!("Loading Studio Scene (Three-Point Lighting)...");
+            studio::build_studio_demo(1200, 10000, 75)
+        }
+        "terrain" => {
+            println!(
+                "Loading Terrain Scene ({})...",
+                options.height_map.as_deref().unwrap_or("procedural ridges")
+            );
+            terrain::build_terrain(
+                options.height_map.as_deref(),
+                options.sun_elevations.as_deref(),
+                1200,
+                10000,
+                75,
+            )
+            .map_err(|e| format!("Cannot build terrain: {}", e))?
+        }
+        "volume" => {
+            let Some(file) = &options.volume else {
+                return Err(
+                    "The volume scene needs a NanoVDB file: --volume <file.nvdb>".to_string(),
+                );
+            };
+            println!("Loading Volume Scene ({})...", file);
+            let style = if options.fire {
+                VolumeStyle::Fire {
+                    temperature_scale: options.fire_temperature,
+                    intensity: options.fire_intensity,
+                }
+            } else if options.cloud {
+                VolumeStyle::Cloud {
+                    multiple_scattering: !options.single_scattering,
+                }
+            } else {
+                VolumeStyle::Smoke
+            };
+            volume::build_volume(file, options.volume_density, style, 1200, 10000, 75)
+                .map_err(|e| format!("Cannot load volume: {}", e))?
+        }
+        _ => {
+            return Err(format!(
+                "Unknown scene '{}'. Available: {}",
+                scene_name,
+                SCENES.join(", ")
+            ));
+        }
+    };
+    Ok(scene)
+}
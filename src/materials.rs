@@ -1,6 +1,12 @@
+pub mod backface_culled;
 pub mod dielectric;
 pub mod diffuse_light;
+pub mod fire;
+pub mod henyey_greenstein;
 pub mod isotropic;
 pub mod lambertian;
+pub mod layered;
 pub mod material_trait;
 pub mod metal;
+pub mod params;
+pub mod shading_graph;
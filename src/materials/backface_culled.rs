@@ -0,0 +1,63 @@
+use crate::core::interaction::Interaction;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3};
+use crate::materials::material_trait::{EmissionSide, Material, ScatterRecord};
+use std::sync::Arc;
+
+/// Turns on backface culling for `material`, which otherwise behaves as before.
+/// Meant for closed meshes such as imported assets, whose inside is never seen:
+/// traversal skips faces pointing away early, and faces with inverted normals are
+/// seen through rather than shaded from behind.
+#[derive(Debug)]
+pub struct BackfaceCulled {
+    material: Arc<dyn Material>,
+}
+
+impl BackfaceCulled {
+    pub fn new(material: Arc<dyn Material>) -> Self {
+        Self { material }
+    }
+}
+
+impl Material for BackfaceCulled {
+    fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
+        self.material.scatter(r_in, isect, srec)
+    }
+
+    fn emitted(&self, r_in: &Ray, isect: &Interaction, u: f64, v: f64, p: &Point3) -> Color {
+        self.material.emitted(r_in, isect, u, v, p)
+    }
+
+    fn emission_side(&self) -> EmissionSide {
+        self.material.emission_side()
+    }
+
+    fn power(&self) -> Color {
+        self.material.power()
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, isect: &Interaction, scattered: &Ray) -> f64 {
+        self.material.scattering_pdf(r_in, isect, scattered)
+    }
+
+    fn absorption(&self) -> Option<Color> {
+        self.material.absorption()
+    }
+
+    fn ior(&self) -> Option<f64> {
+        self.material.ior()
+    }
+
+    fn priority(&self) -> u32 {
+        self.material.priority()
+    }
+
+    fn cull_backfaces(&self) -> bool {
+        true
+    }
+
+    /// The wrapped material's name, so overrides and statistics see through it.
+    fn name(&self) -> &'static str {
+        self.material.name()
+    }
+}
@@ -1,17 +1,75 @@
 use crate::core::interaction::Interaction;
 use crate::core::ray::Ray;
-use crate::core::vec3::{Color, Vec3Ext};
+use crate::core::vec3::{Color, Vec3, Vec3Ext};
 use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::materials::params;
 use crate::sampling::random::random_double;
+use crate::textures::perlin::Perlin;
+
+/// Index of refraction of water.
+const WATER_IOR: f64 = 1.33;
+/// Absorption coefficients of pure water per meter at the sRGB primaries: red is
+/// absorbed within a few meters, giving deep water its cyan tint.
+const WATER_ABSORPTION: [f64; 3] = [0.28, 0.06, 0.015];
+/// Step of the finite differences taking the ripple slope, in noise space.
+const RIPPLE_EPSILON: f64 = 1e-3;
 
 #[derive(Debug)]
 pub struct Dielectric {
     ir: f64, // Index of Refraction
+    /// Absorption coefficient per unit length inside, applied by the integrator.
+    absorption: Color,
+    ripples: Option<Ripples>,
+    /// Nesting priority, see `Material::priority`.
+    priority: u32,
+}
+
+/// Procedural bumps perturbing the surface normal.
+#[derive(Debug)]
+struct Ripples {
+    noise: Perlin,
+    height: f64,
+    frequency: f64,
 }
 
 impl Dielectric {
     pub fn new(ir: f64) -> Self {
-        Self { ir }
+        Self {
+            ir: params::ior("Dielectric IOR", ir),
+            absorption: Color::zeros(),
+            ripples: None,
+            priority: 0,
+        }
+    }
+
+    /// Clear water, in scenes measured in meters: IOR 1.33 and the absorption of pure
+    /// water. Use `with_absorption` to rescale it for other units.
+    pub fn water() -> Self {
+        Self::new(WATER_IOR).with_absorption(Color::from(WATER_ABSORPTION))
+    }
+
+    /// Beer-Lambert absorption of light travelling inside, per unit length.
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption.map(|a| params::non_negative("Dielectric absorption", a));
+        self
+    }
+
+    /// Nesting priority over other dielectrics it overlaps, 0 by default: e.g. 1 for
+    /// ice floating in water of 0.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Normal-mapped ripples: Perlin noise bumps of `height` and roughly
+    /// `wavelength` apart, sloping the surface without changing its geometry.
+    pub fn with_ripples(mut self, height: f64, wavelength: f64) -> Self {
+        self.ripples = Some(Ripples {
+            noise: Perlin::new(),
+            height,
+            frequency: 1.0 / wavelength,
+        });
+        self
     }
 
     fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
@@ -20,6 +78,37 @@ impl Dielectric {
         let r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
+
+    /// Shading normal tilted by the slope of the ripples, if any, on the side of
+    /// the incoming ray.
+    fn normal(&self, unit_direction: &Vec3, isect: &Interaction) -> Vec3 {
+        let n = isect.shading.w();
+        let Some(ripples) = &self.ripples else {
+            return n;
+        };
+
+        let p = isect.p * ripples.frequency;
+        let slope = |axis: Vec3| {
+            let step = axis * RIPPLE_EPSILON;
+            (ripples.noise.noise(&(p + step)) - ripples.noise.noise(&(p - step)))
+                / (2.0 * RIPPLE_EPSILON)
+        };
+        // Ripples finer than the pixel footprint average out to a flat surface,
+        // rather than scattering neighboring pixels into noise
+        let fade = 1.0 / (1.0 + (isect.footprint() * ripples.frequency).powi(2));
+        let gradient = fade
+            * ripples.height
+            * ripples.frequency
+            * Vec3::new(slope(Vec3::x()), slope(Vec3::y()), slope(Vec3::z()));
+
+        let tilted = (n - (gradient - gradient.dot(&n) * n)).normalize();
+        // Steep slopes seen at grazing angles would face away from the ray
+        if unit_direction.dot(&tilted) < 0.0 {
+            tilted
+        } else {
+            n
+        }
+    }
 }
 
 impl Material for Dielectric {
@@ -27,25 +116,44 @@ impl Material for Dielectric {
         srec.attenuation = Color::new(1.0, 1.0, 1.0);
         srec.skip_pdf = true;
 
+        // Relative to the medium around the object, air unless nested in another
         let refraction_ratio = if isect.front_face {
-            1.0 / self.ir
+            isect.outside_ior / self.ir
         } else {
-            self.ir
+            self.ir / isect.outside_ior
         };
         let unit_direction = r_in.dir.normalize();
+        let normal = self.normal(&unit_direction, isect);
 
-        let cos_theta = (-unit_direction).dot(&isect.geometry_normal).min(1.0);
+        let cos_theta = (-unit_direction).dot(&normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction =
-            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random_double() {
-                unit_direction.reflect(&isect.geometry_normal)
-            } else {
-                unit_direction.refract(&isect.geometry_normal, refraction_ratio)
-            };
-
-        srec.skip_pdf_ray = Ray::new(isect.p, direction, r_in.time);
+        let (direction, differentials) = if cannot_refract
+            || Self::reflectance(cos_theta, refraction_ratio) > random_double()
+        {
+            let direction = unit_direction.reflect(&normal);
+            (direction, isect.reflected_differentials(r_in, &direction))
+        } else {
+            let direction = unit_direction.refract(&normal, refraction_ratio);
+            let differentials = isect.refracted_differentials(r_in, &direction, refraction_ratio);
+            (direction, differentials)
+        };
+
+        srec.skip_pdf_ray =
+            Ray::new(isect.p, direction, r_in.time).with_differentials(differentials);
         true
     }
+
+    fn absorption(&self) -> Option<Color> {
+        Some(self.absorption)
+    }
+
+    fn ior(&self) -> Option<f64> {
+        Some(self.ir)
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
 }
@@ -1,18 +1,44 @@
 use crate::core::interaction::Interaction;
 use crate::core::ray::Ray;
 use crate::core::vec3::{Color, Point3};
-use crate::materials::material_trait::{Material, ScatterRecord};
-use crate::textures::texture_trait::Texture;
+use crate::materials::material_trait::{EmissionSide, Material, ScatterRecord};
+use crate::sampling::random::degrees_to_radians;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+/// UV grid resolution over which `power` averages the emission texture.
+const POWER_UV_SAMPLES: u32 = 8;
+
 #[derive(Debug)]
 pub struct DiffuseLight {
     emit: Arc<dyn Texture>,
+    cos_half_spread: f64,
+    side: EmissionSide,
 }
 
 impl DiffuseLight {
     pub fn new(emit: Arc<dyn Texture>) -> Self {
-        Self { emit }
+        Self {
+            emit,
+            cos_half_spread: 0.0,
+            side: EmissionSide::Front,
+        }
+    }
+
+    /// Faces that emit: the front (default), facing the outward normal, the back,
+    /// e.g. for a sphere lighting its inside, or both, e.g. for a hanging panel.
+    pub fn with_side(mut self, side: EmissionSide) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// Restricts emission to a cone of full opening angle `spread` (degrees) around
+    /// the surface normal, like the spread of a softbox. 180 emits into the hemisphere.
+    pub fn with_spread(mut self, spread: f64) -> Self {
+        let half = degrees_to_radians(spread.clamp(0.0, 180.0) / 2.0);
+        self.cos_half_spread = half.cos();
+        self
     }
 }
 
@@ -21,12 +47,46 @@ impl Material for DiffuseLight {
         false // Lights do not scatter/reflect rays in this model
     }
 
-    fn emitted(&self, _r_in: &Ray, isect: &Interaction, u: f64, v: f64, p: &Point3) -> Color {
-        // Only emit light from the front face
-        if isect.front_face {
-            self.emit.value(u, v, p)
-        } else {
-            Color::zeros()
+    fn emitted(&self, r_in: &Ray, isect: &Interaction, _u: f64, _v: f64, _p: &Point3) -> Color {
+        if !self.side.emits(isect.front_face) {
+            return Color::zeros();
+        }
+
+        // Outside the spread cone
+        if self.cos_half_spread > 0.0 {
+            let cos_theta = -r_in.dir.dot(&isect.geometry_normal) / r_in.dir.norm();
+            if cos_theta < self.cos_half_spread {
+                return Color::zeros();
+            }
         }
+
+        // Evaluated at the hit, so textures such as `UvSet` see all of it
+        self.emit
+            .value(&TextureContext::from_interaction(isect, r_in.time))
+            * isect.material_params.emission_scale
+    }
+
+    fn emission_side(&self) -> EmissionSide {
+        self.side
+    }
+
+    /// Radiance averaged over the UV square times the cosine-weighted solid angle of
+    /// the spread cone, pi sin^2 of its half angle (pi for the full hemisphere), for
+    /// each emitting face.
+    fn power(&self) -> Color {
+        let n = POWER_UV_SAMPLES;
+        let mut radiance = Color::zeros();
+        for i in 0..n {
+            for j in 0..n {
+                let (u, v) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                radiance += self
+                    .emit
+                    .value(&TextureContext::new((u, v), Point3::origin()));
+            }
+        }
+        radiance /= (n * n) as f64;
+
+        let sin_squared = 1.0 - self.cos_half_spread * self.cos_half_spread;
+        radiance * PI * sin_squared * self.side.faces()
     }
 }
@@ -0,0 +1,79 @@
+use crate::core::blackbody::blackbody;
+use crate::core::interaction::Interaction;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3};
+use crate::geometry::vdb_medium::DensityField;
+use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::sampling::pdf::SpherePDF;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Temperature at which the emitted radiance equals the `intensity`, in Kelvin.
+const REFERENCE_TEMPERATURE: f64 = 1000.0;
+
+/// Phase function of hot, glowing media such as fire and explosions: soot scatters
+/// isotropically with `albedo` and absorbs the rest, and every collision emits the
+/// absorbed fraction of blackbody radiation at the local temperature.
+///
+/// The temperature field is in simulation units, converted to Kelvin by
+/// `temperature_scale` (1000 by default, so a value of 1 is 1000 K). Radiance grows
+/// with the fourth power of the temperature, as by the Stefan-Boltzmann law, and is
+/// `intensity` at 1000 K.
+#[derive(Debug)]
+pub struct Fire {
+    albedo: Arc<dyn Texture>,
+    temperature: Arc<dyn DensityField>,
+    temperature_scale: f64,
+    intensity: f64,
+}
+
+impl Fire {
+    pub fn new(albedo: Arc<dyn Texture>, temperature: Arc<dyn DensityField>) -> Self {
+        Self {
+            albedo,
+            temperature,
+            temperature_scale: REFERENCE_TEMPERATURE,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn with_temperature_scale(mut self, kelvin: f64) -> Self {
+        self.temperature_scale = kelvin;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Blackbody radiance at `p`.
+    pub fn radiance(&self, p: &Point3) -> Color {
+        let kelvin = self.temperature_scale * self.temperature.density(p);
+        if kelvin <= 0.0 {
+            return Color::zeros();
+        }
+        self.intensity * (kelvin / REFERENCE_TEMPERATURE).powi(4) * blackbody(kelvin)
+    }
+}
+
+impl Material for Fire {
+    fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self
+            .albedo
+            .value(&TextureContext::new(isect.uv, isect.p).with_time(r_in.time));
+        srec.pdf_ptr = Some(Arc::new(SpherePDF));
+        srec.skip_pdf = false;
+        true
+    }
+
+    fn emitted(&self, r_in: &Ray, isect: &Interaction, u: f64, v: f64, p: &Point3) -> Color {
+        let ctx = TextureContext::new((u, v), *p).with_time(r_in.time);
+        let absorbed = (Color::repeat(1.0) - self.albedo.value(&ctx)).map(|c| c.max(0.0));
+        absorbed.component_mul(&self.radiance(p)) * isect.material_params.emission_scale
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _isect: &Interaction, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * std::f64::consts::PI)
+    }
+}
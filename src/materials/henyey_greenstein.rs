@@ -0,0 +1,40 @@
+use crate::core::interaction::Interaction;
+use crate::core::ray::Ray;
+use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::sampling::pdf::HenyeyGreensteinPDF;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Anisotropic phase function for media, such as the strong forward scattering of
+/// cloud droplets. `g` is the mean cosine of the scattering angle; 0 is isotropic.
+#[derive(Debug)]
+pub struct HenyeyGreenstein {
+    albedo: Arc<dyn Texture>,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Arc<dyn Texture>, g: f64) -> Self {
+        Self {
+            albedo,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
+        // Medium points have no surface to look up by
+        srec.attenuation = self
+            .albedo
+            .value(&TextureContext::new(isect.uv, isect.p).with_time(r_in.time));
+        srec.pdf_ptr = Some(Arc::new(HenyeyGreensteinPDF::new(&r_in.dir, self.g)));
+        srec.skip_pdf = false;
+        true
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, _isect: &Interaction, scattered: &Ray) -> f64 {
+        let cos_theta = r_in.dir.normalize().dot(&scattered.dir.normalize());
+        HenyeyGreensteinPDF::phase(self.g, cos_theta)
+    }
+}
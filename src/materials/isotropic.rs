@@ -3,7 +3,7 @@ use crate::core::ray::Ray;
 // Vec3Ext required for random_unit_vector
 use crate::materials::material_trait::{Material, ScatterRecord};
 use crate::sampling::pdf::SpherePDF;
-use crate::textures::texture_trait::Texture;
+use crate::textures::texture_trait::{Texture, TextureContext};
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -18,8 +18,11 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, _r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.texture.value(isect.uv.0, isect.uv.1, &isect.p);
+    fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
+        // Medium points have no surface to look up by
+        srec.attenuation = self
+            .texture
+            .value(&TextureContext::new(isect.uv, isect.p).with_time(r_in.time));
         srec.pdf_ptr = Some(Arc::new(SpherePDF));
         srec.skip_pdf = false;
         true
@@ -1,9 +1,11 @@
 use crate::core::interaction::Interaction;
+use crate::core::onb::cos_theta;
 use crate::core::ray::Ray;
 use crate::core::vec3::Vec3Ext;
 use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::materials::params;
 use crate::sampling::pdf::CosinePDF;
-use crate::textures::texture_trait::Texture;
+use crate::textures::texture_trait::{Texture, TextureContext};
 use std::f64::consts::PI;
 use std::sync::Arc;
 
@@ -19,9 +21,14 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.texture.value(isect.uv.0, isect.uv.1, &isect.p);
-        srec.pdf_ptr = Some(Arc::new(CosinePDF::new(&isect.geometry_normal)));
+    fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
+        // Textures and tints can exceed 1, which would create energy
+        srec.attenuation = params::clamp_reflectance(
+            self.texture
+                .value(&TextureContext::from_interaction(isect, r_in.time))
+                .component_mul(&isect.material_params.tint),
+        );
+        srec.pdf_ptr = Some(Arc::new(CosinePDF::from_frame(isect.shading)));
         srec.skip_pdf = false;
         true
     }
@@ -34,10 +41,15 @@ impl Material for Lambertian {
             return 0.0;
         }
 
-        // Normalize direction before dot product
-        let cos_theta = scattered.dir.normalize().dot(&isect.geometry_normal);
+        // Directions below the geometric surface would leak through it, however
+        // the shading normal bends
+        let direction = scattered.dir.normalize();
+        if direction.dot(&isect.geometry_normal) <= 0.0 {
+            return 0.0;
+        }
 
         // Filter out grazing angles which cause instability in PDF division
+        let cos_theta = cos_theta(&isect.shading.to_local(&direction));
         if cos_theta < 1e-3 {
             0.0
         } else {
@@ -3,12 +3,15 @@ use crate::core::ray::Ray;
 use crate::core::vec3::{Color, Point3};
 use crate::sampling::pdf::PDF;
 use std::fmt::Debug;
+use std::str::FromStr;
 use std::sync::Arc;
 
 /// Record of how a ray scatters from a material.
 pub struct ScatterRecord {
     pub attenuation: Color,
     pub pdf_ptr: Option<Arc<dyn PDF>>,
+    /// The sample comes from a delta (specular) lobe: `skip_pdf_ray` is the only
+    /// direction, with no density to evaluate or to combine with light sampling.
     pub skip_pdf: bool,
     pub skip_pdf_ray: Ray,
 }
@@ -24,6 +27,43 @@ impl Default for ScatterRecord {
     }
 }
 
+/// Faces of a surface that emit light: the front faces the outward normal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmissionSide {
+    Front,
+    Back,
+    Both,
+}
+
+impl EmissionSide {
+    /// Whether a face, front or back, emits.
+    pub fn emits(self, front_face: bool) -> bool {
+        match self {
+            Self::Front => front_face,
+            Self::Back => !front_face,
+            Self::Both => true,
+        }
+    }
+
+    /// Number of faces emitting, 1 or 2.
+    pub fn faces(self) -> f64 {
+        if self == Self::Both { 2.0 } else { 1.0 }
+    }
+}
+
+impl FromStr for EmissionSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "front" => Ok(Self::Front),
+            "back" => Ok(Self::Back),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("Unknown emission side '{}'", s)),
+        }
+    }
+}
+
 pub trait Material: Send + Sync + Debug {
     /// Determines how the ray scatters. returns true if scattered, populating srec.
     fn scatter(&self, _r_in: &Ray, _isect: &Interaction, _srec: &mut ScatterRecord) -> bool {
@@ -35,8 +75,55 @@ pub trait Material: Send + Sync + Debug {
         Color::zeros()
     }
 
+    /// Faces of the surface that `emitted` is non-zero on, for emitters.
+    fn emission_side(&self) -> EmissionSide {
+        EmissionSide::Front
+    }
+
+    /// Radiant exitance of emitters: the power they emit per unit area, for
+    /// weighting lights by power (default: black).
+    fn power(&self) -> Color {
+        Color::zeros()
+    }
+
     /// PDF for scattering direction.
     fn scattering_pdf(&self, _r_in: &Ray, _isect: &Interaction, _scattered: &Ray) -> f64 {
         0.0
     }
+
+    /// For solids that rays refract into, such as dielectrics: the absorption
+    /// coefficient per unit length of their interior, which the path tracer applies
+    /// to every path segment inside. None for surfaces rays never enter.
+    fn absorption(&self) -> Option<Color> {
+        None
+    }
+
+    /// For dielectrics: the index of refraction of their interior, which the path
+    /// tracer tracks as paths nest inside them, so a surface between two of them
+    /// refracts by the ratio of both. None for other materials.
+    fn ior(&self) -> Option<f64> {
+        None
+    }
+
+    /// Nesting priority of dielectrics: where two interiors overlap, the higher
+    /// one fills the space and the other's surfaces within it are ignored. Lets
+    /// the liquid in a glass be modeled overlapping the glass wall, or ice poke
+    /// out of water, without the two surfaces having to coincide.
+    fn priority(&self) -> u32 {
+        0
+    }
+
+    /// Whether flat primitives with this material ignore hits on their back faces,
+    /// for closed meshes seen only from outside: rays pass through faces wound the
+    /// wrong way instead of shading them, and skip the rest of the intersection
+    /// test early.
+    fn cull_backfaces(&self) -> bool {
+        false
+    }
+
+    /// Short type name, e.g. `Lambertian`.
+    fn name(&self) -> &'static str {
+        let full = std::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full)
+    }
 }
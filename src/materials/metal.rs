@@ -1,7 +1,9 @@
 use crate::core::interaction::Interaction;
+use crate::core::onb::reflect;
 use crate::core::ray::Ray;
 use crate::core::vec3::{Color, Vec3, Vec3Ext};
 use crate::materials::material_trait::{Material, ScatterRecord};
+use crate::materials::params;
 
 #[derive(Debug)]
 pub struct Metal {
@@ -10,22 +12,33 @@ pub struct Metal {
 }
 
 impl Metal {
+    /// Metal of reflectance `albedo` whose reflections are blurred by `fuzz`, both
+    /// in [0, 1].
     pub fn new(albedo: Color, fuzz: f64) -> Self {
         Self {
-            albedo,
-            fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
+            albedo: params::reflectance("Metal albedo", albedo),
+            fuzz: params::unit("Metal fuzz", fuzz),
         }
     }
+
+    /// Metal of perceptual `roughness` in [0, 1], remapped so that equal steps
+    /// look equally blurrier.
+    pub fn from_roughness(albedo: Color, roughness: f64) -> Self {
+        Self::new(albedo, params::roughness_to_alpha(roughness))
+    }
 }
 
 impl Material for Metal {
     fn scatter(&self, r_in: &Ray, isect: &Interaction, srec: &mut ScatterRecord) -> bool {
-        let reflected = r_in.dir.normalize().reflect(&isect.geometry_normal);
-        let fuzzed = reflected + self.fuzz * Vec3::random_unit_vector();
+        let wo = isect.shading.to_local(&-r_in.dir.normalize());
+        let reflected = isect.shading.local(&reflect(&wo));
+        let fuzz = (self.fuzz * isect.material_params.roughness_scale).min(1.0);
+        let fuzzed = reflected + fuzz * Vec3::random_unit_vector();
 
-        srec.attenuation = self.albedo;
+        srec.attenuation = self.albedo.component_mul(&isect.material_params.tint);
         srec.skip_pdf = true;
-        srec.skip_pdf_ray = Ray::new(isect.p, fuzzed, r_in.time);
+        srec.skip_pdf_ray = Ray::new(isect.p, fuzzed, r_in.time)
+            .with_differentials(isect.reflected_differentials(r_in, &fuzzed));
 
         true
     }
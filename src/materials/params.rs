@@ -0,0 +1,115 @@
+use crate::core::vec3::Color;
+use std::sync::Mutex;
+
+/// Smallest microfacet alpha: smoother surfaces are numerically perfect mirrors,
+/// and narrower lobes only cause precision trouble.
+pub const MIN_ALPHA: f64 = 1e-3;
+/// Plausible indices of refraction, from vacuum to past diamond (2.42) and the
+/// semiconductors (silicon, 3.9). Nested dielectrics take their ratio themselves,
+/// so relative values below 1 are not needed.
+pub const MIN_IOR: f64 = 1.0;
+pub const MAX_IOR: f64 = 4.0;
+
+/// Warnings already printed, so a material built in a loop warns once.
+static WARNED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn warn(message: String) {
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if !warned.contains(&message) {
+        eprintln!("Warning: {}", message);
+        warned.push(message);
+    }
+}
+
+/// Perceptual roughness in [0, 1], as artists set it, to the alpha of microfacet
+/// distributions: its square, which makes equal steps of roughness look equal.
+pub fn roughness_to_alpha(roughness: f64) -> f64 {
+    unit("roughness", roughness).powi(2).max(MIN_ALPHA)
+}
+
+/// The parameter `name` clamped to [0, 1], warning if it was outside. Non-finite
+/// values become 0.
+pub fn unit(name: &str, value: f64) -> f64 {
+    match check_unit(name, value) {
+        Ok(value) => value,
+        Err(e) => {
+            warn(format!("{}, clamped", e));
+            if value.is_finite() {
+                value.clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// The reflectance `name` clamped to [0, 1] per channel, warning if it was
+/// outside: more than 1 reflects more light than arrives, and renders converge
+/// to the wrong brightness, or not at all.
+pub fn reflectance(name: &str, color: Color) -> Color {
+    color.map(|c| unit(name, c))
+}
+
+/// Reflectance clamped to [0, 1] per channel without a warning, for texture
+/// values looked up at every hit.
+pub fn clamp_reflectance(color: Color) -> Color {
+    color.map(|c| {
+        if c.is_finite() {
+            c.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    })
+}
+
+/// The index of refraction `name` clamped to [`MIN_IOR`, `MAX_IOR`], warning if it
+/// was outside.
+pub fn ior(name: &str, ior: f64) -> f64 {
+    match check_ior(name, ior) {
+        Ok(ior) => ior,
+        Err(e) => {
+            warn(format!("{}, clamped", e));
+            if ior.is_finite() {
+                ior.clamp(MIN_IOR, MAX_IOR)
+            } else {
+                MIN_IOR
+            }
+        }
+    }
+}
+
+/// Non-negative lengths and coefficients, such as absorption, warning about and
+/// zeroing negative ones.
+pub fn non_negative(name: &str, value: f64) -> f64 {
+    if value.is_finite() && value >= 0.0 {
+        value
+    } else {
+        warn(format!(
+            "{} {} is negative or not finite, using 0",
+            name, value
+        ));
+        0.0
+    }
+}
+
+/// Checks that the parameter `name` lies in [0, 1], for values given by the user,
+/// which are rejected rather than clamped.
+pub fn check_unit(name: &str, value: f64) -> Result<f64, String> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} {} is outside [0, 1]", name, value))
+    }
+}
+
+/// Checks that the index of refraction `name` is plausible, see `MIN_IOR`.
+pub fn check_ior(name: &str, ior: f64) -> Result<f64, String> {
+    if (MIN_IOR..=MAX_IOR).contains(&ior) {
+        Ok(ior)
+    } else {
+        Err(format!(
+            "{} {} is outside [{}, {}]",
+            name, ior, MIN_IOR, MAX_IOR
+        ))
+    }
+}
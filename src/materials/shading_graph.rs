@@ -0,0 +1,493 @@
+use crate::core::vec3::{Color, Vec3};
+use crate::materials::backface_culled::BackfaceCulled;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::layered::Layered;
+use crate::materials::material_trait::Material;
+use crate::materials::metal::Metal;
+use crate::textures::checker::CheckerTexture;
+use crate::textures::debug::{NormalTexture, StripesTexture, UvCheckerTexture};
+use crate::textures::image::ImageTexture;
+use crate::textures::noise::NoiseTexture;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture_trait::Texture;
+use crate::textures::uv_set::UvSet;
+use crate::textures::vertex_color::VertexColor;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Kind of value a node produces or one of its inputs accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Socket {
+    /// A literal number, e.g. `0.5`.
+    Number,
+    /// A literal triple, e.g. `0.8,0.1,0.1`.
+    Vector,
+    /// A texture node, or a literal color as a constant texture.
+    Texture,
+    Material,
+    /// A literal file name or other text.
+    Text,
+}
+
+/// Named inputs of a node kind.
+type InputList = &'static [(&'static str, Socket)];
+
+/// Node kinds with their output and their inputs, each optional unless its
+/// builder below requires it.
+const NODES: &[(&str, Socket, InputList)] = &[
+    ("color", Socket::Texture, &[("value", Socket::Vector)]),
+    (
+        "checker",
+        Socket::Texture,
+        &[
+            ("scale", Socket::Number),
+            ("even", Socket::Texture),
+            ("odd", Socket::Texture),
+        ],
+    ),
+    (
+        "noise",
+        Socket::Texture,
+        &[("scale", Socket::Number), ("velocity", Socket::Vector)],
+    ),
+    ("image", Socket::Texture, &[("file", Socket::Text)]),
+    ("uv-checker", Socket::Texture, &[("tiles", Socket::Number)]),
+    ("normals", Socket::Texture, &[]),
+    ("stripes", Socket::Texture, &[("period", Socket::Number)]),
+    (
+        "vertex-color",
+        Socket::Texture,
+        &[("fallback", Socket::Texture)],
+    ),
+    (
+        "uv-set",
+        Socket::Texture,
+        &[("texture", Socket::Texture), ("set", Socket::Number)],
+    ),
+    (
+        "lambertian",
+        Socket::Material,
+        &[("albedo", Socket::Texture)],
+    ),
+    (
+        "metal",
+        Socket::Material,
+        &[("albedo", Socket::Vector), ("roughness", Socket::Number)],
+    ),
+    (
+        "dielectric",
+        Socket::Material,
+        &[
+            ("ior", Socket::Number),
+            ("absorption", Socket::Vector),
+            ("priority", Socket::Number),
+        ],
+    ),
+    (
+        "diffuse-light",
+        Socket::Material,
+        &[("emit", Socket::Texture)],
+    ),
+    (
+        "layered",
+        Socket::Material,
+        &[
+            ("top", Socket::Material),
+            ("bottom", Socket::Material),
+            ("thickness", Socket::Number),
+            ("absorption", Socket::Vector),
+        ],
+    ),
+    (
+        "backface-culled",
+        Socket::Material,
+        &[("material", Socket::Material)],
+    ),
+];
+
+/// One line of a graph: `name = kind input=value ...`.
+#[derive(Debug, Clone)]
+struct Node {
+    name: String,
+    kind: &'static str,
+    output: Socket,
+    inputs: Vec<(String, String)>,
+    line: usize,
+}
+
+/// Textures and materials described declaratively, one node per line, and
+/// compiled into the usual trait objects:
+///
+/// ```text
+/// # Red and black tiles under clear lacquer
+/// tiles = checker scale=0.5 even=0.8,0.1,0.1 odd=0.05,0.05,0.05
+/// paint = lambertian albedo=tiles
+/// lacquer = dielectric ior=1.5
+/// output = layered top=lacquer bottom=paint
+/// ```
+///
+/// Inputs take literals (`0.5`, `r,g,b`, a file name) or the name of another node
+/// of the type they expect; color inputs take either. Nodes may be defined in any
+/// order, and one node may feed several others, which share it.
+#[derive(Debug, Clone)]
+pub struct ShadingGraph {
+    nodes: Vec<Node>,
+}
+
+/// A compiled node.
+#[derive(Debug, Clone)]
+enum Value {
+    Texture(Arc<dyn Texture>),
+    Material(Arc<dyn Material>),
+}
+
+impl ShadingGraph {
+    /// Reads and checks the graph in `filename`.
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(filename)
+            .map_err(|e| format!("Cannot read {}: {}", filename, e))?;
+        source.parse().map_err(|e| format!("{}: {}", filename, e))
+    }
+
+    /// The material the graph exports: the node `output`, or else the last
+    /// material defined.
+    pub fn output(&self) -> Option<&str> {
+        self.nodes
+            .iter()
+            .find(|node| node.name == "output")
+            .or_else(|| {
+                self.nodes
+                    .iter()
+                    .rev()
+                    .find(|node| node.output == Socket::Material)
+            })
+            .map(|node| node.name.as_str())
+    }
+
+    /// Compiles the material node `name` and everything it depends on.
+    pub fn material(&self, name: &str) -> Result<Arc<dyn Material>, String> {
+        match Compiler::new(self).build(name)? {
+            Value::Material(material) => Ok(material),
+            Value::Texture(_) => Err(format!("Node '{}' is a texture, not a material", name)),
+        }
+    }
+
+    /// Compiles the texture node `name` and everything it depends on.
+    pub fn texture(&self, name: &str) -> Result<Arc<dyn Texture>, String> {
+        match Compiler::new(self).build(name)? {
+            Value::Texture(texture) => Ok(texture),
+            Value::Material(_) => Err(format!("Node '{}' is a material, not a texture", name)),
+        }
+    }
+
+    fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Checks that every input names an input of its node and holds a value of
+    /// the type it expects, so graphs fail when loaded rather than when used.
+    fn check(&self) -> Result<(), String> {
+        for node in &self.nodes {
+            let (_, _, inputs) = NODES.iter().find(|(kind, ..)| *kind == node.kind).unwrap();
+            for (input, value) in &node.inputs {
+                let Some((_, socket)) = inputs.iter().find(|(name, _)| name == input) else {
+                    let available: Vec<&str> = inputs.iter().map(|(name, _)| *name).collect();
+                    return Err(format!(
+                        "line {}: Unknown input '{}' of {}. Available: {}",
+                        node.line,
+                        input,
+                        node.kind,
+                        available.join(", ")
+                    ));
+                };
+                let fits = match (socket, self.node(value)) {
+                    (Socket::Texture | Socket::Material, Some(source)) => source.output == *socket,
+                    (Socket::Material, None) => false,
+                    (Socket::Texture | Socket::Vector, None) => parse_vector(value).is_some(),
+                    (Socket::Number, None) => value.parse::<f64>().is_ok_and(f64::is_finite),
+                    (Socket::Text, None) => true,
+                    (Socket::Number | Socket::Vector | Socket::Text, Some(_)) => false,
+                };
+                if !fits {
+                    return Err(format!(
+                        "line {}: Input '{}' of {} expects {:?} but got '{}'",
+                        node.line, input, node.kind, socket, value
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ShadingGraph {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut nodes: Vec<Node> = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, definition) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: Expected <name> = <node>", line_number))?;
+            let name = name.trim().to_string();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!(
+                    "line {}: Invalid node name '{}'",
+                    line_number, name
+                ));
+            }
+            if nodes.iter().any(|node| node.name == name) {
+                return Err(format!(
+                    "line {}: Node '{}' is defined twice",
+                    line_number, name
+                ));
+            }
+
+            let mut words = definition.split_whitespace();
+            let kind = words.next().unwrap_or("");
+            let &(kind, output, _) = NODES.iter().find(|(k, ..)| *k == kind).ok_or_else(|| {
+                let available: Vec<&str> = NODES.iter().map(|(kind, ..)| *kind).collect();
+                format!(
+                    "line {}: Unknown node '{}'. Available: {}",
+                    line_number,
+                    kind,
+                    available.join(", ")
+                )
+            })?;
+            let inputs = words
+                .map(|word| {
+                    word.split_once('=')
+                        .map(|(input, value)| (input.to_string(), value.to_string()))
+                        .ok_or_else(|| {
+                            format!(
+                                "line {}: Expected <input>=<value> but got '{}'",
+                                line_number, word
+                            )
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+            nodes.push(Node {
+                name,
+                kind,
+                output,
+                inputs,
+                line: line_number,
+            });
+        }
+
+        let graph = Self { nodes };
+        graph.check()?;
+        Ok(graph)
+    }
+}
+
+/// Builds nodes on demand, each once, following their inputs depth first.
+struct Compiler<'a> {
+    graph: &'a ShadingGraph,
+    built: HashMap<String, Value>,
+    /// Nodes being built, to report cycles.
+    stack: Vec<String>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(graph: &'a ShadingGraph) -> Self {
+        Self {
+            graph,
+            built: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.built.get(name) {
+            return Ok(value.clone());
+        }
+        let node = self
+            .graph
+            .node(name)
+            .ok_or_else(|| format!("No node '{}' in the graph", name))?;
+        if self.stack.iter().any(|n| n == name) {
+            return Err(format!(
+                "line {}: Node '{}' depends on itself through {}",
+                node.line,
+                name,
+                self.stack.join(" -> ")
+            ));
+        }
+
+        self.stack.push(name.to_string());
+        // Errors of the inputs already name their own line
+        let value = self.build_node(node).map_err(|e| {
+            if e.starts_with("line ") {
+                e
+            } else {
+                format!("line {}: {}", node.line, e)
+            }
+        })?;
+        self.stack.pop();
+        self.built.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    fn build_node(&mut self, node: &Node) -> Result<Value, String> {
+        let texture = |t: Arc<dyn Texture>| Ok(Value::Texture(t));
+        let material = |m: Arc<dyn Material>| Ok(Value::Material(m));
+        let mut inputs = Inputs {
+            node,
+            compiler: self,
+        };
+
+        match node.kind {
+            "color" => texture(Arc::new(SolidColor::new(inputs.vector("value")?))),
+            "checker" => {
+                let scale = inputs.number_or("scale", 1.0)?;
+                let even = inputs.texture_or("even", Color::new(1.0, 1.0, 1.0))?;
+                let odd = inputs.texture_or("odd", Color::zeros())?;
+                texture(Arc::new(CheckerTexture::new(scale, even, odd)))
+            }
+            "noise" => {
+                let noise = NoiseTexture::new(inputs.number_or("scale", 1.0)?)
+                    .with_velocity(inputs.vector_or("velocity", Vec3::zeros())?);
+                texture(Arc::new(noise))
+            }
+            "image" => texture(Arc::new(ImageTexture::new(inputs.text("file")?))),
+            "uv-checker" => texture(Arc::new(UvCheckerTexture::new(
+                inputs.number_or("tiles", 8.0)? as u32,
+            ))),
+            "normals" => texture(Arc::new(NormalTexture)),
+            "stripes" => texture(Arc::new(StripesTexture::new(inputs.number("period")?))),
+            "vertex-color" => texture(Arc::new(VertexColor::new(
+                inputs.texture_or("fallback", Color::new(0.5, 0.5, 0.5))?,
+            ))),
+            "uv-set" => {
+                let set = inputs.number("set")?;
+                texture(Arc::new(UvSet::new(
+                    inputs.texture("texture")?,
+                    set as usize,
+                )))
+            }
+            "lambertian" => material(Arc::new(Lambertian::new(
+                inputs.texture_or("albedo", Color::new(0.5, 0.5, 0.5))?,
+            ))),
+            "metal" => material(Arc::new(Metal::from_roughness(
+                inputs.vector_or("albedo", Color::new(0.9, 0.9, 0.9))?,
+                inputs.number_or("roughness", 0.0)?,
+            ))),
+            "dielectric" => {
+                let dielectric = Dielectric::new(inputs.number_or("ior", 1.5)?)
+                    .with_absorption(inputs.vector_or("absorption", Color::zeros())?)
+                    .with_priority(inputs.number_or("priority", 0.0)? as u32);
+                material(Arc::new(dielectric))
+            }
+            "diffuse-light" => material(Arc::new(DiffuseLight::new(inputs.texture("emit")?))),
+            "layered" => {
+                let layered = Layered::new(inputs.material("top")?, inputs.material("bottom")?)
+                    .with_medium(
+                        inputs.number_or("thickness", 0.0)?,
+                        inputs.vector_or("absorption", Color::zeros())?,
+                    );
+                material(Arc::new(layered))
+            }
+            "backface-culled" => {
+                material(Arc::new(BackfaceCulled::new(inputs.material("material")?)))
+            }
+            kind => unreachable!("node kind {} has no builder", kind),
+        }
+    }
+}
+
+/// Typed access to the inputs of one node; `check` has already validated them.
+struct Inputs<'n, 'c, 'a> {
+    node: &'n Node,
+    compiler: &'c mut Compiler<'a>,
+}
+
+impl Inputs<'_, '_, '_> {
+    fn get(&self, input: &str) -> Option<&str> {
+        self.node
+            .inputs
+            .iter()
+            .find(|(name, _)| name == input)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn required(&self, input: &str) -> Result<&str, String> {
+        self.get(input)
+            .ok_or_else(|| format!("{} needs the input '{}'", self.node.kind, input))
+    }
+
+    fn number(&self, input: &str) -> Result<f64, String> {
+        self.required(input)?
+            .parse()
+            .map_err(|_| format!("Invalid number for '{}'", input))
+    }
+
+    fn number_or(&self, input: &str, default: f64) -> Result<f64, String> {
+        match self.get(input) {
+            Some(_) => self.number(input),
+            None => Ok(default),
+        }
+    }
+
+    fn vector(&self, input: &str) -> Result<Vec3, String> {
+        let value = self.required(input)?;
+        parse_vector(value).ok_or_else(|| format!("Invalid x,y,z for '{}'", input))
+    }
+
+    fn vector_or(&self, input: &str, default: Vec3) -> Result<Vec3, String> {
+        match self.get(input) {
+            Some(_) => self.vector(input),
+            None => Ok(default),
+        }
+    }
+
+    fn text(&self, input: &str) -> Result<&str, String> {
+        self.required(input)
+    }
+
+    fn texture(&mut self, input: &str) -> Result<Arc<dyn Texture>, String> {
+        let value = self.required(input)?.to_string();
+        if let Some(color) = parse_vector(&value) {
+            return Ok(Arc::new(SolidColor::new(color)));
+        }
+        match self.compiler.build(&value)? {
+            Value::Texture(texture) => Ok(texture),
+            Value::Material(_) => Err(format!("Input '{}' expects a texture", input)),
+        }
+    }
+
+    fn texture_or(&mut self, input: &str, default: Color) -> Result<Arc<dyn Texture>, String> {
+        match self.get(input) {
+            Some(_) => self.texture(input),
+            None => Ok(Arc::new(SolidColor::new(default))),
+        }
+    }
+
+    fn material(&mut self, input: &str) -> Result<Arc<dyn Material>, String> {
+        let value = self.required(input)?.to_string();
+        match self.compiler.build(&value)? {
+            Value::Material(material) => Ok(material),
+            Value::Texture(_) => Err(format!("Input '{}' expects a material", input)),
+        }
+    }
+}
+
+/// `x,y,z` with finite components.
+fn parse_vector(s: &str) -> Option<Vec3> {
+    let values: Vec<f64> = s
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, z] if values.iter().all(|v| v.is_finite()) => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
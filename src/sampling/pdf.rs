@@ -8,6 +8,7 @@ use std::fmt::Debug;
 use std::sync::Arc;
 
 /// Probability Density Function trait for importance sampling.
+#[allow(clippy::upper_case_acronyms)]
 pub trait PDF: Send + Sync + Debug {
     /// Returns the probability density value for a given direction.
     fn value(&self, direction: &Vec3) -> f64;
@@ -28,6 +29,12 @@ impl CosinePDF {
             uvw: ONB::build_from_w(w),
         }
     }
+
+    /// Cosine lobe about the `w` axis of an existing frame, such as the shading
+    /// frame of a hit, saving building another.
+    pub fn from_frame(uvw: ONB) -> Self {
+        Self { uvw }
+    }
 }
 
 impl PDF for CosinePDF {
@@ -55,15 +62,109 @@ impl PDF for SpherePDF {
     }
 }
 
+// --- Cone PDF (uniform within a cone, for regularized specular lobes) ---
+#[derive(Debug)]
+pub struct ConePDF {
+    uvw: ONB,
+    cos_theta_max: f64,
+}
+
+impl ConePDF {
+    /// Uniform cone around `axis` with half-angle `theta_max` (radians).
+    pub fn new(axis: &Vec3, theta_max: f64) -> Self {
+        Self {
+            uvw: ONB::build_from_w(axis),
+            cos_theta_max: theta_max.cos(),
+        }
+    }
+}
+
+impl PDF for ConePDF {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.normalize().dot(&self.uvw.w());
+        if cosine < self.cos_theta_max {
+            0.0
+        } else {
+            1.0 / (2.0 * PI * (1.0 - self.cos_theta_max))
+        }
+    }
+
+    fn generate(&self) -> Vec3 {
+        let r1 = random_double();
+        let r2 = random_double();
+        let z = 1.0 - r1 * (1.0 - self.cos_theta_max);
+        let phi = 2.0 * PI * r2;
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        self.uvw
+            .local(&Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
+}
+
+// --- Henyey-Greenstein PDF (for anisotropic media) ---
+#[derive(Debug)]
+pub struct HenyeyGreensteinPDF {
+    uvw: ONB,
+    g: f64,
+}
+
+impl HenyeyGreensteinPDF {
+    /// Scattering of light travelling along `direction`, with mean cosine `g` in
+    /// (-1, 1): positive values scatter forward, negative ones back.
+    pub fn new(direction: &Vec3, g: f64) -> Self {
+        Self {
+            uvw: ONB::build_from_w(direction),
+            g,
+        }
+    }
+
+    /// Phase function value for the cosine between the incoming and scattered directions.
+    pub fn phase(g: f64, cos_theta: f64) -> f64 {
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl PDF for HenyeyGreensteinPDF {
+    fn value(&self, direction: &Vec3) -> f64 {
+        Self::phase(self.g, direction.normalize().dot(&self.uvw.w()))
+    }
+
+    fn generate(&self) -> Vec3 {
+        let r1 = random_double();
+        let r2 = random_double();
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * r1
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * r1);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * r2;
+
+        self.uvw.local(&Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        ))
+    }
+}
+
 // --- Hittable PDF (for Light Sampling) ---
 pub struct HittablePDF {
     objects: Arc<dyn Hittable>,
     origin: Point3,
+    time: f64,
 }
 
 impl HittablePDF {
-    pub fn new(objects: Arc<dyn Hittable>, origin: Point3) -> Self {
-        Self { objects, origin }
+    pub fn new(objects: Arc<dyn Hittable>, origin: Point3, time: f64) -> Self {
+        Self {
+            objects,
+            origin,
+            time,
+        }
     }
 }
 
@@ -79,11 +180,11 @@ impl PDF for HittablePDF {
             return 0.0;
         }
 
-        self.objects.pdf_value(&self.origin, direction)
+        self.objects.pdf_value(&self.origin, direction, self.time)
     }
 
     fn generate(&self) -> Vec3 {
-        self.objects.random(&self.origin)
+        self.objects.random(&self.origin, self.time)
     }
 }
 
@@ -117,3 +218,11 @@ impl PDF for MixturePDF {
         }
     }
 }
+
+/// Power heuristic (beta = 2) MIS weight for a sample drawn with density `f_pdf`
+/// when another strategy could have produced it with density `g_pdf`.
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f2 = f_pdf * f_pdf;
+    let g2 = g_pdf * g_pdf;
+    if f2 + g2 <= 0.0 { 0.0 } else { f2 / (f2 + g2) }
+}
@@ -1,6 +1,14 @@
-use rand::Rng;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
 use std::f64::consts::PI;
 
+thread_local! {
+    /// Per-thread generator behind all the sampling functions below.
+    /// Seeded from the OS unless `seed_thread` pins it down.
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
+
 #[inline]
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
@@ -8,18 +16,37 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
 
 #[inline]
 pub fn random_double() -> f64 {
-    let mut rng = rand::rng();
-    rng.random()
+    RNG.with(|rng| rng.borrow_mut().random())
 }
 
 #[inline]
 pub fn random_double_range(min: f64, max: f64) -> f64 {
-    let mut rng = rand::rng();
-    rng.random_range(min..max)
+    RNG.with(|rng| rng.borrow_mut().random_range(min..max))
 }
 
 #[inline]
 pub fn random_int_range(min: i32, max: i32) -> i32 {
-    let mut rng = rand::rng();
-    rng.random_range(min..=max)
+    RNG.with(|rng| rng.borrow_mut().random_range(min..=max))
+}
+
+/// Reseeds the calling thread's generator, making everything it draws next reproducible.
+pub fn seed_thread(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+/// A seed for a generator of its own, drawn from the calling thread's, so it is
+/// reproducible whenever the thread is seeded.
+pub fn random_seed() -> u64 {
+    RNG.with(|rng| rng.borrow_mut().random())
+}
+
+/// Seed of an independent stream for one sample of one pixel, so a render does not
+/// depend on which thread happens to trace which pixel (SplitMix64 finalizer).
+pub fn sample_seed(seed: u64, pixel: u64, sample: u64) -> u64 {
+    let mut z = seed
+        ^ pixel.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ sample.wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
@@ -1,3 +1,12 @@
+pub mod city;
+pub mod clouds;
 pub mod cornell_box;
 pub mod final_scene;
 pub mod many_balls;
+pub mod ply;
+pub mod pool;
+pub mod scatter;
+pub mod showcase;
+pub mod studio;
+pub mod terrain;
+pub mod volume;
@@ -0,0 +1,195 @@
+use crate::core::camera::Camera;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::hittable::Hittable;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::quad;
+use crate::geometry::quad::Quad;
+use crate::geometry::scene_graph::SceneNode;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::transforms::animated::Keyframe;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::metal::Metal;
+use crate::sampling::random::{random_double, random_double_range};
+use crate::textures::solid_color::SolidColor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const BLOCKS_PER_SIDE: u32 = 12;
+const BLOCK_SIZE: f64 = 12.0;
+const STREET_WIDTH: f64 = 4.0;
+const FLOOR_HEIGHT: f64 = 3.0;
+const WINDOW_SPACING: f64 = 2.0;
+
+/// How the many lights of the city are weighted for light sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightSelection {
+    /// Every light equally likely.
+    Uniform,
+    /// Proportional to surface area (the `HittableList` default).
+    Area,
+    /// Proportional to emitted power (`Hittable::power`).
+    Power,
+}
+
+impl FromStr for LightSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Self::Uniform),
+            "area" => Ok(Self::Area),
+            "power" => Ok(Self::Power),
+            _ => Err(format!("Unknown light selection '{}'", s)),
+        }
+    }
+}
+
+/// Night-time city blocks with thousands of lit windows and streetlights,
+/// a stress test for light selection.
+pub fn build_city(
+    selection: LightSelection,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    let mut add_light = |light: Arc<dyn Hittable>| {
+        match selection {
+            LightSelection::Uniform => lights.add_weighted(light.clone(), 1.0),
+            LightSelection::Area => lights.add(light.clone()),
+            LightSelection::Power => lights.add_by_power(light.clone()),
+        }
+        light
+    };
+
+    let extent = BLOCKS_PER_SIDE as f64 * BLOCK_SIZE;
+
+    // Ground
+    let asphalt = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.08, 0.08, 0.09,
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-extent, 0.0, -extent),
+        Vec3::new(3.0 * extent, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 3.0 * extent),
+        asphalt,
+    )));
+
+    // Buildings with lit windows
+    let mut buildings = HittableList::new();
+    let mut windows = HittableList::new();
+    let window_colors = [
+        Color::new(1.0, 0.85, 0.55), // Incandescent
+        Color::new(0.85, 0.9, 1.0),  // Fluorescent
+        Color::new(1.0, 0.7, 0.4),   // Warm
+    ];
+
+    for i in 0..BLOCKS_PER_SIDE {
+        for j in 0..BLOCKS_PER_SIDE {
+            let x0 = i as f64 * BLOCK_SIZE + 0.5 * STREET_WIDTH;
+            let z0 = j as f64 * BLOCK_SIZE + 0.5 * STREET_WIDTH;
+            let x1 = x0 + BLOCK_SIZE - STREET_WIDTH;
+            let z1 = z0 + BLOCK_SIZE - STREET_WIDTH;
+            let floors = (random_double_range(3.0, 14.0) as u32).max(1);
+            let height = floors as f64 * FLOOR_HEIGHT;
+
+            let grey = random_double_range(0.2, 0.5);
+            let facade = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+                grey, grey, grey,
+            ))));
+            buildings.add(Arc::new(quad::box_new(
+                Point3::new(x0, 0.0, z0),
+                Point3::new(x1, height, z1),
+                facade,
+            )));
+
+            // Faces as (corner, horizontal direction); the outward normal is up x across
+            let faces = [
+                (Point3::new(x0, 0.0, z0), Vec3::new(1.0, 0.0, 0.0)),
+                (Point3::new(x1, 0.0, z0), Vec3::new(0.0, 0.0, 1.0)),
+                (Point3::new(x1, 0.0, z1), Vec3::new(-1.0, 0.0, 0.0)),
+                (Point3::new(x0, 0.0, z1), Vec3::new(0.0, 0.0, -1.0)),
+            ];
+            for (corner, across) in faces {
+                let outward = Vec3::new(0.0, 1.0, 0.0).cross(&across);
+                let columns = ((BLOCK_SIZE - STREET_WIDTH) / WINDOW_SPACING) as u32;
+
+                for floor in 0..floors {
+                    for column in 0..columns {
+                        if random_double() > 0.4 {
+                            continue; // Unlit
+                        }
+
+                        let color = window_colors[(random_double() * 3.0) as usize % 3];
+                        let radiance = color * random_double_range(1.5, 4.0);
+                        let origin = corner
+                            + across * (column as f64 + 0.25) * WINDOW_SPACING
+                            + Vec3::new(0.0, (floor as f64 + 0.3) * FLOOR_HEIGHT, 0.0)
+                            + outward * 0.01;
+
+                        let light_mat =
+                            Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(radiance))));
+                        let window: Arc<dyn Hittable> = Arc::new(Quad::new(
+                            origin,
+                            Vec3::new(0.0, 0.5 * FLOOR_HEIGHT, 0.0),
+                            across * 0.5 * WINDOW_SPACING,
+                            light_mat,
+                        ));
+                        windows.add(add_light(window));
+                    }
+                }
+            }
+        }
+    }
+    world.add(Arc::new(BvhNode::new(&buildings)));
+    world.add(Arc::new(BvhNode::new(&windows)));
+
+    // Streetlights at every intersection, one pole and lamp shared by all of them
+    let pole_mat = Arc::new(Metal::new(Color::new(0.3, 0.3, 0.3), 0.4));
+    let lamp_radiance = Color::new(40.0, 30.0, 15.0);
+    let lamp_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(lamp_radiance))));
+    let pole: Arc<dyn Hittable> = Arc::new(quad::box_new(
+        Point3::new(-0.1, 0.0, -0.1),
+        Point3::new(0.1, 5.0, 0.1),
+        pole_mat,
+    ));
+    let lamp: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(0.0, 5.3, 0.0), 0.3, lamp_mat));
+    let mut streetlights = SceneNode::new("streetlights");
+    for i in 0..=BLOCKS_PER_SIDE {
+        for j in 0..=BLOCKS_PER_SIDE {
+            let x = i as f64 * BLOCK_SIZE + 0.3 * STREET_WIDTH;
+            let z = j as f64 * BLOCK_SIZE + 0.3 * STREET_WIDTH;
+            streetlights = streetlights.with_child(
+                SceneNode::new(&format!("streetlight_{}_{}", i, j))
+                    .with_transform(Keyframe::translation(Vec3::new(x, 0.0, z)))
+                    .with_object(pole.clone())
+                    .with_light(lamp.clone()),
+            );
+        }
+    }
+    let streetlights = streetlights.flatten();
+    for lamp in streetlights.lights {
+        add_light(lamp);
+    }
+    world.add(Arc::new(BvhNode::new(&streetlights.world)));
+
+    println!("City has {} lights", lights.objects.len());
+
+    // Camera Setup
+    let mut cam = Camera::new(image_width, 16.0 / 9.0);
+    cam.vfov = 45.0;
+    cam.lookfrom = Point3::new(-0.2 * extent, 0.6 * extent, -0.4 * extent);
+    cam.lookat = Point3::new(0.5 * extent, 0.0, 0.5 * extent);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::new(0.01, 0.012, 0.03); // Night sky
+
+    cam.initialize();
+
+    (Arc::new(world), Arc::new(lights), cam)
+}
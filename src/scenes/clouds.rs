@@ -0,0 +1,151 @@
+use crate::core::aabb::Aabb;
+use crate::core::camera::Camera;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::quad::Quad;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::vdb_medium::{DensityField, VdbMedium};
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::sampling::random::random_double_range;
+use crate::textures::perlin::Perlin;
+use crate::textures::solid_color::SolidColor;
+use std::sync::Arc;
+
+/// Puffs making up the cumulus.
+const PUFFS: u32 = 24;
+/// Height of the cloud's flat base, where condensation starts.
+const CLOUD_BASE: f64 = 9.0;
+/// Extinction per unit length in the cloud's core.
+const CLOUD_DENSITY: f64 = 4.0;
+
+/// Procedural cumulus: overlapping spherical puffs piled on a flat base, their
+/// edges eroded by turbulence into wisps.
+#[derive(Debug)]
+struct Cumulus {
+    puffs: Vec<(Point3, f64)>,
+    noise: Perlin,
+    bbox: Aabb,
+}
+
+impl Cumulus {
+    fn new() -> Self {
+        // Large puffs along the base, smaller ones heaped towards the top
+        let puffs: Vec<(Point3, f64)> = (0..PUFFS)
+            .map(|k| {
+                let height = k as f64 / PUFFS as f64;
+                let radius = random_double_range(2.0, 3.5) * (1.0 - 0.5 * height);
+                let spread = 7.0 * (1.0 - 0.6 * height);
+                let center = Point3::new(
+                    random_double_range(-spread, spread),
+                    CLOUD_BASE + 1.0 + 6.0 * height,
+                    random_double_range(-0.5 * spread, 0.5 * spread),
+                );
+                (center, radius)
+            })
+            .collect();
+
+        let bbox = puffs
+            .iter()
+            .map(|(center, radius)| {
+                let r = Vec3::repeat(*radius);
+                Aabb::new_point(center - r, center + r)
+            })
+            .reduce(|a, b| a.merge(&b))
+            .unwrap_or_else(Aabb::empty);
+
+        Self {
+            puffs,
+            noise: Perlin::new(),
+            bbox,
+        }
+    }
+}
+
+impl DensityField for Cumulus {
+    fn density(&self, p: &Point3) -> f64 {
+        if p.y < CLOUD_BASE {
+            return 0.0;
+        }
+        let coverage = self
+            .puffs
+            .iter()
+            .map(|(center, radius)| 1.0 - (p - center).norm() / radius)
+            .fold(0.0, f64::max);
+        if coverage <= 0.0 {
+            return 0.0;
+        }
+
+        let erosion = self.noise.turb(&(p * 0.8), 5);
+        let base = ((p.y - CLOUD_BASE) / 0.5).min(1.0);
+        ((coverage - 0.3 * erosion) * 4.0).clamp(0.0, 1.0) * base
+    }
+
+    fn maximum(&self) -> f64 {
+        1.0
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// A single cumulus against a blue sky, lit from the side by a low sun so its
+/// forward-scattering silver lining and bright, soft interior show. Uses the cloud
+/// preset, with the multiple scattering approximation unless `single_scattering`.
+pub fn build_clouds(
+    single_scattering: bool,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let cloud = VdbMedium::from_field(
+        Arc::new(Cumulus::new()),
+        CLOUD_DENSITY,
+        Arc::new(SolidColor::new_rgb(1.0, 1.0, 1.0)),
+    )
+    .with_cloud_preset(!single_scattering);
+    world.add(Arc::new(cloud));
+
+    // Ground
+    let ground_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.3, 0.4, 0.2,
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-1000.0, 0.0, -1000.0),
+        Vec3::new(2000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2000.0),
+        ground_mat,
+    )));
+
+    // Sun: far away, above and behind the cloud to the right
+    let sun_direction = Vec3::new(0.6, 0.45, 0.65).normalize();
+    let sun_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new_rgb(
+        12000.0, 11000.0, 9500.0,
+    ))));
+    let sun = Arc::new(Sphere::new(
+        Point3::origin() + 1000.0 * sun_direction,
+        10.0,
+        sun_mat,
+    ));
+    world.add(sun.clone());
+    lights.add(sun);
+
+    // Camera Setup
+    let mut cam = Camera::new(image_width, 16.0 / 9.0);
+    cam.vfov = 35.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, -30.0);
+    cam.lookat = Point3::new(0.0, 12.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::new(0.35, 0.55, 0.9);
+
+    cam.initialize();
+
+    (Arc::new(world), Arc::new(lights), cam)
+}
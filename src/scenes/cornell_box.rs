@@ -1,18 +1,106 @@
 use crate::core::camera::Camera;
 use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::bvh::BvhNode;
+use crate::geometry::constant_medium::ConstantMedium;
+use crate::geometry::cylinder::Cylinder;
+use crate::geometry::hittable::Hittable;
 use crate::geometry::hittable_list::HittableList;
+use crate::geometry::named::Named;
 use crate::geometry::quad;
 use crate::geometry::quad::Quad;
 use crate::geometry::sphere::Sphere;
 use crate::geometry::transforms::rotate::RotateY;
 use crate::geometry::transforms::translate::Translate;
+use crate::geometry::triangle::Triangle;
 use crate::materials::dielectric::Dielectric;
 use crate::materials::diffuse_light::DiffuseLight;
 use crate::materials::lambertian::Lambertian;
+use crate::materials::material_trait::Material;
 use crate::textures::solid_color::SolidColor;
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// What stands on the floor of the Cornell box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornellContents {
+    /// Book 2: a tall and a short box.
+    Boxes,
+    /// Book 3 cover: a tall box and a glass sphere.
+    Sphere,
+    /// A tall box and a triangle mesh (icosahedron).
+    Mesh,
+    /// A tall box and a capped column.
+    Cylinder,
+}
+
+impl FromStr for CornellContents {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "boxes" => Ok(Self::Boxes),
+            "sphere" => Ok(Self::Sphere),
+            "mesh" => Ok(Self::Mesh),
+            "cylinder" => Ok(Self::Cylinder),
+            _ => Err(format!("Unknown Cornell box contents '{}'", s)),
+        }
+    }
+}
+
+/// Variant of the Cornell box. The default is the Book 3 "Glass Sphere" scene.
+#[derive(Debug, Clone)]
+pub struct CornellBoxConfig {
+    contents: CornellContents,
+    light_scale: f64,
+    light_intensity: f64,
+    left_wall: Color,
+    right_wall: Color,
+    smoke: bool,
+}
+
+impl Default for CornellBoxConfig {
+    fn default() -> Self {
+        Self {
+            contents: CornellContents::Sphere,
+            light_scale: 1.0,
+            light_intensity: 15.0,
+            left_wall: Color::new(0.12, 0.45, 0.15),
+            right_wall: Color::new(0.65, 0.05, 0.05),
+            smoke: false,
+        }
+    }
+}
+
+impl CornellBoxConfig {
+    pub fn with_contents(mut self, contents: CornellContents) -> Self {
+        self.contents = contents;
+        self
+    }
+
+    /// Scales the ceiling light about its center (1.0 is the 130x105 book light)
+    /// and sets its radiance.
+    pub fn with_light(mut self, scale: f64, intensity: f64) -> Self {
+        self.light_scale = scale;
+        self.light_intensity = intensity;
+        self
+    }
+
+    /// Colors of the left (green) and right (red) walls, as seen from the camera.
+    pub fn with_walls(mut self, left: Color, right: Color) -> Self {
+        self.left_wall = left;
+        self.right_wall = right;
+        self
+    }
+
+    /// Replaces the solid boxes with black and white smoke, as in Book 2.
+    pub fn with_smoke(mut self, smoke: bool) -> Self {
+        self.smoke = smoke;
+        self
+    }
+}
+
 pub fn build_cornell_box(
+    config: &CornellBoxConfig,
     image_width: u32,
     samples: u32,
     max_depth: u32,
@@ -21,64 +109,82 @@ pub fn build_cornell_box(
     let mut lights = HittableList::new();
 
     // Materials
-    let red_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
-        0.65, 0.05, 0.05,
+    let red_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+        config.right_wall,
     ))));
     let white_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
         0.73, 0.73, 0.73,
     ))));
-    let green_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
-        0.12, 0.45, 0.15,
-    ))));
-    let light_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new_rgb(
-        15.0, 15.0, 15.0,
-    ))));
+    let green_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new(config.left_wall))));
+    let light_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(Color::repeat(
+        config.light_intensity,
+    )))));
 
     // Cornell Box Walls
-    world.add(Arc::new(Quad::new(
-        Point3::new(555.0, 0.0, 555.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(-555.0, 0.0, 0.0),
-        white_mat.clone(),
+    world.add(Arc::new(Named::new(
+        Arc::new(Quad::new(
+            Point3::new(555.0, 0.0, 555.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(-555.0, 0.0, 0.0),
+            white_mat.clone(),
+        )),
+        1,
+        "back_wall",
     )));
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 0.0, 555.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(0.0, 0.0, -555.0),
-        red_mat.clone(),
+    world.add(Arc::new(Named::new(
+        Arc::new(Quad::new(
+            Point3::new(0.0, 0.0, 555.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, -555.0),
+            red_mat.clone(),
+        )),
+        2,
+        "right_wall",
     ))); // Right
-    world.add(Arc::new(Quad::new(
-        Point3::new(555.0, 0.0, 555.0),
-        Vec3::new(0.0, 0.0, -555.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        green_mat.clone(),
+    world.add(Arc::new(Named::new(
+        Arc::new(Quad::new(
+            Point3::new(555.0, 0.0, 555.0),
+            Vec3::new(0.0, 0.0, -555.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            green_mat.clone(),
+        )),
+        3,
+        "left_wall",
     ))); // Left
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 555.0, 0.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        white_mat.clone(),
+    world.add(Arc::new(Named::new(
+        Arc::new(Quad::new(
+            Point3::new(0.0, 555.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            white_mat.clone(),
+        )),
+        4,
+        "ceiling",
     ))); // Top
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 0.0, 0.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        white_mat.clone(),
+    world.add(Arc::new(Named::new(
+        Arc::new(Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            white_mat.clone(),
+        )),
+        5,
+        "floor",
     ))); // Bottom
 
-    // Light
+    // Light, scaled about its center
+    let light_u = Vec3::new(-130.0, 0.0, 0.0) * config.light_scale;
+    let light_v = Vec3::new(0.0, 0.0, -105.0) * config.light_scale;
     let light = Arc::new(Quad::new(
-        Point3::new(343.0, 554.0, 332.0),
-        Vec3::new(-130.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -105.0),
+        Point3::new(278.0, 554.0, 279.5) - 0.5 * (light_u + light_v),
+        light_u,
+        light_v,
         light_mat.clone(),
     ));
-    world.add(light.clone());
+    world.add(Arc::new(Named::new(light.clone(), 6, "light")));
     lights.add(light.clone());
 
-    // Objects Match Book 3 "Cornell Box with Glass Sphere" cover
-
-    // Box 1 (Rotated & Translated)
+    // Tall box, common to all variants
     let box1 = quad::box_new(
         Point3::new(0.0, 0.0, 0.0),
         Point3::new(165.0, 330.0, 165.0),
@@ -86,19 +192,72 @@ pub fn build_cornell_box(
     );
     let box1_rot = Arc::new(RotateY::new(Arc::new(box1), 15.0));
     let box1_trans = Arc::new(Translate::new(box1_rot, Vec3::new(265.0, 0.0, 295.0)));
-    world.add(box1_trans);
-
-    // Glass Sphere
-    let glass_mat = Arc::new(Dielectric::new(1.5));
-    let glass_sphere = Arc::new(Sphere::new(
-        Point3::new(190.0, 90.0, 190.0),
-        90.0,
-        glass_mat,
-    ));
-    world.add(glass_sphere.clone());
+    add_solid(
+        &mut world,
+        box1_trans,
+        (7, "tall_box"),
+        Color::zeros(),
+        config.smoke,
+    );
 
-    // Add glass sphere to lights for importance sampling (Book 3 technique for caustics)
-    lights.add(glass_sphere);
+    match config.contents {
+        CornellContents::Boxes => {
+            let box2 = quad::box_new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(165.0, 165.0, 165.0),
+                white_mat.clone(),
+            );
+            let box2_rot = Arc::new(RotateY::new(Arc::new(box2), -18.0));
+            let box2_trans = Arc::new(Translate::new(box2_rot, Vec3::new(130.0, 0.0, 65.0)));
+            add_solid(
+                &mut world,
+                box2_trans,
+                (8, "short_box"),
+                Color::new(1.0, 1.0, 1.0),
+                config.smoke,
+            );
+        }
+        CornellContents::Sphere => {
+            let glass_mat = Arc::new(Dielectric::new(1.5));
+            let glass_sphere = Arc::new(Sphere::new(
+                Point3::new(190.0, 90.0, 190.0),
+                90.0,
+                glass_mat,
+            ));
+            // Caustics are handled by BSDF sampling through the glass with MIS, so the
+            // sphere no longer needs to be in the light list
+            world.add(Arc::new(Named::new(glass_sphere, 8, "glass_sphere")));
+        }
+        CornellContents::Mesh => {
+            let mut mesh = HittableList::new();
+            for triangle in mesh_triangles() {
+                mesh.add(triangle);
+            }
+            world.add(Arc::new(Named::new(
+                Arc::new(BvhNode::new(&mesh)),
+                8,
+                "mesh",
+            )));
+        }
+        CornellContents::Cylinder => {
+            let column = Cylinder::new(
+                Point3::new(185.0, 0.0, 170.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                70.0,
+                0.0,
+                200.0,
+                white_mat.clone(),
+            )
+            .with_caps();
+            add_solid(
+                &mut world,
+                Arc::new(column),
+                (8, "column"),
+                Color::new(1.0, 1.0, 1.0),
+                config.smoke,
+            );
+        }
+    }
 
     // Camera Setup
     let mut cam = Camera::new(image_width, 1.0);
@@ -115,3 +274,95 @@ pub fn build_cornell_box(
 
     (Arc::new(world), Arc::new(lights), cam)
 }
+
+/// Adds a named solid object, or a smoke volume with its shape when `smoke` is set.
+fn add_solid(
+    world: &mut HittableList,
+    object: Arc<dyn Hittable>,
+    (id, name): (u32, &str),
+    smoke_color: Color,
+    smoke: bool,
+) {
+    let object: Arc<dyn Hittable> = if smoke {
+        Arc::new(ConstantMedium::new(
+            object,
+            0.01,
+            Arc::new(SolidColor::new(smoke_color)),
+        ))
+    } else {
+        object
+    };
+    world.add(Arc::new(Named::new(object, id, name)));
+}
+
+/// Triangles of the `Mesh` contents, with UVs laid out for baking.
+pub fn mesh_triangles() -> Vec<Arc<Triangle>> {
+    let white_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.73, 0.73, 0.73,
+    ))));
+    // Raised so the lowest two vertices touch the floor
+    icosahedron(Point3::new(190.0, 76.6, 190.0), 90.0, white_mat)
+}
+
+/// Builds an icosahedron inscribed in the sphere of the given center and radius.
+fn icosahedron(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Vec<Arc<Triangle>> {
+    let phi = (1.0 + 5.0_f64.sqrt()) / 2.0;
+    let vertices = [
+        (-1.0, phi, 0.0),
+        (1.0, phi, 0.0),
+        (-1.0, -phi, 0.0),
+        (1.0, -phi, 0.0),
+        (0.0, -1.0, phi),
+        (0.0, 1.0, phi),
+        (0.0, -1.0, -phi),
+        (0.0, 1.0, -phi),
+        (phi, 0.0, -1.0),
+        (phi, 0.0, 1.0),
+        (-phi, 0.0, -1.0),
+        (-phi, 0.0, 1.0),
+    ]
+    .map(|(x, y, z)| center + Vec3::new(x, y, z).normalize() * radius);
+
+    const FACES: [[usize; 3]; 20] = [
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    // Every face gets its own cell of a 5x4 atlas, inset to leave gutters between islands
+    let (cell_u, cell_v) = (1.0 / 5.0, 1.0 / 4.0);
+    let inset = 0.1;
+
+    FACES
+        .iter()
+        .enumerate()
+        .map(|(index, &[a, b, c])| {
+            let u0 = (index % 5) as f64 * cell_u;
+            let v0 = (index / 5) as f64 * cell_v;
+            Arc::new(
+                Triangle::new(vertices[a], vertices[b], vertices[c], mat.clone()).with_uvs(
+                    (u0 + inset * cell_u, v0 + inset * cell_v),
+                    (u0 + (1.0 - inset) * cell_u, v0 + inset * cell_v),
+                    (u0 + inset * cell_u, v0 + (1.0 - inset) * cell_v),
+                ),
+            )
+        })
+        .collect()
+}
@@ -1,13 +1,15 @@
 use crate::core::camera::Camera;
+use crate::core::transform::Transform;
 use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
 use crate::geometry::bvh::BvhNode;
 use crate::geometry::constant_medium::ConstantMedium;
 use crate::geometry::hittable_list::HittableList;
+use crate::geometry::lattice::Lattice;
+use crate::geometry::medium::{OverlapRule, OverlappingMedia};
 use crate::geometry::quad;
 use crate::geometry::quad::Quad;
 use crate::geometry::sphere::Sphere;
-use crate::geometry::transforms::rotate::RotateY;
-use crate::geometry::transforms::translate::Translate;
+use crate::geometry::transforms::instance::Instance;
 use crate::materials::dielectric::Dielectric;
 use crate::materials::diffuse_light::DiffuseLight;
 use crate::materials::lambertian::Lambertian;
@@ -30,27 +32,23 @@ pub fn build_final_scene(
     let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
         0.48, 0.83, 0.53,
     ))));
-    let mut boxes1 = HittableList::new();
     let boxes_per_side = 20;
-    for i in 0..boxes_per_side {
-        for j in 0..boxes_per_side {
-            let w = 100.0;
-            let x0 = -1000.0 + i as f64 * w;
-            let z0 = -1000.0 + j as f64 * w;
-            let y0 = 0.0;
-            let x1 = x0 + w;
-            let y1 = random_double_range(1.0, 101.0);
-            let z1 = z0 + w;
-
-            let box_instance = quad::box_new(
-                Point3::new(x0, y0, z0),
-                Point3::new(x1, y1, z1),
-                ground.clone(),
-            );
-            boxes1.add(Arc::new(box_instance));
-        }
-    }
-    world.add(Arc::new(BvhNode::new(&boxes1)));
+    let w = 100.0;
+    let field = Lattice::grid(
+        Point3::new(-1000.0, 0.0, -1000.0),
+        Vec3::new(w, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, w),
+        boxes_per_side,
+        boxes_per_side,
+    );
+    world.add(Arc::new(field.instantiate(|_| {
+        let height = random_double_range(1.0, 101.0);
+        Arc::new(quad::box_new(
+            Point3::origin(),
+            Point3::new(w, height, w),
+            ground.clone(),
+        ))
+    })));
 
     // Light
     let light_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new_rgb(
@@ -94,23 +92,31 @@ pub fn build_final_scene(
         Arc::new(Dielectric::new(1.5)),
     ));
     world.add(boundary.clone());
-    world.add(Arc::new(ConstantMedium::new(
-        boundary,
-        0.2,
-        Arc::new(SolidColor::new_rgb(0.2, 0.4, 0.9)),
-    )));
 
-    // Global Fog
+    // Global Fog, displaced by the subsurface medium rather than mixed into it
     let boundary2 = Arc::new(Sphere::new(
         Point3::new(0.0, 0.0, 0.0),
         5000.0,
         Arc::new(Dielectric::new(1.5)),
     ));
-    world.add(Arc::new(ConstantMedium::new(
-        boundary2,
-        0.0001,
-        Arc::new(SolidColor::new_rgb(1.0, 1.0, 1.0)),
-    )));
+    let mut media = OverlappingMedia::new(OverlapRule::Priority);
+    media.add(
+        Arc::new(ConstantMedium::new(
+            boundary,
+            0.2,
+            Arc::new(SolidColor::new_rgb(0.2, 0.4, 0.9)),
+        )),
+        1,
+    );
+    media.add(
+        Arc::new(ConstantMedium::new(
+            boundary2,
+            0.0001,
+            Arc::new(SolidColor::new_rgb(1.0, 1.0, 1.0)),
+        )),
+        0,
+    );
+    world.add(Arc::new(media));
 
     // Earth
     let earth_mat = Arc::new(Lambertian::new(Arc::new(ImageTexture::new("earthmap.jpg"))));
@@ -142,9 +148,12 @@ pub fn build_final_scene(
         )));
     }
 
-    let boxes2_rot = Arc::new(RotateY::new(Arc::new(BvhNode::new(&boxes2)), 15.0));
-    let boxes2_trans = Arc::new(Translate::new(boxes2_rot, Vec3::new(-100.0, 270.0, 395.0)));
-    world.add(boxes2_trans);
+    // Built once in its own space and placed by an instance, which could be
+    // repeated at other transforms without copying the spheres or their BVH
+    let cluster = Arc::new(BvhNode::new(&boxes2));
+    let placement = Transform::rotation(Vec3::y(), 15.0)
+        .then(&Transform::translation(Vec3::new(-100.0, 270.0, 395.0)));
+    world.add(Arc::new(Instance::new(cluster, placement)));
 
     let mut cam = Camera::new(image_width, 1.0);
     cam.vfov = 40.0;
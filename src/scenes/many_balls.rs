@@ -1,11 +1,12 @@
 use crate::core::camera::Camera;
-use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
+use crate::core::interval::Interval;
+use crate::core::vec3::{Color, Point3, Vec3};
 use crate::geometry::hittable_list::HittableList;
 use crate::geometry::sphere::Sphere;
 use crate::materials::dielectric::Dielectric;
 use crate::materials::lambertian::Lambertian;
 use crate::materials::metal::Metal;
-use crate::sampling::random::random_double;
+use crate::scenes::scatter::ScatterBuilder;
 use crate::textures::solid_color;
 use std::sync::Arc;
 
@@ -26,39 +27,15 @@ pub fn build_many_balls(
         ground_material,
     )));
 
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = random_double();
-            let center = Point3::new(
-                a as f64 + 0.9 * random_double(),
-                0.2,
-                b as f64 + 0.9 * random_double(),
-            );
-
-            if (center - Point3::new(4.0, 0.2, 0.0)).norm() > 0.9 {
-                if choose_mat < 0.8 {
-                    // Diffuse
-                    // Use component_mul for element-wise multiplication
-                    let albedo = Color::random().component_mul(&Color::random());
-                    let sphere_material = Arc::new(Lambertian::new(Arc::new(
-                        solid_color::SolidColor::new(albedo),
-                    )));
-                    let _center2 = center + Vec3::new(0.0, random_double() * 0.5, 0.0);
-                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
-                } else if choose_mat < 0.95 {
-                    // Metal
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_double() * 0.5;
-                    let sphere_material = Arc::new(Metal::new(albedo, fuzz));
-                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
-                } else {
-                    // Glass
-                    let sphere_material = Arc::new(Dielectric::new(1.5));
-                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
-                }
-            }
-        }
-    }
+    world.add(Arc::new(
+        ScatterBuilder::new(
+            "many_balls",
+            Interval::new(-11.0, 11.0),
+            Interval::new(-11.0, 11.0),
+        )
+        .with_exclusion(Point3::new(4.0, 0.2, 0.0), 0.9)
+        .build(),
+    ));
 
     let material1 = Arc::new(Dielectric::new(1.5));
     world.add(Arc::new(Sphere::new(
@@ -0,0 +1,62 @@
+use crate::core::camera::Camera;
+use crate::core::ply;
+use crate::geometry::accelerator::Accelerator;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::simplify::simplify;
+use crate::geometry::triangle_mesh::TriangleMesh;
+use crate::materials::lambertian::Lambertian;
+use crate::scenes::studio;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::vertex_color::VertexColor;
+use std::sync::Arc;
+
+/// Renders a PLY model in the studio, matte gray or painted with its vertex
+/// colors when it has them, simplified to at most `max_faces` faces if given.
+/// Models without normals are smoothed across edges sharper than `crease_angle`.
+pub fn build_ply(
+    filename: &str,
+    max_faces: Option<usize>,
+    crease_angle: f64,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> Result<(Arc<HittableList>, Arc<HittableList>, Camera), String> {
+    let mesh = Arc::new(load_model(filename, max_faces, crease_angle)?);
+    Ok(studio::build_studio(
+        Accelerator::build_mesh(&mesh),
+        image_width,
+        samples,
+        max_depth,
+    ))
+}
+
+/// Loads the model as the `ply` scene places it, e.g. to bake its lighting.
+pub fn load_model(
+    filename: &str,
+    max_faces: Option<usize>,
+    crease_angle: f64,
+) -> Result<TriangleMesh, String> {
+    let material = Arc::new(Lambertian::new(Arc::new(VertexColor::new(Arc::new(
+        SolidColor::new_rgb(0.8, 0.8, 0.8),
+    )))));
+    let mut mesh = ply::load(filename, material, crease_angle)?;
+    println!(
+        "  {} vertices, {} faces{}",
+        mesh.vertex_count(),
+        mesh.face_count(),
+        if mesh.has_colors() {
+            ", vertex colors"
+        } else {
+            ""
+        }
+    );
+    if let Some(max_faces) = max_faces.filter(|&n| n < mesh.face_count()) {
+        mesh = simplify(&mesh, max_faces)?;
+        println!(
+            "  simplified to {} vertices, {} faces",
+            mesh.vertex_count(),
+            mesh.face_count()
+        );
+    }
+    Ok(mesh)
+}
@@ -0,0 +1,162 @@
+use crate::core::camera::Camera;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::quad;
+use crate::geometry::quad::Quad;
+use crate::geometry::sphere::Sphere;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::textures::checker::CheckerTexture;
+use crate::textures::solid_color::SolidColor;
+use std::sync::Arc;
+
+/// Half extents of the basin and its depth below the deck, in meters.
+const POOL_HALF_WIDTH: f64 = 2.5;
+const POOL_HALF_LENGTH: f64 = 5.0;
+const POOL_DEPTH: f64 = 1.8;
+/// Height of the water surface below the deck.
+const WATER_LINE: f64 = 0.1;
+/// Overlap of the water into the basin walls and floor, so the tiles lie inside
+/// the water rather than coincide with its surface.
+const WATER_OVERLAP: f64 = 0.001;
+/// Index of refraction of ice.
+const ICE_IOR: f64 = 1.31;
+
+/// A tiled swimming pool in the sun, in meters: refraction through rippled water,
+/// its absorption deepening the blue towards the deep end of the view, the
+/// caustics the ripples focus on the floor, and an ice cube nested in the water. Caustics reach the tiles only through
+/// a specular bounce, so render with `--regularize` for them to converge.
+pub fn build_pool(
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let (w, l, d) = (POOL_HALF_WIDTH, POOL_HALF_LENGTH, POOL_DEPTH);
+
+    // Basin: floor and walls lined with tiles, facing inwards
+    let tiles = Arc::new(Lambertian::new(Arc::new(CheckerTexture::new_colors(
+        0.35,
+        Color::new(0.85, 0.88, 0.9),
+        Color::new(0.2, 0.45, 0.75),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-w, -d, -l),
+        Vec3::new(0.0, 0.0, 2.0 * l),
+        Vec3::new(2.0 * w, 0.0, 0.0),
+        tiles.clone(),
+    ))); // Floor
+    world.add(Arc::new(Quad::new(
+        Point3::new(-w, -d, -l),
+        Vec3::new(0.0, d, 0.0),
+        Vec3::new(0.0, 0.0, 2.0 * l),
+        tiles.clone(),
+    ))); // Left
+    world.add(Arc::new(Quad::new(
+        Point3::new(w, -d, -l),
+        Vec3::new(0.0, 0.0, 2.0 * l),
+        Vec3::new(0.0, d, 0.0),
+        tiles.clone(),
+    ))); // Right
+    world.add(Arc::new(Quad::new(
+        Point3::new(-w, -d, -l),
+        Vec3::new(2.0 * w, 0.0, 0.0),
+        Vec3::new(0.0, d, 0.0),
+        tiles.clone(),
+    ))); // Near
+    world.add(Arc::new(Quad::new(
+        Point3::new(-w, -d, l),
+        Vec3::new(0.0, d, 0.0),
+        Vec3::new(2.0 * w, 0.0, 0.0),
+        tiles,
+    ))); // Far
+
+    // A ball resting on the floor, seen through the water
+    let ball_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.8, 0.15, 0.1,
+    ))));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.8, -d + 0.3, 1.0),
+        0.3,
+        ball_mat,
+    )));
+
+    // Water filling the basin up to the water line
+    let water = Arc::new(Dielectric::water().with_ripples(0.01, 0.5));
+    world.add(Arc::new(quad::box_new(
+        Point3::new(-w - WATER_OVERLAP, -d - WATER_OVERLAP, -l - WATER_OVERLAP),
+        Point3::new(w + WATER_OVERLAP, -WATER_LINE, l + WATER_OVERLAP),
+        water,
+    )));
+
+    // Ice floating in it, mostly under water: the ice has priority over the water,
+    // so the water surface inside the cube is ignored and the submerged faces
+    // refract between ice and water
+    let ice = Arc::new(Dielectric::new(ICE_IOR).with_priority(1));
+    world.add(Arc::new(quad::box_new(
+        Point3::new(-1.2, -WATER_LINE - 0.5, -3.8),
+        Point3::new(-0.6, -WATER_LINE + 0.06, -3.2),
+        ice,
+    )));
+
+    // Deck around the pool
+    let deck = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.75, 0.7, 0.6,
+    ))));
+    let extent = 30.0;
+    for (corner, u, v) in [
+        (
+            Point3::new(-extent, 0.0, -extent),
+            Vec3::new(0.0, 0.0, 2.0 * extent),
+            Vec3::new(extent - w, 0.0, 0.0),
+        ),
+        (
+            Point3::new(w, 0.0, -extent),
+            Vec3::new(0.0, 0.0, 2.0 * extent),
+            Vec3::new(extent - w, 0.0, 0.0),
+        ),
+        (
+            Point3::new(-w, 0.0, -extent),
+            Vec3::new(0.0, 0.0, extent - l),
+            Vec3::new(2.0 * w, 0.0, 0.0),
+        ),
+        (
+            Point3::new(-w, 0.0, l),
+            Vec3::new(0.0, 0.0, extent - l),
+            Vec3::new(2.0 * w, 0.0, 0.0),
+        ),
+    ] {
+        world.add(Arc::new(Quad::new(corner, u, v, deck.clone())));
+    }
+
+    // Sun: far away, high behind the camera's left shoulder
+    let sun_direction = Vec3::new(-0.4, 0.8, -0.45).normalize();
+    let sun_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new_rgb(
+        375.0, 355.0, 325.0,
+    ))));
+    let sun = Arc::new(Sphere::new(
+        Point3::origin() + 100.0 * sun_direction,
+        3.0,
+        sun_mat,
+    ));
+    world.add(sun.clone());
+    lights.add(sun);
+
+    // Camera Setup
+    let mut cam = Camera::new(image_width, 16.0 / 9.0);
+    cam.vfov = 45.0;
+    cam.lookfrom = Point3::new(0.0, 2.2, -7.5);
+    cam.lookat = Point3::new(0.0, -1.5, 1.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::new(0.5, 0.7, 1.0);
+
+    cam.initialize();
+
+    (Arc::new(world), Arc::new(lights), cam)
+}
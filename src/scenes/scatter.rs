@@ -0,0 +1,178 @@
+use crate::core::interval::Interval;
+use crate::core::metadata;
+use crate::core::vec3::{Color, Point3};
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::sphere::Sphere;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::material_trait::Material;
+use crate::materials::metal::Metal;
+use crate::sampling::random::random_seed;
+use crate::textures::solid_color::SolidColor;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::sync::{Arc, OnceLock};
+
+/// Seed of every scatter built without one, set once from the command line.
+static DEFAULT_SEED: OnceLock<u64> = OnceLock::new();
+/// Fraction of its grid cell an object's center may stray over.
+const JITTER: f64 = 0.9;
+
+/// Relative weights of the materials scattered objects are given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialMix {
+    /// Matte, with a random albedo.
+    pub diffuse: f64,
+    /// Metal with a light random albedo and some fuzz.
+    pub metal: f64,
+    /// Clear glass.
+    pub glass: f64,
+}
+
+impl Default for MaterialMix {
+    /// The books' final scene: mostly diffuse, some metal, a little glass.
+    fn default() -> Self {
+        Self {
+            diffuse: 0.8,
+            metal: 0.15,
+            glass: 0.05,
+        }
+    }
+}
+
+/// Scatters spheres resting on the ground over a rectangle of the XZ plane, one
+/// per cell of a jittered grid, as in the books' final scene: density, sizes and
+/// materials are set by the builder, and exclusion zones keep the objects off
+/// space saved for hero objects.
+///
+/// The layout comes from its own generator, seeded by `with_seed`, the seed set
+/// with `set_default_seed`, or else a fresh seed from the thread's generator
+/// (fixed by `--seed`). The seed is printed and recorded in the image metadata
+/// under the scatter's label, so a layout can be generated again exactly.
+#[derive(Debug, Clone)]
+pub struct ScatterBuilder {
+    label: String,
+    x: Interval,
+    z: Interval,
+    ground: f64,
+    density: f64,
+    radius: Interval,
+    mix: MaterialMix,
+    exclusions: Vec<(Point3, f64)>,
+    seed: Option<u64>,
+}
+
+impl ScatterBuilder {
+    /// Scatter named `label` over `x` by `z` on the plane y = 0, one object of radius
+    /// 0.2 per unit of area.
+    pub fn new(label: &str, x: Interval, z: Interval) -> Self {
+        Self {
+            label: label.to_string(),
+            x,
+            z,
+            ground: 0.0,
+            density: 1.0,
+            radius: Interval::new(0.2, 0.2),
+            mix: MaterialMix::default(),
+            exclusions: Vec::new(),
+            seed: None,
+        }
+    }
+
+    pub fn set_default_seed(seed: u64) {
+        let _ = DEFAULT_SEED.set(seed);
+    }
+
+    /// Height of the ground the objects rest on.
+    pub fn with_ground(mut self, height: f64) -> Self {
+        self.ground = height;
+        self
+    }
+
+    /// Objects per unit of area, which must be positive.
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Radii drawn uniformly between `min` and `max`.
+    pub fn with_radius(mut self, min: f64, max: f64) -> Self {
+        self.radius = Interval::new(min, max);
+        self
+    }
+
+    pub fn with_material_mix(mut self, mix: MaterialMix) -> Self {
+        self.mix = mix;
+        self
+    }
+
+    /// Leaves out objects whose center falls within `radius` of `center`.
+    pub fn with_exclusion(mut self, center: Point3, radius: f64) -> Self {
+        self.exclusions.push((center, radius));
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(&self) -> HittableList {
+        let seed = self
+            .seed
+            .or_else(|| DEFAULT_SEED.get().copied())
+            .unwrap_or_else(random_seed);
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let cell = 1.0 / self.density.sqrt();
+        let columns = (self.x.size() / cell).floor() as usize;
+        let rows = (self.z.size() / cell).floor() as usize;
+
+        let mut objects = HittableList::new();
+        for a in 0..columns {
+            for b in 0..rows {
+                let choice = rng.random::<f64>();
+                let radius = self.radius.min + self.radius.size() * rng.random::<f64>();
+                let center = Point3::new(
+                    self.x.min + (a as f64 + JITTER * rng.random::<f64>()) * cell,
+                    self.ground + radius,
+                    self.z.min + (b as f64 + JITTER * rng.random::<f64>()) * cell,
+                );
+                let material = self.material(choice, &mut rng);
+                let excluded = self
+                    .exclusions
+                    .iter()
+                    .any(|(zone, zone_radius)| (center - zone).norm() <= *zone_radius);
+                if !excluded {
+                    objects.add(Arc::new(Sphere::new(center, radius, material)));
+                }
+            }
+        }
+
+        println!(
+            "  {}: {} objects scattered with seed {} (replay with --scatter-seed {})",
+            self.label,
+            objects.objects.len(),
+            seed,
+            seed
+        );
+        metadata::record(&format!("{} seed", self.label), seed);
+        objects
+    }
+
+    /// Material picked by `choice`, uniform in [0, 1), from the mix.
+    fn material(&self, choice: f64, rng: &mut SmallRng) -> Arc<dyn Material> {
+        let total = self.mix.diffuse + self.mix.metal + self.mix.glass;
+        let choice = choice * total;
+        let mut color =
+            |min: f64, max: f64| Color::from_fn(|_, _| min + (max - min) * rng.random::<f64>());
+        if choice < self.mix.diffuse {
+            let albedo = color(0.0, 1.0).component_mul(&color(0.0, 1.0));
+            Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))
+        } else if choice < self.mix.diffuse + self.mix.metal {
+            let albedo = color(0.5, 1.0);
+            Arc::new(Metal::new(albedo, 0.5 * rng.random::<f64>()))
+        } else {
+            Arc::new(Dielectric::new(1.5))
+        }
+    }
+}
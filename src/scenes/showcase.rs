@@ -0,0 +1,200 @@
+use crate::core::camera::Camera;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::depth_limit::DepthLimit;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::quad::Quad;
+use crate::geometry::sphere::Sphere;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::layered::Layered;
+use crate::materials::material_trait::Material;
+use crate::materials::metal::Metal;
+use crate::textures::checker::CheckerTexture;
+use crate::textures::solid_color::SolidColor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Number of steps of the swept parameter along each row.
+const COLUMNS: usize = 5;
+/// Specular bounces in a row allowed inside a hollow glass ball.
+const NESTED_GLASS_DEPTH: u32 = 12;
+
+/// Material family swept by the showcase grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialFamily {
+    /// Albedo from dark to bright, one hue per row.
+    Lambertian,
+    /// Fuzz from mirror to rough, one tint per row.
+    Metal,
+    /// IOR from 1.0 to 2.4, solid balls and hollow shells.
+    Dielectric,
+    /// Clear coats over diffuse and metal bases, from thin to thick or smooth to rough.
+    Layered,
+}
+
+impl FromStr for MaterialFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lambertian" => Ok(Self::Lambertian),
+            "metal" => Ok(Self::Metal),
+            "dielectric" => Ok(Self::Dielectric),
+            "layered" => Ok(Self::Layered),
+            _ => Err(format!("Unknown material family '{}'", s)),
+        }
+    }
+}
+
+/// Lays out a grid of shader balls sweeping one parameter of `family` per column,
+/// lit only by a sky-colored environment.
+pub fn build_showcase(
+    family: MaterialFamily,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut world = HittableList::new();
+    let lights = HittableList::new(); // lit by the background
+
+    // Ground
+    let checker = Arc::new(CheckerTexture::new_colors(
+        0.5,
+        Color::new(0.2, 0.2, 0.2),
+        Color::new(0.8, 0.8, 0.8),
+    ));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        Arc::new(Lambertian::new(checker)),
+    )));
+
+    let rows = variants(family);
+    let radius = 0.4;
+    let spacing = 1.0;
+    // The camera looks down +z, so +x is on the left of the image
+    let x0 = 0.5 * spacing * (COLUMNS - 1) as f64;
+    let z0 = -0.5 * spacing * (rows.len() - 1) as f64;
+
+    for (row, variant) in rows.iter().enumerate() {
+        for column in 0..COLUMNS {
+            let t = column as f64 / (COLUMNS - 1) as f64;
+            let center = Point3::new(
+                x0 - spacing * column as f64,
+                radius,
+                z0 + spacing * row as f64,
+            );
+            let ball = Arc::new(Sphere::new(center, radius, variant.material(t)));
+
+            // Air bubble inside the hollow glass row, filling its space over the glass,
+            // whose internal reflections between the nested surfaces are capped
+            if let Variant::Glass { hollow: true } = variant {
+                let mut shell = HittableList::new();
+                shell.add(ball);
+                shell.add(Arc::new(Sphere::new(
+                    center,
+                    0.8 * radius,
+                    Arc::new(Dielectric::new(1.0).with_priority(1)),
+                )));
+                world.add(Arc::new(DepthLimit::new(
+                    Arc::new(shell),
+                    NESTED_GLASS_DEPTH,
+                )));
+            } else {
+                world.add(ball);
+            }
+        }
+    }
+
+    // Camera Setup
+    let mut cam = Camera::new(image_width, 16.0 / 9.0);
+    cam.vfov = 25.0;
+    cam.lookfrom = Point3::new(0.0, 3.5, -8.0);
+    cam.lookat = Point3::new(0.0, 0.3, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::new(0.70, 0.80, 1.00);
+
+    cam.initialize();
+
+    (Arc::new(world), Arc::new(lights), cam)
+}
+
+/// One row of the grid.
+enum Variant {
+    Diffuse(Color),
+    Metal(Color),
+    Glass {
+        hollow: bool,
+    },
+    /// Amber varnish of growing thickness over a diffuse base.
+    Varnish(Color),
+    /// Clear coat over metal of growing roughness.
+    CoatedMetal(Color),
+    /// Two clear coats, the inner one tinted, over a diffuse base.
+    DoubleCoat(Color),
+}
+
+impl Variant {
+    /// Material at position `t` in [0, 1] along the row.
+    fn material(&self, t: f64) -> Arc<dyn Material> {
+        match self {
+            Variant::Diffuse(hue) => Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+                hue * (0.1 + 0.8 * t),
+            )))),
+            Variant::Metal(tint) => Arc::new(Metal::new(*tint, t)),
+            Variant::Glass { .. } => Arc::new(Dielectric::new(Self::ior(t))),
+            Variant::Varnish(base) => Arc::new(
+                Layered::new(Self::coat(), Self::diffuse(*base))
+                    .with_medium(t, Color::new(0.2, 0.8, 2.0)),
+            ),
+            Variant::CoatedMetal(tint) => {
+                Arc::new(Layered::new(Self::coat(), Arc::new(Metal::new(*tint, t))))
+            }
+            Variant::DoubleCoat(base) => {
+                let inner = Layered::new(Self::coat(), Self::diffuse(*base))
+                    .with_medium(0.5, Color::new(2.0, 0.4, 2.0) * t);
+                Arc::new(Layered::new(Self::coat(), Arc::new(inner)))
+            }
+        }
+    }
+
+    fn coat() -> Arc<dyn Material> {
+        Arc::new(Dielectric::new(1.5))
+    }
+
+    fn diffuse(albedo: Color) -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))
+    }
+
+    fn ior(t: f64) -> f64 {
+        1.0 + 1.4 * t
+    }
+}
+
+fn variants(family: MaterialFamily) -> Vec<Variant> {
+    match family {
+        MaterialFamily::Lambertian => vec![
+            Variant::Diffuse(Color::new(1.0, 1.0, 1.0)),
+            Variant::Diffuse(Color::new(1.0, 0.3, 0.2)),
+            Variant::Diffuse(Color::new(0.2, 0.4, 1.0)),
+        ],
+        MaterialFamily::Metal => vec![
+            Variant::Metal(Color::new(0.95, 0.95, 0.95)), // Silver
+            Variant::Metal(Color::new(1.0, 0.78, 0.34)),  // Gold
+            Variant::Metal(Color::new(0.95, 0.64, 0.54)), // Copper
+        ],
+        MaterialFamily::Dielectric => vec![
+            Variant::Glass { hollow: false },
+            Variant::Glass { hollow: true },
+        ],
+        MaterialFamily::Layered => vec![
+            Variant::Varnish(Color::new(0.9, 0.9, 0.9)),
+            Variant::CoatedMetal(Color::new(1.0, 0.78, 0.34)), // Gold
+            Variant::DoubleCoat(Color::new(0.9, 0.9, 0.9)),
+        ],
+    }
+}
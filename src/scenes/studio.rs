@@ -0,0 +1,177 @@
+use crate::core::camera::Camera;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::disk::Disk;
+use crate::geometry::hittable::{Hittable, SHUTTER};
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::quad;
+use crate::geometry::quad::Quad;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::transforms::rotate::RotateY;
+use crate::geometry::transforms::translate::Translate;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::material_trait::Material;
+use crate::materials::metal::Metal;
+use crate::textures::solid_color::SolidColor;
+use std::f64::consts::FRAC_PI_2;
+use std::sync::Arc;
+
+/// Number of quads approximating the curved cove between floor and wall.
+const COVE_SEGMENTS: u32 = 12;
+
+/// Places `subject` in a product-shot studio: a seamless backdrop sweep lit by a
+/// three-point rig (key, fill and rim softboxes). Everything is sized and framed
+/// from the subject's bounding box, which is assumed to rest on the floor.
+pub fn build_studio(
+    subject: Arc<dyn Hittable>,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let bbox = subject.bounding_box(SHUTTER);
+    let center = Point3::new(
+        0.5 * (bbox.x.min + bbox.x.max),
+        0.5 * (bbox.y.min + bbox.y.max),
+        0.5 * (bbox.z.min + bbox.z.max),
+    );
+    let size = 0.5 * Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).norm();
+    let floor = bbox.y.min;
+
+    world.add(subject);
+
+    // Seamless backdrop: floor, quarter-cylinder cove and back wall
+    let backdrop_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.8, 0.8, 0.8,
+    ))));
+    for part in backdrop(center, floor, size, backdrop_mat) {
+        world.add(part);
+    }
+
+    // Three-point rig, each softbox aimed at the subject
+    let key_mat = softbox(Color::new(12.0, 11.5, 10.5), 70.0);
+    let fill_mat = softbox(Color::new(2.5, 2.6, 3.0), 100.0);
+    let rim_mat = softbox(Color::new(15.0, 15.0, 15.0), 50.0);
+
+    let rig = [
+        (Vec3::new(-3.0, 2.5, -3.0), 1.0, key_mat), // Key: front left, high
+        (Vec3::new(3.5, 1.0, -2.5), 1.5, fill_mat), // Fill: front right, low
+        (Vec3::new(1.5, 3.5, 3.0), 0.75, rim_mat),  // Rim: behind, above
+    ];
+    for (offset, radius, mat) in rig {
+        let position = center + offset * size;
+        let light = Arc::new(Disk::new(position, center - position, radius * size, mat));
+        world.add(light.clone());
+        lights.add(light);
+    }
+
+    // Camera Setup
+    let mut cam = Camera::new(image_width, 3.0 / 2.0);
+    cam.vfov = 30.0;
+    cam.lookfrom = center + Vec3::new(0.0, 0.8, -4.5) * size;
+    cam.lookat = center;
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::zeros();
+
+    cam.initialize();
+
+    (Arc::new(world), Arc::new(lights), cam)
+}
+
+/// Demo subject for the studio: a few objects showing off the rig's highlights.
+pub fn build_studio_demo(
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> (Arc<HittableList>, Arc<HittableList>, Camera) {
+    let mut subject = HittableList::new();
+
+    let clay_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.7, 0.35, 0.25,
+    ))));
+    let block = quad::box_new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 1.6, 1.0),
+        clay_mat,
+    );
+    let block = Arc::new(RotateY::new(Arc::new(block), 30.0));
+    subject.add(Arc::new(Translate::new(block, Vec3::new(-1.6, 0.0, 0.2))));
+
+    subject.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 0.7, 0.0),
+        0.7,
+        Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.05)),
+    )));
+    subject.add(Arc::new(Sphere::new(
+        Point3::new(1.4, 0.5, -0.4),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+
+    build_studio(Arc::new(subject), image_width, samples, max_depth)
+}
+
+fn softbox(radiance: Color, spread: f64) -> Arc<DiffuseLight> {
+    Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(radiance))).with_spread(spread))
+}
+
+fn backdrop(
+    center: Point3,
+    floor: f64,
+    size: f64,
+    mat: Arc<dyn Material>,
+) -> Vec<Arc<dyn Hittable>> {
+    let half_width = 8.0 * size;
+    let cove_radius = 2.0 * size;
+    let cove_start = center.z + 2.0 * size;
+    let width = Vec3::new(2.0 * half_width, 0.0, 0.0);
+    let left = center.x - half_width;
+
+    let mut parts: Vec<Arc<dyn Hittable>> = Vec::new();
+
+    // Floor, running from behind the camera up to the cove
+    let front = center.z - 8.0 * size;
+    parts.push(Arc::new(Quad::new(
+        Point3::new(left, floor, front),
+        Vec3::new(0.0, 0.0, cove_start - front),
+        width,
+        mat.clone(),
+    )));
+
+    // Cove, bending the floor up into the wall
+    let cove_point = |angle: f64| {
+        Point3::new(
+            left,
+            floor + cove_radius * (1.0 - angle.cos()),
+            cove_start + cove_radius * angle.sin(),
+        )
+    };
+    for i in 0..COVE_SEGMENTS {
+        let a0 = FRAC_PI_2 * i as f64 / COVE_SEGMENTS as f64;
+        let a1 = FRAC_PI_2 * (i + 1) as f64 / COVE_SEGMENTS as f64;
+        let p0 = cove_point(a0);
+        parts.push(Arc::new(Quad::new(
+            p0,
+            cove_point(a1) - p0,
+            width,
+            mat.clone(),
+        )));
+    }
+
+    // Back wall
+    let wall_bottom = cove_point(FRAC_PI_2);
+    parts.push(Arc::new(Quad::new(
+        wall_bottom,
+        Vec3::new(0.0, 8.0 * size, 0.0),
+        width,
+        mat,
+    )));
+
+    parts
+}
@@ -0,0 +1,200 @@
+use crate::core::camera::Camera;
+use crate::core::interaction::MaterialParams;
+use crate::core::vec3::{Color, Point3, Vec3};
+use crate::geometry::accelerator::Accelerator;
+use crate::geometry::displacement::Displacement;
+use crate::geometry::hittable::Hittable;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::lattice::Lattice;
+use crate::geometry::material_override::ParameterOverride;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::sun::Sun;
+use crate::geometry::transforms::scale::Scale;
+use crate::geometry::triangle_mesh::TriangleMesh;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::material_trait::EmissionSide;
+use crate::sampling::random::random_double_range;
+use crate::textures::image::ImageTexture;
+use crate::textures::perlin::Perlin;
+use crate::textures::sky::{Sky, SunPath, Sunlight};
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Side of the square patch of ground, centered on the origin.
+const TERRAIN_SIZE: f64 = 20.0;
+/// Cells per side of the flat grid that is displaced.
+const BASE_CELLS: u32 = 8;
+/// Height of the tallest ridges.
+const RELIEF: f64 = 2.5;
+/// Largest error of the tessellation on screen.
+const TOLERANCE_PIXELS: f64 = 0.5;
+/// Pebbles scattered over the ground.
+const PEBBLES: usize = 400;
+/// Spatial frequency of the largest default hills, per unit.
+const HILL_FREQUENCY: f64 = 0.15;
+/// Octaves of noise in the default hills.
+const HILL_OCTAVES: i32 = 4;
+/// Radius of the sky dome, beyond the sun.
+const SKY_RADIUS: f64 = 10000.0;
+
+/// Default height map: a few octaves of Perlin noise, folded where it crosses zero
+/// into creased valleys between rounded hills.
+#[derive(Debug)]
+struct Hills {
+    noise: Perlin,
+}
+
+impl Texture for Hills {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let h = self
+            .noise
+            .turb(&(ctx.p * HILL_FREQUENCY), HILL_OCTAVES)
+            .min(1.0);
+        Color::new(h, h, h)
+    }
+}
+
+/// A patch of hilly ground seen from low down against the sky, its relief real
+/// geometry displaced by `height_map` (by default Perlin noise hills) and
+/// tessellated finely near the camera, coarsely in the distance, with pebbles
+/// scattered over it. Given `sun_elevations`, the sun passes through them over
+/// the frames of a sequence under a procedural sky, for a timelapse.
+pub fn build_terrain(
+    height_map: Option<&str>,
+    sun_elevations: Option<&[f64]>,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> Result<(Arc<HittableList>, Arc<HittableList>, Camera), String> {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    // Camera Setup, first: the tessellation depends on it
+    let mut cam = Camera::new(image_width, 16.0 / 9.0);
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.5, -15.0);
+    cam.lookat = Point3::new(0.0, 0.5, 2.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.samples_per_pixel = samples;
+    cam.max_depth = max_depth;
+    cam.background = Color::new(0.45, 0.6, 0.85);
+    cam.initialize();
+
+    let height: Arc<dyn Texture> = match height_map {
+        Some(file) => Arc::new(ImageTexture::new(file)),
+        None => Arc::new(Hills {
+            noise: Perlin::new(),
+        }),
+    };
+    let ground_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.55, 0.5, 0.4,
+    ))));
+    let base = grid(ground_mat)?;
+    let terrain = Displacement::new(height, RELIEF)
+        .with_camera(&cam, TOLERANCE_PIXELS)
+        .apply(&base)?;
+    println!(
+        "  {} faces displaced into {}",
+        base.face_count(),
+        terrain.face_count()
+    );
+    let ground = Accelerator::build_mesh(&Arc::new(terrain));
+
+    // Pebbles strewn over the ground, half buried
+    let pebble_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+        0.35, 0.33, 0.3,
+    ))));
+    // One shared sphere, flattened and stretched into each pebble
+    let pebble: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::origin(), 1.0, pebble_mat));
+    let pebbles = Lattice::on_surface(ground.as_ref(), PEBBLES)
+        .with_random_rotation()
+        .instantiate(|_| {
+            let size = random_double_range(0.05, 0.15);
+            let pebble = Arc::new(Scale::new(
+                pebble.clone(),
+                size * Vec3::new(
+                    random_double_range(1.0, 1.6),
+                    random_double_range(0.5, 0.8),
+                    1.0,
+                ),
+            ));
+            // Lighter, darker and warmer stones from the one material
+            let shade = random_double_range(0.7, 1.4);
+            let warmth = random_double_range(0.0, 0.15);
+            let tint = shade * Color::new(1.0 + warmth, 1.0, 1.0 - warmth);
+            Arc::new(ParameterOverride::new(
+                pebble,
+                MaterialParams::default().with_tint(tint),
+            ))
+        });
+    world.add(ground);
+    world.add(Arc::new(pebbles));
+
+    // Sun: low in the sky to the left, so the ridges cast long shadows
+    let sun_direction = Vec3::new(-0.7, 0.35, 0.6).normalize();
+    let sun_radiance = Color::new(12000.0, 11000.0, 9500.0);
+    let sun: Arc<dyn Hittable> = match sun_elevations {
+        Some(elevations) => {
+            // The sun follows the keys over the frames, under a sky to match
+            let path = Arc::new(SunPath::new(elevations.to_vec(), sun_direction));
+            let sun_mat = Arc::new(DiffuseLight::new(Arc::new(Sunlight::new(
+                path.clone(),
+                sun_radiance,
+            ))));
+            let sky_mat = DiffuseLight::new(Arc::new(Sky::new(path.clone())));
+            world.add(Arc::new(Sphere::new(
+                Point3::origin(),
+                SKY_RADIUS,
+                Arc::new(sky_mat.with_side(EmissionSide::Back)),
+            )));
+            cam.background = Color::zeros();
+            Arc::new(Sun::new(path, 1000.0, 10.0, sun_mat))
+        }
+        None => {
+            let sun_mat = Arc::new(DiffuseLight::new(Arc::new(SolidColor::new(sun_radiance))));
+            Arc::new(Sphere::new(
+                Point3::origin() + 1000.0 * sun_direction,
+                10.0,
+                sun_mat,
+            ))
+        }
+    };
+    world.add(sun.clone());
+    lights.add(sun);
+
+    Ok((Arc::new(world), Arc::new(lights), cam))
+}
+
+/// Flat square grid facing up, with UVs spanning it.
+fn grid(material: Arc<Lambertian>) -> Result<TriangleMesh, String> {
+    let n = BASE_CELLS;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    for j in 0..=n {
+        for i in 0..=n {
+            let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+            positions.push(Point3::new(
+                (u - 0.5) * TERRAIN_SIZE,
+                0.0,
+                (v - 0.5) * TERRAIN_SIZE,
+            ));
+            uvs.push((u, v));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for j in 0..n {
+        for i in 0..n {
+            let corner = j * (n + 1) + i;
+            let (right, up) = (corner + 1, corner + n + 1);
+            // Wound counter-clockwise seen from above
+            indices.push([corner, up, right]);
+            indices.push([right, up, up + 1]);
+        }
+    }
+
+    Ok(TriangleMesh::new(positions, indices, material)?.with_uvs(uvs))
+}
@@ -0,0 +1,104 @@
+use crate::core::camera::Camera;
+use crate::core::nanovdb;
+use crate::geometry::hittable_list::HittableList;
+use crate::geometry::vdb_medium::VdbMedium;
+use crate::scenes::studio;
+use crate::textures::solid_color::SolidColor;
+use std::sync::Arc;
+
+/// Name of the density grid in simulation caches from Blender and Houdini.
+const DENSITY_GRID: &str = "density";
+/// Name of the temperature grid in simulation caches from Blender and Houdini.
+const TEMPERATURE_GRID: &str = "temperature";
+
+/// How the `volume` scene shades its grids.
+#[derive(Debug, Clone, Copy)]
+pub enum VolumeStyle {
+    /// White, isotropically scattering smoke from all float grids.
+    Smoke,
+    /// The cloud preset, with or without its multiple scattering approximation.
+    Cloud { multiple_scattering: bool },
+    /// Fire from the `density` and `temperature` grids; `temperature_scale` is the
+    /// Kelvin per grid unit and `intensity` the radiance at 1000 K.
+    Fire {
+        temperature_scale: f64,
+        intensity: f64,
+    },
+}
+
+/// Renders the grids of a NanoVDB file as a participating medium in the studio, with
+/// `density_scale` converting grid values to extinction per world unit.
+pub fn build_volume(
+    filename: &str,
+    density_scale: f64,
+    style: VolumeStyle,
+    image_width: u32,
+    samples: u32,
+    max_depth: u32,
+) -> Result<(Arc<HittableList>, Arc<HittableList>, Camera), String> {
+    let grids = nanovdb::load(filename)?;
+    if grids.is_empty() {
+        return Err(format!("{}: no float grids", filename));
+    }
+    for grid in &grids {
+        let voxel = grid.voxel_size();
+        println!(
+            "  Grid '{}': voxel size {:.4} x {:.4} x {:.4}, max value {:.3}",
+            grid.name,
+            voxel.x,
+            voxel.y,
+            voxel.z,
+            grid.maximum()
+        );
+    }
+
+    let medium = match style {
+        VolumeStyle::Smoke => VdbMedium::new(
+            grids,
+            density_scale,
+            Arc::new(SolidColor::new_rgb(0.9, 0.9, 0.9)),
+        ),
+        VolumeStyle::Cloud {
+            multiple_scattering,
+        } => VdbMedium::new(
+            grids,
+            density_scale,
+            Arc::new(SolidColor::new_rgb(0.9, 0.9, 0.9)),
+        )
+        .with_cloud_preset(multiple_scattering),
+        VolumeStyle::Fire {
+            temperature_scale,
+            intensity,
+        } => {
+            let (density, rest): (Vec<_>, Vec<_>) = grids
+                .into_iter()
+                .partition(|grid| grid.name == DENSITY_GRID);
+            let (temperature, ignored): (Vec<_>, Vec<_>) = rest
+                .into_iter()
+                .partition(|grid| grid.name == TEMPERATURE_GRID);
+            if density.is_empty() || temperature.is_empty() {
+                return Err(format!(
+                    "{}: fire needs grids named '{}' and '{}'",
+                    filename, DENSITY_GRID, TEMPERATURE_GRID
+                ));
+            }
+            for grid in &ignored {
+                println!("  Ignoring grid '{}'", grid.name);
+            }
+            VdbMedium::fire(
+                density,
+                temperature,
+                density_scale,
+                temperature_scale,
+                intensity,
+            )
+        }
+    };
+
+    Ok(studio::build_studio(
+        Arc::new(medium),
+        image_width,
+        samples,
+        max_depth,
+    ))
+}
@@ -1,6 +1,12 @@
 pub mod checker;
+pub mod curvature;
+pub mod debug;
 pub mod image;
 pub mod noise;
+pub mod occlusion;
 pub mod perlin;
+pub mod sky;
 pub mod solid_color;
 pub mod texture_trait;
+pub mod uv_set;
+pub mod vertex_color;
@@ -1,6 +1,6 @@
 use crate::core::vec3::{Color, Point3};
 use crate::textures::solid_color::SolidColor;
-use crate::textures::texture_trait::Texture;
+use crate::textures::texture_trait::{Texture, TextureContext};
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -20,26 +20,28 @@ impl CheckerTexture {
     }
 
     pub fn new_colors(scale: f64, c1: Color, c2: Color) -> Self {
-        Self {
-            inv_scale: 1.0 / scale,
-            even: Arc::new(SolidColor::new(c1)),
-            odd: Arc::new(SolidColor::new(c2)),
-        }
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(c1)),
+            Arc::new(SolidColor::new(c2)),
+        )
     }
-}
 
-impl Texture for CheckerTexture {
-    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+    fn is_even(&self, p: &Point3) -> bool {
         let x_integer = (self.inv_scale * p.x).floor() as i32;
         let y_integer = (self.inv_scale * p.y).floor() as i32;
         let z_integer = (self.inv_scale * p.z).floor() as i32;
 
-        let is_even = (x_integer + y_integer + z_integer) % 2 == 0;
+        (x_integer + y_integer + z_integer) % 2 == 0
+    }
+}
 
-        if is_even {
-            self.even.value(u, v, p)
+impl Texture for CheckerTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        if self.is_even(&ctx.p) {
+            self.even.value(ctx)
         } else {
-            self.odd.value(u, v, p)
+            self.odd.value(ctx)
         }
     }
 }
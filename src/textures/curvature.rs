@@ -0,0 +1,159 @@
+use crate::core::interaction::Interaction;
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Vec3};
+use crate::geometry::hittable::Hittable;
+use crate::sampling::random::random_double;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Tangent directions probed per lookup.
+const PROBES: u32 = 8;
+
+/// Convexity mask for edge wear: blends from `flat` to `worn` where `surface` bends
+/// away from the viewer, so box edges and rims show bare material while faces and
+/// cavities keep their finish.
+///
+/// Curvature is estimated from local geometry at surface hits: probe rays aimed at a
+/// ring of tangent offsets `radius` away find the neighboring surface, and the
+/// largest rate at which its normal turns away gives the sharpest convex curvature
+/// there. Edges rounder than `radius`, and creases within `radius`, are fully worn.
+/// Plain coordinate lookups have no normal and read as flat. Probes are traced at
+/// the time of the lookup, like those of `OcclusionTexture`.
+#[derive(Debug)]
+pub struct CurvatureTexture {
+    surface: Arc<dyn Hittable>,
+    radius: f64,
+    flat: Arc<dyn Texture>,
+    worn: Arc<dyn Texture>,
+}
+
+impl CurvatureTexture {
+    pub fn new(
+        surface: Arc<dyn Hittable>,
+        radius: f64,
+        flat: Arc<dyn Texture>,
+        worn: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            surface,
+            radius,
+            flat,
+            worn,
+        }
+    }
+
+    pub fn new_colors(surface: Arc<dyn Hittable>, radius: f64, flat: Color, worn: Color) -> Self {
+        Self::new(
+            surface,
+            radius,
+            Arc::new(SolidColor::new(flat)),
+            Arc::new(SolidColor::new(worn)),
+        )
+    }
+
+    /// Sharpest convex curvature at `isect`, seen at `time`, times `radius`,
+    /// clamped to [0, 1].
+    pub fn wear(&self, isect: &Interaction, time: f64) -> f64 {
+        let n = isect.shading_normal;
+        let uvw = isect.shading;
+        let above = isect.p + self.radius * n;
+        let start = 2.0 * PI * random_double();
+
+        let mut curvature: f64 = 0.0;
+        for k in 0..PROBES {
+            let phi = start + 2.0 * PI * k as f64 / PROBES as f64;
+            let tangent = uvw.local(&Vec3::new(phi.cos(), phi.sin(), 0.0));
+
+            // Aimed at the tangent plane point, the probe lands on the neighboring
+            // surface. Past a convex crease it misses, so a second probe comes back
+            // from beyond the point to land on the adjacent face
+            let target = isect.p + self.radius * tangent;
+            let beyond = above + 2.0 * self.radius * tangent;
+            let found = [above, beyond].iter().find_map(|origin| {
+                let probe = Ray::new(*origin, target - origin, time);
+                self.surface.hit(&probe, Interval::new(0.001, 3.0))
+            });
+            let Some(hit) = found else {
+                continue;
+            };
+            let turn = (hit.shading_normal - n).dot(&tangent) / self.radius;
+            curvature = curvature.max(turn);
+        }
+
+        (curvature * self.radius).clamp(0.0, 1.0)
+    }
+}
+
+impl Texture for CurvatureTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let Some(isect) = ctx.isect else {
+            return self.flat.value(ctx);
+        };
+        let t = self.wear(isect, ctx.time);
+        (1.0 - t) * self.flat.value(ctx) + t * self.worn.value(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::vec3::Point3;
+    use crate::geometry::quad::Quad;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::materials::material_trait::Material;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+            0.5, 0.5, 0.5,
+        ))))
+    }
+
+    /// Wear at the top of `surface`, hit straight down the y axis.
+    fn wear_at_top(surface: Arc<dyn Hittable>, radius: f64) -> f64 {
+        let ray = Ray::new(Point3::new(0.0, 100.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let isect = surface
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        let texture = CurvatureTexture::new_colors(
+            surface,
+            radius,
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        texture.wear(&isect, 0.0)
+    }
+
+    #[test]
+    fn flat_plane_is_not_worn() {
+        let plane = Arc::new(Quad::new(
+            Point3::new(-50.0, 0.0, -50.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            material(),
+        ));
+        assert_eq!(wear_at_top(plane, 1.0), 0.0);
+    }
+
+    #[test]
+    fn sphere_wear_is_radius_times_curvature() {
+        // Curvature 1 / 10, probed 1 away: wear about 0.1, slightly more as the
+        // probes land a little past the probe radius on the falling surface
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, -10.0, 0.0), 10.0, material()));
+        let wear = wear_at_top(sphere, 1.0);
+        assert!((wear - 0.1).abs() < 0.01, "wear {wear}");
+
+        // Tighter spheres wear more
+        let wears: Vec<f64> = [20.0, 10.0, 4.0, 2.0]
+            .iter()
+            .map(|&r| {
+                let sphere = Arc::new(Sphere::new(Point3::new(0.0, -r, 0.0), r, material()));
+                wear_at_top(sphere, 1.0)
+            })
+            .collect();
+        assert!(wears.windows(2).all(|pair| pair[0] < pair[1]), "{wears:?}");
+    }
+}
@@ -0,0 +1,128 @@
+use crate::core::vec3::Color;
+use crate::textures::texture_trait::{Texture, TextureContext};
+
+/// 3x5 bitmaps of the digits 0-9, rows top to bottom, 3 bits per row.
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+
+/// Checker grid in UV space with each tile numbered, row by row from (0, 0), and
+/// tinted red along u and green along v, so flipped, rotated or stretched UVs on
+/// imported meshes are easy to spot.
+#[derive(Debug, Clone)]
+pub struct UvCheckerTexture {
+    tiles: u32,
+}
+
+impl UvCheckerTexture {
+    /// A grid of `tiles` x `tiles`, at most 10 so numbers stay at two digits.
+    pub fn new(tiles: u32) -> Self {
+        Self {
+            tiles: tiles.clamp(1, 10),
+        }
+    }
+
+    /// Whether the tile's number covers the point at (`x`, `y`) in [0, 1) within the tile.
+    fn label(number: u32, x: f64, y: f64) -> bool {
+        let digits: Vec<u32> = number
+            .to_string()
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .collect();
+
+        // Digits three cells wide with one cell between them, in the middle of the tile
+        let columns = 4 * digits.len() - 1;
+        let (left, width, bottom, height) = (0.2, 0.6, 0.3, 0.4);
+        if !(left..left + width).contains(&x) || !(bottom..bottom + height).contains(&y) {
+            return false;
+        }
+        let column = ((x - left) / width * columns as f64) as usize;
+        let row = 4 - ((y - bottom) / height * 5.0) as usize;
+        if column % 4 == 3 {
+            return false;
+        }
+
+        let bits = DIGITS[digits[column / 4] as usize];
+        (bits >> (14 - (3 * row + column % 4))) & 1 == 1
+    }
+}
+
+impl Texture for UvCheckerTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let (u, v) = ctx.uv;
+        let n = self.tiles as f64;
+        let (x, y) = (u.rem_euclid(1.0) * n, v.rem_euclid(1.0) * n);
+        let (column, row) = (x.floor(), y.floor());
+
+        let dark = (column + row) as u32 % 2 == 1;
+        if Self::label(
+            row as u32 * self.tiles + column as u32,
+            x.fract(),
+            y.fract(),
+        ) {
+            return if dark {
+                Color::new(0.9, 0.9, 0.9)
+            } else {
+                Color::new(0.05, 0.05, 0.05)
+            };
+        }
+
+        let tint = Color::new((column + 0.5) / n, (row + 0.5) / n, 0.5);
+        if dark {
+            0.3 * tint
+        } else {
+            0.5 * tint + Color::new(0.4, 0.4, 0.4)
+        }
+    }
+}
+
+/// Shading normal mapped from [-1, 1] to [0, 1] per channel. Coordinate lookups
+/// without a surface read as mid gray.
+#[derive(Debug, Clone)]
+pub struct NormalTexture;
+
+impl Texture for NormalTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        match ctx.isect {
+            Some(isect) => (isect.shading_normal.add_scalar(1.0)) * 0.5,
+            None => Color::new(0.5, 0.5, 0.5),
+        }
+    }
+}
+
+/// World-space stripes of the given period along x (red), y (green) and z (blue),
+/// for checking positions, scale and mesh placement independently of UVs.
+#[derive(Debug, Clone)]
+pub struct StripesTexture {
+    period: f64,
+}
+
+impl StripesTexture {
+    pub fn new(period: f64) -> Self {
+        Self { period }
+    }
+
+    fn stripe(&self, x: f64) -> f64 {
+        if (x / self.period).rem_euclid(1.0) < 0.5 {
+            0.9
+        } else {
+            0.15
+        }
+    }
+}
+
+impl Texture for StripesTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let p = &ctx.p;
+        Color::new(self.stripe(p.x), self.stripe(p.y), self.stripe(p.z))
+    }
+}
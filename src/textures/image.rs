@@ -1,5 +1,8 @@
-use crate::core::vec3::{Color, Point3};
-use crate::textures::texture_trait::Texture;
+use crate::core::assets;
+use crate::core::memory::{self, Category};
+use crate::core::vec3::Color;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use image::imageops::FilterType;
 use image::{DynamicImage, GenericImageView};
 
 #[derive(Debug)]
@@ -7,21 +10,71 @@ pub struct ImageTexture {
     image: Option<DynamicImage>,
     width: u32,
     height: u32,
+    /// Box-filtered copies of the image at half the size of the one before, down
+    /// to a single texel, for lookups whose footprint spans many texels.
+    mip_levels: Vec<MipLevel>,
+}
+
+#[derive(Debug)]
+struct MipLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<[u8; 3]>,
+}
+
+impl MipLevel {
+    fn texel(&self, i: u32, j: u32) -> [u8; 3] {
+        self.texels[(j * self.width + i) as usize]
+    }
+
+    /// The level at half the size, each texel the mean of the two by two it
+    /// covers, clamped at odd edges.
+    fn halved(&self) -> MipLevel {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let texels = (0..height)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                let mut sum = [0u32; 3];
+                for (di, dj) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = self.texel(
+                        (2 * i + di).min(self.width - 1),
+                        (2 * j + dj).min(self.height - 1),
+                    );
+                    for (s, t) in sum.iter_mut().zip(texel) {
+                        *s += t as u32;
+                    }
+                }
+                sum.map(|s| ((s + 2) / 4) as u8)
+            })
+            .collect();
+        MipLevel {
+            width,
+            height,
+            texels,
+        }
+    }
 }
 
 impl ImageTexture {
+    /// Loads an image, looking it up through the asset search paths.
     pub fn new(filename: &str) -> Self {
-        let img_result = image::open(filename);
+        let img_result = assets::resolve(filename)
+            .and_then(|path| image::open(&path).map_err(|e| format!("{}: {}", path.display(), e)));
 
         // Simple fallback logic, can be expanded
         match img_result {
             Ok(img) => {
+                let img = Self::fit_memory(img, filename);
                 let width = img.width();
                 let height = img.height();
+                let mip_levels = Self::mip_levels(&img);
+                let mip_bytes: usize = mip_levels.iter().map(|mip| 3 * mip.texels.len()).sum();
+                memory::record(Category::Textures, img.as_bytes().len() + mip_bytes);
                 Self {
                     image: Some(img),
                     width,
                     height,
+                    mip_levels,
                 }
             }
             Err(e) => {
@@ -30,34 +83,106 @@ impl ImageTexture {
                     image: None,
                     width: 0,
                     height: 0,
+                    mip_levels: Vec::new(),
                 }
             }
         }
     }
-}
 
-impl Texture for ImageTexture {
-    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
-        if self.image.is_none() {
-            return Color::new(0.0, 1.0, 1.0); // Cyan debugging color
+    /// Halves `img` until it and its mip levels fit in the memory left under the
+    /// limit, if there is one, rather than failing the whole scene over one texture.
+    fn fit_memory(img: DynamicImage, filename: &str) -> DynamicImage {
+        let Some(remaining) = memory::remaining() else {
+            return img;
+        };
+        // Mip levels add a third of the full-size RGB texels
+        let (width, height) = img.dimensions();
+        let texels = width as u64 * height as u64;
+        if img.as_bytes().len() as u64 + texels <= remaining {
+            return img;
+        }
+
+        let (mut w, mut h) = (width, height);
+        while (w > 1 || h > 1) && 4 * w as u64 * h as u64 > remaining {
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
         }
+        eprintln!(
+            "Downscaling texture '{}' from {}x{} to {}x{} to stay within --max-memory",
+            filename, width, height, w, h
+        );
+        DynamicImage::ImageRgb8(img.resize_exact(w, h, FilterType::Triangle).to_rgb8())
+    }
+
+    /// Mip levels below the full-size image, from half its size down to one texel.
+    fn mip_levels(img: &DynamicImage) -> Vec<MipLevel> {
+        let full = MipLevel {
+            width: img.width(),
+            height: img.height(),
+            texels: img.to_rgb8().pixels().map(|p| p.0).collect(),
+        };
+        let mut levels: Vec<MipLevel> = Vec::new();
+        loop {
+            let last = levels.last().unwrap_or(&full);
+            if last.width == 1 && last.height == 1 {
+                return levels;
+            }
+            let next = last.halved();
+            levels.push(next);
+        }
+    }
 
+    /// Texel nearest to (u, v) at mip `level`, 0 being the full-size image.
+    fn texel(&self, level: usize, u: f64, v: f64) -> Color {
         let u = u.clamp(0.0, 1.0);
         let v = 1.0 - v.clamp(0.0, 1.0); // Flip V
 
-        let i = (u * self.width as f64) as u32;
-        let j = (v * self.height as f64) as u32;
+        let scale = 1.0 / 255.0;
+        let rgb = |pixel: [u8; 3]| {
+            Color::new(
+                pixel[0] as f64 * scale,
+                pixel[1] as f64 * scale,
+                pixel[2] as f64 * scale,
+            )
+        };
+
+        if level == 0 {
+            let i = ((u * self.width as f64) as u32).min(self.width - 1);
+            let j = ((v * self.height as f64) as u32).min(self.height - 1);
+            let pixel = self.image.as_ref().unwrap().get_pixel(i, j);
+            return rgb([pixel[0], pixel[1], pixel[2]]);
+        }
+
+        let mip = &self.mip_levels[level - 1];
+        let i = ((u * mip.width as f64) as u32).min(mip.width - 1);
+        let j = ((v * mip.height as f64) as u32).min(mip.height - 1);
+        rgb(mip.texel(i, j))
+    }
+}
 
-        let i = i.min(self.width - 1);
-        let j = j.min(self.height - 1);
+impl Texture for ImageTexture {
+    /// Filtered by the pixel footprint: where neighboring pixels land more than a
+    /// texel apart, the texture is read from the mip level whose texels are about
+    /// that size, blending the two nearest, so minified textures average out
+    /// instead of aliasing. Lookups without a footprint read the full image.
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let (u, v) = ctx.uv;
+        if self.image.is_none() {
+            return Color::new(0.0, 1.0, 1.0); // Cyan debugging color
+        }
 
-        let pixel = self.image.as_ref().unwrap().get_pixel(i, j);
+        let texels = |(du, dv): (f64, f64)| (du * self.width as f64).hypot(dv * self.height as f64);
+        let width = texels(ctx.duvdx).max(texels(ctx.duvdy));
+        if width <= 1.0 || self.mip_levels.is_empty() {
+            return self.texel(0, u, v);
+        }
 
-        let scale = 1.0 / 255.0;
-        Color::new(
-            pixel[0] as f64 * scale,
-            pixel[1] as f64 * scale,
-            pixel[2] as f64 * scale,
-        )
+        let level = width.log2().min(self.mip_levels.len() as f64);
+        let below = level.floor() as usize;
+        let t = level - below as f64;
+        if below >= self.mip_levels.len() {
+            return self.texel(below, u, v);
+        }
+        (1.0 - t) * self.texel(below, u, v) + t * self.texel(below + 1, u, v)
     }
 }
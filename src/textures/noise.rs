@@ -1,11 +1,12 @@
-use crate::core::vec3::{Color, Point3};
+use crate::core::vec3::{Color, Vec3};
 use crate::textures::perlin::Perlin;
-use crate::textures::texture_trait::Texture;
+use crate::textures::texture_trait::{Texture, TextureContext};
 
 #[derive(Debug)]
 pub struct NoiseTexture {
     noise: Perlin,
     scale: f64,
+    velocity: Vec3,
 }
 
 impl NoiseTexture {
@@ -13,13 +14,22 @@ impl NoiseTexture {
         Self {
             noise: Perlin::new(),
             scale,
+            velocity: Vec3::zeros(),
         }
     }
+
+    /// Drifts the pattern by `velocity` per unit of time, so it flows across a
+    /// surface in motion blur and frame sequences, like smoke or lava.
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
 }
 
 impl Texture for NoiseTexture {
-    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+    fn value(&self, ctx: &TextureContext) -> Color {
         // Marble-like texture using sine of turbulence
+        let p = &(ctx.p - ctx.time * self.velocity);
         let s = self.scale * p.z + 10.0 * self.noise.turb(p, 7);
         Color::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + s.sin())
     }
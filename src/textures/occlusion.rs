@@ -0,0 +1,153 @@
+use crate::core::interval::Interval;
+use crate::core::ray::Ray;
+use crate::core::vec3::{Color, Point3, Vec3, Vec3Ext};
+use crate::geometry::hittable::Hittable;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Probe rays traced per lookup unless set with `with_samples`.
+const DEFAULT_SAMPLES: u32 = 16;
+/// Probe rays start this far from the shaded point to avoid hitting its own surface.
+const PROBE_EPSILON: f64 = 0.001;
+
+/// Ambient occlusion computed on the fly, for dirt in crevices without painted masks.
+/// Blends from `occluded` to `open` by how much of the surroundings within `distance`
+/// are free of `occluders`, usually the geometry the texture is applied to.
+///
+/// At surface hits, short cosine-distributed probe rays are shot around the shading
+/// normal and the escaping fraction measures openness. Plain coordinate lookups have
+/// no normal, so probes cover the whole sphere instead: a point on a solid surface
+/// loses about half of them to its own side and twice the escaping fraction (clamped)
+/// is used, which reads thin sheets as open. Probes are traced at the time of the
+/// lookup, so moving occluders are seen where they are.
+#[derive(Debug)]
+pub struct OcclusionTexture {
+    occluders: Arc<dyn Hittable>,
+    distance: f64,
+    samples: u32,
+    open: Arc<dyn Texture>,
+    occluded: Arc<dyn Texture>,
+}
+
+impl OcclusionTexture {
+    pub fn new(
+        occluders: Arc<dyn Hittable>,
+        distance: f64,
+        open: Arc<dyn Texture>,
+        occluded: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            occluders,
+            distance,
+            samples: DEFAULT_SAMPLES,
+            open,
+            occluded,
+        }
+    }
+
+    pub fn new_colors(
+        occluders: Arc<dyn Hittable>,
+        distance: f64,
+        open: Color,
+        grime: Color,
+    ) -> Self {
+        Self::new(
+            occluders,
+            distance,
+            Arc::new(SolidColor::new(open)),
+            Arc::new(SolidColor::new(grime)),
+        )
+    }
+
+    /// Probe rays per lookup: fewer is faster and noisier.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Fraction of probe rays from `p` drawn by `direction` that escape the occluders.
+    fn escaped(&self, p: &Point3, time: f64, direction: impl Fn() -> Vec3) -> f64 {
+        let probe_t = Interval::new(PROBE_EPSILON, self.distance);
+        let mut escaped = 0;
+        for _ in 0..self.samples {
+            let probe = Ray::new(*p, direction(), time);
+            if self.occluders.hit(&probe, probe_t).is_none() {
+                escaped += 1;
+            }
+        }
+        escaped as f64 / self.samples as f64
+    }
+}
+
+impl Texture for OcclusionTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let t = match ctx.isect {
+            Some(isect) => {
+                let uvw = isect.shading;
+                self.escaped(&ctx.p, ctx.time, || {
+                    uvw.local(&Vec3::random_cosine_direction())
+                })
+            }
+            None => (2.0 * self.escaped(&ctx.p, ctx.time, Vec3::random_unit_vector)).min(1.0),
+        };
+        (1.0 - t) * self.occluded.value(ctx) + t * self.open.value(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::hittable_list::HittableList;
+    use crate::geometry::quad::Quad;
+    use crate::materials::lambertian::Lambertian;
+
+    /// A horizontal square of side 100 centered on the y axis at `height`.
+    fn plane(height: f64) -> Arc<dyn Hittable> {
+        let material = Arc::new(Lambertian::new(Arc::new(SolidColor::new_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        Arc::new(Quad::new(
+            Point3::new(-50.0, height, -50.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            material,
+        ))
+    }
+
+    /// Openness at the floor's center, from white where open to black where occluded.
+    fn openness(floor: &Arc<dyn Hittable>, occluders: Arc<dyn Hittable>) -> f64 {
+        let texture = OcclusionTexture::new_colors(
+            occluders,
+            1.0,
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.0, 0.0, 0.0),
+        )
+        .with_samples(64);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let isect = floor
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        texture
+            .value(&TextureContext::from_interaction(&isect, 0.0))
+            .x
+    }
+
+    #[test]
+    fn flat_plane_is_open() {
+        let floor = plane(0.0);
+        assert_eq!(openness(&floor, floor.clone()), 1.0);
+    }
+
+    #[test]
+    fn low_ceiling_occludes() {
+        let floor = plane(0.0);
+        let mut room = HittableList::new();
+        room.add(floor.clone());
+        room.add(plane(0.1));
+        // Only probes within about 6 degrees of the floor pass under the ceiling
+        // farther than the occlusion distance
+        let open = openness(&floor, Arc::new(room));
+        assert!(open < 0.25, "openness {open} under a ceiling");
+    }
+}
@@ -1,6 +1,5 @@
 use crate::core::vec3::{Point3, Vec3};
-use crate::sampling::random::random_int_range;
-use rand::Rng;
+use crate::sampling::random::{random_double_range, random_int_range};
 
 #[derive(Debug)]
 pub struct Perlin {
@@ -14,13 +13,11 @@ impl Perlin {
     pub fn new() -> Self {
         let ranvec_count = 256;
         let mut ranvec = Vec::with_capacity(ranvec_count);
-        let mut rng = rand::rng();
-
         for _ in 0..ranvec_count {
             let v = Vec3::new(
-                rng.random_range(-1.0..1.0),
-                rng.random_range(-1.0..1.0),
-                rng.random_range(-1.0..1.0),
+                random_double_range(-1.0, 1.0),
+                random_double_range(-1.0, 1.0),
+                random_double_range(-1.0, 1.0),
             );
             ranvec.push(v.normalize());
         }
@@ -37,6 +34,7 @@ impl Perlin {
         }
     }
 
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: &Point3) -> f64 {
         let u = p.x - p.x.floor();
         let v = p.y - p.y.floor();
@@ -94,6 +92,7 @@ impl Perlin {
         }
     }
 
+    #[allow(clippy::needless_range_loop)]
     fn trilinear_interp(c: [[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
         let mut accum = 0.0;
         for i in 0..2 {
@@ -115,3 +114,9 @@ impl Perlin {
         accum
     }
 }
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
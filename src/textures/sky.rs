@@ -0,0 +1,209 @@
+use crate::core::vec3::{Color, Vec3};
+use crate::geometry::hittable::shutter_fraction;
+use crate::sampling::random::degrees_to_radians;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Elevation below which the sun is down and the sky dark, in degrees: the end of
+/// civil twilight.
+const TWILIGHT: f64 = -6.0;
+/// Extinction of sunlight per air mass, by channel: blue scatters out most, so a
+/// low sun is dimmer and redder.
+const EXTINCTION: [f64; 3] = [0.1, 0.2, 0.4];
+/// Sharpness of the glow around the sun.
+const GLOW_EXPONENT: i32 = 16;
+
+const NIGHT: Color = Color::new(0.005, 0.006, 0.015);
+const ZENITH: Color = Color::new(0.25, 0.45, 0.85);
+const HORIZON: Color = Color::new(0.7, 0.8, 0.95);
+const DUSK: Color = Color::new(0.9, 0.45, 0.2);
+
+/// The sun's course over the animation timeline, the shutter: elevations in
+/// degrees keyed at evenly spaced times, from when it opens to when it closes,
+/// and interpolated linearly between, at a fixed azimuth. A sequence of frames
+/// then renders a timelapse.
+#[derive(Debug, Clone)]
+pub struct SunPath {
+    elevations: Vec<f64>,
+    /// Horizontal unit direction toward the sun.
+    azimuth: Vec3,
+}
+
+impl SunPath {
+    /// Path through `elevations`, at least one, toward `azimuth` seen from above.
+    pub fn new(elevations: Vec<f64>, azimuth: Vec3) -> Self {
+        let elevations = if elevations.is_empty() {
+            vec![45.0]
+        } else {
+            elevations
+        };
+        Self {
+            elevations,
+            azimuth: Vec3::new(azimuth.x, 0.0, azimuth.z).normalize(),
+        }
+    }
+
+    /// Elevation in degrees at `time`, held at the first and last keys outside
+    /// the shutter.
+    pub fn elevation(&self, time: f64) -> f64 {
+        let last = self.elevations.len() - 1;
+        let t = shutter_fraction(time).clamp(0.0, 1.0) * last as f64;
+        let key = (t.floor() as usize).min(last.saturating_sub(1));
+        match self.elevations.get(key + 1) {
+            Some(next) => {
+                let f = t - key as f64;
+                self.elevations[key] * (1.0 - f) + next * f
+            }
+            None => self.elevations[key],
+        }
+    }
+
+    /// Lowest and highest elevations the sun reaches.
+    pub fn elevation_range(&self) -> (f64, f64) {
+        let fold = |init, f: fn(f64, f64) -> f64| self.elevations.iter().copied().fold(init, f);
+        (
+            fold(f64::INFINITY, f64::min),
+            fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+
+    /// Unit direction toward the sun at `time`.
+    pub fn direction(&self, time: f64) -> Vec3 {
+        self.direction_at(self.elevation(time))
+    }
+
+    /// Unit direction toward the sun at `elevation` degrees.
+    pub fn direction_at(&self, elevation: f64) -> Vec3 {
+        let elevation = degrees_to_radians(elevation);
+        self.azimuth * elevation.cos() + Vec3::new(0.0, elevation.sin(), 0.0)
+    }
+
+    /// Fraction of each channel of sunlight crossing the atmosphere at `time`,
+    /// relative to a sun overhead, by the Kasten-Young air mass; none once the
+    /// sun has set.
+    pub fn transmittance(&self, time: f64) -> Color {
+        let elevation = self.elevation(time);
+        if elevation <= -1.0 {
+            return Color::zeros();
+        }
+        let air_mass = 1.0
+            / (degrees_to_radians(elevation).sin() + 0.50572 * (elevation + 6.07995).powf(-1.6364));
+        let [r, g, b] = EXTINCTION.map(|k| (-k * (air_mass - 1.0)).exp());
+        Color::new(r, g, b)
+    }
+
+    /// How far the sky is into daylight at `time`: 0 from the end of twilight
+    /// down, 1 once the sun is 10 degrees up.
+    fn daylight(&self, time: f64) -> f64 {
+        smoothstep(TWILIGHT, 10.0, self.elevation(time))
+    }
+}
+
+/// Procedural sky following a `SunPath`, for the inside of a dome centered on the
+/// origin: a gradient from the horizon to the zenith with a glow around the sun,
+/// warm while it is low and fading into night after it sets.
+#[derive(Debug)]
+pub struct Sky {
+    sun: Arc<SunPath>,
+}
+
+impl Sky {
+    pub fn new(sun: Arc<SunPath>) -> Self {
+        Self { sun }
+    }
+}
+
+impl Texture for Sky {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let direction = ctx.p.coords.normalize();
+        let elevation = self.sun.elevation(ctx.time);
+        let daylight = self.sun.daylight(ctx.time);
+
+        // The horizon glows orange around sunrise and sunset
+        let horizon = DUSK.lerp(&HORIZON, smoothstep(0.0, 20.0, elevation));
+        let up = direction.y.max(0.0).sqrt();
+        let sky = horizon.lerp(&ZENITH, up);
+
+        let toward_sun = direction.dot(&self.sun.direction(ctx.time)).max(0.0);
+        let glow = toward_sun.powi(GLOW_EXPONENT) * DUSK * (1.0 - smoothstep(0.0, 30.0, elevation));
+        NIGHT.lerp(&(sky + glow), daylight)
+    }
+}
+
+/// Emission of the sun disk along a `SunPath`: `radiance` with the sun overhead,
+/// dimmed and reddened by the air it crosses at the time of the lookup.
+#[derive(Debug)]
+pub struct Sunlight {
+    sun: Arc<SunPath>,
+    radiance: Color,
+}
+
+impl Sunlight {
+    pub fn new(sun: Arc<SunPath>, radiance: Color) -> Self {
+        Self { sun, radiance }
+    }
+}
+
+impl Texture for Sunlight {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        self.radiance
+            .component_mul(&self.sun.transmittance(ctx.time))
+    }
+}
+
+/// Hermite step from 0 at `edge0` to 1 at `edge1`.
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::vec3::Point3;
+    use crate::geometry::hittable::SHUTTER;
+
+    fn at(fraction: f64) -> f64 {
+        SHUTTER.min + SHUTTER.size() * fraction
+    }
+
+    #[test]
+    fn elevation_follows_keys() {
+        let path = SunPath::new(vec![0.0, 60.0, 10.0], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(path.elevation(at(0.0)), 0.0);
+        assert!((path.elevation(at(0.25)) - 30.0).abs() < 1e-9);
+        assert!((path.elevation(at(0.5)) - 60.0).abs() < 1e-9);
+        assert!((path.elevation(at(1.0)) - 10.0).abs() < 1e-9);
+        assert_eq!(path.elevation(at(2.0)), 10.0);
+
+        let direction = path.direction(at(0.5));
+        assert!((direction.norm() - 1.0).abs() < 1e-9);
+        assert!((direction.y - 60f64.to_radians().sin()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn low_sun_is_dimmer_and_redder() {
+        let path = SunPath::new(vec![90.0, 5.0, -10.0], Vec3::new(0.0, 0.0, 1.0));
+        let overhead = path.transmittance(at(0.0));
+        assert!((overhead - Color::new(1.0, 1.0, 1.0)).norm() < 1e-3);
+
+        let low = path.transmittance(at(0.5));
+        assert!(low.x < 1.0 && low.z < low.y && low.y < low.x);
+        assert_eq!(path.transmittance(at(1.0)), Color::zeros());
+    }
+
+    #[test]
+    fn sky_darkens_after_sunset() {
+        let sky = Sky::new(Arc::new(SunPath::new(
+            vec![45.0, -20.0],
+            Vec3::new(1.0, 0.0, 0.0),
+        )));
+        let zenith = TextureContext::new((0.0, 0.0), Point3::new(0.0, 100.0, 0.0));
+        let day = sky.value(&zenith.with_time(at(0.0)));
+        let night = sky.value(&zenith.with_time(at(1.0)));
+        assert!(
+            day.sum() > 10.0 * night.sum(),
+            "day {day:?}, night {night:?}"
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use crate::core::vec3::{Color, Point3};
-use crate::textures::texture_trait::Texture;
+use crate::core::vec3::Color;
+use crate::textures::texture_trait::{Texture, TextureContext};
 
 #[derive(Debug, Clone)]
 pub struct SolidColor {
@@ -17,7 +17,7 @@ impl SolidColor {
 }
 
 impl Texture for SolidColor {
-    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+    fn value(&self, _ctx: &TextureContext) -> Color {
         self.albedo
     }
 }
@@ -1,10 +1,76 @@
-use crate::core::vec3::{Color, Point3};
+use crate::core::interaction::Interaction;
+use crate::core::vec3::{Color, Point3, Vec3};
 use std::fmt::Debug;
 
+/// Where a texture is evaluated: the texture coordinates and point, how they
+/// change to the neighboring pixels for filtered lookups, the time for animated
+/// textures, and the surface hit, if any, for textures that depend on more, such
+/// as the normal or vertex colors.
+#[derive(Clone, Copy)]
+pub struct TextureContext<'a> {
+    /// Texture coordinates, usually in [0, 1].
+    pub uv: (f64, f64),
+    /// World space point, for solid textures like Perlin noise.
+    pub p: Point3,
+    /// Change of `uv` to the next pixel along x and y, zero if unknown.
+    pub duvdx: (f64, f64),
+    pub duvdy: (f64, f64),
+    /// Change of `p` to the next pixel along x and y, zero if unknown.
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+    /// Time of the ray that hit the surface, within the camera shutter.
+    pub time: f64,
+    pub isect: Option<&'a Interaction>,
+}
+
+impl<'a> TextureContext<'a> {
+    /// Lookup at bare coordinates, with no footprint, at time 0 and away from any
+    /// surface, e.g. when baking or integrating a texture over its domain.
+    pub fn new(uv: (f64, f64), p: Point3) -> Self {
+        Self {
+            uv,
+            p,
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
+            dpdx: Vec3::zeros(),
+            dpdy: Vec3::zeros(),
+            time: 0.0,
+            isect: None,
+        }
+    }
+
+    /// Lookup at a surface hit by a ray at `time`, with the footprint of the ray's
+    /// differentials.
+    pub fn from_interaction(isect: &'a Interaction, time: f64) -> Self {
+        Self {
+            uv: isect.uv,
+            p: isect.p,
+            duvdx: isect.duvdx,
+            duvdy: isect.duvdy,
+            dpdx: isect.dpdx,
+            dpdy: isect.dpdy,
+            time,
+            isect: Some(isect),
+        }
+    }
+
+    /// The same lookup at other texture coordinates, whose derivatives are unknown.
+    pub fn with_uv(mut self, uv: (f64, f64)) -> Self {
+        self.uv = uv;
+        self.duvdx = (0.0, 0.0);
+        self.duvdy = (0.0, 0.0);
+        self
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+}
+
 /// Abstract interface for textures.
 pub trait Texture: Send + Sync + Debug {
-    /// Returns the color value of the texture at the given coordinates.
-    /// u, v: texture coordinates [0, 1]
-    /// p: world space point (for procedural textures like Perlin noise)
-    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+    /// Returns the color value of the texture at `ctx`. Textures that wrap others
+    /// pass the context on, adjusted where they change the lookup.
+    fn value(&self, ctx: &TextureContext) -> Color;
 }
@@ -0,0 +1,31 @@
+use crate::core::vec3::Color;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// Looks `texture` up by another UV set of the surface than the first, such as a
+/// lightmap's unique, non-overlapping layout of a mesh whose first set tiles a
+/// material. Surfaces without the set use their first, and lookups away from
+/// surfaces keep their coordinates. The footprint of the first set does not carry
+/// over, so lookups through another set are unfiltered.
+#[derive(Debug)]
+pub struct UvSet {
+    texture: Arc<dyn Texture>,
+    set: usize,
+}
+
+impl UvSet {
+    /// Set 0 is the surface's first UV set, set 1 the first added with
+    /// `TriangleMesh::with_uv_set`, and so on.
+    pub fn new(texture: Arc<dyn Texture>, set: usize) -> Self {
+        Self { texture, set }
+    }
+}
+
+impl Texture for UvSet {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        match ctx.isect {
+            Some(isect) if self.set > 0 => self.texture.value(&ctx.with_uv(isect.uv_set(self.set))),
+            _ => self.texture.value(ctx),
+        }
+    }
+}
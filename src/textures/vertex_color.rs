@@ -0,0 +1,24 @@
+use crate::core::vec3::Color;
+use crate::textures::texture_trait::{Texture, TextureContext};
+use std::sync::Arc;
+
+/// The interpolated vertex colors of meshes that have them, such as painted or
+/// scanned PLY models, and `fallback` elsewhere.
+#[derive(Debug)]
+pub struct VertexColor {
+    fallback: Arc<dyn Texture>,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Arc<dyn Texture>) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Texture for VertexColor {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        ctx.isect
+            .and_then(|isect| isect.vertex_color)
+            .unwrap_or_else(|| self.fallback.value(ctx))
+    }
+}